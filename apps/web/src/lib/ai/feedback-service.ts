@@ -3,6 +3,7 @@
  * Handles communication with the local Glow Bridge server for AI feedback
  */
 
+import { StreamCloseCode } from './types';
 import type { FeedbackRequest, FeedbackResponse, StreamMessage, SuggestedEdit } from './types';
 
 /** Default bridge server URL */
@@ -47,6 +48,8 @@ export interface HealthResponse {
 export class AIFeedbackService {
   private bridgeUrl: string;
   private ws: WebSocket | null = null;
+  /** Stream tokens of created feedback sessions, by feedback ID */
+  private streamTokens = new Map<string, string>();
 
   constructor(bridgeUrl: string = DEFAULT_BRIDGE_URL) {
     this.bridgeUrl = bridgeUrl;
@@ -141,6 +144,9 @@ export class AIFeedbackService {
 
     const data: FeedbackResponse = await response.json();
     console.log('[AI Service] Got feedback response:', data);
+    if (data.streamToken) {
+      this.streamTokens.set(data.id, data.streamToken);
+    }
     return data.id;
   }
 
@@ -148,12 +154,16 @@ export class AIFeedbackService {
    * Get the status of a feedback request.
    */
   async getFeedbackStatus(feedbackId: string): Promise<FeedbackResponse> {
-    const response = await fetch(`${this.bridgeUrl}/api/feedback/${feedbackId}`);
+    const token = encodeURIComponent(this.streamTokens.get(feedbackId) ?? '');
+    const response = await fetch(`${this.bridgeUrl}/api/feedback/${feedbackId}?token=${token}`);
 
     if (!response.ok) {
       if (response.status === 404) {
         throw new Error('Feedback request not found');
       }
+      if (response.status === 403) {
+        throw new Error('Not authorized to read this feedback request');
+      }
       throw new Error(`Failed to get feedback status: ${response.statusText}`);
     }
 
@@ -164,7 +174,8 @@ export class AIFeedbackService {
    * Cancel a feedback request.
    */
   async cancelFeedback(feedbackId: string): Promise<void> {
-    const response = await fetch(`${this.bridgeUrl}/api/feedback/${feedbackId}`, {
+    const token = encodeURIComponent(this.streamTokens.get(feedbackId) ?? '');
+    const response = await fetch(`${this.bridgeUrl}/api/feedback/${feedbackId}?token=${token}`, {
       method: 'DELETE',
     });
 
@@ -177,7 +188,8 @@ export class AIFeedbackService {
    * Stream feedback results via WebSocket.
    */
   streamFeedback(feedbackId: string, callbacks: StreamCallbacks): () => void {
-    const wsUrl = `ws://${new URL(this.bridgeUrl).host}/api/feedback/${feedbackId}/ws`;
+    const token = encodeURIComponent(this.streamTokens.get(feedbackId) ?? '');
+    const wsUrl = `ws://${new URL(this.bridgeUrl).host}/api/feedback/${feedbackId}/ws?token=${token}`;
     console.log('[AI Service] Opening WebSocket to', wsUrl);
     this.ws = new WebSocket(wsUrl);
 
@@ -221,6 +233,11 @@ export class AIFeedbackService {
 
     this.ws.onclose = (event) => {
       console.log('[AI Service] WebSocket closed', event.code, event.reason);
+      switch (event.code) {
+        case StreamCloseCode.Failed:
+          callbacks.onError(event.reason || 'Feedback session failed');
+          break;
+      }
     };
 
     // Return cleanup function
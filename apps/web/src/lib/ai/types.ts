@@ -91,16 +91,34 @@ export interface FeedbackResponse {
   sessionId: string;
   /** Error message if failed */
   error?: string;
+  /** ISO 639-3 language code of the response, if detected */
+  language?: string;
+  /** Secret required to open the stream, returned only on creation */
+  streamToken?: string;
+  /** Executor run instead of the requested one because it was unavailable */
+  fallbackExecutor?: string;
 }
 
 /** Streaming message from the AI */
 export type StreamMessage =
   | { type: 'chunk'; content: string }
   | { type: 'edit'; edit: SuggestedEdit }
-  | { type: 'thinking'; content: string }
+  | { type: 'thinking'; content: string; preview: string }
   | { type: 'complete' }
   | { type: 'error'; message: string };
 
+/** WebSocket close codes the bridge sends when it ends a feedback stream */
+export const StreamCloseCode = {
+  /** The session finished and has nothing more to stream */
+  Completed: 1000,
+  /** The session failed; the close reason carries its error */
+  Failed: 1011,
+  /** The session was cancelled */
+  Cancelled: 4000,
+  /** An administrator closed the session's connections; reconnecting is allowed */
+  Revoked: 4001,
+} as const;
+
 /** Parsed @mention from comment content */
 export interface MentionMatch {
   /** The agent name (claude, codex, gemini, ai) */
@@ -0,0 +1,116 @@
+//! Comment thread endpoints.
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    routing::get,
+};
+use serde::Deserialize;
+
+use crate::state::{AppState, SessionSummary};
+
+/// Build the comments router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/comments/{comment_id}/feedback", get(get_comment_feedback))
+}
+
+/// Query parameters for listing a comment's feedback sessions.
+#[derive(Debug, Default, Deserialize)]
+pub struct CommentFeedbackQuery {
+    /// Comma-separated stream tokens of the sessions the caller started.
+    pub tokens: Option<String>,
+}
+
+/// List the feedback sessions of a comment thread, oldest first.
+///
+/// Only sessions whose stream token the caller passes are listed, so knowing
+/// a comment ID alone reveals neither session IDs nor their errors. A comment
+/// without such sessions has an empty thread.
+async fn get_comment_feedback(
+    State(state): State<AppState>,
+    Path(comment_id): Path<String>,
+    Query(query): Query<CommentFeedbackQuery>,
+) -> Json<Vec<SessionSummary>> {
+    let tokens: Vec<&str> = query
+        .tokens
+        .as_deref()
+        .map(|tokens| tokens.split(',').filter(|token| !token.is_empty()).collect())
+        .unwrap_or_default();
+    Json(state.sessions_for_comment(&comment_id, &tokens).await)
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request, http::StatusCode};
+    use glow_executors::DocumentAgent;
+    use glow_executors::executors::ClaudeCode;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    /// Create a session replying to `comment_id`, returning its ID and stream token.
+    async fn create_session(state: &AppState, comment_id: &str) -> (String, String) {
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session(comment_id.to_owned(), "doc-1".to_owned(), agent, true).await;
+        let s = session.read().await;
+        (s.id.clone(), s.stream_token.clone())
+    }
+
+    async fn list(state: &AppState, uri: &str) -> Vec<serde_json::Value> {
+        let request = Request::get(uri).body(Body::empty());
+        let response = router()
+            .with_state(state.clone())
+            .oneshot(request.expect("valid request"))
+            .await
+            .expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        serde_json::from_slice(&body).expect("should be JSON")
+    }
+
+    #[tokio::test]
+    async fn test_comment_sessions_are_listed_in_order() {
+        let state = AppState::new();
+        let first = create_session(&state, "comment-1").await;
+        let other = create_session(&state, "comment-2").await;
+        let sessions = [
+            first,
+            create_session(&state, "comment-1").await,
+            create_session(&state, "comment-1").await,
+        ];
+        state.get_session(&sessions[0].0).await.expect("session exists").write().await.fail("boom");
+
+        let tokens: Vec<&str> = sessions.iter().map(|(_, token)| token.as_str()).collect();
+        let uri = format!("/comments/comment-1/feedback?tokens={},{}", tokens.join(","), other.1);
+        let thread = list(&state, &uri).await;
+
+        let listed: Vec<_> = thread.iter().map(|s| s["id"].as_str().unwrap_or_default()).collect();
+        let ids: Vec<_> = sessions.iter().map(|(id, _)| id.as_str()).collect();
+        assert_eq!(listed, ids);
+        assert_eq!(thread[0]["state"], "failed");
+        assert_eq!(thread[0]["error"], "boom");
+        assert_eq!(thread[1]["state"], "pending");
+    }
+
+    #[tokio::test]
+    async fn test_comment_sessions_are_listed_only_with_their_tokens() {
+        let state = AppState::new();
+        let (first, first_token) = create_session(&state, "comment-1").await;
+        create_session(&state, "comment-1").await;
+
+        assert_eq!(
+            list(&state, "/comments/comment-1/feedback").await,
+            Vec::<serde_json::Value>::new()
+        );
+        assert_eq!(
+            list(&state, "/comments/comment-1/feedback?tokens=wrong").await,
+            Vec::<serde_json::Value>::new()
+        );
+        let thread =
+            list(&state, &format!("/comments/comment-1/feedback?tokens={first_token}")).await;
+        assert_eq!(thread.len(), 1);
+        assert_eq!(thread[0]["id"], first);
+    }
+}
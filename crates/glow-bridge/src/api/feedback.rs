@@ -2,26 +2,46 @@
 
 use axum::{
     Json, Router,
-    extract::{Path, State, WebSocketUpgrade},
-    response::IntoResponse,
+    extract::{DefaultBodyLimit, Path, Query, State, WebSocketUpgrade},
+    response::{
+        IntoResponse,
+        sse::{Event, KeepAlive, Sse},
+    },
     routing::{delete, get, post},
 };
+use futures::{Stream, StreamExt};
 use glow_executors::{
-    DocumentAgent, FeedbackRequest, FeedbackResponse, FeedbackStatus, StreamMessage,
-    executors::ClaudeCode,
+    BaseDocumentAgent, ContextWindow, DocumentAgent, ExecutorError, FallbackChoice,
+    FeedbackRequest, FeedbackResponse, FeedbackStatus, NormalizedEntry, NormalizedEntryType,
+    PromptTemplate, StreamControl, StreamMessage, estimate_tokens,
+    executors::{ClaudeCode, claude::ClaudeLogProcessor},
 };
-use serde::Deserialize;
-use tracing::{error, info};
+use serde::{Deserialize, Serialize};
+use tokio_util::sync::CancellationToken;
+use tower_http::limit::RequestBodyLimitLayer;
+use tracing::{error, info, warn};
 
-use crate::state::{AppState, SessionState};
+use crate::audit::SpawnAudit;
+use crate::lines::BoundedLines;
+use crate::state::{AppState, SessionExecutor, SessionState};
+use crate::transcript;
+use crate::usage::UsageRecord;
 
 /// Build the feedback router.
-pub fn router() -> Router<AppState> {
+///
+/// Request bodies larger than `max_body_size` bytes are rejected with 413.
+pub fn router(max_body_size: usize) -> Router<AppState> {
     Router::new()
         .route("/", post(create_feedback))
+        .route("/validate", post(validate_feedback))
         .route("/{id}", get(get_feedback))
         .route("/{id}", delete(cancel_feedback))
+        .route("/{id}/log", get(get_feedback_log))
+        .route("/{id}/transcript.html", get(get_feedback_transcript))
         .route("/{id}/ws", get(feedback_websocket))
+        .route("/{id}/sse", get(feedback_sse))
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_body_size))
 }
 
 /// Request body for creating feedback.
@@ -44,13 +64,229 @@ pub struct CreateFeedbackRequest {
     pub comment_id: String,
     /// Session ID for follow-ups.
     pub session_id: Option<String>,
+    /// Whether to stream the model's thinking (defaults to true).
+    pub include_thinking: Option<bool>,
+    /// Dispatch priority when queued behind other requests; higher runs first.
+    #[serde(default)]
+    pub priority: u8,
+    /// Model to use, overriding the bridge default.
+    pub model: Option<String>,
+    /// Whether the executor may suggest edits or only analyze.
+    #[serde(default)]
+    pub mode: FeedbackMode,
+    /// System prompt replacing the built-in one, such as a copyeditor or fact-checker persona.
+    pub system_prompt: Option<String>,
+    /// Cost in USD past which the session is aborted; can only lower the bridge ceiling.
+    pub max_cost_usd: Option<f64>,
+    /// Tags of the comment, used to route the request to an executor profile.
+    #[serde(default)]
+    pub tags: Vec<String>,
+}
+
+impl CreateFeedbackRequest {
+    /// The executor-facing request, without the bridge's own options.
+    fn into_feedback_request(self) -> FeedbackRequest {
+        FeedbackRequest {
+            document_id: self.document_id,
+            document_content: self.document_content,
+            document_title: self.document_title,
+            selected_text: self.selected_text,
+            selected_range: glow_executors::TextRange {
+                from: 0,
+                to: 0,
+                quoted_text: String::new(),
+            },
+            instruction: self.instruction,
+            executor: self.executor,
+            comment_id: self.comment_id,
+            session_id: self.session_id,
+        }
+    }
+}
+
+/// How a feedback session treats the document.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FeedbackMode {
+    /// Review the document and suggest edits.
+    #[default]
+    Review,
+    /// Analyze the document read-only, without suggesting edits.
+    Plan,
+    /// Answer a question about the document read-only, without suggesting edits.
+    Question,
+}
+
+/// Check a request's system prompt override against the bridge policy.
+///
+/// Rejects overrides with 403 when they are disabled and with 400 when they
+/// exceed the length cap.
+fn check_system_prompt(
+    state: &AppState,
+    req: &CreateFeedbackRequest,
+) -> Result<(), axum::http::StatusCode> {
+    let Some(system_prompt) = &req.system_prompt else {
+        return Ok(());
+    };
+    if !state.allow_system_prompt_override {
+        warn!(comment_id = %req.comment_id, "Rejected system prompt override: overrides are disabled");
+        return Err(axum::http::StatusCode::FORBIDDEN);
+    }
+    let chars = system_prompt.chars().count();
+    if chars > state.max_system_prompt_chars {
+        warn!(
+            comment_id = %req.comment_id,
+            chars,
+            max = state.max_system_prompt_chars,
+            "Rejected system prompt override: too long"
+        );
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+    Ok(())
+}
+
+/// Select the executor for a request.
+///
+/// Registered custom executors take precedence over built-ins; anything else
+/// runs Claude Code with the request's model, or the bridge default. Plan mode
+/// runs Claude Code read-only with a prompt that rules out suggesting edits,
+/// and question mode runs it read-only with a prompt that never mentions them.
+/// A system prompt in the request replaces the built-in one for any mode.
+/// Built-in requests start from the profile their instruction or comment tags
+/// route to, unless a `fallback` from the fallback chain stands in for an
+/// unavailable executor. A model in the request overrides the profile's.
+fn select_executor(
+    state: &AppState,
+    req: &CreateFeedbackRequest,
+    fallback: Option<FallbackChoice>,
+) -> SessionExecutor {
+    if let Some(custom) = state.executor_registry.resolve(&req.executor) {
+        return SessionExecutor::custom(&req.executor, custom);
+    }
+    let base = fallback
+        .filter(FallbackChoice::is_substitute)
+        .map(|choice| DocumentAgent::from_base(choice.executor))
+        .or_else(|| routed_agent(state, req))
+        .unwrap_or_else(|| DocumentAgent::from_base(BaseDocumentAgent::ClaudeCode));
+    let DocumentAgent::ClaudeCode(base) = base;
+
+    // Unknown executor names default to Claude Code as well
+    let (plan, system_prompt) = match req.mode {
+        FeedbackMode::Review => (None, ClaudeCode::document_feedback_system_prompt()),
+        FeedbackMode::Plan => (Some(true), ClaudeCode::plan_feedback_system_prompt()),
+        FeedbackMode::Question => (Some(true), ClaudeCode::document_review_system_prompt()),
+    };
+    let system_prompt =
+        req.system_prompt.clone().filter(|p| !p.trim().is_empty()).unwrap_or(system_prompt);
+    let claude = ClaudeCode {
+        plan,
+        system_prompt: Some(system_prompt),
+        model: req.model.clone().or(base.model).or_else(|| state.default_model.clone()),
+        ..base
+    };
+    SessionExecutor::Agent(DocumentAgent::ClaudeCode(claude))
+}
+
+/// The configured agent of the profile a request routes to, if any.
+fn routed_agent(state: &AppState, req: &CreateFeedbackRequest) -> Option<DocumentAgent> {
+    let configs = &state.executor_configs;
+    let profile = configs.routing.route(&req.instruction, &req.tags)?;
+    let agent = configs.get_agent(profile).cloned();
+    let (executor, variant) = (&profile.executor, profile.variant_name());
+    if agent.is_some() {
+        info!(%executor, variant, "Routing feedback to profile");
+    } else {
+        warn!(%executor, variant, "Routed profile is not configured");
+    }
+    agent
+}
+
+/// Walk the configured fallback chain to find an available built-in executor.
+///
+/// Returns `None` for registered custom executors or when no chain is configured.
+async fn select_fallback(
+    state: &AppState,
+    req: &CreateFeedbackRequest,
+) -> Result<Option<FallbackChoice>, ExecutorError> {
+    use glow_executors::StandardDocumentExecutor;
+
+    if state.executor_registry.contains(&req.executor) {
+        return Ok(None);
+    }
+    // Availability checks run the executor's CLI, so keep them off the runtime
+    let configs = std::sync::Arc::clone(&state.executor_configs);
+    tokio::task::spawn_blocking(move || {
+        configs.select_available(|agent| DocumentAgent::from_base(agent).get_availability_info())
+    })
+    .await
+    .unwrap_or_else(|e| Err(ExecutorError::NotAvailable(e.to_string())))
+}
+
+/// How a feedback request would run, reported without running it.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ValidateFeedbackResponse {
+    /// Prompt the executor would receive.
+    pub prompt: String,
+    /// System prompt the executor would run with, if any.
+    pub system_prompt: Option<String>,
+    /// Estimated input tokens: the system prompt, the prompt and the document content.
+    pub estimated_tokens: u32,
+    /// Most input tokens the model accepts.
+    pub max_input_tokens: u32,
+    /// Whether the estimate is over the model's input limit.
+    pub exceeds_limit: bool,
+    /// Whether the document content would be truncated.
+    pub content_truncated: bool,
+    /// Executor that would run the request.
+    pub executor: String,
+    /// Model the executor would use, or `None` for its default.
+    pub model: Option<String>,
+}
+
+/// Assemble a feedback request's prompt and check it fits the model, without
+/// spawning an executor.
+///
+/// Takes the same body as creating feedback. The fallback chain is not
+/// walked, since checking availability runs the executors.
+async fn validate_feedback(
+    State(state): State<AppState>,
+    Json(req): Json<CreateFeedbackRequest>,
+) -> Result<Json<ValidateFeedbackResponse>, axum::http::StatusCode> {
+    check_system_prompt(&state, &req)?;
+    let (executor, model, system_prompt) = match select_executor(&state, &req, None) {
+        SessionExecutor::Agent(agent) => {
+            let DocumentAgent::ClaudeCode(claude) = &agent;
+            (agent.base_agent().to_string(), claude.model.clone(), claude.system_prompt.clone())
+        }
+        SessionExecutor::Custom { name, .. } => (name, None, None),
+    };
+
+    let request = req.into_feedback_request();
+    let (doc_context, prompt) = assemble_prompt(&state, &request, &std::env::temp_dir());
+    let parts =
+        [system_prompt.as_deref().unwrap_or_default(), &prompt, &doc_context.document_content];
+    let estimated_tokens = parts.into_iter().map(estimate_tokens).fold(0, u32::saturating_add);
+    let limit =
+        model.as_deref().map_or(state.model_limits.fallback, |m| state.model_limits.limit_for(m));
+
+    Ok(Json(ValidateFeedbackResponse {
+        prompt,
+        system_prompt,
+        estimated_tokens,
+        max_input_tokens: limit.max_input_tokens,
+        exceeds_limit: estimated_tokens > limit.max_input_tokens,
+        content_truncated: doc_context.was_truncated,
+        executor,
+        model,
+    }))
 }
 
 /// Create a new feedback request.
 async fn create_feedback(
     State(state): State<AppState>,
     Json(req): Json<CreateFeedbackRequest>,
-) -> Json<FeedbackResponse> {
+) -> Result<Json<FeedbackResponse>, axum::http::StatusCode> {
     info!(
         document_id = %req.document_id,
         comment_id = %req.comment_id,
@@ -58,215 +294,596 @@ async fn create_feedback(
         "Creating feedback request"
     );
 
-    // Select executor based on request
-    let executor = match req.executor.to_lowercase().as_str() {
-        "claude" | "claude_code" | "claudecode" => {
-            let mut claude = ClaudeCode::default();
+    check_system_prompt(&state, &req)?;
+    if req.max_cost_usd.is_some_and(|max| !max.is_finite() || max < 0.0) {
+        warn!(comment_id = %req.comment_id, max_cost_usd = ?req.max_cost_usd, "Rejected invalid cost ceiling");
+        return Err(axum::http::StatusCode::BAD_REQUEST);
+    }
+    let fallback = select_fallback(&state, &req).await;
+    let choice = fallback.as_ref().ok().copied().flatten();
+    if let Some(choice) = choice.filter(FallbackChoice::is_substitute) {
+        warn!(executor = %choice.executor, "Requested executor unavailable, using fallback");
+    }
+    let executor = select_executor(&state, &req, choice);
 
-            // Set document feedback system prompt
-            claude.system_prompt = Some(ClaudeCode::document_feedback_system_prompt());
+    // Create session
+    let session = state
+        .create_session(
+            req.comment_id.clone(),
+            req.document_id.clone(),
+            executor,
+            req.include_thinking.unwrap_or(true),
+        )
+        .await;
+    {
+        let mut s = session.write().await;
+        s.priority = req.priority;
+        s.max_cost_usd = match (req.max_cost_usd, state.max_cost_usd) {
+            (Some(requested), Some(configured)) => Some(requested.min(configured)),
+            (requested, configured) => requested.or(configured),
+        };
+    }
 
-            DocumentAgent::ClaudeCode(claude)
-        }
-        _ => {
-            // Default to Claude Code
-            let mut claude = ClaudeCode::default();
-            claude.system_prompt = Some(ClaudeCode::document_feedback_system_prompt());
-            DocumentAgent::ClaudeCode(claude)
-        }
+    let (session_id, stream_token) = {
+        let s = session.read().await;
+        (s.id.clone(), s.stream_token.clone())
     };
 
-    // Create session
-    let session =
-        state.create_session(req.comment_id.clone(), req.document_id.clone(), executor).await;
+    if let Err(e) = fallback {
+        error!(error = %e, "No executor available");
+        let message = e.to_string();
+        let msg_store = session.read().await.msg_store.clone();
+        msg_store.push_error(message.clone()).await;
+        session.write().await.fail(message.clone());
+        msg_store.finalize().await;
+        return Ok(Json(FeedbackResponse {
+            id: session_id.clone(),
+            status: FeedbackStatus::Failed,
+            content: None,
+            suggested_edits: vec![],
+            session_id,
+            error: Some(message),
+            language: None,
+            stream_token: Some(stream_token),
+            fallback_executor: None,
+        }));
+    }
 
-    let session_id = session.read().await.id.clone();
+    let fallback_executor =
+        choice.filter(FallbackChoice::is_substitute).map(|choice| choice.executor);
 
     // Spawn background task to run the executor
     let session_clone = session.clone();
-    let request = FeedbackRequest {
-        document_id: req.document_id,
-        document_content: req.document_content,
-        document_title: req.document_title,
-        selected_text: req.selected_text,
-        selected_range: glow_executors::TextRange { from: 0, to: 0, quoted_text: String::new() },
-        instruction: req.instruction,
-        executor: req.executor,
-        comment_id: req.comment_id,
-        session_id: req.session_id,
-    };
+    let msg_store = session.read().await.msg_store.clone();
+    let request = req.into_feedback_request();
 
     tokio::spawn(async move {
-        if let Err(e) = run_feedback_session(session_clone, request).await {
+        // Queue behind other sessions of the same executor type
+        let Some(_permit) = state.acquire_executor_permit(&session_clone).await else {
+            info!("Feedback session cancelled before starting");
+            return;
+        };
+
+        state.metrics.session_started();
+        if let Err(e) = run_feedback_session(&state, session_clone.clone(), request).await {
             error!(error = %e, "Feedback session failed");
         }
+        state.metrics.session_finished(session_clone.read().await.state);
+        // Follow-ups stream into a segment of their own
+        msg_store.finalize().await;
     });
 
-    Json(FeedbackResponse {
+    Ok(Json(FeedbackResponse {
         id: session_id.clone(),
         status: FeedbackStatus::Processing,
         content: None,
         suggested_edits: vec![],
         session_id,
         error: None,
-    })
+        language: None,
+        stream_token: Some(stream_token),
+        fallback_executor,
+    }))
 }
 
 /// Run the feedback session with the executor.
 async fn run_feedback_session(
+    state: &AppState,
     session: std::sync::Arc<tokio::sync::RwLock<crate::state::FeedbackSession>>,
     request: FeedbackRequest,
 ) -> anyhow::Result<()> {
-    use glow_executors::{DocumentContext, ExecutionEnv};
-
-    // Update state to running
-    {
-        let mut s = session.write().await;
-        s.state = SessionState::Running;
-    }
-
-    // Build the prompt
-    let prompt = build_feedback_prompt(&request);
+    use glow_executors::{ExecutionEnv, StandardDocumentExecutor};
 
     // Get working directory (use temp dir)
     let working_dir = std::env::temp_dir();
-
-    // Build execution environment
-    let mut doc_context = DocumentContext::new(&request.document_id, &request.document_content)
-        .with_working_dir(working_dir.clone());
-
-    if let Some(title) = &request.document_title {
-        doc_context.document_title = Some(title.clone());
-    }
+    let (doc_context, prompt) = assemble_prompt(state, &request, &working_dir);
 
     let env = ExecutionEnv::from_document(doc_context);
 
     // Get executor and spawn
-    let _executor = session.read().await.executor.clone();
+    let executor = session.read().await.executor.clone();
     let msg_store = session.read().await.msg_store.clone();
 
+    audit_spawn(state, &executor, &request.document_id, &prompt, &env);
+    let agent = match executor {
+        SessionExecutor::Agent(agent) => agent,
+        SessionExecutor::Custom { name, executor } => {
+            info!(executor = %name, prompt_len = prompt.len(), "Spawning custom executor");
+            let spawned = executor.spawn_review(&working_dir, &prompt, None, &env).await;
+            run_custom_executor(&session, executor.as_ref(), &working_dir, spawned).await;
+            return Ok(());
+        }
+    };
+
     info!(prompt_len = prompt.len(), "Spawning executor");
 
-    // Bypass executor spawn and use direct tokio::process::Command for debugging
-    use std::process::Stdio;
-    use tokio::process::Command;
-
-    let mut cmd = Command::new("npx");
-    cmd.arg("-y")
-        .arg("@anthropic-ai/claude-code@2.1.7")
-        .arg("--output-format=stream-json")
-        .arg("--verbose")
-        .arg("--include-partial-messages")
-        .arg("--permission-mode=bypassPermissions")
-        .arg("-p")
-        .arg(&prompt)
-        .current_dir(&working_dir)
-        .stdin(Stdio::null())
-        .stdout(Stdio::piped())
-        .stderr(Stdio::piped())
-        .kill_on_drop(true)
-        // CI=true disables interactive TTY requirements
-        .env("CI", "true");
-
-    env.apply_to_command(&mut cmd);
-
-    match cmd.spawn() {
-        Ok(mut child) => {
+    match agent.spawn(&working_dir, &prompt, &env).await {
+        Ok(mut spawned) => {
             info!("Executor spawned successfully, reading output...");
+            let child = spawned.child.inner();
+            // Close stdin so it is not awaited, unless the prompt is being written to it
+            drop(child.stdin.take());
+            run_claude_child(state, &session, child).await;
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to spawn executor");
+            session.write().await.fail(e.to_string());
+            msg_store.push_error(e.to_string()).await;
+        }
+    }
 
-            // With CI=true, Claude Code outputs stream-json to stdout
-            // Read stdout and process logs
-            if let Some(stdout) = child.stdout.take() {
-                use glow_executors::executors::claude::ClaudeLogProcessor;
-                use tokio::io::{AsyncBufReadExt, BufReader};
-
-                let mut processor = ClaudeLogProcessor::new(msg_store.clone());
-                let reader = BufReader::new(stdout);
-                let mut lines = reader.lines();
-
-                info!("Starting to read stdout (stream-json output)...");
-                let mut line_count = 0;
-                while let Ok(Some(line)) = lines.next_line().await {
-                    line_count += 1;
-                    if line_count <= 5 || line_count % 10 == 0 {
-                        info!(line_num = line_count, line_len = line.len(), "Read stdout line");
-                    }
-                    processor.process_chunk(&line).await;
-                    processor.process_chunk("\n").await;
-                }
-                info!(total_lines = line_count, "Finished reading stdout");
+    Ok(())
+}
+
+/// Record an executor spawn in the audit log, if one is configured.
+fn audit_spawn(
+    state: &AppState,
+    executor: &SessionExecutor,
+    document_id: &str,
+    prompt: &str,
+    env: &glow_executors::ExecutionEnv,
+) {
+    let Some(audit_log) = &state.audit_log else {
+        return;
+    };
+    let name = executor.name();
+    let model = match executor {
+        SessionExecutor::Agent(DocumentAgent::ClaudeCode(claude)) => claude.model.as_deref(),
+        SessionExecutor::Custom { .. } => None,
+    };
+    let spawn = SpawnAudit { executor: &name, model, document_id, prompt, env };
+    if let Err(e) = audit_log.record(&spawn) {
+        warn!(error = %e, "Failed to write audit record");
+    }
+}
 
-                processor.flush().await;
-            } else {
-                error!("No stdout available from child process");
+/// Stream a Claude Code process's output into the session and record how it ended.
+async fn run_claude_child(
+    state: &AppState,
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+    child: &mut tokio::process::Child,
+) {
+    let (msg_store, cancel, claude, max_cost_usd) = {
+        let s = session.read().await;
+        let claude = match &s.executor {
+            SessionExecutor::Agent(DocumentAgent::ClaudeCode(claude)) => claude.clone(),
+            SessionExecutor::Custom { .. } => ClaudeCode::default(),
+        };
+        (s.msg_store.clone(), s.cancel.clone(), claude, s.max_cost_usd)
+    };
+    let mut processor = ClaudeLogProcessor::new(msg_store.clone())
+        .with_strip_markdown(claude.strip_markdown.unwrap_or(false))
+        .with_language_detection(claude.detect_language.unwrap_or(false))
+        .with_verbosity(claude.verbosity.unwrap_or_default())
+        .with_max_cost_usd(max_cost_usd);
+
+    let limits = ExecutorLimits::from_state(state);
+    let outcome = drive_executor(child, &mut processor, &msg_store, &cancel, limits).await;
+    state.metrics.record_tokens(processor.total_tokens());
+    record_usage(state, session, &processor).await;
+    // The reported cost only arrives once the run ends, too late to stop it
+    if let Some(max) = max_cost_usd.filter(|max| processor.total_cost_usd() > *max) {
+        warn!(spent = processor.total_cost_usd(), max, "Executor finished over its cost ceiling");
+    }
+    if let Some(agent_session_id) = processor.session_id() {
+        session.write().await.agent_session_id = Some(agent_session_id.to_owned());
+    }
+    if let Some(language) = processor.language() {
+        session.write().await.language = Some(language);
+    }
+
+    let status = match outcome {
+        ExecutorOutcome::Exited(status) => status,
+        ExecutorOutcome::Cancelled => {
+            info!("Feedback session cancelled, killing executor");
+            if let Err(e) = child.kill().await {
+                error!(error = %e, "Failed to kill cancelled executor");
             }
+            return;
+        }
+        ExecutorOutcome::IdleTimeout => {
+            warn!(idle_timeout = ?limits.idle_timeout, "Executor produced no output, killing it");
+            if let Err(e) = child.kill().await {
+                error!(error = %e, "Failed to kill idle executor");
+            }
+            let message = format!(
+                "{}: no output for {}s",
+                ExecutorError::Timeout,
+                limits.idle_timeout.unwrap_or_default().as_secs()
+            );
+            msg_store.push_error(message.clone()).await;
+            session.write().await.fail(message);
+            return;
+        }
+        ExecutorOutcome::CostLimit => {
+            let spent = processor.estimated_cost_usd();
+            let max = max_cost_usd.unwrap_or_default();
+            warn!(spent, max, "Executor's estimated cost passed its ceiling, killing it");
+            if let Err(e) = child.kill().await {
+                error!(error = %e, "Failed to kill executor over its cost ceiling");
+            }
+            let message = format!("cost limit exceeded: estimated ${spent:.4} of ${max:.4}");
+            msg_store.push_error(message.clone()).await;
+            session.write().await.fail(message);
+            return;
+        }
+    };
+    info!(status = ?status, "Executor process completed");
 
-            // Drain stderr (error messages go here)
-            if let Some(stderr) = child.stderr.take() {
-                use tokio::io::{AsyncBufReadExt, BufReader};
-                let reader = BufReader::new(stderr);
-                let mut lines = reader.lines();
-                while let Ok(Some(line)) = lines.next_line().await {
-                    if !line.is_empty() {
-                        error!(stderr_line = %line, "Claude Code stderr");
-                    }
+    finish_session(session, status).await;
+}
+
+/// Add the cost and tokens of a finished run to the usage ledger.
+async fn record_usage(
+    state: &AppState,
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+    processor: &ClaudeLogProcessor,
+) {
+    let record = {
+        let s = session.read().await;
+        UsageRecord {
+            session_id: s.id.clone(),
+            executor: s.executor.name(),
+            session_created_at: s.created_at,
+            cost_usd: processor.total_cost_usd(),
+            tokens: processor.total_tokens(),
+        }
+    };
+    if let Err(e) = state.usage.record(record) {
+        warn!(error = %e, "Failed to write usage record");
+    }
+}
+
+/// Record how an executor process exited: completed on success, otherwise
+/// failed with the exit status.
+async fn finish_session(
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+    status: std::io::Result<std::process::ExitStatus>,
+) {
+    let error = match status {
+        Ok(status) if status.success() => None,
+        Ok(status) => Some(ExecutorError::ProcessFailed(status.to_string())),
+        Err(e) => Some(ExecutorError::ProcessFailed(e.to_string())),
+    };
+    let mut s = session.write().await;
+    match error {
+        None => s.finish(SessionState::Completed),
+        Some(e) => s.fail(e.to_string()),
+    }
+}
+
+/// Continue a session with a follow-up instruction, streaming into its message store.
+///
+/// Resumes the executor's own session, so the session must have reported one.
+async fn run_follow_up(
+    state: &AppState,
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+    instruction: &str,
+) {
+    use glow_executors::{DocumentContext, ExecutionEnv, StandardDocumentExecutor};
+
+    let (executor, agent_session_id, document_id, msg_store) = {
+        let s = session.read().await;
+        (s.executor.clone(), s.agent_session_id.clone(), s.document_id.clone(), s.msg_store.clone())
+    };
+    let Some(agent_session_id) = agent_session_id else {
+        let message = "session cannot be resumed: no executor session ID";
+        msg_store.push_error(message).await;
+        session.write().await.fail(message);
+        return;
+    };
+
+    // The resumed session already has the document in context
+    let working_dir = std::env::temp_dir();
+    let env = ExecutionEnv::from_document(
+        DocumentContext::new(document_id.clone(), String::new())
+            .with_working_dir(working_dir.clone()),
+    );
+
+    info!(agent_session_id = %agent_session_id, "Spawning follow-up");
+    audit_spawn(state, &executor, &document_id, instruction, &env);
+    match executor {
+        SessionExecutor::Agent(agent) => {
+            match agent.spawn_follow_up(&working_dir, instruction, &agent_session_id, &env).await {
+                Ok(mut spawned) => {
+                    let child = spawned.child.inner();
+                    // Close stdin so it is not awaited, unless the prompt is being written to it
+                    drop(child.stdin.take());
+                    run_claude_child(state, session, child).await;
+                }
+                Err(e) => {
+                    error!(error = %e, "Failed to spawn follow-up");
+                    msg_store.push_error(e.to_string()).await;
+                    session.write().await.fail(e.to_string());
                 }
             }
+        }
+        SessionExecutor::Custom { executor, .. } => {
+            let spawned =
+                executor.spawn_follow_up(&working_dir, instruction, &agent_session_id, &env).await;
+            run_custom_executor(session, executor.as_ref(), &working_dir, spawned).await;
+        }
+    }
+}
 
-            // Wait for process to complete
-            let status: Result<std::process::ExitStatus, std::io::Error> = child.wait().await;
-            info!(status = ?status, "Executor process completed");
+/// How a Claude Code process run ended.
+enum ExecutorOutcome {
+    /// The process exited on its own.
+    Exited(std::io::Result<std::process::ExitStatus>),
+    /// The session was cancelled.
+    Cancelled,
+    /// No log message was pushed within the idle timeout.
+    IdleTimeout,
+    /// The estimated cost of the running process passed the session's ceiling.
+    CostLimit,
+}
 
-            // Update session state
-            let mut s = session.write().await;
-            s.state = if status.map(|st| st.success()).unwrap_or(false) {
-                SessionState::Completed
-            } else {
-                SessionState::Failed
-            };
+/// Bounds on how a Claude Code process's output is read.
+#[derive(Debug, Clone, Copy)]
+struct ExecutorLimits {
+    /// How long the process may go without output, or `None` to wait indefinitely.
+    idle_timeout: Option<std::time::Duration>,
+    /// Longest output line kept, in bytes.
+    max_line_bytes: usize,
+}
+
+impl ExecutorLimits {
+    const fn from_state(state: &AppState) -> Self {
+        Self { idle_timeout: state.idle_timeout, max_line_bytes: state.max_line_bytes }
+    }
+}
+
+/// Read a Claude Code process's output and wait for it to exit.
+///
+/// Gives up if the session is cancelled or `msg_store` sees no new message
+/// within the idle timeout, or stops reading once the processor estimates the
+/// cost ceiling was passed. Output lines past the length limit are truncated.
+/// The caller is responsible for killing the process.
+async fn drive_executor(
+    child: &mut tokio::process::Child,
+    processor: &mut ClaudeLogProcessor,
+    msg_store: &glow_executors::MsgStore,
+    cancel: &CancellationToken,
+    limits: ExecutorLimits,
+) -> ExecutorOutcome {
+    let ExecutorLimits { idle_timeout, max_line_bytes } = limits;
+    let run = async {
+        // With CI=true, Claude Code outputs stream-json to stdout
+        if let Some(stdout) = child.stdout.take() {
+            read_executor_stdout(stdout, processor, cancel, max_line_bytes).await;
+        } else {
+            error!("No stdout available from child process");
+        }
+        if processor.cost_limit_exceeded() {
+            return ExecutorOutcome::CostLimit;
         }
-        Err(e) => {
-            error!(error = %e, "Failed to spawn executor");
 
-            // Update session state
-            let mut s = session.write().await;
-            s.state = SessionState::Failed;
+        // Drain stderr (error messages go here)
+        if let Some(stderr) = child.stderr.take() {
+            let mut lines = BoundedLines::new(tokio::io::BufReader::new(stderr), max_line_bytes);
+            while let Some(Ok(Some(line))) = cancel.run_until_cancelled(lines.next_line()).await {
+                if !line.text.is_empty() {
+                    error!(stderr_line = %line.text, "Claude Code stderr");
+                }
+            }
+        }
+
+        let status = cancel.run_until_cancelled(child.wait()).await;
+        status.map_or(ExecutorOutcome::Cancelled, ExecutorOutcome::Exited)
+    };
+    let idle = async {
+        match idle_timeout {
+            Some(window) => msg_store.idle_for(window).await,
+            None => std::future::pending().await,
+        }
+    };
+
+    tokio::select! {
+        outcome = run => outcome,
+        () = idle => ExecutorOutcome::IdleTimeout,
+    }
+}
+
+/// Feed executor stdout through the Claude log processor until EOF, cancellation,
+/// or the processor estimates its cost ceiling was passed.
+///
+/// Lines longer than `max_line_bytes` are truncated, so they fail to parse and
+/// reach the message store as raw output instead of stopping the read.
+///
+/// Returns the number of lines read.
+async fn read_executor_stdout(
+    stdout: impl tokio::io::AsyncRead + Unpin,
+    processor: &mut ClaudeLogProcessor,
+    cancel: &CancellationToken,
+    max_line_bytes: usize,
+) -> usize {
+    let mut lines = BoundedLines::new(tokio::io::BufReader::new(stdout), max_line_bytes);
+
+    info!("Starting to read stdout (stream-json output)...");
+    let mut line_count = 0;
+    while let Some(Ok(Some(line))) = cancel.run_until_cancelled(lines.next_line()).await {
+        line_count += 1;
+        if line.truncated_bytes > 0 {
+            warn!(
+                line_num = line_count,
+                truncated_bytes = line.truncated_bytes,
+                max_line_bytes,
+                "Truncated oversized stdout line"
+            );
+        }
+        if line_count <= 5 || line_count % 10 == 0 {
+            info!(line_num = line_count, line_len = line.text.len(), "Read stdout line");
+        }
+        processor.process_chunk(&line.text).await;
+        processor.process_chunk("\n").await;
+        if processor.cost_limit_exceeded() {
+            break;
+        }
+    }
+    info!(total_lines = line_count, cancelled = cancel.is_cancelled(), "Finished reading stdout");
 
-            // Push error to msg store
+    processor.flush().await;
+    line_count
+}
+
+/// Run a spawned registry-provided executor to completion and record how it ended.
+async fn run_custom_executor(
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+    executor: &(dyn glow_executors::StandardDocumentExecutor + Send + Sync),
+    working_dir: &std::path::Path,
+    spawned: Result<glow_executors::executors::SpawnedChild, ExecutorError>,
+) {
+    let (msg_store, cancel) = {
+        let s = session.read().await;
+        (s.msg_store.clone(), s.cancel.clone())
+    };
+
+    match spawned {
+        Ok(mut spawned) => {
+            executor.normalize_logs(msg_store.clone(), working_dir);
+            let Some(status) = cancel.run_until_cancelled(spawned.child.wait()).await else {
+                info!("Feedback session cancelled, killing custom executor");
+                if let Err(e) = spawned.child.kill().await {
+                    error!(error = %e, "Failed to kill cancelled executor");
+                }
+                return;
+            };
+            // Output may still be draining into the store after the process exits
+            if let Some(exit_signal) = spawned.exit_signal.as_mut()
+                && let Some(Some(Err(e))) = cancel.run_until_cancelled(exit_signal.recv()).await
+            {
+                warn!(error = %e, "Custom executor output ended with an error");
+            }
+            info!(status = ?status, "Custom executor completed");
+            finish_session(session, status).await;
+        }
+        Err(e) => {
+            error!(error = %e, "Failed to spawn custom executor");
             msg_store.push_error(e.to_string()).await;
+            session.write().await.fail(e.to_string());
         }
     }
+}
 
-    Ok(())
+/// Build the document context and the prompt for a request.
+///
+/// Document content past the bridge's size limit is truncated.
+fn assemble_prompt(
+    state: &AppState,
+    request: &FeedbackRequest,
+    working_dir: &std::path::Path,
+) -> (glow_executors::DocumentContext, String) {
+    let mut doc_context =
+        glow_executors::DocumentContext::new(&request.document_id, &request.document_content)
+            .with_working_dir(working_dir);
+
+    if let Some(title) = &request.document_title {
+        doc_context.document_title = Some(title.clone());
+    }
+    if let Some(max_chars) = state.max_content_chars {
+        doc_context = doc_context.with_max_content_chars(max_chars);
+    }
+
+    let prompt = build_feedback_prompt(
+        &state.prompt_template,
+        request,
+        &doc_context,
+        state.prompt_context_chars,
+    );
+    (doc_context, prompt)
 }
 
 /// Build the prompt for feedback.
-fn build_feedback_prompt(request: &FeedbackRequest) -> String {
-    format!(
-        r#"DOCUMENT CONTEXT:
-Title: {}
-Document ID: {}
+///
+/// With `context_chars` and a template using `{context}`, the text around the
+/// selection is included; a selection inside it is marked there rather than
+/// repeated. Warns the model when the document content was truncated.
+fn build_feedback_prompt(
+    template: &PromptTemplate,
+    request: &FeedbackRequest,
+    doc_context: &glow_executors::DocumentContext,
+    context_chars: Option<usize>,
+) -> String {
+    // A template without the context would lose a selection marked only there
+    let context_chars = context_chars.filter(|_| template.as_str().contains("{context}"));
+    let window = context_chars.map(|radius| {
+        ContextWindow::around(&doc_context.document_content, &request.selected_text, radius)
+    });
+    let context = window
+        .as_ref()
+        .map(|window| format!("\nSURROUNDING TEXT:\n{}\n", window.text))
+        .unwrap_or_default();
+    let selected_text = match &window {
+        Some(window) if window.contains_selection => SELECTION_IN_CONTEXT,
+        _ => request.selected_text.as_str(),
+    };
+    let vars = std::collections::HashMap::from([
+        ("title", request.document_title.as_deref().unwrap_or("Untitled")),
+        ("document_id", request.document_id.as_str()),
+        ("selected_text", selected_text),
+        ("instruction", request.instruction.as_str()),
+        ("context", context.as_str()),
+    ]);
+    let mut prompt = template.render(&vars);
+    if doc_context.was_truncated {
+        prompt.push_str(TRUNCATION_NOTICE);
+    }
+    prompt
+}
 
-SELECTED TEXT:
-{}
+/// Stands in for selected text already marked in the surrounding text.
+const SELECTION_IN_CONTEXT: &str = "(marked with <selection> tags in the surrounding text above)";
 
-USER INSTRUCTION:
-{}
+/// Appended to the prompt when the document content was truncated.
+const TRUNCATION_NOTICE: &str = "\n\nNOTE: The document is too long and its content was \
+truncated. Parts of the document are missing, so avoid conclusions about the missing parts.";
 
-Please analyze the selected text according to the user's instruction and provide helpful feedback. If you have specific text improvements to suggest, use the suggest_edit tool."#,
-        request.document_title.as_deref().unwrap_or("Untitled"),
-        request.document_id,
-        request.selected_text,
-        request.instruction
-    )
+/// Look up a session, refusing requests without its stream token with 403.
+///
+/// Knowing a session ID alone does not expose its output.
+pub async fn authorized_session(
+    state: &AppState,
+    id: &str,
+    token: Option<&str>,
+) -> Result<
+    std::sync::Arc<tokio::sync::RwLock<crate::state::FeedbackSession>>,
+    axum::http::StatusCode,
+> {
+    let session = state.get_session(id).await.ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    if !session.read().await.accepts_stream_token(token) {
+        warn!(session_id = %id, "Rejected feedback request with a missing or wrong stream token");
+        return Err(axum::http::StatusCode::FORBIDDEN);
+    }
+    Ok(session)
 }
 
 /// Get feedback status.
 async fn get_feedback(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
 ) -> Result<Json<FeedbackResponse>, axum::http::StatusCode> {
-    let session = state.get_session(&id).await.ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
 
     let s = session.read().await;
 
@@ -284,7 +901,7 @@ async fn get_feedback(
         .iter()
         .filter_map(|msg| {
             if let glow_executors::LogMsg::Entry(entry) = msg {
-                if entry.entry_type == glow_executors::NormalizedEntryType::AssistantMessage {
+                if entry.entry_type == NormalizedEntryType::AssistantMessage {
                     return Some(entry.content.clone());
                 }
             }
@@ -299,52 +916,284 @@ async fn get_feedback(
         content: if content.is_empty() { None } else { Some(content) },
         suggested_edits: vec![],
         session_id: s.id.clone(),
-        error: None,
+        error: s.last_error.clone(),
+        language: s.language.clone(),
+        stream_token: None,
+        fallback_executor: None,
     }))
 }
 
+/// Query parameters for fetching a session log.
+#[derive(Debug, Default, Deserialize)]
+pub struct LogQuery {
+    /// Comma-separated entry types to include, such as `tool_call,thinking_message`.
+    /// All entries are returned when absent.
+    pub types: Option<String>,
+    /// The `streamToken` returned when the session was created.
+    pub token: Option<String>,
+}
+
+/// Get every normalized log entry of a session, in order.
+///
+/// Unlike [`get_feedback`], this keeps tool calls, thinking and suggested
+/// edits, for debugging and audit views. Unknown entry types in the filter
+/// are rejected with 400, and requests without the stream token with 403.
+async fn get_feedback_log(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<LogQuery>,
+) -> Result<Json<Vec<NormalizedEntry>>, axum::http::StatusCode> {
+    let types = query
+        .types
+        .as_deref()
+        .map(|types| {
+            types
+                .split(',')
+                .map(str::trim)
+                .filter(|t| !t.is_empty())
+                .map(|t| serde_json::from_value::<NormalizedEntryType>(t.into()))
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .transpose()
+        .map_err(|_| axum::http::StatusCode::BAD_REQUEST)?;
+
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+
+    let entries = logged_entries(&session)
+        .await
+        .into_iter()
+        .filter(|entry| types.as_ref().is_none_or(|types| types.contains(&entry.entry_type)))
+        .collect();
+    Ok(Json(entries))
+}
+
+/// Render a session as a self-contained HTML page, for sharing outside Glow.
+///
+/// The page is served with a restrictive `Content-Security-Policy`, so
+/// nothing in it can run scripts on the bridge's origin. Like the session's
+/// stream, it requires the stream token.
+async fn get_feedback_transcript(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
+) -> Result<impl IntoResponse, axum::http::StatusCode> {
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+    let document_id = session.read().await.document_id.clone();
+
+    let entries = logged_entries(&session).await;
+    let heading = format!("Feedback on document {document_id}");
+    Ok((
+        [(axum::http::header::CONTENT_SECURITY_POLICY, transcript::CONTENT_SECURITY_POLICY)],
+        axum::response::Html(transcript::render_html(&heading, &entries)),
+    ))
+}
+
+/// Every normalized entry in a session's log, across all its runs, in order.
+async fn logged_entries(
+    session: &tokio::sync::RwLock<crate::state::FeedbackSession>,
+) -> Vec<NormalizedEntry> {
+    let stores: Vec<_> = session.read().await.stores().cloned().collect();
+    let mut entries = Vec::new();
+    for msg_store in stores {
+        entries.extend(msg_store.get_history().await.into_iter().filter_map(|msg| match msg {
+            glow_executors::LogMsg::Entry(entry) => Some(entry),
+            _ => None,
+        }));
+    }
+    entries
+}
+
 /// Cancel a feedback request.
+///
+/// Like reading the session, cancelling it requires the stream token.
 async fn cancel_feedback(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
 ) -> Result<Json<serde_json::Value>, axum::http::StatusCode> {
-    let session = state.get_session(&id).await.ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
 
-    {
+    let msg_store = {
         let mut s = session.write().await;
-        s.state = SessionState::Cancelled;
-    }
+        if matches!(s.state, SessionState::Pending | SessionState::Running) {
+            state.metrics.session_cancelled();
+        }
+        s.finish(SessionState::Cancelled);
+        // Stops output reading, the process wait and queueing for a slot
+        s.cancel.cancel();
+        s.msg_store.clone()
+    };
 
-    // TODO: Actually interrupt the executor process
+    // Output flushed by the stopping executor must not follow the end of the stream
+    msg_store.finalize().await;
 
     Ok(Json(serde_json::json!({ "cancelled": true })))
 }
 
+/// Query parameters for reading a feedback session or opening its stream.
+#[derive(Debug, Default, Deserialize)]
+pub struct StreamQuery {
+    /// The `streamToken` returned when the session was created.
+    pub token: Option<String>,
+}
+
+/// Why the server closed a feedback stream, sent as the WebSocket close code.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamClose {
+    /// The session finished and has nothing more to stream.
+    Completed,
+    /// The session was cancelled.
+    Cancelled,
+    /// The session failed; the close reason carries its error.
+    Failed,
+    /// The session's connections were revoked by an administrator.
+    Revoked,
+}
+
+/// Longest close reason allowed by the WebSocket protocol, in bytes.
+const MAX_CLOSE_REASON_BYTES: usize = 123;
+
+impl StreamClose {
+    /// Close code sent to the client.
+    #[must_use]
+    pub const fn code(self) -> u16 {
+        match self {
+            Self::Completed => 1000,
+            Self::Cancelled => 4000,
+            Self::Failed => 1011,
+            Self::Revoked => 4001,
+        }
+    }
+
+    /// Default close reason sent to the client.
+    #[must_use]
+    pub const fn reason(self) -> &'static str {
+        match self {
+            Self::Completed => "completed",
+            Self::Cancelled => "cancelled",
+            Self::Failed => "failed",
+            Self::Revoked => "revoked",
+        }
+    }
+
+    /// How a stream ends for a session in `state`.
+    #[must_use]
+    pub const fn for_state(state: SessionState) -> Self {
+        match state {
+            SessionState::Cancelled => Self::Cancelled,
+            SessionState::Failed => Self::Failed,
+            SessionState::Pending | SessionState::Running | SessionState::Completed => {
+                Self::Completed
+            }
+        }
+    }
+
+    /// Build the close frame, using `detail` as the reason if given.
+    ///
+    /// Reasons are cut at a character boundary to fit the protocol limit.
+    fn frame(self, detail: Option<&str>) -> axum::extract::ws::CloseFrame {
+        let mut reason = detail.unwrap_or_else(|| self.reason());
+        if reason.len() > MAX_CLOSE_REASON_BYTES {
+            let end = (0..=MAX_CLOSE_REASON_BYTES)
+                .rev()
+                .find(|&i| reason.is_char_boundary(i))
+                .unwrap_or_default();
+            reason = &reason[..end];
+        }
+        axum::extract::ws::CloseFrame { code: self.code(), reason: reason.into() }
+    }
+}
+
 /// WebSocket handler for streaming feedback.
+///
+/// Requests without the session's stream token are refused with 403 before
+/// the upgrade.
 async fn feedback_websocket(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
     ws: WebSocketUpgrade,
 ) -> Result<impl IntoResponse, axum::http::StatusCode> {
-    let session = state.get_session(&id).await.ok_or(axum::http::StatusCode::NOT_FOUND)?;
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+    Ok(ws.on_upgrade(move |socket| handle_feedback_socket(socket, state, session)))
+}
 
-    Ok(ws.on_upgrade(move |socket| handle_feedback_socket(socket, session)))
+/// Server-Sent Events handler for streaming feedback.
+///
+/// An alternative to [`feedback_websocket`] for clients that cannot use
+/// a WebSocket. Each [`StreamMessage`] is sent as a `data:` event: history
+/// first, then live messages, ending after `complete` or when the session's
+/// connections are revoked. Requires the same stream token, refusing requests
+/// without it with 403.
+async fn feedback_sse(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
+) -> Result<Sse<impl Stream<Item = Result<Event, axum::Error>>>, axum::http::StatusCode> {
+    use tokio_stream::wrappers::{BroadcastStream, errors::BroadcastStreamRecvError};
+
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+    let s = session.read().await;
+    let msg_store = s.msg_store.clone();
+    let connection = s.connections.connect();
+    drop(s);
+
+    let (history, rx) = msg_store.subscribe_shared().await;
+    let replay: Vec<StreamMessage> = history.iter().map(log_msg_to_stream_message).collect();
+    // A finished run has nothing live to wait for
+    let finished = matches!(replay.last(), Some(StreamMessage::Complete));
+
+    let live = BroadcastStream::new(rx)
+        .filter_map(move |result| {
+            let message = match result {
+                Ok(msg) => msg.as_ref().as_ref().ok().map(log_msg_to_stream_message),
+                Err(BroadcastStreamRecvError::Lagged(skipped)) => {
+                    warn!(skipped, "Feedback event stream subscriber lagged");
+                    msg_store.record_subscriber_lag(skipped);
+                    Some(StreamMessage::Error { message: format!("dropped {skipped} messages") })
+                }
+            };
+            std::future::ready(message)
+        })
+        .scan(false, |completed, message| {
+            if *completed {
+                return std::future::ready(None);
+            }
+            *completed = matches!(message, StreamMessage::Complete);
+            std::future::ready(Some(message))
+        });
+
+    let events = futures::stream::iter(replay)
+        .chain(futures::stream::iter((!finished).then_some(live)).flatten())
+        .take_until(async move { connection.revoked().await })
+        .map(|message| Event::default().json_data(message));
+    Ok(Sse::new(events).keep_alive(KeepAlive::default()))
 }
 
 /// Handle WebSocket connection for streaming feedback.
+///
+/// Once the message store is finalized the socket is closed with the
+/// [`StreamClose`] matching the session's state, or with
+/// [`StreamClose::Revoked`] if its connections are revoked first.
+/// A follow-up started over the socket moves it on to the follow-up's store.
 async fn handle_feedback_socket(
     mut socket: axum::extract::ws::WebSocket,
+    state: AppState,
     session: std::sync::Arc<tokio::sync::RwLock<crate::state::FeedbackSession>>,
 ) {
     use axum::extract::ws::Message;
+    use tokio::sync::broadcast::error::RecvError;
 
-    let msg_store = session.read().await.msg_store.clone();
-    let mut rx = msg_store.subscribe();
+    let (mut msg_store, connection) = {
+        let s = session.read().await;
+        (s.msg_store.clone(), s.connections.connect())
+    };
 
-    // Send existing history
-    let history = msg_store.get_history().await;
-    for msg in history {
-        let stream_msg = log_msg_to_stream_message(&msg);
+    // Send existing history, sharing one snapshot between concurrent subscribers
+    let (history, mut rx) = msg_store.subscribe_shared().await;
+    let mut delivered = history.len();
+    for msg in history.iter() {
+        let stream_msg = log_msg_to_stream_message(msg);
         let json = serde_json::to_string(&stream_msg).unwrap_or_default();
         if socket.send(Message::Text(json.into())).await.is_err() {
             return;
@@ -352,34 +1201,146 @@ async fn handle_feedback_socket(
     }
 
     // Stream new messages
-    loop {
-        tokio::select! {
+    let revoked = loop {
+        let messages: Vec<StreamMessage> = tokio::select! {
             // Receive from message store
-            result = rx.recv() => {
-                match result {
-                    Ok(msg) => {
-                        if let Ok(ref log_msg) = *msg {
-                            let stream_msg = log_msg_to_stream_message(log_msg);
-                            let json = serde_json::to_string(&stream_msg).unwrap_or_default();
-                            if socket.send(Message::Text(json.into())).await.is_err() {
-                                break;
-                            }
-                        }
-                    }
-                    Err(_) => break,
+            result = rx.recv() => match result {
+                Ok(msg) => {
+                    delivered += 1;
+                    msg.as_ref().as_ref().map(log_msg_to_stream_message).into_iter().collect()
                 }
-            }
+                Err(RecvError::Lagged(skipped)) => {
+                    let (messages, fresh) =
+                        resync_after_lag(&msg_store, skipped, &mut delivered).await;
+                    rx = fresh;
+                    messages
+                }
+                Err(RecvError::Closed) => break false,
+            },
+            () = connection.revoked() => break true,
             // Receive from client
-            msg = socket.recv() => {
-                match msg {
-                    Some(Ok(Message::Close(_))) | None => break,
-                    _ => {}
+            msg = socket.recv() => match msg {
+                Some(Ok(Message::Close(_))) | None => return,
+                Some(Ok(Message::Text(text))) => {
+                    match handle_control_message(&state, &session, &text).await {
+                        // Follow the follow-up into the store its output streams into
+                        Ok(Some(segment)) => {
+                            let (missed, fresh) = segment.subscribe_from(0).await;
+                            (msg_store, rx, delivered) = (segment, fresh, missed.len());
+                            missed.iter().map(log_msg_to_stream_message).collect()
+                        }
+                        Ok(None) => continue,
+                        Err(message) => vec![StreamMessage::Error { message }],
+                    }
                 }
+                _ => continue,
+            },
+        };
+
+        for stream_msg in messages {
+            let json = serde_json::to_string(&stream_msg).unwrap_or_default();
+            if socket.send(Message::Text(json.into())).await.is_err() {
+                return;
             }
         }
+    };
+
+    let frame = if revoked {
+        StreamClose::Revoked.frame(None)
+    } else {
+        let s = session.read().await;
+        let close = StreamClose::for_state(s.state);
+        let frame = close.frame(s.last_error.as_deref().filter(|_| close == StreamClose::Failed));
+        drop(s);
+        frame
+    };
+    let _ = socket.send(Message::Close(Some(frame))).await;
+}
+
+/// Recover a subscriber that fell behind the message store's broadcast buffer.
+///
+/// Returns a notice about the dropped messages followed by everything the client
+/// has not seen yet, plus a fresh receiver positioned after those messages.
+async fn resync_after_lag(
+    msg_store: &glow_executors::MsgStore,
+    skipped: u64,
+    delivered: &mut usize,
+) -> (
+    Vec<StreamMessage>,
+    tokio::sync::broadcast::Receiver<std::sync::Arc<Result<glow_executors::LogMsg, String>>>,
+) {
+    warn!(skipped, "Feedback stream subscriber lagged, resyncing from history");
+    msg_store.record_subscriber_lag(skipped);
+
+    let (missed, rx) = msg_store.subscribe_from(*delivered).await;
+    *delivered += missed.len();
+
+    let mut messages =
+        vec![StreamMessage::Error { message: format!("dropped {skipped} messages") }];
+    messages.extend(missed.iter().map(log_msg_to_stream_message));
+    (messages, rx)
+}
+
+/// Handle a control message sent by the client over the feedback WebSocket.
+///
+/// Returns the message store of a follow-up it started, or a message for the
+/// client if the request cannot be honoured.
+async fn handle_control_message(
+    state: &AppState,
+    session: &std::sync::Arc<tokio::sync::RwLock<crate::state::FeedbackSession>>,
+    text: &str,
+) -> Result<Option<std::sync::Arc<glow_executors::MsgStore>>, String> {
+    let control: StreamControl =
+        serde_json::from_str(text).map_err(|e| format!("invalid control message: {e}"))?;
+
+    match control {
+        StreamControl::FollowUp { instruction } => {
+            start_follow_up(state, session, instruction).await.map(Some)
+        }
+        StreamControl::Interrupt | StreamControl::Approve { .. } | StreamControl::Deny { .. } => {
+            Ok(None)
+        }
     }
 }
 
+/// Queue a follow-up run on an idle session, returning the new message store
+/// its output streams into.
+async fn start_follow_up(
+    state: &AppState,
+    session: &std::sync::Arc<tokio::sync::RwLock<crate::state::FeedbackSession>>,
+    instruction: String,
+) -> Result<std::sync::Arc<glow_executors::MsgStore>, String> {
+    let msg_store = {
+        let mut s = session.write().await;
+        if s.cancel.is_cancelled() {
+            return Err("session was cancelled".to_owned());
+        }
+        if matches!(s.state, SessionState::Pending | SessionState::Running) {
+            return Err("session is still running".to_owned());
+        }
+        s.state = SessionState::Pending;
+        s.last_error = None;
+        s.begin_segment()
+    };
+
+    info!(instruction_len = instruction.len(), "Starting follow-up");
+    let state = state.clone();
+    let session = session.clone();
+    let segment = msg_store.clone();
+    tokio::spawn(async move {
+        let Some(_permit) = state.acquire_executor_permit(&session).await else {
+            info!("Follow-up cancelled before starting");
+            return;
+        };
+
+        state.metrics.session_started();
+        run_follow_up(&state, &session, &instruction).await;
+        state.metrics.session_finished(session.read().await.state);
+        segment.finalize().await;
+    });
+    Ok(msg_store)
+}
+
 /// Convert a log message to a stream message.
 fn log_msg_to_stream_message(msg: &glow_executors::LogMsg) -> StreamMessage {
     use glow_executors::{LogMsg, NormalizedEntryType};
@@ -389,9 +1350,13 @@ fn log_msg_to_stream_message(msg: &glow_executors::LogMsg) -> StreamMessage {
             NormalizedEntryType::AssistantMessage => {
                 StreamMessage::Chunk { content: entry.content.clone() }
             }
-            NormalizedEntryType::ThinkingMessage => {
-                StreamMessage::Thinking { content: entry.content.clone() }
-            }
+            NormalizedEntryType::ThinkingMessage => StreamMessage::Thinking {
+                content: entry.content.clone(),
+                preview: entry.preview().map_or_else(
+                    || glow_executors::thinking_preview(&entry.content),
+                    str::to_owned,
+                ),
+            },
             NormalizedEntryType::ErrorMessage => {
                 StreamMessage::Error { message: entry.content.clone() }
             }
@@ -402,3 +1367,1119 @@ fn log_msg_to_stream_message(msg: &glow_executors::LogMsg) -> StreamMessage {
         _ => StreamMessage::Chunk { content: String::new() },
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::state::DEFAULT_MAX_LINE_BYTES;
+
+    #[tokio::test]
+    async fn test_oversized_feedback_body_is_rejected() {
+        let app = router(1024).with_state(AppState::new());
+        let body = serde_json::json!({
+            "documentId": "doc-1",
+            "documentContent": "x".repeat(4096),
+            "selectedText": "x",
+            "instruction": "Review",
+            "executor": "claude",
+            "commentId": "comment-1",
+        });
+
+        let request = Request::post("/")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("should build request");
+
+        let response = app.oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    async fn validate(state: &AppState, content: &str) -> serde_json::Value {
+        let body = serde_json::json!({
+            "documentId": "doc-1",
+            "documentContent": content,
+            "selectedText": "Body",
+            "instruction": "Review",
+            "executor": "claude",
+            "commentId": "comment-1",
+            "model": "tiny",
+        });
+        let request = Request::post("/validate")
+            .header("content-type", "application/json")
+            .body(Body::from(body.to_string()))
+            .expect("should build request");
+        let response = router(1 << 20)
+            .with_state(state.clone())
+            .oneshot(request)
+            .await
+            .expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        serde_json::from_slice(&body).expect("should be JSON")
+    }
+
+    #[tokio::test]
+    async fn test_validate_estimates_tokens_against_model_limit() {
+        let mut limits = glow_executors::ModelLimits::with_defaults();
+        limits.models.insert("tiny".to_owned(), glow_executors::ModelLimit::new(1_000, 100));
+        let state = AppState::new().with_model_limits(limits).with_max_content_chars(None);
+
+        let fits = validate(&state, "A short document.").await;
+        assert_eq!(fits["executor"], "CLAUDE_CODE");
+        assert_eq!(fits["model"], "tiny");
+        assert_eq!(fits["maxInputTokens"], 1_000);
+        assert_eq!(fits["exceedsLimit"], false);
+        assert!(fits["prompt"].as_str().is_some_and(|p| p.contains("USER INSTRUCTION:\nReview")));
+        let estimate = fits["estimatedTokens"].as_u64().expect("should estimate tokens");
+        assert!(estimate > 0 && estimate < 1_000, "unexpected estimate {estimate}");
+
+        let too_long = validate(&state, &"word ".repeat(1_000)).await;
+        assert!(too_long["estimatedTokens"].as_u64() > Some(1_000));
+        assert_eq!(too_long["exceedsLimit"], true);
+        assert_eq!(too_long["contentTruncated"], false);
+        assert!(state.sessions.read().await.is_empty(), "validation should not start sessions");
+    }
+
+    fn selected_claude(state: &AppState, extra: serde_json::Value) -> ClaudeCode {
+        let mut body = serde_json::json!({
+            "documentId": "doc-1",
+            "documentContent": "Body",
+            "selectedText": "Body",
+            "instruction": "Review",
+            "executor": "claude",
+            "commentId": "comment-1",
+        });
+        let serde_json::Value::Object(extra) = extra else {
+            unreachable!("extra fields should be an object");
+        };
+        body.as_object_mut().expect("body is an object").extend(extra);
+        let req: CreateFeedbackRequest =
+            serde_json::from_value(body).expect("should parse request");
+        match select_executor(state, &req, None) {
+            SessionExecutor::Agent(DocumentAgent::ClaudeCode(claude)) => claude,
+            _ => unreachable!("claude requests should run Claude Code"),
+        }
+    }
+
+    #[test]
+    fn test_routing_picks_profile_by_keyword() {
+        use glow_executors::{ExecutorConfigs, ExecutorProfileId, FeedbackRoute};
+
+        let claude = BaseDocumentAgent::ClaudeCode;
+        let mut configs = ExecutorConfigs::with_defaults();
+        let cheap = DocumentAgent::ClaudeCode(ClaudeCode::new().with_model("haiku"));
+        configs
+            .executors
+            .get_mut(&claude)
+            .expect("claude is configured")
+            .add_variant("CHEAP", cheap);
+        configs.routing.routes.push(FeedbackRoute {
+            keywords: vec!["grammar".to_owned()],
+            tags: vec![],
+            profile: ExecutorProfileId::with_variant(claude, "CHEAP"),
+        });
+        configs.routing.default = Some(ExecutorProfileId::new(claude));
+        let mut state = AppState::new().with_default_model(Some("sonnet".to_owned()));
+        state.executor_configs = std::sync::Arc::new(configs);
+
+        let grammar = serde_json::json!({ "instruction": "Fix the grammar" });
+        assert_eq!(selected_claude(&state, grammar).model.as_deref(), Some("haiku"));
+        let overridden = serde_json::json!({ "instruction": "Fix the grammar", "model": "opus" });
+        assert_eq!(selected_claude(&state, overridden).model.as_deref(), Some("opus"));
+        let other = serde_json::json!({ "instruction": "Is this clear?" });
+        assert_eq!(selected_claude(&state, other).model.as_deref(), Some("sonnet"));
+    }
+
+    #[test]
+    fn test_default_model_and_request_override() {
+        let no_fields = serde_json::json!({});
+        assert_eq!(selected_claude(&AppState::new(), no_fields.clone()).model, None);
+
+        let state = AppState::new().with_default_model(Some("sonnet".to_owned()));
+        assert_eq!(selected_claude(&state, no_fields).model.as_deref(), Some("sonnet"));
+        let overridden = selected_claude(&state, serde_json::json!({ "model": "opus" }));
+        assert_eq!(overridden.model.as_deref(), Some("opus"));
+    }
+
+    #[test]
+    fn test_plan_mode_selects_plan_config() {
+        let state = AppState::new();
+        let review = selected_claude(&state, serde_json::json!({}));
+        assert_eq!(review.plan, None);
+        assert_eq!(review.system_prompt, Some(ClaudeCode::document_feedback_system_prompt()));
+
+        let plan = selected_claude(&state, serde_json::json!({ "mode": "plan" }));
+        assert_eq!(plan.plan, Some(true));
+        assert_eq!(plan.system_prompt, Some(ClaudeCode::plan_feedback_system_prompt()));
+
+        let question = selected_claude(&state, serde_json::json!({ "mode": "question" }));
+        assert_eq!(question.plan, Some(true));
+        assert_eq!(question.system_prompt, Some(ClaudeCode::document_review_system_prompt()));
+    }
+
+    #[test]
+    fn test_system_prompt_override_replaces_default() {
+        let state = AppState::new();
+        let persona = "You are a meticulous fact-checker.";
+        let claude = selected_claude(&state, serde_json::json!({ "systemPrompt": persona }));
+        assert_eq!(claude.system_prompt.as_deref(), Some(persona));
+
+        let plan =
+            selected_claude(&state, serde_json::json!({ "systemPrompt": persona, "mode": "plan" }));
+        assert_eq!(plan.plan, Some(true));
+        assert_eq!(plan.system_prompt.as_deref(), Some(persona));
+    }
+
+    #[tokio::test]
+    async fn test_system_prompt_override_policy() {
+        let post_feedback = |state: AppState, system_prompt: String| async move {
+            let body = serde_json::json!({
+                "documentId": "doc-1",
+                "documentContent": "Body",
+                "selectedText": "Body",
+                "instruction": "Review",
+                "executor": "claude",
+                "commentId": "comment-1",
+                "systemPrompt": system_prompt,
+            });
+            let request = Request::post("/")
+                .header("content-type", "application/json")
+                .body(Body::from(body.to_string()))
+                .expect("should build request");
+            let app = router(4096).with_state(state);
+            app.oneshot(request).await.expect("should respond").status()
+        };
+
+        let disabled = AppState::new().with_system_prompt_override(false);
+        let status = post_feedback(disabled.clone(), "You are a copyeditor.".to_owned()).await;
+        assert_eq!(status, axum::http::StatusCode::FORBIDDEN);
+        assert!(disabled.sessions.read().await.is_empty());
+
+        let capped = AppState::new().with_max_system_prompt_chars(10);
+        let status = post_feedback(capped, "x".repeat(11)).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_cancel_counts_only_unfinished_sessions() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        let app = router(1024).with_state(state.clone());
+
+        let request =
+            Request::delete(format!("/{id}?token=wrong")).body(Body::empty()).expect("valid");
+        let response = app.clone().oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+        assert!(!session.read().await.cancel.is_cancelled());
+
+        for _ in 0..2 {
+            let request = Request::delete(format!("/{id}?token={token}"))
+                .body(Body::empty())
+                .expect("should build request");
+            let response = app.clone().oneshot(request).await.expect("should respond");
+            assert_eq!(response.status(), axum::http::StatusCode::OK);
+        }
+
+        assert!(session.read().await.cancel.is_cancelled());
+        assert!(session.read().await.msg_store.is_finalized());
+        assert_eq!(state.metrics.snapshot().sessions_cancelled, 1);
+    }
+
+    /// GET `uri`, returning the status and the body parsed as log entries.
+    async fn fetch_log(
+        app: &Router,
+        uri: String,
+    ) -> (axum::http::StatusCode, Option<Vec<NormalizedEntry>>) {
+        let request = Request::get(uri).body(Body::empty()).expect("should build request");
+        let response = app.clone().oneshot(request).await.expect("should respond");
+        let status = response.status();
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        (status, serde_json::from_slice(&body).ok())
+    }
+
+    #[tokio::test]
+    async fn test_log_includes_tool_calls_and_thinking() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token, msg_store) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone(), s.msg_store.clone())
+        };
+        msg_store.push_entry(NormalizedEntry::thinking("Checking the intro")).await;
+        msg_store.push_entry(NormalizedEntry::tool_call("Read", serde_json::json!({}))).await;
+        msg_store.push_entry(NormalizedEntry::assistant_message("Looks good")).await;
+        let app = router(1024).with_state(state);
+
+        let types = |entries: Vec<NormalizedEntry>| {
+            entries.into_iter().map(|e| e.entry_type).collect::<Vec<_>>()
+        };
+
+        let (status, _) = fetch_log(&app, format!("/{id}/log")).await;
+        assert_eq!(status, axum::http::StatusCode::FORBIDDEN);
+
+        let (status, entries) = fetch_log(&app, format!("/{id}/log?token={token}")).await;
+        assert_eq!(status, axum::http::StatusCode::OK);
+        assert_eq!(
+            types(entries.expect("should be entries")),
+            [
+                NormalizedEntryType::ThinkingMessage,
+                NormalizedEntryType::ToolCall,
+                NormalizedEntryType::AssistantMessage,
+            ]
+        );
+
+        let (_, entries) =
+            fetch_log(&app, format!("/{id}/log?types=tool_call,thinking_message&token={token}"))
+                .await;
+        assert_eq!(
+            types(entries.expect("should be entries")),
+            [NormalizedEntryType::ThinkingMessage, NormalizedEntryType::ToolCall]
+        );
+
+        let (status, _) = fetch_log(&app, format!("/{id}/log?types=bogus&token={token}")).await;
+        assert_eq!(status, axum::http::StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_transcript_renders_messages_and_edits() {
+        use tower::ServiceExt;
+
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token, msg_store) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone(), s.msg_store.clone())
+        };
+        let edit = glow_executors::SuggestedEdit {
+            id: "edit-1".to_owned(),
+            original_text: "teh cat".to_owned(),
+            suggested_text: "the cat".to_owned(),
+            explanation: "Fix the <typo>".to_owned(),
+            range: glow_executors::TextRange { from: 0, to: 7, quoted_text: "teh cat".to_owned() },
+            applied: false,
+            rejected: false,
+        };
+        msg_store.push_entry(NormalizedEntry::thinking("Checking spelling")).await;
+        msg_store.push_entry(NormalizedEntry::tool_call("Read", serde_json::json!({}))).await;
+        msg_store
+            .push_entry(NormalizedEntry::assistant_message(
+                "The intro is **clear**.\n\n<script>alert(1)</script>\n\n\
+                 [x](javascript:alert(1)) [y](<Java\tScript:alert(2)>) ![z](data:image/png,AA) \
+                 [docs](https://example.com/docs) [local](notes.md#intro)",
+            ))
+            .await;
+        msg_store
+            .push_entry(NormalizedEntry {
+                timestamp: None,
+                entry_type: NormalizedEntryType::SuggestedEdit,
+                content: serde_json::to_string(&edit).expect("should serialize edit"),
+                metadata: None,
+            })
+            .await;
+        let app = router(1024).with_state(state);
+
+        let request = Request::get(format!("/{id}/transcript.html"))
+            .body(Body::empty())
+            .expect("should build request");
+        let response = app.clone().oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+        let request = Request::get(format!("/{id}/transcript.html?token={token}"))
+            .body(Body::empty())
+            .expect("should build request");
+        let response = app.oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let csp = &response.headers()["content-security-policy"];
+        assert!(csp.to_str().is_ok_and(|csp| csp.contains("default-src 'none'")));
+        assert!(
+            response.headers()["content-type"].to_str().is_ok_and(|t| t.starts_with("text/html"))
+        );
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        let html = String::from_utf8(body.to_vec()).expect("should be UTF-8");
+
+        assert!(html.contains("<p>The intro is <strong>clear</strong>.</p>"));
+        assert!(html.contains("<summary>Thinking</summary><pre>Checking spelling</pre>"));
+        assert!(html.contains("<del>teh cat</del> <ins>the cat</ins>"));
+        assert!(html.contains("Fix the &lt;typo&gt;"));
+        assert!(!html.contains("<script>"));
+        assert!(!html.to_lowercase().contains("javascript"));
+        assert!(!html.contains("data:"));
+        assert!(html.contains("<a href=\"#\">x</a> <a href=\"#\">y</a>"));
+        assert!(html.contains("<a href=\"https://example.com/docs\">docs</a>"));
+        assert!(html.contains("<a href=\"notes.md#intro\">local</a>"));
+        assert!(!html.contains("Read"));
+    }
+
+    /// Custom executor whose follow-ups reply with a fixed assistant message.
+    ///
+    /// The follow-up's exit signal fires once the reply is in the store.
+    #[derive(Default)]
+    struct ReplyExecutor {
+        replied: std::sync::Mutex<Option<tokio::sync::mpsc::Sender<Result<(), ExecutorError>>>>,
+    }
+
+    #[async_trait::async_trait]
+    impl glow_executors::StandardDocumentExecutor for ReplyExecutor {
+        async fn spawn(
+            &self,
+            _current_dir: &std::path::Path,
+            _prompt: &str,
+            _env: &glow_executors::ExecutionEnv,
+        ) -> Result<glow_executors::executors::SpawnedChild, ExecutorError> {
+            Err(ExecutorError::NotAvailable("reply".to_owned()))
+        }
+
+        async fn spawn_follow_up(
+            &self,
+            _current_dir: &std::path::Path,
+            _prompt: &str,
+            session_id: &str,
+            _env: &glow_executors::ExecutionEnv,
+        ) -> Result<glow_executors::executors::SpawnedChild, ExecutorError> {
+            use command_group::AsyncCommandGroup;
+
+            assert_eq!(session_id, "claude-1");
+            let child = tokio::process::Command::new("true").group_spawn()?;
+            let (replied, exit_signal) = tokio::sync::mpsc::channel(1);
+            *self.replied.lock().expect("lock should not be poisoned") = Some(replied);
+            Ok(glow_executors::executors::SpawnedChild {
+                child,
+                exit_signal: Some(exit_signal),
+                interrupt_sender: None,
+            })
+        }
+
+        fn normalize_logs(
+            &self,
+            msg_store: std::sync::Arc<glow_executors::MsgStore>,
+            _worktree_path: &std::path::Path,
+        ) {
+            let replied = self.replied.lock().expect("lock should not be poisoned").take();
+            let replied = replied.expect("a follow-up should have been spawned");
+            tokio::spawn(async move {
+                let reply = NormalizedEntry::assistant_message("follow-up reply");
+                msg_store.push_entry(reply).await;
+                let _ = replied.send(Ok(())).await;
+            });
+        }
+
+        fn default_mcp_config_path(&self) -> Option<std::path::PathBuf> {
+            None
+        }
+    }
+
+    /// Read stream messages from the socket until a chunk arrives.
+    async fn next_chunk<S>(socket: &mut S) -> String
+    where
+        S: Stream<
+                Item = Result<
+                    tokio_tungstenite::tungstenite::Message,
+                    tokio_tungstenite::tungstenite::Error,
+                >,
+            > + Unpin,
+    {
+        use futures::StreamExt;
+
+        while let Some(frame) = socket.next().await {
+            let text = frame.expect("should receive frame").into_text().expect("should be text");
+            let message: serde_json::Value =
+                serde_json::from_str(&text).expect("should be a stream message");
+            if message["type"] == "chunk" {
+                return message["content"].as_str().unwrap_or_default().to_owned();
+            }
+        }
+        String::new()
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_over_websocket_streams_reply() {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        let state = AppState::new();
+        let executor = SessionExecutor::custom("reply", Box::new(ReplyExecutor::default()));
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), executor, true).await;
+        let (id, token) = {
+            let mut s = session.write().await;
+            s.state = SessionState::Completed;
+            s.agent_session_id = Some("claude-1".to_owned());
+            (s.id.clone(), s.stream_token.clone())
+        };
+
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        tokio::spawn(axum::serve(listener, router(1024).with_state(state)).into_future());
+
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/{id}/ws?token={token}"))
+                .await
+                .expect("should connect");
+        let follow_up = serde_json::json!({ "type": "follow_up", "instruction": "Shorter" });
+        socket.send(Message::text(follow_up.to_string())).await.expect("should send follow-up");
+
+        let reply =
+            tokio::time::timeout(std::time::Duration::from_secs(5), next_chunk(&mut socket))
+                .await
+                .expect("reply should stream back");
+        assert_eq!(reply, "follow-up reply");
+    }
+
+    /// Serve the feedback router on a local port.
+    async fn serve(state: AppState) -> std::net::SocketAddr {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        tokio::spawn(axum::serve(listener, router(1024).with_state(state)).into_future());
+        addr
+    }
+
+    /// Read frames from the socket until the server closes it.
+    async fn next_close<S>(
+        socket: &mut S,
+    ) -> Option<tokio_tungstenite::tungstenite::protocol::CloseFrame>
+    where
+        S: Stream<
+                Item = Result<
+                    tokio_tungstenite::tungstenite::Message,
+                    tokio_tungstenite::tungstenite::Error,
+                >,
+            > + Unpin,
+    {
+        use futures::StreamExt;
+        use tokio_tungstenite::tungstenite::Message;
+
+        while let Some(frame) = socket.next().await {
+            if let Message::Close(frame) = frame.expect("should receive frame") {
+                return frame;
+            }
+        }
+        None
+    }
+
+    /// Open a session's stream and return the code and reason it is closed with.
+    async fn stream_close(
+        addr: std::net::SocketAddr,
+        id: impl std::fmt::Display,
+        token: impl std::fmt::Display,
+    ) -> (u16, String) {
+        let (mut socket, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/{id}/ws?token={token}"))
+                .await
+                .expect("should connect");
+        let frame =
+            tokio::time::timeout(std::time::Duration::from_secs(5), next_close(&mut socket))
+                .await
+                .expect("stream should close")
+                .expect("close should carry a code");
+        (u16::from(frame.code), frame.reason.to_string())
+    }
+
+    /// End the stream of a session's current run.
+    async fn finalize(session: &tokio::sync::RwLock<crate::state::FeedbackSession>) {
+        let msg_store = session.read().await.msg_store.clone();
+        msg_store.finalize().await;
+    }
+
+    #[tokio::test]
+    async fn test_stream_close_codes() {
+        let state = AppState::new();
+        let addr = serve(state.clone()).await;
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let new_session = || {
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent.clone(), true)
+        };
+
+        let completed = new_session().await;
+        completed.write().await.state = SessionState::Completed;
+        finalize(&completed).await;
+        let (id, token) = {
+            let s = completed.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        assert_eq!(stream_close(addr, &id, &token).await, (1000, "completed".to_owned()));
+
+        let failed = new_session().await;
+        failed.write().await.fail("x".repeat(200));
+        finalize(&failed).await;
+        let (id, token) = {
+            let s = failed.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        assert_eq!(stream_close(addr, &id, &token).await, (1011, "x".repeat(123)));
+
+        let cancelled = new_session().await;
+        let (id, token) = {
+            let s = cancelled.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        let cancel =
+            Request::delete(format!("/{id}?token={token}")).body(Body::empty()).expect("valid");
+        let response =
+            router(1024).with_state(state.clone()).oneshot(cancel).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(stream_close(addr, &id, &token).await, (4000, "cancelled".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_connected_stream_closes_when_the_run_ends() {
+        let state = AppState::new();
+        let addr = serve(state.clone()).await;
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let new_session = || {
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent.clone(), true)
+        };
+
+        let completed = new_session().await;
+        completed.write().await.state = SessionState::Running;
+        let (id, token) = {
+            let s = completed.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        let connected = tokio::spawn(stream_close(addr, id, token));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        completed.write().await.finish(SessionState::Completed);
+        finalize(&completed).await;
+        let closed = connected.await.expect("stream task should finish");
+        assert_eq!(closed, (1000, "completed".to_owned()));
+
+        let cancelled = new_session().await;
+        cancelled.write().await.state = SessionState::Running;
+        let (id, token) = {
+            let s = cancelled.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+        let connected = tokio::spawn(stream_close(addr, id.clone(), token.clone()));
+        tokio::time::sleep(std::time::Duration::from_millis(100)).await;
+        let cancel =
+            Request::delete(format!("/{id}?token={token}")).body(Body::empty()).expect("valid");
+        let response =
+            router(1024).with_state(state.clone()).oneshot(cancel).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        let closed = connected.await.expect("stream task should finish");
+        assert_eq!(closed, (4000, "cancelled".to_owned()));
+    }
+
+    #[tokio::test]
+    async fn test_sse_replays_history_then_streams_until_complete() {
+        use glow_executors::LogMsg;
+
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token, msg_store) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone(), s.msg_store.clone())
+        };
+        msg_store.push_entry(NormalizedEntry::assistant_message("Tighten")).await;
+        let app = router(1024).with_state(state);
+
+        let forbidden = Request::get(format!("/{id}/sse?token=wrong")).body(Body::empty());
+        let response =
+            app.clone().oneshot(forbidden.expect("valid request")).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::FORBIDDEN);
+
+        let request = Request::get(format!("/{id}/sse?token={token}")).body(Body::empty());
+        let response = app.oneshot(request.expect("valid request")).await.expect("should respond");
+        assert_eq!(response.status(), axum::http::StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], "text/event-stream");
+
+        msg_store.push_entry(NormalizedEntry::assistant_message("the intro")).await;
+        msg_store.push(LogMsg::Ended).await;
+        msg_store.push_entry(NormalizedEntry::assistant_message("after completion")).await;
+
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX);
+        let body = tokio::time::timeout(std::time::Duration::from_secs(5), body)
+            .await
+            .expect("stream should end after complete")
+            .expect("should read body");
+        let events: Vec<serde_json::Value> = String::from_utf8_lossy(&body)
+            .lines()
+            .filter_map(|line| line.strip_prefix("data: "))
+            .map(|data| serde_json::from_str(data).expect("should be a stream message"))
+            .collect();
+        assert_eq!(
+            events,
+            [
+                serde_json::json!({ "type": "chunk", "content": "Tighten" }),
+                serde_json::json!({ "type": "chunk", "content": "the intro" }),
+                serde_json::json!({ "type": "complete" }),
+            ]
+        );
+    }
+
+    /// Wait until `msg_store` is finalized.
+    async fn finalized(msg_store: &glow_executors::MsgStore) {
+        while !msg_store.is_finalized() {
+            tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_follow_up_streams_into_a_new_segment() {
+        let state = AppState::new();
+        let executor = SessionExecutor::custom("reply", Box::new(ReplyExecutor::default()));
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), executor, true).await;
+        let first = {
+            let mut s = session.write().await;
+            s.state = SessionState::Completed;
+            s.agent_session_id = Some("claude-1".to_owned());
+            s.msg_store.clone()
+        };
+        first.push_entry(NormalizedEntry::assistant_message("first reply")).await;
+        first.finalize().await;
+
+        let second = start_follow_up(&state, &session, "Shorter".to_owned())
+            .await
+            .expect("follow-up should start");
+        assert!(!std::sync::Arc::ptr_eq(&first, &second));
+        tokio::time::timeout(std::time::Duration::from_secs(5), finalized(&second))
+            .await
+            .expect("follow-up stream should end");
+
+        let replies: Vec<String> =
+            logged_entries(&session).await.into_iter().map(|entry| entry.content).collect();
+        assert_eq!(replies, ["first reply", "follow-up reply"]);
+        assert_eq!(session.read().await.state, SessionState::Completed);
+    }
+
+    #[tokio::test]
+    async fn test_stream_requires_session_token() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+
+        let addr = serve(state).await;
+        tokio_tungstenite::connect_async(format!("ws://{addr}/{id}/ws?token={token}"))
+            .await
+            .expect("valid token should connect");
+
+        for wrong in ["wrong", ""] {
+            assert_eq!(
+                refused_status(addr, &id, wrong).await,
+                Some(axum::http::StatusCode::FORBIDDEN)
+            );
+        }
+    }
+
+    /// The HTTP status a stream upgrade is refused with, or `None` if it was accepted.
+    async fn refused_status(
+        addr: std::net::SocketAddr,
+        id: &str,
+        token: &str,
+    ) -> Option<axum::http::StatusCode> {
+        match tokio_tungstenite::connect_async(format!("ws://{addr}/{id}/ws?token={token}")).await {
+            Err(tokio_tungstenite::tungstenite::Error::Http(response)) => Some(response.status()),
+            _ => None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_spawn_writes_audit_record() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("audit.log");
+        let audit_log = crate::audit::AuditLog::open(&path).expect("should open audit log");
+        let state = AppState::new().with_audit_log(audit_log);
+        let executor = SessionExecutor::custom("reply", Box::new(ReplyExecutor::default()));
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), executor, true).await;
+        let request = FeedbackRequest {
+            document_id: "doc-1".to_owned(),
+            document_content: "Secret plans".to_owned(),
+            document_title: None,
+            selected_text: "plans".to_owned(),
+            selected_range: glow_executors::TextRange {
+                from: 7,
+                to: 12,
+                quoted_text: "plans".to_owned(),
+            },
+            instruction: "Review".to_owned(),
+            executor: "reply".to_owned(),
+            comment_id: "comment-1".to_owned(),
+            session_id: None,
+        };
+
+        run_feedback_session(&state, session, request).await.expect("session should run");
+
+        let log = std::fs::read_to_string(&path).expect("should read audit log");
+        let records: Vec<serde_json::Value> =
+            log.lines().map(|l| serde_json::from_str(l).expect("line should be JSON")).collect();
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0]["executor"], "reply");
+        assert_eq!(records[0]["document_id"], "doc-1");
+        assert_eq!(records[0]["prompt_sha256"].as_str().map(str::len), Some(64));
+        assert!(!log.contains("Secret plans"));
+    }
+
+    #[tokio::test]
+    async fn test_registered_command_executor_streams_its_output() {
+        let mut registry = glow_executors::ExecutorRegistry::new();
+        registry
+            .register("echo", || Box::new(glow_executors::executors::CommandExecutor::new("cat")));
+        let state = AppState::new().with_executor_registry(registry);
+        let req: CreateFeedbackRequest = serde_json::from_value(serde_json::json!({
+            "documentId": "doc-1",
+            "documentContent": "Body",
+            "selectedText": "Body",
+            "instruction": "Review",
+            "executor": "Echo",
+            "commentId": "comment-1",
+        }))
+        .expect("valid request");
+        let executor = select_executor(&state, &req, None);
+        assert_eq!(executor.name(), "Echo");
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), executor, true).await;
+
+        run_feedback_session(&state, session.clone(), req.into_feedback_request())
+            .await
+            .expect("session should run");
+
+        assert_eq!(session.read().await.state, SessionState::Completed);
+        let echoed: String =
+            logged_entries(&session).await.into_iter().map(|entry| entry.content + "\n").collect();
+        assert!(echoed.contains("Review"), "prompt should be echoed back: {echoed}");
+    }
+
+    /// The `error` reported by `GET /{id}` for a session.
+    async fn reported_error(state: &AppState, id: &str) -> Option<String> {
+        let session = state.get_session(id).await.expect("session should exist");
+        let token = session.read().await.stream_token.clone();
+        let request =
+            Request::get(format!("/{id}?token={token}")).body(Body::empty()).expect("should build");
+        let response =
+            router(1024).with_state(state.clone()).oneshot(request).await.expect("should respond");
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        let feedback: FeedbackResponse = serde_json::from_slice(&body).expect("should be feedback");
+        assert_eq!(feedback.status, FeedbackStatus::Failed);
+        feedback.error
+    }
+
+    #[tokio::test]
+    async fn test_spawn_failure_is_reported() {
+        let state = AppState::new();
+        let executor = SessionExecutor::custom("reply", Box::new(ReplyExecutor::default()));
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), executor, true).await;
+        let id = session.read().await.id.clone();
+        let request = FeedbackRequest {
+            document_id: "doc-1".to_owned(),
+            document_content: "Body".to_owned(),
+            document_title: None,
+            selected_text: "Body".to_owned(),
+            selected_range: glow_executors::TextRange {
+                from: 0,
+                to: 0,
+                quoted_text: String::new(),
+            },
+            instruction: "Review".to_owned(),
+            executor: "reply".to_owned(),
+            comment_id: "comment-1".to_owned(),
+            session_id: None,
+        };
+
+        run_feedback_session(&state, session, request).await.expect("session should run");
+
+        let error = reported_error(&state, &id).await;
+        assert_eq!(error.as_deref(), Some("executor not available: reply"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_is_reported() {
+        use std::process::Stdio;
+        use std::time::Duration;
+
+        let state = AppState::new().with_idle_timeout(Some(Duration::from_millis(100)));
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let id = session.read().await.id.clone();
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("should spawn sleep");
+
+        run_claude_child(&state, &session, &mut child).await;
+
+        let error = reported_error(&state, &id).await.expect("should report an error");
+        assert!(error.starts_with("operation timed out"), "unexpected error: {error}");
+    }
+
+    #[tokio::test]
+    async fn test_cost_ceiling_aborts_session() {
+        use std::process::Stdio;
+
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        session.write().await.max_cost_usd = Some(0.05);
+        let id = session.read().await.id.clone();
+
+        // Streams a message worth $0.06 at sonnet prices, then keeps running
+        let start = r#"{"type":"stream_event","event":{"type":"message_start","message":{"model":"claude-sonnet-4-20250514","usage":{"input_tokens":20000,"output_tokens":0}}}}"#;
+        let mut child = tokio::process::Command::new("sh")
+            .arg("-c")
+            .arg(format!("echo '{start}'; sleep 30"))
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("should spawn sh");
+
+        tokio::time::timeout(
+            std::time::Duration::from_secs(5),
+            run_claude_child(&state, &session, &mut child),
+        )
+        .await
+        .expect("session should be aborted before the process exits");
+
+        let error = reported_error(&state, &id).await.expect("should report an error");
+        assert_eq!(error, "cost limit exceeded: estimated $0.0600 of $0.0500");
+    }
+
+    #[tokio::test]
+    async fn test_non_zero_exit_is_reported() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let id = session.read().await.id.clone();
+        let mut child = tokio::process::Command::new("false").spawn().expect("should spawn false");
+
+        finish_session(&session, child.wait().await).await;
+
+        let error = reported_error(&state, &id).await.expect("should report an error");
+        assert!(error.starts_with("executor process failed"), "unexpected error: {error}");
+    }
+
+    #[test]
+    fn test_prompt_warns_about_truncated_content() {
+        let request = FeedbackRequest {
+            document_id: "doc-1".to_owned(),
+            document_content: "Long document".to_owned(),
+            document_title: None,
+            selected_text: "Long".to_owned(),
+            selected_range: glow_executors::TextRange {
+                from: 0,
+                to: 0,
+                quoted_text: String::new(),
+            },
+            instruction: "Review".to_owned(),
+            executor: "claude".to_owned(),
+            comment_id: "comment-1".to_owned(),
+            session_id: None,
+        };
+        let template = PromptTemplate::default();
+
+        let full = glow_executors::DocumentContext::new("doc-1", "Long document");
+        assert!(
+            !build_feedback_prompt(&template, &request, &full, None).contains(TRUNCATION_NOTICE)
+        );
+
+        let truncated = full.with_max_content_chars(4);
+        assert!(
+            build_feedback_prompt(&template, &request, &truncated, None)
+                .ends_with(TRUNCATION_NOTICE)
+        );
+    }
+
+    #[test]
+    fn test_prompt_marks_selection_inside_context() {
+        let selection = "a remarkably specific phrase";
+        let content = format!("Intro text. Then {selection} appears. Outro.");
+        let request = FeedbackRequest {
+            document_id: "doc-1".to_owned(),
+            document_content: content.clone(),
+            document_title: None,
+            selected_text: selection.to_owned(),
+            selected_range: glow_executors::TextRange {
+                from: 0,
+                to: 0,
+                quoted_text: String::new(),
+            },
+            instruction: "Review".to_owned(),
+            executor: "claude".to_owned(),
+            comment_id: "comment-1".to_owned(),
+            session_id: None,
+        };
+        let template = PromptTemplate::default();
+        let doc_context = glow_executors::DocumentContext::new("doc-1", content);
+
+        let prompt = build_feedback_prompt(&template, &request, &doc_context, Some(20));
+        assert_eq!(prompt.matches(selection).count(), 1);
+        assert!(prompt.contains(&format!("<selection>{selection}</selection>")));
+        assert!(prompt.contains(SELECTION_IN_CONTEXT));
+
+        // Without context the selection is quoted as before
+        let prompt = build_feedback_prompt(&template, &request, &doc_context, None);
+        assert!(prompt.contains(&format!("SELECTED TEXT:\n{selection}\n")));
+        assert!(!prompt.contains("SURROUNDING TEXT"));
+        assert!(!prompt.contains("<selection>"));
+
+        // A template without the context keeps the selection itself
+        let template = PromptTemplate::new("Review {selected_text}");
+        let prompt = build_feedback_prompt(&template, &request, &doc_context, Some(20));
+        assert_eq!(prompt, format!("Review {selection}"));
+    }
+
+    #[tokio::test]
+    async fn test_cancel_stops_stdout_read() {
+        use std::time::Duration;
+        use tokio::io::AsyncWriteExt;
+
+        // Keep the writer open so the read would otherwise block forever
+        let (mut writer, reader) = tokio::io::duplex(1024);
+        writer.write_all(b"not json\n").await.expect("should write line");
+
+        let cancel = CancellationToken::new();
+        let mut processor =
+            ClaudeLogProcessor::new(std::sync::Arc::new(glow_executors::MsgStore::new()));
+        let read = tokio::spawn({
+            let cancel = cancel.clone();
+            async move {
+                read_executor_stdout(reader, &mut processor, &cancel, DEFAULT_MAX_LINE_BYTES).await
+            }
+        });
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        cancel.cancel();
+
+        let lines = tokio::time::timeout(Duration::from_secs(1), read)
+            .await
+            .expect("read should stop promptly after cancel")
+            .expect("read task should not panic");
+        assert_eq!(lines, 1);
+        drop(writer);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_stdout_line_is_truncated() {
+        let store = std::sync::Arc::new(glow_executors::MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone());
+        let huge = serde_json::json!({
+            "type": "assistant",
+            "message": { "content": [{
+                "type": "tool_use",
+                "id": "tool-1",
+                "name": "Write",
+                "input": { "content": "x".repeat(64 * 1024) },
+            }] },
+        });
+        let reply = serde_json::json!({
+            "type": "assistant",
+            "message": { "content": [{ "type": "text", "text": "Done" }] },
+        });
+        let output = format!("{huge}\n{reply}\n");
+
+        let lines = read_executor_stdout(
+            output.as_bytes(),
+            &mut processor,
+            &CancellationToken::new(),
+            1024,
+        )
+        .await;
+
+        assert_eq!(lines, 2);
+        let history = store.get_history().await;
+        assert!(matches!(&history[0], glow_executors::LogMsg::Raw(raw)
+            if raw.len() < 2048 && raw.ends_with(crate::lines::TRUNCATION_MARKER)));
+        assert!(matches!(&history[1], glow_executors::LogMsg::Entry(e) if e.content == "Done"));
+    }
+
+    #[tokio::test]
+    async fn test_idle_timeout_stops_silent_executor() {
+        use std::process::Stdio;
+        use std::time::Duration;
+
+        // A process that never writes anything
+        let mut child = tokio::process::Command::new("sleep")
+            .arg("30")
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .kill_on_drop(true)
+            .spawn()
+            .expect("should spawn sleep");
+
+        let msg_store = std::sync::Arc::new(glow_executors::MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(msg_store.clone());
+        let cancel = CancellationToken::new();
+        let outcome = tokio::time::timeout(
+            Duration::from_secs(5),
+            drive_executor(
+                &mut child,
+                &mut processor,
+                &msg_store,
+                &cancel,
+                ExecutorLimits {
+                    idle_timeout: Some(Duration::from_millis(100)),
+                    max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+                },
+            ),
+        )
+        .await
+        .expect("idle timeout should fire before the process exits");
+
+        assert!(matches!(outcome, ExecutorOutcome::IdleTimeout));
+        child.kill().await.expect("should kill idle process");
+    }
+
+    #[tokio::test]
+    async fn test_lagged_subscriber_resyncs_from_history() {
+        use glow_executors::{LogMsg, MsgStore};
+        use tokio::sync::broadcast::error::RecvError;
+
+        let store = MsgStore::new();
+        let (_, mut rx) = store.subscribe_from(0).await;
+        for i in 0..300 {
+            store.push(LogMsg::Raw(i.to_string())).await;
+        }
+
+        let Err(RecvError::Lagged(skipped)) = rx.recv().await else {
+            unreachable!("subscriber should have lagged");
+        };
+
+        let mut delivered = 0;
+        let (messages, mut rx) = resync_after_lag(&store, skipped, &mut delivered).await;
+        assert!(matches!(
+            &messages[0],
+            StreamMessage::Error { message } if *message == format!("dropped {skipped} messages")
+        ));
+        assert_eq!(messages.len(), 301);
+        assert_eq!(delivered, 300);
+        assert_eq!(store.drain_subscriber_lag(), skipped);
+
+        // The fresh receiver continues with new messages only
+        store.push(LogMsg::Ended).await;
+        let next = rx.recv().await.expect("should receive after resync");
+        assert!(matches!(*next, Ok(LogMsg::Ended)));
+    }
+}
@@ -1,6 +1,13 @@
 //! Health check endpoint.
 
-use axum::{Json, Router, routing::get};
+use axum::{
+    Json, Router,
+    extract::{Path, State},
+    http::StatusCode,
+    routing::get,
+};
+use glow_executors::executors::claude::ToolchainReport;
+use glow_executors::{BaseDocumentAgent, Capabilities, DocumentAgent, StandardDocumentExecutor};
 use serde::Serialize;
 
 use crate::state::AppState;
@@ -23,16 +30,22 @@ pub struct ExecutorStatus {
     pub name: String,
     /// Availability status.
     pub available: bool,
+    /// Diagnostics of the toolchain the executor runs on, if it has one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub toolchain: Option<ToolchainReport>,
 }
 
 /// Build the health router.
 pub fn router() -> Router<AppState> {
-    Router::new().route("/health", get(health_check)).route("/executors", get(list_executors))
+    Router::new()
+        .route("/health", get(health_check))
+        .route("/executors", get(list_executors))
+        .route("/executors/{name}/capabilities", get(get_capabilities))
 }
 
 /// Health check handler.
 async fn health_check() -> Json<HealthResponse> {
-    use glow_executors::{StandardDocumentExecutor, executors::ClaudeCode};
+    use glow_executors::executors::ClaudeCode;
 
     let claude_available = matches!(
         ClaudeCode::default().get_availability_info(),
@@ -46,13 +59,16 @@ async fn health_check() -> Json<HealthResponse> {
         executors: vec![ExecutorStatus {
             name: "CLAUDE_CODE".to_owned(),
             available: claude_available,
+            toolchain: None,
         }],
     })
 }
 
-/// List available executors.
-async fn list_executors() -> Json<Vec<ExecutorStatus>> {
-    use glow_executors::{StandardDocumentExecutor, executors::ClaudeCode};
+/// List available executors, with diagnostics of the toolchain each runs on.
+///
+/// Toolchain probes are cached briefly, so repeated calls do not spawn processes.
+async fn list_executors(State(state): State<AppState>) -> Json<Vec<ExecutorStatus>> {
+    use glow_executors::executors::ClaudeCode;
 
     let claude_available = matches!(
         ClaudeCode::default().get_availability_info(),
@@ -60,5 +76,89 @@ async fn list_executors() -> Json<Vec<ExecutorStatus>> {
             | glow_executors::AvailabilityInfo::InstallationFound
     );
 
-    Json(vec![ExecutorStatus { name: "CLAUDE_CODE".to_owned(), available: claude_available }])
+    Json(vec![ExecutorStatus {
+        name: "CLAUDE_CODE".to_owned(),
+        available: claude_available,
+        toolchain: Some(state.toolchain.report().await),
+    }])
+}
+
+/// Optional features an executor supports.
+///
+/// Registered custom executors take precedence over built-ins of the same name.
+async fn get_capabilities(
+    State(state): State<AppState>,
+    Path(name): Path<String>,
+) -> Result<Json<Capabilities>, StatusCode> {
+    if let Some(custom) = state.executor_registry.resolve(&name) {
+        return Ok(Json(custom.capabilities()));
+    }
+    let base = name.parse::<BaseDocumentAgent>().map_err(|_| StatusCode::NOT_FOUND)?;
+    Ok(Json(DocumentAgent::from_base(base).capabilities()))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::toolchain::ToolchainCache;
+
+    #[tokio::test]
+    async fn test_executors_report_cached_toolchain() {
+        use std::sync::atomic::{AtomicUsize, Ordering};
+
+        let probes = std::sync::Arc::new(AtomicUsize::new(0));
+        let counter = std::sync::Arc::clone(&probes);
+        let cache = ToolchainCache::with_probe(std::time::Duration::from_secs(60), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            ToolchainReport {
+                node_version: None,
+                npm_version: Some("10.2.4".to_owned()),
+                npx_version: Some("10.2.4".to_owned()),
+                package_resolvable: true,
+                problems: vec!["Node.js was not found on PATH".to_owned()],
+            }
+        });
+        let mut state = AppState::new();
+        state.toolchain = std::sync::Arc::new(cache);
+        let app = router().with_state(state);
+
+        for _ in 0..2 {
+            let request =
+                Request::get("/executors").body(Body::empty()).expect("should build request");
+            let response = app.clone().oneshot(request).await.expect("should respond");
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .expect("should read body");
+            let json: serde_json::Value = serde_json::from_slice(&body).expect("should be JSON");
+            let toolchain = &json[0]["toolchain"];
+            assert_eq!(toolchain["npmVersion"], "10.2.4");
+            assert_eq!(toolchain["problems"][0], "Node.js was not found on PATH");
+        }
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+
+    #[tokio::test]
+    async fn test_capabilities_by_executor_name() {
+        let app = router().with_state(AppState::new());
+
+        let request = Request::get("/executors/CLAUDE_CODE/capabilities")
+            .body(Body::empty())
+            .expect("should build request");
+        let response = app.clone().oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        let json: serde_json::Value = serde_json::from_slice(&body).expect("should be JSON");
+        assert_eq!(json["followUps"], true);
+        assert_eq!(json["suggestedEdits"], true);
+
+        let request = Request::get("/executors/NOPE/capabilities")
+            .body(Body::empty())
+            .expect("should build request");
+        let response = app.oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+    }
 }
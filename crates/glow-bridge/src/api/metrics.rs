@@ -0,0 +1,74 @@
+//! Metrics endpoint.
+
+use axum::{
+    Json, Router,
+    extract::State,
+    http::{HeaderMap, header},
+    response::{IntoResponse, Response},
+    routing::get,
+};
+
+use crate::state::AppState;
+
+/// Build the metrics router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/metrics", get(get_metrics))
+}
+
+/// Return the bridge counters as JSON, or Prometheus text when the client accepts `text/plain`.
+async fn get_metrics(State(state): State<AppState>, headers: HeaderMap) -> Response {
+    let snapshot = state.metrics.snapshot();
+
+    let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or_default();
+    let wants_text = accept
+        .split(',')
+        .map(|range| range.split(';').next().unwrap_or_default().trim())
+        .any(|media| media == "text/plain");
+
+    if wants_text {
+        ([(header::CONTENT_TYPE, "text/plain; version=0.0.4")], snapshot.to_prometheus())
+            .into_response()
+    } else {
+        Json(snapshot).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn get_metrics_body(state: AppState, accept: &str) -> (String, String) {
+        let request = Request::get("/metrics")
+            .header(header::ACCEPT, accept)
+            .body(Body::empty())
+            .expect("should build request");
+        let response = router().with_state(state).oneshot(request).await.expect("should respond");
+        let content_type = response.headers()[header::CONTENT_TYPE]
+            .to_str()
+            .expect("content type should be text")
+            .to_owned();
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        (content_type, String::from_utf8(body.to_vec()).expect("body should be utf-8"))
+    }
+
+    #[tokio::test]
+    async fn test_metrics_content_negotiation() {
+        let state = AppState::new();
+        state.metrics.session_started();
+        state.metrics.record_tokens(42);
+
+        let (content_type, body) = get_metrics_body(state.clone(), "application/json").await;
+        assert_eq!(content_type, "application/json");
+        let json: serde_json::Value = serde_json::from_str(&body).expect("should be JSON");
+        assert_eq!(json["sessionsStarted"], 1);
+        assert_eq!(json["tokensConsumed"], 42);
+
+        let (content_type, body) = get_metrics_body(state, "text/plain").await;
+        assert!(content_type.starts_with("text/plain"));
+        assert!(body.contains("glow_bridge_tokens_consumed_total 42\n"));
+    }
+}
@@ -1,13 +1,25 @@
 //! API routes for the bridge server.
 
+mod comments;
 mod feedback;
 mod health;
+mod metrics;
+mod sessions;
+mod usage;
 
 use axum::Router;
 
 use crate::state::AppState;
 
 /// Build the API router.
-pub fn router() -> Router<AppState> {
-    Router::new().nest("/feedback", feedback::router()).merge(health::router())
+///
+/// Feedback requests with bodies larger than `max_body_size` bytes are rejected.
+pub fn router(max_body_size: usize) -> Router<AppState> {
+    Router::new()
+        .nest("/feedback", feedback::router(max_body_size))
+        .merge(comments::router())
+        .merge(health::router())
+        .merge(metrics::router())
+        .merge(sessions::router())
+        .merge(usage::router())
 }
@@ -0,0 +1,171 @@
+//! Session administration endpoints.
+
+use axum::{
+    Json, Router,
+    extract::{Path, Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use serde::Serialize;
+use tracing::info;
+
+use super::feedback::{StreamQuery, authorized_session};
+use crate::state::AppState;
+
+/// Build the sessions router.
+pub fn router() -> Router<AppState> {
+    Router::new()
+        .route("/sessions/{id}/connections", get(get_connections).delete(revoke_connections))
+}
+
+/// Stream connections of a session.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ConnectionsResponse {
+    /// Open WebSocket and event-stream connections.
+    pub connections: usize,
+}
+
+/// Connections closed by a revoke.
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct RevokeResponse {
+    /// Connections that were open and are being closed.
+    pub revoked: usize,
+}
+
+/// Count a session's open stream connections.
+///
+/// Requires the session's stream token, like every other per-session endpoint.
+async fn get_connections(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
+) -> Result<Json<ConnectionsResponse>, StatusCode> {
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+    let connections = session.read().await.connections.active();
+    Ok(Json(ConnectionsResponse { connections }))
+}
+
+/// Close all of a session's open stream connections, such as stuck clients.
+///
+/// Requires the session's stream token. Clients may reconnect afterwards.
+async fn revoke_connections(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    Query(query): Query<StreamQuery>,
+) -> Result<Json<RevokeResponse>, StatusCode> {
+    let session = authorized_session(&state, &id, query.token.as_deref()).await?;
+    let revoked = session.read().await.connections.revoke_all();
+    info!(session_id = %id, revoked, "Revoked stream connections");
+    Ok(Json(RevokeResponse { revoked }))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use axum::{body::Body, http::Request};
+    use futures::StreamExt;
+    use glow_executors::DocumentAgent;
+    use glow_executors::executors::ClaudeCode;
+    use tokio_tungstenite::tungstenite::Message;
+    use tower::ServiceExt;
+
+    use super::*;
+
+    async fn call(state: &AppState, request: Request<Body>) -> serde_json::Value {
+        let response =
+            router().with_state(state.clone()).oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        serde_json::from_slice(&body).expect("should be JSON")
+    }
+
+    async fn status(state: &AppState, request: Request<Body>) -> StatusCode {
+        router().with_state(state.clone()).oneshot(request).await.expect("should respond").status()
+    }
+
+    async fn connections(state: &AppState, id: &str, token: &str) -> serde_json::Value {
+        let request =
+            Request::get(format!("/sessions/{id}/connections?token={token}")).body(Body::empty());
+        call(state, request.expect("valid request")).await["connections"].clone()
+    }
+
+    /// Wait for connections to register or unregister, which happens asynchronously.
+    async fn wait_for_connections(state: &AppState, id: &str, token: &str, expected: usize) {
+        for _ in 0..100 {
+            if connections(state, id, token).await == expected {
+                break;
+            }
+            tokio::time::sleep(Duration::from_millis(10)).await;
+        }
+        assert_eq!(connections(state, id, token).await, expected);
+    }
+
+    #[tokio::test]
+    async fn test_revoke_closes_stream_connections() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let (id, token) = {
+            let s = session.read().await;
+            (s.id.clone(), s.stream_token.clone())
+        };
+
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let app = crate::api::router(1024).with_state(state.clone());
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        let url = format!("ws://{addr}/feedback/{id}/ws?token={token}");
+        let mut sockets = Vec::new();
+        for _ in 0..2 {
+            let (socket, _) = tokio_tungstenite::connect_async(&url).await.expect("should connect");
+            sockets.push(socket);
+        }
+        wait_for_connections(&state, &id, &token, 2).await;
+
+        let revoke = Request::delete(format!("/sessions/{id}/connections?token={token}"))
+            .body(Body::empty());
+        assert_eq!(call(&state, revoke.expect("valid request")).await["revoked"], 2);
+
+        for mut socket in sockets {
+            let frame = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("socket should be closed")
+                .expect("should receive a frame")
+                .expect("should receive a frame");
+            assert!(
+                matches!(&frame, Message::Close(Some(close)) if u16::from(close.code) == 4001),
+                "expected a revoked close frame, got {frame:?}"
+            );
+        }
+        wait_for_connections(&state, &id, &token, 0).await;
+    }
+
+    #[tokio::test]
+    async fn test_connections_require_the_stream_token() {
+        let state = AppState::new();
+        let agent = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let session =
+            state.create_session("comment-1".to_owned(), "doc-1".to_owned(), agent, true).await;
+        let id = session.read().await.id.clone();
+
+        let missing = format!("/sessions/{id}/connections");
+        let wrong = format!("/sessions/{id}/connections?token=wrong");
+        let requests = [
+            Request::get(&missing),
+            Request::delete(&missing),
+            Request::get(&wrong),
+            Request::delete(&wrong),
+        ];
+        for request in requests {
+            let request = request.body(Body::empty()).expect("valid request");
+            assert_eq!(status(&state, request).await, StatusCode::FORBIDDEN);
+        }
+    }
+}
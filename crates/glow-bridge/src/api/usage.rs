@@ -0,0 +1,112 @@
+//! Executor usage reporting endpoint.
+
+use axum::{
+    Json, Router,
+    extract::{Query, State},
+    http::StatusCode,
+    routing::get,
+};
+use chrono::{DateTime, Utc};
+use serde::Deserialize;
+
+use crate::state::AppState;
+use crate::usage::UsageSummary;
+
+/// Build the usage router.
+pub fn router() -> Router<AppState> {
+    Router::new().route("/usage", get(get_usage))
+}
+
+/// Window of sessions to report on, by creation time.
+#[derive(Debug, Deserialize)]
+pub struct UsageQuery {
+    /// Only sessions created at or after this time.
+    since: Option<DateTime<Utc>>,
+    /// Only sessions created before this time.
+    until: Option<DateTime<Utc>>,
+}
+
+/// Total cost and tokens of the sessions in the window, with a per-executor breakdown.
+async fn get_usage(
+    State(state): State<AppState>,
+    Query(query): Query<UsageQuery>,
+) -> Result<Json<UsageSummary>, StatusCode> {
+    if let (Some(since), Some(until)) = (query.since, query.until)
+        && since > until
+    {
+        return Err(StatusCode::BAD_REQUEST);
+    }
+    Ok(Json(state.usage.summarize(query.since, query.until)))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use chrono::TimeDelta;
+    use tower::ServiceExt;
+
+    use super::*;
+    use crate::usage::UsageRecord;
+
+    async fn get_usage_json(state: AppState, query: &str) -> (StatusCode, serde_json::Value) {
+        let request = Request::get(format!("/usage{query}"))
+            .body(Body::empty())
+            .expect("should build request");
+        let response = router().with_state(state).oneshot(request).await.expect("should respond");
+        let status = response.status();
+        let body =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        (status, serde_json::from_slice(&body).unwrap_or_default())
+    }
+
+    #[tokio::test]
+    async fn test_usage_aggregates_sessions_by_executor() {
+        let state = AppState::new();
+        let now = Utc::now();
+        let record = |session_id: &str, executor: &str, age_days, cost_usd, tokens| UsageRecord {
+            session_id: session_id.to_owned(),
+            executor: executor.to_owned(),
+            session_created_at: now - TimeDelta::days(age_days),
+            cost_usd,
+            tokens,
+        };
+        let records = [
+            record("first", "CLAUDE_CODE", 1, 0.5, 1_000),
+            // A follow-up run of the same session
+            record("first", "CLAUDE_CODE", 1, 0.25, 400),
+            record("second", "CLAUDE_CODE", 2, 1.0, 2_000),
+            record("third", "reviewer", 2, 0.125, 100),
+            // Outside the window below
+            record("old", "CLAUDE_CODE", 30, 9.0, 9_000),
+        ];
+        for record in records {
+            state.usage.record(record).expect("should record");
+        }
+
+        let since = (now - TimeDelta::days(7)).format("%Y-%m-%dT%H:%M:%SZ");
+        let (status, json) = get_usage_json(state.clone(), &format!("?since={since}")).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(json["sessions"], 3);
+        assert_eq!(json["totalCostUsd"], 1.875);
+        assert_eq!(json["totalTokens"], 3_500);
+        let claude = &json["byExecutor"]["CLAUDE_CODE"];
+        assert_eq!(
+            (&claude["sessions"], &claude["totalCostUsd"], &claude["totalTokens"]),
+            (&2.into(), &1.75.into(), &3_400.into())
+        );
+        let reviewer = &json["byExecutor"]["reviewer"];
+        assert_eq!(
+            (&reviewer["sessions"], &reviewer["totalCostUsd"], &reviewer["totalTokens"]),
+            (&1.into(), &0.125.into(), &100.into())
+        );
+
+        // Without a window every session counts
+        let (_, json) = get_usage_json(state.clone(), "").await;
+        assert_eq!(json["sessions"], 4);
+        assert_eq!(json["totalCostUsd"], 10.875);
+
+        let until = (now - TimeDelta::days(60)).format("%Y-%m-%dT%H:%M:%SZ");
+        let (status, _) = get_usage_json(state, &format!("?since={since}&until={until}")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
@@ -0,0 +1,127 @@
+//! Append-only audit log of executor spawns.
+
+use std::collections::BTreeMap;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use chrono::Utc;
+use glow_executors::ExecutionEnv;
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+/// Writes one JSON line per executor spawn.
+///
+/// Prompts are recorded as a SHA-256 hash unless [`AuditLog::with_prompts`] is set,
+/// and secret environment variables are always redacted.
+pub struct AuditLog {
+    file: Mutex<File>,
+    include_prompts: bool,
+}
+
+/// An executor about to be spawned.
+pub struct SpawnAudit<'a> {
+    /// Executor name.
+    pub executor: &'a str,
+    /// Model requested, if any.
+    pub model: Option<&'a str>,
+    /// Document the prompt was built from.
+    pub document_id: &'a str,
+    /// Prompt sent to the executor.
+    pub prompt: &'a str,
+    /// Environment the executor runs with.
+    pub env: &'a ExecutionEnv,
+}
+
+/// A line of the audit log.
+#[derive(Serialize)]
+struct AuditRecord<'a> {
+    timestamp: String,
+    executor: &'a str,
+    model: Option<&'a str>,
+    document_id: &'a str,
+    prompt_sha256: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    prompt: Option<&'a str>,
+    env: BTreeMap<String, String>,
+}
+
+impl AuditLog {
+    /// Open the audit log at `path` for appending, creating it if needed.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).append(true).open(path)?;
+        Ok(Self { file: Mutex::new(file), include_prompts: false })
+    }
+
+    /// Record full prompts in addition to their hashes.
+    #[must_use]
+    pub const fn with_prompts(mut self, include_prompts: bool) -> Self {
+        self.include_prompts = include_prompts;
+        self
+    }
+
+    /// Append a record for a spawn.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written.
+    pub fn record(&self, spawn: &SpawnAudit<'_>) -> std::io::Result<()> {
+        let record = AuditRecord {
+            timestamp: Utc::now().to_rfc3339(),
+            executor: spawn.executor,
+            model: spawn.model,
+            document_id: spawn.document_id,
+            prompt_sha256: format!("{:x}", Sha256::digest(spawn.prompt.as_bytes())),
+            prompt: self.include_prompts.then_some(spawn.prompt),
+            env: spawn.env.redacted_vars(),
+        };
+        let mut line = serde_json::to_string(&record)?;
+        line.push('\n');
+
+        let mut file = self.file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_record_hashes_prompt_and_redacts_env() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("audit.log");
+        let mut env = ExecutionEnv::new();
+        env.insert("ANTHROPIC_API_KEY", "sk-ant-123");
+        let spawn = SpawnAudit {
+            executor: "CLAUDE_CODE",
+            model: Some("sonnet"),
+            document_id: "doc-1",
+            prompt: "Review this",
+            env: &env,
+        };
+
+        AuditLog::open(&path).expect("should open log").record(&spawn).expect("should record");
+        let with_prompts = AuditLog::open(&path).expect("should reopen log").with_prompts(true);
+        with_prompts.record(&spawn).expect("should record");
+
+        let text = std::fs::read_to_string(&path).expect("should read log");
+        let lines: Vec<serde_json::Value> =
+            text.lines().map(|l| serde_json::from_str(l).expect("line should be JSON")).collect();
+        assert_eq!(lines.len(), 2);
+        assert_eq!(lines[0]["executor"], "CLAUDE_CODE");
+        assert_eq!(lines[0]["model"], "sonnet");
+        assert_eq!(lines[0]["document_id"], "doc-1");
+        assert_eq!(lines[0]["prompt_sha256"].as_str().map(str::len), Some(64));
+        assert_eq!(lines[0].get("prompt"), None);
+        assert_eq!(lines[0]["env"]["ANTHROPIC_API_KEY"], glow_executors::REDACTED);
+        assert_eq!(lines[1]["prompt"], "Review this");
+        assert!(!text.contains("sk-ant-123"));
+    }
+}
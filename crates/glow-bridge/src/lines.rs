@@ -0,0 +1,96 @@
+//! Line reading with a cap on line length.
+
+use tokio::io::{AsyncBufRead, AsyncBufReadExt};
+
+/// Appended to a line that was cut at the length limit.
+pub const TRUNCATION_MARKER: &str = "…[truncated]";
+
+/// A line read by [`BoundedLines`].
+#[derive(Debug)]
+pub struct BoundedLine {
+    /// The line without its terminator, ending in [`TRUNCATION_MARKER`] if it was cut.
+    pub text: String,
+    /// Bytes discarded past the length limit.
+    pub truncated_bytes: usize,
+}
+
+/// Splits a reader into lines of at most `max_len` bytes.
+///
+/// Unlike [`tokio::io::Lines`], bytes past the limit are discarded as they
+/// arrive instead of buffered, so a single enormous line cannot exhaust
+/// memory. Invalid UTF-8 is replaced rather than treated as an error.
+pub struct BoundedLines<R> {
+    reader: R,
+    max_len: usize,
+}
+
+impl<R: AsyncBufRead + Unpin> BoundedLines<R> {
+    /// Read lines from `reader`, keeping at most `max_len` bytes of each.
+    pub const fn new(reader: R, max_len: usize) -> Self {
+        Self { reader, max_len }
+    }
+
+    /// Read the next line, or `None` at end of input.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if reading from the underlying reader fails.
+    pub async fn next_line(&mut self) -> std::io::Result<Option<BoundedLine>> {
+        let mut line = Vec::new();
+        let mut truncated_bytes = 0;
+        let mut read_any = false;
+        loop {
+            let available = self.reader.fill_buf().await?;
+            if available.is_empty() {
+                break;
+            }
+            read_any = true;
+
+            let newline = available.iter().position(|&b| b == b'\n');
+            let chunk = &available[..newline.unwrap_or(available.len())];
+            let keep = chunk.len().min(self.max_len.saturating_sub(line.len()));
+            line.extend_from_slice(&chunk[..keep]);
+            truncated_bytes += chunk.len() - keep;
+
+            let used = chunk.len() + usize::from(newline.is_some());
+            self.reader.consume(used);
+            if newline.is_some() {
+                break;
+            }
+        }
+
+        if !read_any {
+            return Ok(None);
+        }
+        if truncated_bytes == 0 && line.last() == Some(&b'\r') {
+            line.pop();
+        }
+        let mut text = String::from_utf8_lossy(&line).into_owned();
+        if truncated_bytes > 0 {
+            text.push_str(TRUNCATION_MARKER);
+        }
+        Ok(Some(BoundedLine { text, truncated_bytes }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_long_lines_are_truncated() {
+        let input = format!("short\r\n{}\nlast", "x".repeat(100));
+        let mut lines = BoundedLines::new(input.as_bytes(), 16);
+
+        let short = lines.next_line().await.expect("should read").expect("should have line");
+        assert_eq!((short.text.as_str(), short.truncated_bytes), ("short", 0));
+
+        let long = lines.next_line().await.expect("should read").expect("should have line");
+        assert_eq!(long.text, format!("{}{TRUNCATION_MARKER}", "x".repeat(16)));
+        assert_eq!(long.truncated_bytes, 84);
+
+        let last = lines.next_line().await.expect("should read").expect("should have line");
+        assert_eq!(last.text, "last");
+        assert!(lines.next_line().await.expect("should read").is_none());
+    }
+}
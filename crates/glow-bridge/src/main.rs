@@ -14,6 +14,9 @@
 //!
 //! # Check available executors
 //! glow-bridge check
+//!
+//! # Machine-readable check for scripts (exits non-zero if anything is missing)
+//! glow-bridge check --json
 //! ```
 
 use clap::{Parser, Subcommand};
@@ -21,8 +24,15 @@ use tracing::info;
 use tracing_subscriber::EnvFilter;
 
 mod api;
+mod audit;
+mod lines;
+mod metrics;
+mod queue;
 mod server;
 mod state;
+mod toolchain;
+mod transcript;
+mod usage;
 
 /// Glow Bridge - Local server for AI document feedback.
 #[derive(Parser)]
@@ -35,6 +45,7 @@ struct Cli {
 }
 
 #[derive(Subcommand)]
+#[allow(clippy::large_enum_variant, reason = "parsed once at startup")]
 enum Commands {
     /// Start the bridge server.
     Serve {
@@ -49,6 +60,78 @@ enum Commands {
         /// Allowed origins for CORS (comma-separated).
         #[arg(long, default_value = "http://localhost:5173,http://127.0.0.1:5173")]
         allowed_origins: String,
+
+        /// Maximum request body size in bytes for feedback requests.
+        #[arg(long, default_value = "4194304")]
+        max_body_size: usize,
+
+        /// Maximum number of Claude Code processes running at once; extra requests queue.
+        #[arg(
+            long,
+            default_value_t = state::DEFAULT_MAX_CONCURRENT,
+            value_parser = clap::builder::RangedU64ValueParser::<usize>::new().range(1..),
+        )]
+        max_concurrent_claude: usize,
+
+        /// File containing a custom feedback prompt template.
+        ///
+        /// Supports `{title}`, `{document_id}`, `{selected_text}`, `{instruction}` and `{context}`.
+        #[arg(long)]
+        prompt_template: Option<std::path::PathBuf>,
+
+        /// Maximum document characters sent to the executor; longer content is truncated (0 disables).
+        #[arg(long, default_value_t = state::DEFAULT_MAX_CONTENT_CHARS)]
+        max_content_chars: usize,
+
+        /// Characters of surrounding text included either side of the selection in prompts (0 disables).
+        #[arg(long, default_value_t = 0)]
+        prompt_context_chars: usize,
+
+        /// Seconds an executor may go without output before the session fails (0 disables).
+        #[arg(long, default_value_t = state::DEFAULT_IDLE_TIMEOUT_SECS)]
+        idle_timeout_secs: u64,
+
+        /// Seconds a finished session is kept before it is removed (0 keeps sessions forever).
+        #[arg(long, default_value_t = state::DEFAULT_SESSION_TTL_SECS)]
+        session_ttl_secs: u64,
+
+        /// Maximum bytes kept from a single line of executor output; the rest is dropped.
+        #[arg(long, default_value_t = state::DEFAULT_MAX_LINE_BYTES)]
+        max_line_bytes: usize,
+
+        /// Append a record of every executor spawn to this file.
+        #[arg(long)]
+        audit_log: Option<std::path::PathBuf>,
+
+        /// Record full prompts in the audit log instead of only their hashes.
+        #[arg(long, requires = "audit_log")]
+        audit_include_prompt: bool,
+
+        /// Append the cost and tokens of every executor run to this file, and
+        /// include the runs already in it in usage reports.
+        #[arg(long)]
+        usage_log: Option<std::path::PathBuf>,
+
+        /// Reject requests that replace the built-in system prompt.
+        #[arg(long)]
+        disable_system_prompt_override: bool,
+
+        /// Maximum characters in a request's system prompt override.
+        #[arg(long, default_value_t = state::DEFAULT_MAX_SYSTEM_PROMPT_CHARS)]
+        max_system_prompt_chars: usize,
+
+        /// Cost in USD past which an executor is stopped and its session fails.
+        #[arg(long, value_parser = parse_cost_usd)]
+        max_cost_usd: Option<f64>,
+
+        /// JSON file of model token limits, added to the built-in table.
+        #[arg(long)]
+        model_limits: Option<std::path::PathBuf>,
+
+        /// Run COMMAND for requests naming executor NAME: the prompt is written to
+        /// its stdin and each line it prints is streamed back. Repeatable.
+        #[arg(long = "executor", value_name = "NAME=COMMAND", value_parser = parse_executor_command)]
+        executors: Vec<(String, std::path::PathBuf)>,
     },
 
     /// Check available executors.
@@ -56,6 +139,10 @@ enum Commands {
         /// Specific executor to check.
         #[arg(short, long)]
         executor: Option<String>,
+
+        /// Emit results as JSON and exit non-zero if any executor is missing.
+        #[arg(long)]
+        json: bool,
     },
 }
 
@@ -71,57 +158,289 @@ async fn main() -> anyhow::Result<()> {
     let cli = Cli::parse();
 
     match cli.command {
-        Commands::Serve { port, host, allowed_origins } => {
+        Commands::Serve {
+            port,
+            host,
+            allowed_origins,
+            max_body_size,
+            max_concurrent_claude,
+            prompt_template,
+            max_content_chars,
+            prompt_context_chars,
+            idle_timeout_secs,
+            session_ttl_secs,
+            max_line_bytes,
+            audit_log,
+            audit_include_prompt,
+            usage_log,
+            disable_system_prompt_override,
+            max_system_prompt_chars,
+            max_cost_usd,
+            model_limits,
+            executors,
+        } => {
             info!(host = %host, port = %port, "Starting Glow Bridge server");
 
             let origins: Vec<String> =
                 allowed_origins.split(',').map(|s| s.trim().to_owned()).collect();
 
-            server::start(&host, port, &origins).await?;
+            let prompt_template = match prompt_template {
+                Some(path) => glow_executors::PromptTemplate::load_from_file(&path)?,
+                None => glow_executors::PromptTemplate::default(),
+            };
+
+            let model_limits = match model_limits {
+                Some(path) => glow_executors::ModelLimits::load_from_file(&path)
+                    .map_err(|e| anyhow::anyhow!("failed to load model limits: {e}"))?,
+                None => glow_executors::ModelLimits::with_defaults(),
+            };
+
+            let mut state = state::AppState::new()
+                .with_executor_registry(executor_registry(executors))
+                .with_executor_limit(
+                    glow_executors::BaseDocumentAgent::ClaudeCode,
+                    max_concurrent_claude,
+                )
+                .with_prompt_template(prompt_template)
+                .with_max_content_chars((max_content_chars > 0).then_some(max_content_chars))
+                .with_prompt_context_chars(
+                    (prompt_context_chars > 0).then_some(prompt_context_chars),
+                )
+                .with_idle_timeout(
+                    (idle_timeout_secs > 0)
+                        .then(|| std::time::Duration::from_secs(idle_timeout_secs)),
+                )
+                .with_session_ttl(
+                    (session_ttl_secs > 0)
+                        .then(|| std::time::Duration::from_secs(session_ttl_secs)),
+                )
+                .with_default_model(
+                    std::env::var(state::DEFAULT_MODEL_ENV).ok().filter(|m| !m.is_empty()),
+                )
+                .with_max_line_bytes(max_line_bytes)
+                .with_system_prompt_override(!disable_system_prompt_override)
+                .with_max_system_prompt_chars(max_system_prompt_chars)
+                .with_max_cost_usd(max_cost_usd)
+                .with_model_limits(model_limits);
+
+            if let Some(path) = audit_log {
+                info!(path = %path.display(), "Writing executor audit log");
+                let audit_log = audit::AuditLog::open(&path)?.with_prompts(audit_include_prompt);
+                state = state.with_audit_log(audit_log);
+            }
+
+            if let Some(path) = usage_log {
+                info!(path = %path.display(), "Recording executor usage");
+                state = state.with_usage_ledger(usage::UsageLedger::open(&path)?);
+            }
+
+            let _reaper = state.spawn_session_reaper();
+            server::start(&host, port, &origins, max_body_size, state).await?;
         }
 
-        Commands::Check { executor } => {
-            check_executors(executor).await?;
+        Commands::Check { executor, json } => {
+            let all_ready = check_executors(executor.as_deref(), json, &mut std::io::stdout())?;
+            if json && !all_ready {
+                std::process::exit(1);
+            }
         }
     }
 
     Ok(())
 }
 
-async fn check_executors(executor_name: Option<String>) -> anyhow::Result<()> {
-    use glow_executors::{BaseDocumentAgent, StandardDocumentExecutor, executors::ClaudeCode};
+/// Availability of a single executor, as reported by `check --json`.
+#[derive(Debug, serde::Serialize)]
+struct ExecutorStatus {
+    name: String,
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    reason: Option<String>,
+}
 
-    println!("Checking available executors...\n");
+impl ExecutorStatus {
+    fn new(
+        agent: glow_executors::BaseDocumentAgent,
+        info: glow_executors::AvailabilityInfo,
+    ) -> Self {
+        use glow_executors::AvailabilityInfo;
 
-    let executors: Vec<(BaseDocumentAgent, Box<dyn Fn() -> glow_executors::AvailabilityInfo>)> =
-        vec![(
-            BaseDocumentAgent::ClaudeCode,
-            Box::new(|| ClaudeCode::default().get_availability_info()),
-        )];
+        let (status, reason) = match info {
+            AvailabilityInfo::Available => ("available", None),
+            AvailabilityInfo::InstallationFound => ("installation_found", None),
+            AvailabilityInfo::NotFound => ("not_found", None),
+            AvailabilityInfo::Unavailable { reason } => ("unavailable", Some(reason)),
+        };
+        Self { name: agent.to_string(), status, reason }
+    }
+
+    /// Whether the executor can be used (possibly after logging in).
+    fn is_ready(&self) -> bool {
+        matches!(self.status, "available" | "installation_found")
+    }
+}
 
-    for (agent, check_fn) in executors {
-        let name = format!("{agent}");
+/// Parse a cost in USD, rejecting negative and non-finite amounts.
+fn parse_cost_usd(value: &str) -> Result<f64, String> {
+    let cost: f64 = value.parse().map_err(|e| format!("{e}"))?;
+    if cost.is_finite() && cost >= 0.0 {
+        Ok(cost)
+    } else {
+        Err(format!("{value} is not a non-negative amount"))
+    }
+}
 
-        if let Some(ref filter) = executor_name {
-            if !name.to_lowercase().contains(&filter.to_lowercase()) {
-                continue;
-            }
+/// Registry running each `(name, command)` as a [`CommandExecutor`](glow_executors::executors::CommandExecutor).
+fn executor_registry(
+    executors: Vec<(String, std::path::PathBuf)>,
+) -> glow_executors::ExecutorRegistry {
+    let mut registry = glow_executors::ExecutorRegistry::new();
+    for (name, program) in executors {
+        info!(executor = %name, program = %program.display(), "Registering executor command");
+        registry.register(name, move || {
+            Box::new(glow_executors::executors::CommandExecutor::new(program.clone()))
+        });
+    }
+    registry
+}
+
+/// Parse a `NAME=COMMAND` executor registration.
+fn parse_executor_command(value: &str) -> Result<(String, std::path::PathBuf), String> {
+    match value.split_once('=') {
+        Some((name, command)) if !name.trim().is_empty() && !command.trim().is_empty() => {
+            Ok((name.trim().to_owned(), command.trim().into()))
         }
+        _ => Err(format!("{value} is not of the form NAME=COMMAND")),
+    }
+}
 
-        let info = check_fn();
-        let status = match info {
-            glow_executors::AvailabilityInfo::Available => "✓ Available",
-            glow_executors::AvailabilityInfo::InstallationFound => "◐ Installation found",
-            glow_executors::AvailabilityInfo::NotFound => "✗ Not found",
-            glow_executors::AvailabilityInfo::Unavailable { ref reason } => {
-                println!("  {name}: ✗ Unavailable ({reason})");
-                continue;
-            }
+/// Writes executor availability to `out`, returning whether every executor is ready.
+fn check_executors(
+    executor_name: Option<&str>,
+    json: bool,
+    out: &mut impl std::io::Write,
+) -> anyhow::Result<bool> {
+    let statuses: Vec<ExecutorStatus> = executor_availability(executor_name)
+        .into_iter()
+        .map(|(agent, info)| ExecutorStatus::new(agent, info))
+        .collect();
+    let all_ready = statuses.iter().all(ExecutorStatus::is_ready);
+
+    if json {
+        serde_json::to_writer_pretty(&mut *out, &statuses)?;
+        writeln!(out)?;
+        return Ok(all_ready);
+    }
+
+    writeln!(out, "Checking available executors...\n")?;
+    for status in &statuses {
+        let name = &status.name;
+        match (status.status, &status.reason) {
+            ("available", _) => writeln!(out, "  {name}: ✓ Available")?,
+            ("installation_found", _) => writeln!(out, "  {name}: ◐ Installation found")?,
+            (_, Some(reason)) => writeln!(out, "  {name}: ✗ Unavailable ({reason})")?,
+            _ => writeln!(out, "  {name}: ✗ Not found")?,
+        }
+    }
+    writeln!(out)?;
+
+    Ok(all_ready)
+}
+
+/// Check the availability of every supported executor whose name contains `filter`.
+fn executor_availability(
+    filter: Option<&str>,
+) -> Vec<(glow_executors::BaseDocumentAgent, glow_executors::AvailabilityInfo)> {
+    use glow_executors::{DocumentAgent, StandardDocumentExecutor};
+
+    DocumentAgent::all_base_agents()
+        .into_iter()
+        .filter(|agent| {
+            filter.is_none_or(|f| agent.to_string().to_lowercase().contains(&f.to_lowercase()))
+        })
+        .map(|agent| (agent, DocumentAgent::from_base(agent).get_availability_info()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use glow_executors::{BaseDocumentAgent, DocumentAgent};
+
+    use super::*;
+
+    #[test]
+    fn test_max_concurrent_claude_must_be_positive() {
+        let parse = |value: &str| {
+            Cli::try_parse_from(["glow-bridge", "serve", "--max-concurrent-claude", value])
         };
+        assert!(parse("0").is_err());
+        assert!(parse("2").is_ok());
+    }
 
-        println!("  {name}: {status}");
+    #[test]
+    fn test_parse_cost_usd() {
+        assert_eq!(parse_cost_usd("0.5"), Ok(0.5));
+        assert_eq!(parse_cost_usd("0"), Ok(0.0));
+        for invalid in ["-1", "NaN", "inf", "-inf", "cheap"] {
+            assert!(parse_cost_usd(invalid).is_err(), "{invalid} should be rejected");
+        }
     }
 
-    println!();
-    Ok(())
+    #[test]
+    fn test_executor_commands_are_parsed() {
+        let cli = Cli::try_parse_from([
+            "glow-bridge",
+            "serve",
+            "--executor",
+            "echo=/usr/bin/cat",
+            "--executor",
+            "lint = ./lint.sh",
+        ])
+        .expect("should parse");
+        let Commands::Serve { executors, .. } = cli.command else {
+            unreachable!("parsed the serve command");
+        };
+        assert_eq!(
+            executors,
+            [("echo".to_owned(), "/usr/bin/cat".into()), ("lint".to_owned(), "./lint.sh".into())]
+        );
+        for invalid in ["echo", "=cat", "echo="] {
+            assert!(parse_executor_command(invalid).is_err(), "{invalid} should be rejected");
+        }
+    }
+
+    #[test]
+    fn test_executor_availability_lists_all_variants() {
+        let agents: Vec<BaseDocumentAgent> =
+            executor_availability(None).into_iter().map(|(agent, _)| agent).collect();
+        assert_eq!(agents, DocumentAgent::all_base_agents());
+    }
+
+    #[test]
+    fn test_executor_availability_filter() {
+        let matched = executor_availability(Some("claude"));
+        assert_eq!(matched.len(), 1);
+        assert_eq!(matched[0].0, BaseDocumentAgent::ClaudeCode);
+
+        assert!(executor_availability(Some("no-such-executor")).is_empty());
+    }
+
+    #[test]
+    fn test_check_executors_json() {
+        let mut out = Vec::new();
+        let all_ready = check_executors(None, true, &mut out).expect("check should succeed");
+
+        let parsed: Vec<serde_json::Value> =
+            serde_json::from_slice(&out).expect("output should be valid JSON");
+        assert_eq!(parsed.len(), DocumentAgent::all_base_agents().len());
+        for entry in &parsed {
+            assert!(entry["name"].is_string());
+            assert!(entry["status"].is_string());
+        }
+
+        let any_missing = parsed
+            .iter()
+            .any(|e| matches!(e["status"].as_str(), Some("not_found" | "unavailable")));
+        assert_eq!(all_ready, !any_missing);
+    }
 }
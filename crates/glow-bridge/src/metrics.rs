@@ -0,0 +1,151 @@
+//! Counters for monitoring the bridge.
+
+use serde::Serialize;
+use std::fmt::Write;
+use std::sync::atomic::{AtomicU64, Ordering};
+
+use crate::state::SessionState;
+
+/// Session and token counters, updated atomically.
+#[derive(Debug, Default)]
+pub struct Metrics {
+    sessions_started: AtomicU64,
+    sessions_completed: AtomicU64,
+    sessions_failed: AtomicU64,
+    sessions_cancelled: AtomicU64,
+    tokens_consumed: AtomicU64,
+    active_sessions: AtomicU64,
+}
+
+/// Point-in-time copy of the bridge counters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct MetricsSnapshot {
+    /// Sessions that started running an executor.
+    pub sessions_started: u64,
+    /// Sessions that completed successfully.
+    pub sessions_completed: u64,
+    /// Sessions that failed.
+    pub sessions_failed: u64,
+    /// Sessions cancelled by a client.
+    pub sessions_cancelled: u64,
+    /// Input and output tokens consumed by executors.
+    pub tokens_consumed: u64,
+    /// Sessions currently running an executor.
+    pub active_sessions: u64,
+}
+
+impl Metrics {
+    /// Record that a session started running.
+    pub fn session_started(&self) {
+        self.sessions_started.fetch_add(1, Ordering::Relaxed);
+        self.active_sessions.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record that a running session finished in `state`.
+    ///
+    /// Cancellations are counted by [`Metrics::session_cancelled`] when they are
+    /// requested, so they only release the active slot here.
+    pub fn session_finished(&self, state: SessionState) {
+        match state {
+            SessionState::Completed => {
+                self.sessions_completed.fetch_add(1, Ordering::Relaxed);
+            }
+            SessionState::Cancelled => {}
+            SessionState::Pending | SessionState::Running | SessionState::Failed => {
+                self.sessions_failed.fetch_add(1, Ordering::Relaxed);
+            }
+        }
+        self.active_sessions.fetch_sub(1, Ordering::Relaxed);
+    }
+
+    /// Record that a client cancelled a session.
+    pub fn session_cancelled(&self) {
+        self.sessions_cancelled.fetch_add(1, Ordering::Relaxed);
+    }
+
+    /// Record tokens consumed by an executor.
+    pub fn record_tokens(&self, tokens: u64) {
+        self.tokens_consumed.fetch_add(tokens, Ordering::Relaxed);
+    }
+
+    /// Take a snapshot of all counters.
+    #[must_use]
+    pub fn snapshot(&self) -> MetricsSnapshot {
+        MetricsSnapshot {
+            sessions_started: self.sessions_started.load(Ordering::Relaxed),
+            sessions_completed: self.sessions_completed.load(Ordering::Relaxed),
+            sessions_failed: self.sessions_failed.load(Ordering::Relaxed),
+            sessions_cancelled: self.sessions_cancelled.load(Ordering::Relaxed),
+            tokens_consumed: self.tokens_consumed.load(Ordering::Relaxed),
+            active_sessions: self.active_sessions.load(Ordering::Relaxed),
+        }
+    }
+}
+
+impl MetricsSnapshot {
+    /// Render the counters in the Prometheus text exposition format.
+    #[must_use]
+    pub fn to_prometheus(self) -> String {
+        let metrics = [
+            ("sessions_started_total", "counter", "Sessions started.", self.sessions_started),
+            ("sessions_completed_total", "counter", "Sessions completed.", self.sessions_completed),
+            ("sessions_failed_total", "counter", "Sessions failed.", self.sessions_failed),
+            ("sessions_cancelled_total", "counter", "Sessions cancelled.", self.sessions_cancelled),
+            ("tokens_consumed_total", "counter", "Tokens consumed.", self.tokens_consumed),
+            ("active_sessions", "gauge", "Sessions currently running.", self.active_sessions),
+        ];
+
+        let mut out = String::new();
+        for (name, kind, help, value) in metrics {
+            let _ = writeln!(out, "# HELP glow_bridge_{name} {help}");
+            let _ = writeln!(out, "# TYPE glow_bridge_{name} {kind}");
+            let _ = writeln!(out, "glow_bridge_{name} {value}");
+        }
+        out
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_lifecycle_counters() {
+        let metrics = Metrics::default();
+
+        metrics.session_started();
+        metrics.session_started();
+        metrics.session_started();
+        assert_eq!(metrics.snapshot().active_sessions, 3);
+
+        metrics.record_tokens(150);
+        metrics.session_finished(SessionState::Completed);
+        metrics.session_finished(SessionState::Failed);
+        metrics.session_cancelled();
+        metrics.session_finished(SessionState::Cancelled);
+
+        assert_eq!(
+            metrics.snapshot(),
+            MetricsSnapshot {
+                sessions_started: 3,
+                sessions_completed: 1,
+                sessions_failed: 1,
+                sessions_cancelled: 1,
+                tokens_consumed: 150,
+                active_sessions: 0,
+            }
+        );
+    }
+
+    #[test]
+    fn test_prometheus_format() {
+        let metrics = Metrics::default();
+        metrics.session_started();
+
+        let text = metrics.snapshot().to_prometheus();
+        assert!(text.contains("# TYPE glow_bridge_sessions_started_total counter\n"));
+        assert!(text.contains("glow_bridge_sessions_started_total 1\n"));
+        assert!(text.contains("glow_bridge_active_sessions 1\n"));
+    }
+}
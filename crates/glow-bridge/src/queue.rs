@@ -0,0 +1,167 @@
+//! Priority queue for feedback sessions waiting on an executor slot.
+
+use std::cmp::Ordering;
+use std::collections::BinaryHeap;
+use std::sync::{Arc, Mutex, PoisonError};
+
+use tokio::sync::oneshot;
+
+/// Bounded-concurrency queue that dispatches waiting sessions by priority.
+///
+/// Higher priorities are dispatched first; equal priorities are dispatched in
+/// arrival order. Cloning shares the same slots.
+#[derive(Clone)]
+pub struct FeedbackQueue {
+    state: Arc<Mutex<QueueState>>,
+}
+
+/// Slots and waiters, guarded by the queue mutex.
+struct QueueState {
+    available: usize,
+    waiting: BinaryHeap<Waiter>,
+    next_seq: u64,
+}
+
+/// A session waiting for a slot.
+struct Waiter {
+    priority: u8,
+    seq: u64,
+    sender: oneshot::Sender<QueuePermit>,
+}
+
+impl PartialEq for Waiter {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for Waiter {}
+
+impl PartialOrd for Waiter {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Ord for Waiter {
+    fn cmp(&self, other: &Self) -> Ordering {
+        // Max-heap: highest priority first, then earliest arrival
+        self.priority.cmp(&other.priority).then_with(|| other.seq.cmp(&self.seq))
+    }
+}
+
+/// A slot in a [`FeedbackQueue`], handed to the next waiter when dropped.
+pub struct QueuePermit {
+    state: Option<Arc<Mutex<QueueState>>>,
+}
+
+impl FeedbackQueue {
+    /// Create a queue that runs at most `permits` sessions at once.
+    #[must_use]
+    pub fn new(permits: usize) -> Self {
+        Self {
+            state: Arc::new(Mutex::new(QueueState {
+                available: permits,
+                waiting: BinaryHeap::new(),
+                next_seq: 0,
+            })),
+        }
+    }
+
+    /// Wait for a slot, behind any waiters of equal or higher priority.
+    ///
+    /// Dropping the future gives up the place in the queue.
+    pub async fn acquire(&self, priority: u8) -> QueuePermit {
+        let receiver = {
+            let mut state = self.state.lock().unwrap_or_else(PoisonError::into_inner);
+            if state.available > 0 && state.waiting.is_empty() {
+                state.available -= 1;
+                return QueuePermit { state: Some(self.state.clone()) };
+            }
+            let (sender, receiver) = oneshot::channel();
+            let seq = state.next_seq;
+            state.next_seq += 1;
+            state.waiting.push(Waiter { priority, seq, sender });
+            receiver
+        };
+
+        // The sender is only dropped by handing over a permit
+        receiver.await.unwrap_or(QueuePermit { state: None })
+    }
+
+    /// Number of sessions waiting for a slot.
+    #[cfg(test)]
+    #[must_use]
+    pub fn queued(&self) -> usize {
+        self.state.lock().unwrap_or_else(PoisonError::into_inner).waiting.len()
+    }
+}
+
+impl Drop for QueuePermit {
+    fn drop(&mut self) {
+        let Some(shared) = self.state.take() else {
+            return;
+        };
+        let mut state = shared.lock().unwrap_or_else(PoisonError::into_inner);
+        while let Some(waiter) = state.waiting.pop() {
+            match waiter.sender.send(Self { state: Some(shared.clone()) }) {
+                Ok(()) => return,
+                // The waiter gave up; disarm the permit and try the next one
+                Err(mut permit) => permit.state = None,
+            }
+        }
+        state.available += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    async fn record_dispatch(queue: FeedbackQueue, priority: u8, order: Arc<Mutex<Vec<u8>>>) {
+        let _permit = queue.acquire(priority).await;
+        order.lock().expect("order lock").push(priority);
+    }
+
+    async fn wait_until_queued(queue: &FeedbackQueue, count: usize) {
+        while queue.queued() < count {
+            tokio::task::yield_now().await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dispatches_by_priority() {
+        let queue = FeedbackQueue::new(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let running = queue.acquire(0).await;
+
+        let mut tasks = Vec::new();
+        for priority in [1, 5, 3] {
+            tasks.push(tokio::spawn(record_dispatch(queue.clone(), priority, order.clone())));
+            wait_until_queued(&queue, tasks.len()).await;
+        }
+
+        drop(running);
+        for task in tasks {
+            task.await.expect("task should finish");
+        }
+        assert_eq!(*order.lock().expect("order lock"), [5, 3, 1]);
+    }
+
+    #[tokio::test]
+    async fn test_abandoned_waiter_is_skipped() {
+        let queue = FeedbackQueue::new(1);
+        let order = Arc::new(Mutex::new(Vec::new()));
+        let running = queue.acquire(0).await;
+
+        let abandoned = tokio::spawn(record_dispatch(queue.clone(), 9, order.clone()));
+        wait_until_queued(&queue, 1).await;
+        abandoned.abort();
+        let _ = abandoned.await;
+
+        drop(running);
+        let _permit = queue.acquire(0).await;
+        assert_eq!(queue.queued(), 0);
+        assert!(order.lock().expect("order lock").is_empty());
+    }
+}
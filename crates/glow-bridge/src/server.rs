@@ -10,15 +10,19 @@ use crate::api;
 use crate::state::AppState;
 
 /// Start the bridge server.
-pub async fn start(host: &str, port: u16, allowed_origins: &[String]) -> anyhow::Result<()> {
-    let state = AppState::new();
-
+pub async fn start(
+    host: &str,
+    port: u16,
+    allowed_origins: &[String],
+    max_body_size: usize,
+    state: AppState,
+) -> anyhow::Result<()> {
     // Build CORS layer
     let cors = build_cors_layer(allowed_origins);
 
     // Build router
     let app = Router::new()
-        .nest("/api", api::router())
+        .nest("/api", api::router(max_body_size))
         .layer(cors)
         .layer(TraceLayer::new_for_http())
         .with_state(state);
@@ -31,11 +35,17 @@ pub async fn start(host: &str, port: u16, allowed_origins: &[String]) -> anyhow:
     info!("");
     info!("API Endpoints:");
     info!("  POST   /api/feedback          - Submit feedback request");
+    info!("  POST   /api/feedback/validate - Preview a request's prompt and token estimate");
     info!("  GET    /api/feedback/:id      - Get feedback status");
     info!("  DELETE /api/feedback/:id      - Cancel feedback request");
-    info!("  GET    /api/feedback/:id/ws   - WebSocket stream");
+    info!("  GET    /api/feedback/:id/ws   - WebSocket stream (?token=<streamToken>)");
+    info!("  GET    /api/feedback/:id/sse  - Server-Sent Events stream (?token=<streamToken>)");
+    info!("  GET    /api/comments/:id/feedback - Feedback sessions of a comment thread");
     info!("  GET    /api/executors         - List available executors");
     info!("  GET    /api/health            - Health check");
+    info!("  GET    /api/metrics           - Session and token counters");
+    info!("  GET    /api/sessions/:id/connections - Count open stream connections");
+    info!("  DELETE /api/sessions/:id/connections - Close open stream connections");
 
     // Start server
     let listener = tokio::net::TcpListener::bind(addr).await?;
@@ -1,9 +1,50 @@
 //! Application state for the bridge server.
 
-use glow_executors::{DocumentAgent, ExecutorConfigs, MsgStore};
+use chrono::{DateTime, Utc};
+use glow_executors::executors::DynExecutor;
+use glow_executors::{
+    BaseDocumentAgent, DocumentAgent, ExecutorConfigs, ExecutorRegistry, ModelLimits, MsgStore,
+    PromptTemplate, StandardDocumentExecutor,
+};
+use serde::Serialize;
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 use tokio::sync::RwLock;
+use tokio::task::JoinHandle;
+use tokio_util::sync::CancellationToken;
+use tracing::debug;
+
+use crate::audit::AuditLog;
+use crate::metrics::Metrics;
+use crate::queue::{FeedbackQueue, QueuePermit};
+use crate::toolchain::ToolchainCache;
+use crate::usage::UsageLedger;
+
+/// Default number of executor processes of each type allowed to run at once.
+pub const DEFAULT_MAX_CONCURRENT: usize = 4;
+
+/// Default maximum number of document characters sent to an executor.
+pub const DEFAULT_MAX_CONTENT_CHARS: usize = 200_000;
+
+/// Default number of seconds an executor may go without output before it is stopped.
+pub const DEFAULT_IDLE_TIMEOUT_SECS: u64 = 300;
+
+/// Default number of seconds a finished session is kept before it is reaped.
+pub const DEFAULT_SESSION_TTL_SECS: u64 = 3600;
+
+/// Longest wait between sweeps for expired sessions.
+const MAX_REAP_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Default maximum bytes kept from a single line of executor output.
+pub const DEFAULT_MAX_LINE_BYTES: usize = 16 * 1024 * 1024;
+
+/// Default maximum length, in characters, of a request's system prompt override.
+pub const DEFAULT_MAX_SYSTEM_PROMPT_CHARS: usize = 8_000;
+
+/// Environment variable naming the model used when a request does not pick one.
+pub const DEFAULT_MODEL_ENV: &str = "GLOW_DEFAULT_MODEL";
 
 /// A feedback session in progress.
 pub struct FeedbackSession {
@@ -14,15 +55,206 @@ pub struct FeedbackSession {
     /// Document ID being analyzed.
     pub document_id: String,
     /// The executor being used.
-    pub executor: DocumentAgent,
-    /// Message store for streaming logs.
+    pub executor: SessionExecutor,
+    /// Message store the current run streams into.
     pub msg_store: Arc<MsgStore>,
+    /// Finished message stores of earlier runs, oldest first.
+    pub earlier_stores: Vec<Arc<MsgStore>>,
     /// Session state.
     pub state: SessionState,
+    /// Cancelled when the session is cancelled; all session work stops on it.
+    pub cancel: CancellationToken,
+    /// Session ID reported by the executor, used to resume it for follow-ups.
+    pub agent_session_id: Option<String>,
+    /// Language of the executor's final response, if detected.
+    pub language: Option<String>,
+    /// Dispatch priority while queued; higher values run first.
+    pub priority: u8,
+    /// Cost in USD past which the executor is stopped and the session fails.
+    pub max_cost_usd: Option<f64>,
+    /// Secret a client must present to open the session's WebSocket stream.
+    pub stream_token: String,
+    /// Why the session last failed, if it did.
+    pub last_error: Option<String>,
+    /// Clients currently streaming the session.
+    pub connections: Arc<StreamConnections>,
+    /// When the session was created.
+    pub created_at: DateTime<Utc>,
+    /// When the session last finished, if it has.
+    pub finished_at: Option<DateTime<Utc>>,
+}
+
+impl FeedbackSession {
+    /// Summarize the session for listings.
+    #[must_use]
+    pub fn summary(&self) -> SessionSummary {
+        SessionSummary {
+            id: self.id.clone(),
+            document_id: self.document_id.clone(),
+            state: self.state,
+            created_at: self.created_at,
+            error: self.last_error.clone(),
+        }
+    }
+
+    /// Move the session to a finished `state`, recording when.
+    pub fn finish(&mut self, state: SessionState) {
+        self.state = state;
+        self.finished_at = Some(Utc::now());
+    }
+
+    /// Mark the session failed, recording why.
+    pub fn fail(&mut self, error: impl Into<String>) {
+        self.finish(SessionState::Failed);
+        self.last_error = Some(error.into());
+    }
+
+    /// Start a new message store for a follow-up run, returning it.
+    ///
+    /// Each run's store is finalized when the run ends, so a follow-up cannot
+    /// stream into it; the old store is kept for the session's log.
+    pub fn begin_segment(&mut self) -> Arc<MsgStore> {
+        let next = Arc::new(self.msg_store.next_segment());
+        self.earlier_stores.push(std::mem::replace(&mut self.msg_store, Arc::clone(&next)));
+        next
+    }
+
+    /// Message stores of every run, oldest first.
+    pub fn stores(&self) -> impl Iterator<Item = &Arc<MsgStore>> {
+        self.earlier_stores.iter().chain(std::iter::once(&self.msg_store))
+    }
+
+    /// Returns whether `token` grants access to the session's stream.
+    #[must_use]
+    pub fn accepts_stream_token(&self, token: Option<&str>) -> bool {
+        token.is_some_and(|token| token == self.stream_token)
+    }
+
+    /// Whether the session finished at least `ttl` before `now`.
+    #[must_use]
+    pub fn is_expired(&self, now: DateTime<Utc>, ttl: chrono::Duration) -> bool {
+        self.state.is_finished() && self.finished_at.is_some_and(|at| at + ttl <= now)
+    }
+}
+
+/// Live stream connections to a session, which can be closed on demand.
+#[derive(Debug, Default)]
+pub struct StreamConnections {
+    active: AtomicUsize,
+    revoke: Mutex<CancellationToken>,
+}
+
+impl StreamConnections {
+    /// Register a connection, counted until the returned guard drops.
+    #[must_use]
+    pub fn connect(self: &Arc<Self>) -> StreamConnection {
+        self.active.fetch_add(1, Ordering::Relaxed);
+        let revoked = self.revoke.lock().unwrap_or_else(PoisonError::into_inner).clone();
+        StreamConnection { connections: Arc::clone(self), revoked }
+    }
+
+    /// Number of open connections.
+    #[must_use]
+    pub fn active(&self) -> usize {
+        self.active.load(Ordering::Relaxed)
+    }
+
+    /// Close every open connection, returning how many there were.
+    ///
+    /// Connections opened afterwards are unaffected.
+    pub fn revoke_all(&self) -> usize {
+        let revoke =
+            std::mem::take(&mut *self.revoke.lock().unwrap_or_else(PoisonError::into_inner));
+        revoke.cancel();
+        self.active()
+    }
+}
+
+/// A registered stream connection; dropping it unregisters the connection.
+#[derive(Debug)]
+pub struct StreamConnection {
+    connections: Arc<StreamConnections>,
+    revoked: CancellationToken,
+}
+
+impl StreamConnection {
+    /// Completes once the connection is revoked.
+    pub async fn revoked(&self) {
+        self.revoked.cancelled().await;
+    }
+}
+
+impl Drop for StreamConnection {
+    fn drop(&mut self) {
+        self.connections.active.fetch_sub(1, Ordering::Relaxed);
+    }
+}
+
+/// Executor backing a feedback session.
+#[derive(Clone)]
+pub enum SessionExecutor {
+    /// A built-in executor.
+    Agent(DocumentAgent),
+    /// A custom executor resolved from the [`ExecutorRegistry`].
+    Custom {
+        /// Name the executor was registered under.
+        name: String,
+        /// The executor instance.
+        executor: Arc<dyn StandardDocumentExecutor + Send + Sync>,
+    },
+}
+
+impl SessionExecutor {
+    /// Wrap an executor resolved from the registry.
+    #[must_use]
+    pub fn custom(name: impl Into<String>, executor: DynExecutor) -> Self {
+        Self::Custom { name: name.into(), executor: Arc::from(executor) }
+    }
+
+    /// Name of the executor, as recorded in audit and usage logs.
+    #[must_use]
+    pub fn name(&self) -> String {
+        match self {
+            Self::Agent(agent) => agent.base_agent().to_string(),
+            Self::Custom { name, .. } => name.clone(),
+        }
+    }
+
+    /// Base agent type, for built-in executors.
+    #[must_use]
+    pub fn base_agent(&self) -> Option<BaseDocumentAgent> {
+        match self {
+            Self::Agent(agent) => Some(agent.base_agent()),
+            Self::Custom { .. } => None,
+        }
+    }
+}
+
+impl From<DocumentAgent> for SessionExecutor {
+    fn from(agent: DocumentAgent) -> Self {
+        Self::Agent(agent)
+    }
+}
+
+/// Overview of a feedback session, without its log.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SessionSummary {
+    /// Session ID.
+    pub id: String,
+    /// Document ID being analyzed.
+    pub document_id: String,
+    /// Session state.
+    pub state: SessionState,
+    /// When the session was created.
+    pub created_at: DateTime<Utc>,
+    /// Why the session failed, if it did.
+    pub error: Option<String>,
 }
 
 /// State of a feedback session.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SessionState {
     /// Session is queued for processing.
     Pending,
@@ -36,6 +268,14 @@ pub enum SessionState {
     Cancelled,
 }
 
+impl SessionState {
+    /// Returns whether the session has stopped for good.
+    #[must_use]
+    pub const fn is_finished(self) -> bool {
+        matches!(self, Self::Completed | Self::Failed | Self::Cancelled)
+    }
+}
+
 /// Shared application state.
 #[derive(Clone)]
 pub struct AppState {
@@ -43,6 +283,41 @@ pub struct AppState {
     pub sessions: Arc<RwLock<HashMap<String, Arc<RwLock<FeedbackSession>>>>>,
     /// Executor configurations.
     pub executor_configs: Arc<ExecutorConfigs>,
+    /// Custom executors, consulted before the built-ins.
+    pub executor_registry: Arc<ExecutorRegistry>,
+    /// Template used to build feedback prompts.
+    pub prompt_template: Arc<PromptTemplate>,
+    /// Session and token counters.
+    pub metrics: Arc<Metrics>,
+    /// Document content longer than this many characters is truncated.
+    pub max_content_chars: Option<usize>,
+    /// Characters of surrounding text included either side of the selection in
+    /// prompts, or `None` to leave it out.
+    pub prompt_context_chars: Option<usize>,
+    /// How long an executor may go without output before its session fails.
+    pub idle_timeout: Option<Duration>,
+    /// Model for the built-in Claude Code executor when a request does not pick one.
+    pub default_model: Option<String>,
+    /// Audit log that records every executor spawn, if enabled.
+    pub audit_log: Option<Arc<AuditLog>>,
+    /// Cost and tokens of every executor run.
+    pub usage: Arc<UsageLedger>,
+    /// Longest line of executor output kept, in bytes; longer lines are truncated.
+    pub max_line_bytes: usize,
+    /// Whether requests may replace the built-in system prompt.
+    pub allow_system_prompt_override: bool,
+    /// Longest system prompt override accepted, in characters.
+    pub max_system_prompt_chars: usize,
+    /// Cost in USD past which an executor is stopped and its session fails.
+    pub max_cost_usd: Option<f64>,
+    /// Token limits of the models executors can run.
+    pub model_limits: Arc<ModelLimits>,
+    /// How long after finishing a session is kept, or `None` to keep it forever.
+    pub session_ttl: Option<Duration>,
+    /// Recent diagnostics of the Node toolchain behind Claude Code.
+    pub toolchain: Arc<ToolchainCache>,
+    /// Concurrency-limited queues per executor type.
+    executor_queues: Arc<HashMap<BaseDocumentAgent, FeedbackQueue>>,
 }
 
 impl AppState {
@@ -51,25 +326,196 @@ impl AppState {
     pub fn new() -> Self {
         Self {
             sessions: Arc::new(RwLock::new(HashMap::new())),
-            executor_configs: Arc::new(ExecutorConfigs::new()),
+            executor_configs: Arc::new(ExecutorConfigs::load_user_or_defaults()),
+            executor_registry: Arc::new(ExecutorRegistry::new()),
+            prompt_template: Arc::new(PromptTemplate::default()),
+            metrics: Arc::new(Metrics::default()),
+            max_content_chars: Some(DEFAULT_MAX_CONTENT_CHARS),
+            prompt_context_chars: None,
+            idle_timeout: Some(Duration::from_secs(DEFAULT_IDLE_TIMEOUT_SECS)),
+            default_model: None,
+            audit_log: None,
+            usage: Arc::new(UsageLedger::default()),
+            max_line_bytes: DEFAULT_MAX_LINE_BYTES,
+            allow_system_prompt_override: true,
+            max_system_prompt_chars: DEFAULT_MAX_SYSTEM_PROMPT_CHARS,
+            max_cost_usd: None,
+            model_limits: Arc::new(ModelLimits::with_defaults()),
+            session_ttl: Some(Duration::from_secs(DEFAULT_SESSION_TTL_SECS)),
+            toolchain: Arc::new(ToolchainCache::default()),
+            executor_queues: Arc::new(
+                DocumentAgent::all_base_agents()
+                    .into_iter()
+                    .map(|agent| (agent, FeedbackQueue::new(DEFAULT_MAX_CONCURRENT)))
+                    .collect(),
+            ),
         }
     }
 
+    /// Consult `registry` for custom executors before the built-ins.
+    #[must_use]
+    pub fn with_executor_registry(mut self, registry: ExecutorRegistry) -> Self {
+        self.executor_registry = Arc::new(registry);
+        self
+    }
+
+    /// Use a custom template for feedback prompts.
+    #[must_use]
+    pub fn with_prompt_template(mut self, template: PromptTemplate) -> Self {
+        self.prompt_template = Arc::new(template);
+        self
+    }
+
+    /// Set the document content size limit, or `None` to send documents whole.
+    #[must_use]
+    pub const fn with_max_content_chars(mut self, max_content_chars: Option<usize>) -> Self {
+        self.max_content_chars = max_content_chars;
+        self
+    }
+
+    /// Include this many characters of surrounding text either side of the
+    /// selection in prompts, or `None` to leave it out.
+    #[must_use]
+    pub const fn with_prompt_context_chars(mut self, context_chars: Option<usize>) -> Self {
+        self.prompt_context_chars = context_chars;
+        self
+    }
+
+    /// Set how long an executor may go without output, or `None` to wait indefinitely.
+    #[must_use]
+    pub const fn with_idle_timeout(mut self, idle_timeout: Option<Duration>) -> Self {
+        self.idle_timeout = idle_timeout;
+        self
+    }
+
+    /// Set the model used when a request does not pick one, or `None` for the CLI default.
+    #[must_use]
+    pub fn with_default_model(mut self, model: Option<String>) -> Self {
+        self.default_model = model;
+        self
+    }
+
+    /// Record every executor spawn in an audit log.
+    #[must_use]
+    pub fn with_audit_log(mut self, audit_log: AuditLog) -> Self {
+        self.audit_log = Some(Arc::new(audit_log));
+        self
+    }
+
+    /// Record executor usage in `usage`, such as a ledger backed by a file.
+    #[must_use]
+    pub fn with_usage_ledger(mut self, usage: UsageLedger) -> Self {
+        self.usage = Arc::new(usage);
+        self
+    }
+
+    /// Set the longest line of executor output kept, in bytes.
+    #[must_use]
+    pub const fn with_max_line_bytes(mut self, max_line_bytes: usize) -> Self {
+        self.max_line_bytes = max_line_bytes;
+        self
+    }
+
+    /// Allow or forbid requests replacing the built-in system prompt.
+    #[must_use]
+    pub const fn with_system_prompt_override(mut self, allow: bool) -> Self {
+        self.allow_system_prompt_override = allow;
+        self
+    }
+
+    /// Set the longest system prompt override accepted, in characters.
+    #[must_use]
+    pub const fn with_max_system_prompt_chars(mut self, max_chars: usize) -> Self {
+        self.max_system_prompt_chars = max_chars;
+        self
+    }
+
+    /// Set the cost in USD past which an executor is stopped, or `None` for no ceiling.
+    #[must_use]
+    pub const fn with_max_cost_usd(mut self, max_cost_usd: Option<f64>) -> Self {
+        self.max_cost_usd = max_cost_usd;
+        self
+    }
+
+    /// Use custom model token limits.
+    #[must_use]
+    pub fn with_model_limits(mut self, limits: ModelLimits) -> Self {
+        self.model_limits = Arc::new(limits);
+        self
+    }
+
+    /// Set how long finished sessions are kept, or `None` to keep them forever.
+    #[must_use]
+    pub const fn with_session_ttl(mut self, session_ttl: Option<Duration>) -> Self {
+        self.session_ttl = session_ttl;
+        self
+    }
+
+    /// Limit how many executors of the given type may run at once.
+    #[must_use]
+    pub fn with_executor_limit(mut self, agent: BaseDocumentAgent, permits: usize) -> Self {
+        Arc::make_mut(&mut self.executor_queues).insert(agent, FeedbackQueue::new(permits));
+        self
+    }
+
+    /// Wait for a free executor slot for the session, then mark it running.
+    ///
+    /// The session stays `Pending` while queued; queued sessions are dispatched
+    /// by priority. Returns `None` if the session was cancelled while waiting.
+    /// The slot is released when the permit drops. Custom executors are not limited.
+    pub async fn acquire_executor_permit(
+        &self,
+        session: &Arc<RwLock<FeedbackSession>>,
+    ) -> Option<QueuePermit> {
+        let (agent, cancel, priority) = {
+            let s = session.read().await;
+            (s.executor.base_agent(), s.cancel.clone(), s.priority)
+        };
+        let queue = agent
+            .and_then(|agent| self.executor_queues.get(&agent))
+            .cloned()
+            .unwrap_or_else(|| FeedbackQueue::new(usize::MAX));
+        let permit = cancel.run_until_cancelled(queue.acquire(priority)).await?;
+
+        let mut s = session.write().await;
+        if s.state == SessionState::Cancelled {
+            return None;
+        }
+        s.state = SessionState::Running;
+        drop(s);
+
+        Some(permit)
+    }
+
     /// Create a new feedback session.
+    ///
+    /// With `include_thinking` false, thinking entries are not streamed or kept.
     pub async fn create_session(
         &self,
         comment_id: String,
         document_id: String,
-        executor: DocumentAgent,
+        executor: impl Into<SessionExecutor>,
+        include_thinking: bool,
     ) -> Arc<RwLock<FeedbackSession>> {
         let id = uuid::Uuid::new_v4().to_string();
         let session = Arc::new(RwLock::new(FeedbackSession {
             id: id.clone(),
             comment_id,
             document_id,
-            executor,
-            msg_store: Arc::new(MsgStore::new()),
+            executor: executor.into(),
+            msg_store: Arc::new(MsgStore::new().with_include_thinking(include_thinking)),
+            earlier_stores: Vec::new(),
             state: SessionState::Pending,
+            cancel: CancellationToken::new(),
+            agent_session_id: None,
+            language: None,
+            priority: 0,
+            max_cost_usd: None,
+            stream_token: uuid::Uuid::new_v4().simple().to_string(),
+            last_error: None,
+            connections: Arc::default(),
+            created_at: Utc::now(),
+            finished_at: None,
         }));
 
         self.sessions.write().await.insert(id, session.clone());
@@ -81,10 +527,80 @@ impl AppState {
         self.sessions.read().await.get(id).cloned()
     }
 
+    /// Summaries of the sessions replying to a comment, oldest first.
+    ///
+    /// Only sessions whose stream token is among `tokens` are included.
+    pub async fn sessions_for_comment(
+        &self,
+        comment_id: &str,
+        tokens: &[&str],
+    ) -> Vec<SessionSummary> {
+        let sessions: Vec<_> = self.sessions.read().await.values().cloned().collect();
+        let mut summaries = Vec::new();
+        for session in sessions {
+            let s = session.read().await;
+            if s.comment_id == comment_id && tokens.contains(&s.stream_token.as_str()) {
+                summaries.push(s.summary());
+            }
+        }
+        summaries.sort_by_key(|summary| summary.created_at);
+        summaries
+    }
+
     /// Remove a session by ID.
     pub async fn remove_session(&self, id: &str) -> Option<Arc<RwLock<FeedbackSession>>> {
         self.sessions.write().await.remove(id)
     }
+
+    /// Remove sessions that finished more than the session TTL before `now`.
+    ///
+    /// Reaped sessions have their cancellation token fired and their stream
+    /// connections closed. Pending and running sessions are never reaped.
+    /// Returns how many sessions were removed.
+    pub async fn reap_finished_sessions(&self, now: DateTime<Utc>) -> usize {
+        let Some(ttl) = self.session_ttl.and_then(|ttl| chrono::Duration::from_std(ttl).ok())
+        else {
+            return 0;
+        };
+        // Expiry is checked under the write lock, so a session restarted by a
+        // follow-up is never removed. Sessions locked for a change are skipped
+        // until the next pass.
+        let mut reaped = Vec::new();
+        self.sessions.write().await.retain(|_, session| {
+            let expired = session.try_read().is_ok_and(|s| s.is_expired(now, ttl));
+            if expired {
+                reaped.push(Arc::clone(session));
+            }
+            !expired
+        });
+        for session in &reaped {
+            let s = session.read().await;
+            s.cancel.cancel();
+            s.connections.revoke_all();
+        }
+        reaped.len()
+    }
+
+    /// Periodically reap expired sessions in the background.
+    ///
+    /// Returns `None` without a session TTL, since nothing would expire.
+    #[must_use]
+    pub fn spawn_session_reaper(&self) -> Option<JoinHandle<()>> {
+        let interval = self.session_ttl?.min(MAX_REAP_INTERVAL);
+        Some(tokio::spawn(reap_sessions_every(self.clone(), interval)))
+    }
+}
+
+/// Reap expired sessions of `state` once per `interval`, forever.
+async fn reap_sessions_every(state: AppState, interval: Duration) {
+    let mut ticks = tokio::time::interval(interval);
+    loop {
+        ticks.tick().await;
+        let reaped = state.reap_finished_sessions(Utc::now()).await;
+        if reaped > 0 {
+            debug!(reaped, "Reaped expired feedback sessions");
+        }
+    }
 }
 
 impl Default for AppState {
@@ -92,3 +608,107 @@ impl Default for AppState {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+
+    use glow_executors::executors::ClaudeCode;
+
+    use super::*;
+
+    async fn claude_session(state: &AppState) -> Arc<RwLock<FeedbackSession>> {
+        let executor = DocumentAgent::ClaudeCode(ClaudeCode::default());
+        state.create_session("comment".to_owned(), "doc".to_owned(), executor, true).await
+    }
+
+    async fn queue_session(state: AppState, session: Arc<RwLock<FeedbackSession>>) -> bool {
+        state.acquire_executor_permit(&session).await.is_some()
+    }
+
+    #[tokio::test]
+    async fn test_executor_limit_queues_sessions() {
+        let state = AppState::new().with_executor_limit(BaseDocumentAgent::ClaudeCode, 1);
+        let first = claude_session(&state).await;
+        let second = claude_session(&state).await;
+
+        let permit = state.acquire_executor_permit(&first).await.expect("should get permit");
+        assert_eq!(first.read().await.state, SessionState::Running);
+
+        let waiter = tokio::spawn(queue_session(state.clone(), second.clone()));
+
+        tokio::time::sleep(Duration::from_millis(50)).await;
+        assert_eq!(second.read().await.state, SessionState::Pending);
+
+        drop(permit);
+        assert!(waiter.await.expect("task should finish"));
+        assert_eq!(second.read().await.state, SessionState::Running);
+    }
+
+    #[tokio::test]
+    async fn test_cancelled_while_queued_does_not_run() {
+        let state = AppState::new().with_executor_limit(BaseDocumentAgent::ClaudeCode, 1);
+        let first = claude_session(&state).await;
+        let second = claude_session(&state).await;
+
+        let _permit = state.acquire_executor_permit(&first).await;
+        let waiter = tokio::spawn(queue_session(state.clone(), second.clone()));
+
+        // Cancelling stops the wait even though the first session still holds the slot
+        second.write().await.state = SessionState::Cancelled;
+        second.read().await.cancel.cancel();
+        assert!(!waiter.await.expect("task should finish"));
+        assert_eq!(second.read().await.state, SessionState::Cancelled);
+    }
+
+    #[tokio::test]
+    async fn test_reaper_removes_only_expired_finished_sessions() {
+        let state = AppState::new().with_session_ttl(Some(Duration::from_secs(60)));
+        let old_done = claude_session(&state).await;
+        let old_running = claude_session(&state).await;
+        let recent_done = claude_session(&state).await;
+        let connection = old_done.read().await.connections.connect();
+        old_done.write().await.fail("boom");
+        old_running.write().await.state = SessionState::Running;
+        recent_done.write().await.finish(SessionState::Completed);
+
+        // Finished long after it was created, but only just expired
+        let finished = old_done.read().await.finished_at.expect("should be finished");
+        old_done.write().await.created_at = finished - chrono::Duration::days(1);
+        old_running.write().await.created_at = finished - chrono::Duration::days(1);
+        recent_done.write().await.finished_at = Some(finished + chrono::Duration::seconds(30));
+
+        assert_eq!(state.reap_finished_sessions(finished + chrono::Duration::seconds(59)).await, 0);
+        let now = finished + chrono::Duration::seconds(61);
+        assert_eq!(state.reap_finished_sessions(now).await, 1);
+        assert!(state.get_session(&old_done.read().await.id).await.is_none());
+        assert!(state.get_session(&old_running.read().await.id).await.is_some());
+        assert!(state.get_session(&recent_done.read().await.id).await.is_some());
+        assert!(old_done.read().await.cancel.is_cancelled());
+        tokio::time::timeout(Duration::from_secs(1), connection.revoked())
+            .await
+            .expect("stream should be closed");
+
+        let kept = state.with_session_ttl(None);
+        assert_eq!(kept.reap_finished_sessions(now + chrono::Duration::days(365)).await, 0);
+    }
+
+    #[tokio::test]
+    async fn test_reaper_keeps_sessions_being_changed() {
+        let state = AppState::new().with_session_ttl(Some(Duration::from_secs(60)));
+        let session = claude_session(&state).await;
+        session.write().await.finish(SessionState::Completed);
+        let now = Utc::now() + chrono::Duration::seconds(61);
+
+        // A follow-up restarting the session holds its write lock
+        let mut restarting = session.write().await;
+        assert_eq!(state.reap_finished_sessions(now).await, 0);
+        restarting.state = SessionState::Running;
+        drop(restarting);
+        assert_eq!(state.reap_finished_sessions(now).await, 0);
+
+        session.write().await.finish(SessionState::Completed);
+        let later = Utc::now() + chrono::Duration::seconds(61);
+        assert_eq!(state.reap_finished_sessions(later).await, 1);
+    }
+}
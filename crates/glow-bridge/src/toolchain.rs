@@ -0,0 +1,119 @@
+//! Cached diagnostics of the Node toolchain Claude Code runs on.
+
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+use glow_executors::executors::claude::ToolchainReport;
+use tokio::sync::Mutex;
+
+/// How long a toolchain probe is reused before the toolchain is probed again.
+pub const TOOLCHAIN_CACHE_TTL: Duration = Duration::from_secs(60);
+
+/// How long a toolchain probe may run before it is reported as timed out.
+pub const TOOLCHAIN_PROBE_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Probes the toolchain at most once per TTL, so health checks stay cheap.
+pub struct ToolchainCache {
+    /// How long a probe's result is reused.
+    ttl: Duration,
+    /// How long a probe may run.
+    timeout: Duration,
+    /// Runs the probe; blocking, since it spawns processes.
+    probe: Arc<dyn Fn() -> ToolchainReport + Send + Sync>,
+    /// The latest report and when it was taken.
+    latest: Mutex<Option<(Instant, ToolchainReport)>>,
+}
+
+impl ToolchainCache {
+    /// A cache that reuses each probe of the real toolchain for `ttl`.
+    #[must_use]
+    pub fn new(ttl: Duration) -> Self {
+        Self::with_probe(ttl, ToolchainReport::probe)
+    }
+
+    /// A cache that runs `probe` instead of probing the real toolchain.
+    #[must_use]
+    pub fn with_probe(
+        ttl: Duration,
+        probe: impl Fn() -> ToolchainReport + Send + Sync + 'static,
+    ) -> Self {
+        Self {
+            ttl,
+            timeout: TOOLCHAIN_PROBE_TIMEOUT,
+            probe: Arc::new(probe),
+            latest: Mutex::new(None),
+        }
+    }
+
+    /// The latest report, probing again if it is older than the TTL.
+    ///
+    /// The cache is not locked while probing, so callers arriving during a
+    /// probe may each run one. A probe that times out is reported and cached
+    /// like any other, so a hung toolchain is not probed on every call.
+    pub async fn report(&self) -> ToolchainReport {
+        if let Some((taken_at, report)) = &*self.latest.lock().await
+            && taken_at.elapsed() < self.ttl
+        {
+            return report.clone();
+        }
+
+        let probe = Arc::clone(&self.probe);
+        let report =
+            match tokio::time::timeout(self.timeout, tokio::task::spawn_blocking(move || probe()))
+                .await
+            {
+                Ok(Ok(report)) => report,
+                Ok(Err(e)) => return failed_report(format!("toolchain probe failed: {e}")),
+                Err(_) => failed_report(format!(
+                    "toolchain probe timed out after {}s",
+                    self.timeout.as_secs_f32()
+                )),
+            };
+        *self.latest.lock().await = Some((Instant::now(), report.clone()));
+        report
+    }
+}
+
+/// A report of a probe that could not diagnose the toolchain.
+fn failed_report(problem: String) -> ToolchainReport {
+    ToolchainReport {
+        node_version: None,
+        npm_version: None,
+        npx_version: None,
+        package_resolvable: false,
+        problems: vec![problem],
+    }
+}
+
+impl Default for ToolchainCache {
+    fn default() -> Self {
+        Self::new(TOOLCHAIN_CACHE_TTL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::*;
+
+    #[tokio::test]
+    async fn test_hung_probe_times_out_and_is_cached() {
+        let probes = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&probes);
+        let mut cache = ToolchainCache::with_probe(Duration::from_secs(60), move || {
+            counter.fetch_add(1, Ordering::SeqCst);
+            std::thread::sleep(Duration::from_millis(500));
+            failed_report("should have timed out".to_owned())
+        });
+        cache.timeout = Duration::from_millis(50);
+
+        for _ in 0..2 {
+            let report = tokio::time::timeout(Duration::from_millis(250), cache.report())
+                .await
+                .expect("report should not wait for the hung probe");
+            assert_eq!(report.problems, ["toolchain probe timed out after 0.05s"]);
+        }
+        assert_eq!(probes.load(Ordering::SeqCst), 1);
+    }
+}
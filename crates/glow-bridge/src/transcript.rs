@@ -0,0 +1,144 @@
+//! Self-contained HTML transcripts of feedback sessions, for sharing.
+
+use std::fmt::Write;
+
+use glow_executors::{NormalizedEntry, NormalizedEntryType, SuggestedEdit};
+use pulldown_cmark::{CowStr, Event, Options, Parser, Tag, html};
+
+/// `Content-Security-Policy` for serving transcripts: inline styles and
+/// remote images only, with no scripts, frames or forms.
+pub const CONTENT_SECURITY_POLICY: &str = "default-src 'none'; style-src 'unsafe-inline'; \
+     img-src http: https:; base-uri 'none'; form-action 'none'; frame-ancestors 'none'";
+
+/// URL schemes links and images in a transcript may use; relative URLs are also allowed.
+const SAFE_URL_SCHEMES: [&str; 3] = ["http", "https", "mailto"];
+
+/// Styles inlined into every transcript, so the page needs nothing else.
+const STYLE: &str = "\
+body{font-family:system-ui,sans-serif;max-width:48rem;margin:2rem auto;padding:0 1rem;\
+color:#1f2328;line-height:1.5}\
+h1{font-size:1.4rem}\
+.meta{color:#656d76;font-size:.9rem}\
+.entry{margin:1rem 0;padding:.75rem 1rem;border-radius:6px}\
+.user{background:#f6f8fa}\
+.assistant{border:1px solid #d0d7de}\
+.error{background:#ffebe9;color:#82071e}\
+.thinking{color:#656d76;font-size:.9rem}\
+.thinking summary{cursor:pointer}\
+.edit{border-left:4px solid #bf8700;background:#fff8c5}\
+.edit del{background:#ffebe9;text-decoration:line-through}\
+.edit ins{background:#dafbe1;text-decoration:none}\
+.edit .explanation{margin:.5rem 0 0;font-size:.9rem}\
+pre{white-space:pre-wrap}";
+
+/// Renders a session's entries as a standalone HTML page.
+///
+/// Assistant messages are rendered from markdown, thinking is collapsed and
+/// suggested edits show the original and replacement text. Tool calls,
+/// system messages and progress updates are left out. Raw HTML in entries is
+/// escaped rather than passed through, and links and images with unsafe URLs,
+/// such as `javascript:`, point nowhere.
+#[must_use]
+pub fn render_html(heading: &str, entries: &[NormalizedEntry]) -> String {
+    let mut page = String::new();
+    let heading = escape_html(heading);
+    let _ = write!(
+        page,
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n\
+         <title>{heading}</title>\n<style>{STYLE}</style>\n</head>\n<body>\n<h1>{heading}</h1>\n"
+    );
+    for entry in entries {
+        render_entry(&mut page, entry);
+    }
+    page.push_str("</body>\n</html>\n");
+    page
+}
+
+/// Appends the markup for one entry, if it belongs in a transcript.
+fn render_entry(page: &mut String, entry: &NormalizedEntry) {
+    let content = &entry.content;
+    let _ = match entry.entry_type {
+        NormalizedEntryType::UserMessage => {
+            writeln!(page, "<div class=\"entry user\"><pre>{}</pre></div>", escape_html(content))
+        }
+        NormalizedEntryType::AssistantMessage => {
+            writeln!(page, "<div class=\"entry assistant\">{}</div>", render_markdown(content))
+        }
+        NormalizedEntryType::ThinkingMessage => writeln!(
+            page,
+            "<details class=\"entry thinking\"><summary>Thinking</summary><pre>{}</pre></details>",
+            escape_html(content)
+        ),
+        NormalizedEntryType::ErrorMessage => {
+            writeln!(page, "<div class=\"entry error\">{}</div>", escape_html(content))
+        }
+        NormalizedEntryType::SuggestedEdit => {
+            let Ok(edit) = serde_json::from_str::<SuggestedEdit>(content) else {
+                return;
+            };
+            writeln!(
+                page,
+                "<div class=\"entry edit\"><del>{}</del> <ins>{}</ins>\
+                 <p class=\"explanation\">{}</p></div>",
+                escape_html(&edit.original_text),
+                escape_html(&edit.suggested_text),
+                escape_html(&edit.explanation)
+            )
+        }
+        NormalizedEntryType::ToolCall
+        | NormalizedEntryType::ToolResult
+        | NormalizedEntryType::SystemMessage
+        | NormalizedEntryType::Progress
+        | NormalizedEntryType::Unknown => Ok(()),
+    };
+}
+
+/// Renders markdown to HTML, showing any raw HTML in it as text and
+/// neutralizing unsafe link and image URLs.
+fn render_markdown(markdown: &str) -> String {
+    let events = Parser::new_ext(markdown, Options::ENABLE_TABLES | Options::ENABLE_STRIKETHROUGH)
+        .map(|event| match event {
+            Event::Html(raw) | Event::InlineHtml(raw) => Event::Text(raw),
+            Event::Start(Tag::Link { link_type, dest_url, title, id }) => {
+                Event::Start(Tag::Link { link_type, dest_url: safe_url(dest_url), title, id })
+            }
+            Event::Start(Tag::Image { link_type, dest_url, title, id }) => {
+                Event::Start(Tag::Image { link_type, dest_url: safe_url(dest_url), title, id })
+            }
+            event => event,
+        });
+    let mut rendered = String::new();
+    html::push_html(&mut rendered, events);
+    rendered
+}
+
+/// `url` if it is relative or uses one of [`SAFE_URL_SCHEMES`], otherwise `#`.
+fn safe_url(url: CowStr<'_>) -> CowStr<'_> {
+    // Browsers ignore whitespace and control characters in a scheme, as in `java\tscript:`
+    let cleaned: String =
+        url.chars().filter(|c| !c.is_ascii_whitespace() && !c.is_ascii_control()).collect();
+    let scheme = cleaned
+        .find([':', '/', '?', '#'])
+        .filter(|&end| cleaned[end..].starts_with(':'))
+        .map(|end| cleaned[..end].to_ascii_lowercase());
+    match scheme {
+        Some(scheme) if !SAFE_URL_SCHEMES.contains(&scheme.as_str()) => CowStr::Borrowed("#"),
+        _ => url,
+    }
+}
+
+/// Escapes text for use in HTML content and attribute values.
+fn escape_html(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
@@ -0,0 +1,161 @@
+//! Ledger of executor cost and tokens per session, for spend reports.
+
+use std::collections::{BTreeMap, BTreeSet};
+use std::fs::{File, OpenOptions};
+use std::io::{BufRead, BufReader, Write};
+use std::path::Path;
+use std::sync::{Mutex, PoisonError};
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tracing::warn;
+
+/// Cost and tokens of one executor run.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageRecord {
+    /// Session the run belonged to.
+    pub session_id: String,
+    /// Executor name.
+    pub executor: String,
+    /// When the session was created; reports select sessions by this.
+    pub session_created_at: DateTime<Utc>,
+    /// Cost reported by the executor, in USD.
+    pub cost_usd: f64,
+    /// Input and output tokens reported by the executor.
+    pub tokens: u64,
+}
+
+/// Usage added up over a set of sessions.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageTotals {
+    /// Sessions with recorded usage; follow-up runs count once.
+    pub sessions: usize,
+    /// Total cost in USD.
+    pub total_cost_usd: f64,
+    /// Total input and output tokens.
+    pub total_tokens: u64,
+}
+
+/// Usage of the sessions created in a window, overall and per executor.
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct UsageSummary {
+    /// Start of the window, inclusive.
+    pub since: Option<DateTime<Utc>>,
+    /// End of the window, exclusive.
+    pub until: Option<DateTime<Utc>>,
+    /// Usage across all executors.
+    #[serde(flatten)]
+    pub totals: UsageTotals,
+    /// Usage keyed by executor name.
+    pub by_executor: BTreeMap<String, UsageTotals>,
+}
+
+/// Records executor usage, optionally appending each record to a JSON-lines file.
+///
+/// A ledger opened on an existing file starts with its records, so reports
+/// cover usage from before a restart.
+#[derive(Default)]
+pub struct UsageLedger {
+    records: Mutex<Vec<UsageRecord>>,
+    file: Option<Mutex<File>>,
+}
+
+impl UsageLedger {
+    /// Open the ledger at `path`, loading its records and appending new ones.
+    ///
+    /// Lines that are not valid records are skipped with a warning.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the file cannot be opened or read.
+    pub fn open(path: &Path) -> std::io::Result<Self> {
+        let file = OpenOptions::new().create(true).read(true).append(true).open(path)?;
+        let mut records = Vec::new();
+        for line in BufReader::new(&file).lines() {
+            let line = line?;
+            if line.trim().is_empty() {
+                continue;
+            }
+            let parsed = serde_json::from_str(&line).inspect_err(|e| {
+                warn!(error = %e, path = %path.display(), "Skipping invalid usage record");
+            });
+            records.extend(parsed.ok());
+        }
+        Ok(Self { records: Mutex::new(records), file: Some(Mutex::new(file)) })
+    }
+
+    /// Add a record, appending it to the file if there is one.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the record cannot be written; it is still counted.
+    pub fn record(&self, record: UsageRecord) -> std::io::Result<()> {
+        let line = self.file.as_ref().map(|_| serde_json::to_string(&record)).transpose()?;
+        self.records.lock().unwrap_or_else(PoisonError::into_inner).push(record);
+
+        let (Some(file), Some(mut line)) = (&self.file, line) else {
+            return Ok(());
+        };
+        line.push('\n');
+        let mut file = file.lock().unwrap_or_else(PoisonError::into_inner);
+        file.write_all(line.as_bytes())?;
+        file.flush()
+    }
+
+    /// Usage of the sessions created at or after `since` and before `until`.
+    #[must_use]
+    pub fn summarize(
+        &self,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> UsageSummary {
+        let in_window = |record: &&UsageRecord| {
+            since.is_none_or(|since| record.session_created_at >= since)
+                && until.is_none_or(|until| record.session_created_at < until)
+        };
+
+        let mut totals = UsageTotals::default();
+        let mut by_executor: BTreeMap<String, UsageTotals> = BTreeMap::new();
+        let mut seen = BTreeSet::new();
+        let records = self.records.lock().unwrap_or_else(PoisonError::into_inner);
+        for record in records.iter().filter(in_window) {
+            let first_run = seen.insert((&record.executor, &record.session_id));
+            for group in [&mut totals, by_executor.entry(record.executor.clone()).or_default()] {
+                group.sessions += usize::from(first_run);
+                group.total_cost_usd += record.cost_usd;
+                group.total_tokens += record.tokens;
+            }
+        }
+        drop(records);
+        UsageSummary { since, until, totals, by_executor }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_ledger_reloads_records_from_file() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("usage.log");
+        let record = UsageRecord {
+            session_id: "session-1".to_owned(),
+            executor: "CLAUDE_CODE".to_owned(),
+            session_created_at: Utc::now(),
+            cost_usd: 0.25,
+            tokens: 1_200,
+        };
+
+        let ledger = UsageLedger::open(&path).expect("should open ledger");
+        ledger.record(record.clone()).expect("should record");
+        ledger.record(UsageRecord { cost_usd: 0.5, ..record }).expect("should record");
+        drop(ledger);
+
+        let reopened = UsageLedger::open(&path).expect("should reopen ledger");
+        let totals = reopened.summarize(None, None).totals;
+        assert_eq!(totals, UsageTotals { sessions: 1, total_cost_usd: 0.75, total_tokens: 2_400 });
+    }
+}
@@ -2,16 +2,101 @@
 //!
 //! Uses Yrs (Rust port of Yjs) for conflict-free replicated data types.
 
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+
+use yrs::types::Delta;
+use yrs::types::text::TextEvent;
 use yrs::updates::decoder::Decode;
 use yrs::updates::encoder::Encode;
-use yrs::{Doc, GetString, ReadTxn, Text, TextRef, Transact, Update};
+use yrs::{
+    Any, Doc, GetString, Observable, OffsetKind, Out, ReadTxn, Text, TextRef, Transact, Update,
+};
 
+use crate::diff::{DiffOp, diff_chars};
 use crate::error::{Error, Result};
 
+/// Most remote updates held back while waiting for the updates they build on.
+pub const MAX_PENDING_UPDATES: usize = 256;
+
+/// Most snapshots kept; capturing another drops the oldest.
+pub const MAX_SNAPSHOTS: usize = 64;
+
 /// Manages CRDT synchronization for a document.
 #[derive(Debug)]
 pub struct DocumentSync {
     doc: Doc,
+    read_only: AtomicBool,
+    /// Encoded remote updates that arrived before the updates they build on.
+    pending: Mutex<Vec<Vec<u8>>>,
+    /// Word count kept up to date by an observer of the text.
+    words: Arc<Mutex<WordCounter>>,
+    /// Encoded full states captured by [`DocumentSync::capture_snapshot`], oldest first.
+    snapshots: Mutex<VecDeque<(u64, Vec<u8>)>>,
+}
+
+/// Counts words as the text changes, recounting only the words an edit touches.
+///
+/// Keeps its own copy of the text, since a deletion's observer no longer sees
+/// the removed characters.
+#[derive(Debug, Default)]
+struct WordCounter {
+    text: String,
+    count: usize,
+}
+
+impl WordCounter {
+    fn new(text: String) -> Self {
+        let count = count_words(&text);
+        Self { text, count }
+    }
+
+    /// Applies a text change, given in UTF-8 byte offsets.
+    fn apply(&mut self, delta: &[Delta]) {
+        let mut pos = 0;
+        for change in delta {
+            match change {
+                Delta::Retain(len, _) => pos += *len as usize,
+                Delta::Deleted(len) => self.splice(pos, *len as usize, ""),
+                Delta::Inserted(Out::Any(Any::String(inserted)), _) => {
+                    self.splice(pos, 0, inserted);
+                    pos += inserted.len();
+                }
+                // Embeds take up one position but hold no words
+                Delta::Inserted(..) => {}
+            }
+        }
+    }
+
+    /// Replaces `removed` bytes at `pos` with `inserted`, recounting only the
+    /// words around the edit.
+    fn splice(&mut self, pos: usize, removed: usize, inserted: &str) {
+        let pos = floor_char_boundary(&self.text, pos);
+        let end = floor_char_boundary(&self.text, pos + removed);
+        // Widen to whitespace on both sides so no word straddles the window
+        let start = self.text[..pos].rfind(char::is_whitespace).map_or(0, |i| i + 1);
+        let stop = self.text[end..].find(char::is_whitespace).map_or(self.text.len(), |i| end + i);
+
+        let before = count_words(&self.text[start..stop]);
+        self.text.replace_range(pos..end, inserted);
+        let after = count_words(&self.text[start..stop - (end - pos) + inserted.len()]);
+        self.count = self.count + after - before;
+    }
+}
+
+/// Number of whitespace-separated words in `text`.
+fn count_words(text: &str) -> usize {
+    text.split_whitespace().count()
+}
+
+/// The largest character boundary in `text` at or before `index`.
+fn floor_char_boundary(text: &str, index: usize) -> usize {
+    let mut index = index.min(text.len());
+    while !text.is_char_boundary(index) {
+        index -= 1;
+    }
+    index
 }
 
 impl DocumentSync {
@@ -21,7 +106,32 @@ impl DocumentSync {
         let doc = Doc::new();
         // Pre-create the text field
         let _ = doc.get_or_insert_text("content");
-        Self { doc }
+        Self::from_doc(doc)
+    }
+
+    /// Wraps a document, counting its words from now on.
+    fn from_doc(doc: Doc) -> Self {
+        let text = doc.get_or_insert_text("content");
+        let words = Arc::new(Mutex::new(WordCounter::new(text.get_string(&doc.transact()))));
+        let counter = Arc::clone(&words);
+        text.observe_with("word-count", move |txn, event: &TextEvent| {
+            counter.lock().unwrap_or_else(PoisonError::into_inner).apply(event.delta(txn));
+        });
+        Self {
+            doc,
+            read_only: AtomicBool::new(false),
+            pending: Mutex::default(),
+            words,
+            snapshots: Mutex::default(),
+        }
+    }
+
+    /// Creates a document sync instance seeded with `content`.
+    #[must_use]
+    pub fn with_content(content: &str) -> Self {
+        let sync = Self::new();
+        sync.replace_content(content);
+        sync
     }
 
     /// Creates a document sync from existing CRDT state.
@@ -39,7 +149,28 @@ impl DocumentSync {
         txn.apply_update(update).map_err(|e| Error::Crdt(e.to_string()))?;
         drop(txn);
 
-        Ok(Self { doc })
+        Ok(Self::from_doc(doc))
+    }
+
+    /// Returns whether local edits are rejected.
+    #[must_use]
+    pub fn is_read_only(&self) -> bool {
+        self.read_only.load(Ordering::Relaxed)
+    }
+
+    /// Freezes or unfreezes the document for local edits.
+    ///
+    /// Updates from remote peers are still applied so replicas converge.
+    pub fn set_read_only(&self, read_only: bool) {
+        self.read_only.store(read_only, Ordering::Relaxed);
+    }
+
+    /// Returns an error if the document is read-only.
+    fn ensure_writable(&self) -> Result<()> {
+        if self.is_read_only() {
+            return Err(Error::InvalidState("document is read-only".to_owned()));
+        }
+        Ok(())
     }
 
     /// Gets the text reference for operations.
@@ -50,12 +181,55 @@ impl DocumentSync {
     /// Gets the current text content.
     #[must_use]
     pub fn get_content(&self) -> String {
+        // Resolve the text ref before opening the read transaction; looking it
+        // up needs a write transaction of its own and would otherwise deadlock.
+        let text = self.text();
         let txn = self.doc.transact();
-        self.text().get_string(&txn)
+        text.get_string(&txn)
+    }
+
+    /// Number of whitespace-separated words in the content.
+    ///
+    /// Kept up to date as the text changes, including by remote updates, so
+    /// reading it does not scan the document.
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.words.lock().unwrap_or_else(PoisonError::into_inner).count
     }
 
     /// Sets the text content, replacing all existing content.
-    pub fn set_content(&self, content: &str) {
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn set_content(&self, content: &str) -> Result<()> {
+        self.ensure_writable()?;
+        self.replace_content(content);
+        Ok(())
+    }
+
+    /// Removes all content, keeping the document and its sync history.
+    ///
+    /// The removal is a single transaction, so observers see one update and
+    /// peers keep syncing with this instance.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn clear(&self) -> Result<()> {
+        self.ensure_writable()?;
+        let text = self.text();
+        let mut txn = self.doc.transact_mut();
+        let len = text.len(&txn);
+        if len > 0 {
+            text.remove_range(&mut txn, 0, len);
+        }
+        drop(txn);
+        Ok(())
+    }
+
+    /// Replaces all existing content, without checking for read-only.
+    fn replace_content(&self, content: &str) {
         let text = self.text();
         let mut txn = self.doc.transact_mut();
         let len = text.len(&txn);
@@ -66,17 +240,133 @@ impl DocumentSync {
     }
 
     /// Inserts text at the given position.
-    pub fn insert(&self, index: u32, content: &str) {
+    ///
+    /// `index` is in the document's native index unit (UTF-8 bytes); use
+    /// [`DocumentSync::insert_at_char`] for character offsets.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn insert(&self, index: u32, content: &str) -> Result<()> {
+        self.ensure_writable()?;
         let text = self.text();
         let mut txn = self.doc.transact_mut();
         text.insert(&mut txn, index, content);
+        drop(txn);
+        Ok(())
     }
 
     /// Deletes text at the given range.
-    pub fn delete(&self, index: u32, length: u32) {
+    ///
+    /// `index` and `length` are in the document's native index unit (UTF-8
+    /// bytes); use [`DocumentSync::delete_at_char`] for character offsets.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn delete(&self, index: u32, length: u32) -> Result<()> {
+        self.ensure_writable()?;
         let text = self.text();
         let mut txn = self.doc.transact_mut();
         text.remove_range(&mut txn, index, length);
+        drop(txn);
+        Ok(())
+    }
+
+    /// Inserts text at a character (Unicode scalar value) offset.
+    ///
+    /// Offsets past the end insert at the end.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn insert_at_char(&self, char_offset: u32, content: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let text = self.text();
+        let mut txn = self.doc.transact_mut();
+        let current = text.get_string(&txn);
+        let index = self.native_index(&current, char_offset);
+        text.insert(&mut txn, index, content);
+        drop(txn);
+        Ok(())
+    }
+
+    /// Deletes `char_len` characters starting at a character offset.
+    ///
+    /// The range is clamped to the end of the text.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn delete_at_char(&self, char_offset: u32, char_len: u32) -> Result<()> {
+        self.ensure_writable()?;
+        let text = self.text();
+        let mut txn = self.doc.transact_mut();
+        let current = text.get_string(&txn);
+        let start = self.native_index(&current, char_offset);
+        let end = self.native_index(&current, char_offset.saturating_add(char_len));
+        if end > start {
+            text.remove_range(&mut txn, start, end - start);
+        }
+        drop(txn);
+        Ok(())
+    }
+
+    /// Replaces the content with `new_content` using the fewest edits.
+    ///
+    /// Unlike [`DocumentSync::set_content`], unchanged ranges are kept, so
+    /// the update stays small and concurrent edits to them still merge. All
+    /// edits are applied in one transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn apply_text_diff(&self, new_content: &str) -> Result<()> {
+        self.ensure_writable()?;
+        let text = self.text();
+        let mut txn = self.doc.transact_mut();
+        let old: Vec<char> = text.get_string(&txn).chars().collect();
+        let new: Vec<char> = new_content.chars().collect();
+
+        let (mut old_at, mut new_at, mut index) = (0, 0, 0);
+        for op in diff_chars(&old, &new) {
+            match op {
+                DiffOp::Equal(n) => {
+                    index += self.native_len(&old[old_at..old_at + n]);
+                    old_at += n;
+                    new_at += n;
+                }
+                DiffOp::Delete(n) => {
+                    text.remove_range(&mut txn, index, self.native_len(&old[old_at..old_at + n]));
+                    old_at += n;
+                }
+                DiffOp::Insert(n) => {
+                    let inserted: String = new[new_at..new_at + n].iter().collect();
+                    text.insert(&mut txn, index, &inserted);
+                    index += self.native_len(&new[new_at..new_at + n]);
+                    new_at += n;
+                }
+            }
+        }
+        drop(txn);
+        Ok(())
+    }
+
+    /// Length of `chars` in this document's index unit.
+    fn native_len(&self, chars: &[char]) -> u32 {
+        let len: usize = match self.doc.offset_kind() {
+            OffsetKind::Bytes => chars.iter().map(|c| c.len_utf8()).sum(),
+            OffsetKind::Utf16 => chars.iter().map(|c| c.len_utf16()).sum(),
+        };
+        u32::try_from(len).unwrap_or(u32::MAX)
+    }
+
+    /// Converts a character offset in `text` into this document's index unit.
+    fn native_index(&self, text: &str, char_offset: u32) -> u32 {
+        match self.doc.offset_kind() {
+            OffsetKind::Bytes => char_to_utf8_offset(text, char_offset),
+            OffsetKind::Utf16 => char_to_utf16_offset(text, char_offset),
+        }
     }
 
     /// Gets the state vector for synchronization.
@@ -101,19 +391,170 @@ impl DocumentSync {
         Some(txn.encode_state_as_update_v1(&sv))
     }
 
+    /// Returns whether a peer at `state_vector` has seen every change in this document.
+    ///
+    /// Returns `false` if the state vector cannot be decoded.
+    #[must_use]
+    pub fn is_covered_by(&self, state_vector: &[u8]) -> bool {
+        let Ok(remote) = yrs::StateVector::decode_v1(state_vector) else {
+            return false;
+        };
+        let txn = self.doc.transact();
+        txn.state_vector().iter().all(|(client, clock)| remote.get(client) >= *clock)
+    }
+
+    /// Returns how many operations this document has that a peer at `state_vector` is missing.
+    ///
+    /// This is the sum of per-client clock deltas, and is zero when the peer is
+    /// caught up. A state vector that cannot be decoded is treated as empty.
+    #[must_use]
+    pub fn divergence_from(&self, state_vector: &[u8]) -> usize {
+        let remote = yrs::StateVector::decode_v1(state_vector).unwrap_or_default();
+        let txn = self.doc.transact();
+        txn.state_vector()
+            .iter()
+            .map(|(client, clock)| clock.saturating_sub(remote.get(client)) as usize)
+            .sum()
+    }
+
     /// Applies an update from a remote peer.
     ///
+    /// An update that starts past what this document has seen from one of its
+    /// authors arrived ahead of the updates it builds on. It is held back and
+    /// applied once a later call brings the document up to its start, so
+    /// updates reordered by the network still converge.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the update cannot be applied.
+    /// Returns an error if the update cannot be decoded or applied, or if
+    /// [`MAX_PENDING_UPDATES`] updates are already held back.
     pub fn apply_update(&self, update: &[u8]) -> Result<()> {
-        let update = Update::decode_v1(update).map_err(|e| Error::Crdt(e.to_string()))?;
-
-        let mut txn = self.doc.transact_mut();
-        txn.apply_update(update).map_err(|e| Error::Crdt(e.to_string()))?;
+        let decoded = Update::decode_v1(update).map_err(|e| Error::Crdt(e.to_string()))?;
+        if !self.is_ready(&decoded) {
+            let mut pending = self.lock_pending();
+            if pending.len() >= MAX_PENDING_UPDATES {
+                return Err(Error::Crdt(format!(
+                    "{MAX_PENDING_UPDATES} updates are already waiting for missing updates"
+                )));
+            }
+            pending.push(update.to_vec());
+            drop(pending);
+            return Ok(());
+        }
 
+        self.integrate(decoded)?;
+        while let Some(ready) = self.take_ready_update() {
+            self.integrate(ready)?;
+        }
         Ok(())
     }
+
+    /// Number of remote updates held back until the updates they build on arrive.
+    #[must_use]
+    pub fn pending_update_count(&self) -> usize {
+        self.lock_pending().len()
+    }
+
+    /// Records the current state as a new version, returning its number.
+    ///
+    /// Versions count up from 1. Only the latest [`MAX_SNAPSHOTS`] are kept.
+    pub fn capture_snapshot(&self) -> u64 {
+        let version = self.lock_snapshots().back().map_or(1, |(version, _)| version + 1);
+        self.capture_snapshot_at(version);
+        version
+    }
+
+    /// Records the current state as `version`, such as the document version
+    /// it was saved as, replacing any earlier snapshot of that version.
+    ///
+    /// Versions should be captured in increasing order. Only the latest
+    /// [`MAX_SNAPSHOTS`] are kept.
+    pub fn capture_snapshot_at(&self, version: u64) {
+        let state = self.get_state();
+        let mut snapshots = self.lock_snapshots();
+        snapshots.retain(|(v, _)| *v != version);
+        if snapshots.len() >= MAX_SNAPSHOTS {
+            snapshots.pop_front();
+        }
+        snapshots.push_back((version, state));
+    }
+
+    /// Encoded full state captured as `version`, if it is still kept.
+    ///
+    /// Load it with [`DocumentSync::from_state`] to read that version's content.
+    #[must_use]
+    pub fn snapshot_state(&self, version: u64) -> Option<Vec<u8>> {
+        self.lock_snapshots().iter().find(|(v, _)| *v == version).map(|(_, state)| state.clone())
+    }
+
+    fn lock_snapshots(&self) -> MutexGuard<'_, VecDeque<(u64, Vec<u8>)>> {
+        self.snapshots.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    fn lock_pending(&self) -> MutexGuard<'_, Vec<Vec<u8>>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Whether this document has every change from each of the update's
+    /// authors that precedes the update.
+    fn is_ready(&self, update: &Update) -> bool {
+        let local = self.doc.transact().state_vector();
+        update.state_vector_lower().iter().all(|(client, clock)| local.get(client) >= *clock)
+    }
+
+    /// Removes and returns a held-back update that can now be applied.
+    fn take_ready_update(&self) -> Option<Update> {
+        let mut pending = self.lock_pending();
+        let (index, update) = pending.iter().enumerate().find_map(|(index, bytes)| {
+            let update = Update::decode_v1(bytes).ok()?;
+            self.is_ready(&update).then_some((index, update))
+        })?;
+        pending.remove(index);
+        drop(pending);
+        Some(update)
+    }
+
+    fn integrate(&self, update: Update) -> Result<()> {
+        let mut txn = self.doc.transact_mut();
+        txn.apply_update(update).map_err(|e| Error::Crdt(e.to_string()))
+    }
+}
+
+/// Converts a character (Unicode scalar value) offset into a UTF-8 byte offset.
+///
+/// This is the index unit of documents created by [`DocumentSync`] and of Rust
+/// string slicing. Offsets past the end map to the text's byte length.
+#[must_use]
+pub fn char_to_utf8_offset(text: &str, char_offset: u32) -> u32 {
+    let bytes = text.char_indices().nth(char_offset as usize).map_or(text.len(), |(i, _)| i);
+    u32::try_from(bytes).unwrap_or(u32::MAX)
+}
+
+/// Converts a character (Unicode scalar value) offset into a UTF-16 code unit offset.
+///
+/// This is the index unit used by JavaScript strings and Yjs peers. Offsets
+/// past the end map to the text's UTF-16 length.
+#[must_use]
+pub fn char_to_utf16_offset(text: &str, char_offset: u32) -> u32 {
+    let units: usize = text.chars().take(char_offset as usize).map(char::len_utf16).sum();
+    u32::try_from(units).unwrap_or(u32::MAX)
+}
+
+/// Merges two diverged documents into a new one, leaving both untouched.
+///
+/// The result starts as a copy of `a` and then applies the changes from `b`
+/// that `a` has not seen, so it holds every edit made on either side.
+///
+/// # Errors
+///
+/// Returns an error if either document's state cannot be decoded or applied.
+pub fn merge(a: &DocumentSync, b: &DocumentSync) -> Result<DocumentSync> {
+    let merged = DocumentSync::from_state(&a.get_state())?;
+    let missing = b
+        .get_update_from(&merged.get_state_vector())
+        .ok_or_else(|| Error::Crdt("invalid state vector".to_owned()))?;
+    merged.apply_update(&missing)?;
+    Ok(merged)
 }
 
 impl Default for DocumentSync {
@@ -124,6 +565,9 @@ impl Default for DocumentSync {
 
 #[cfg(test)]
 mod tests {
+    use std::sync::Arc;
+    use std::sync::atomic::AtomicUsize;
+
     use super::*;
 
     #[test]
@@ -135,30 +579,73 @@ mod tests {
     #[test]
     fn test_set_and_get_content() {
         let sync = DocumentSync::new();
-        sync.set_content("Hello, world!");
+        sync.set_content("Hello, world!").expect("should edit");
         assert_eq!(sync.get_content(), "Hello, world!");
     }
 
     #[test]
     fn test_insert_text() {
         let sync = DocumentSync::new();
-        sync.set_content("Hello!");
-        sync.insert(5, ", world");
+        sync.set_content("Hello!").expect("should edit");
+        sync.insert(5, ", world").expect("should edit");
         assert_eq!(sync.get_content(), "Hello, world!");
     }
 
     #[test]
     fn test_delete_text() {
         let sync = DocumentSync::new();
-        sync.set_content("Hello, world!");
-        sync.delete(5, 7);
+        sync.set_content("Hello, world!").expect("should edit");
+        sync.delete(5, 7).expect("should edit");
         assert_eq!(sync.get_content(), "Hello!");
     }
 
+    #[test]
+    fn test_insert_at_char_after_emoji() {
+        let sync = DocumentSync::new();
+        sync.set_content("a😀b").expect("should edit");
+        sync.insert_at_char(2, "!").expect("should edit");
+        assert_eq!(sync.get_content(), "a😀!b");
+
+        sync.insert_at_char(100, "?").expect("should edit");
+        assert_eq!(sync.get_content(), "a😀!b?");
+    }
+
+    #[test]
+    fn test_delete_at_char_removes_whole_emoji() {
+        let sync = DocumentSync::new();
+        sync.set_content("héllo 😀 world").expect("should edit");
+        sync.delete_at_char(6, 2).expect("should edit");
+        assert_eq!(sync.get_content(), "héllo world");
+    }
+
+    #[test]
+    fn test_char_offset_conversions() {
+        let text = "é😀x";
+        assert_eq!(char_to_utf8_offset(text, 2), 6);
+        assert_eq!(char_to_utf16_offset(text, 2), 3);
+        assert_eq!(char_to_utf8_offset(text, 10), 7);
+        assert_eq!(char_to_utf16_offset(text, 10), 4);
+    }
+
+    #[test]
+    fn test_read_only_rejects_local_edits() {
+        let sync = DocumentSync::with_content("Frozen");
+        sync.set_read_only(true);
+
+        assert!(matches!(sync.set_content("Changed"), Err(Error::InvalidState(_))));
+        assert!(sync.insert(0, "x").is_err());
+        assert!(sync.delete_at_char(0, 1).is_err());
+        assert_eq!(sync.get_content(), "Frozen");
+
+        sync.set_read_only(false);
+        sync.insert(6, "!").expect("should edit");
+        assert_eq!(sync.get_content(), "Frozen!");
+    }
+
     #[test]
     fn test_state_roundtrip() {
         let sync1 = DocumentSync::new();
-        sync1.set_content("Test content");
+        sync1.set_content("Test content").expect("should edit");
 
         let state = sync1.get_state();
         let sync2 = DocumentSync::from_state(&state).expect("should decode state");
@@ -172,7 +659,7 @@ mod tests {
         let peer2 = DocumentSync::new();
 
         // Peer 1 makes changes
-        peer1.set_content("Hello from peer 1");
+        peer1.set_content("Hello from peer 1").expect("should edit");
 
         // Get update for peer 2
         let sv = peer2.get_state_vector();
@@ -183,4 +670,203 @@ mod tests {
 
         assert_eq!(peer2.get_content(), "Hello from peer 1");
     }
+
+    #[test]
+    fn test_clear_keeps_syncing_with_peers() {
+        let peer1 = DocumentSync::with_content("Draft\nwith two lines");
+        let peer2 = DocumentSync::from_state(&peer1.get_state()).expect("should load state");
+
+        let updates = Arc::new(AtomicUsize::new(0));
+        let counter = Arc::clone(&updates);
+        let _subscription = peer1
+            .doc
+            .observe_update_v1(move |_, _| {
+                counter.fetch_add(1, Ordering::Relaxed);
+            })
+            .expect("should observe updates");
+
+        peer1.clear().expect("should clear");
+        assert_eq!(peer1.get_content(), "");
+        assert_eq!(updates.load(Ordering::Relaxed), 1);
+
+        peer1.insert(0, "Fresh start").expect("should edit");
+        let update = peer1.get_update_from(&peer2.get_state_vector()).expect("should get update");
+        peer2.apply_update(&update).expect("should apply update");
+        assert_eq!(peer2.get_content(), "Fresh start");
+    }
+
+    #[test]
+    fn test_out_of_order_update_waits_for_its_base() {
+        let author = DocumentSync::new();
+        let peer = DocumentSync::new();
+        let empty = peer.get_state_vector();
+
+        author.insert(0, "Hello").expect("should edit");
+        let base = author.get_update_from(&empty).expect("should get update");
+        let after_base = author.get_state_vector();
+        author.insert(5, " world").expect("should edit");
+        let dependent = author.get_update_from(&after_base).expect("should get update");
+
+        peer.apply_update(&dependent).expect("should hold dependent update");
+        assert_eq!(peer.pending_update_count(), 1);
+        assert_eq!(peer.get_content(), "");
+
+        peer.apply_update(&base).expect("should apply base update");
+        assert_eq!(peer.pending_update_count(), 0);
+        assert_eq!(peer.get_content(), "Hello world");
+    }
+
+    /// Asserts the incremental word count matches a recount of the content.
+    fn assert_word_count(sync: &DocumentSync) {
+        let content = sync.get_content();
+        assert_eq!(sync.word_count(), content.split_whitespace().count(), "in {content:?}");
+    }
+
+    #[test]
+    fn test_word_count_follows_edits() {
+        let sync = DocumentSync::with_content("The quick brown fox");
+        assert_eq!(sync.word_count(), 4);
+
+        sync.delete_at_char(3, 1).expect("should join words");
+        assert_word_count(&sync);
+        sync.insert_at_char(6, " ").expect("should split a word");
+        assert_word_count(&sync);
+        sync.insert_at_char(19, " jumps  over\n").expect("should append");
+        assert_word_count(&sync);
+        sync.insert_at_char(0, "🦊 ").expect("should insert emoji");
+        assert_word_count(&sync);
+        sync.delete_at_char(4, 9).expect("should delete across words");
+        assert_word_count(&sync);
+        sync.insert_at_char(0, "\t ").expect("should insert whitespace");
+        assert_word_count(&sync);
+        sync.clear().expect("should clear");
+        assert_eq!(sync.word_count(), 0);
+
+        // Remote updates, including a whole document loaded from state
+        let peer = DocumentSync::with_content("one two three");
+        sync.apply_update(&peer.get_state()).expect("should apply");
+        peer.insert_at_char(3, "-and").expect("should insert");
+        sync.apply_update(&peer.get_update_from(&sync.get_state_vector()).expect("valid"))
+            .expect("should apply");
+        assert_word_count(&sync);
+        assert_eq!(DocumentSync::from_state(&peer.get_state()).expect("valid").word_count(), 3);
+    }
+
+    #[test]
+    fn test_apply_text_diff_makes_small_edits() {
+        let text = "# Notes\n\nThe quick brown fox jumps over the lazy dog.\n".repeat(20);
+        let edited = text.replacen("lazy", "sleepy 🐕", 1);
+
+        let sync = DocumentSync::with_content(&text);
+        let before = sync.get_state_vector();
+        sync.apply_text_diff(&edited).expect("should apply diff");
+        assert_eq!(sync.get_content(), edited);
+        assert_word_count(&sync);
+        let diff_update = sync.get_update_from(&before).expect("should have an update");
+
+        let replaced = DocumentSync::with_content(&text);
+        let before = replaced.get_state_vector();
+        replaced.set_content(&edited).expect("should set content");
+        let replace_update = replaced.get_update_from(&before).expect("should have an update");
+        assert!(diff_update.len() * 10 < replace_update.len());
+
+        for next in ["", "fresh start", "🦊 fresh  start\n", "# Notes\n"] {
+            sync.apply_text_diff(next).expect("should apply diff");
+            assert_eq!(sync.get_content(), next);
+            assert_word_count(&sync);
+        }
+
+        sync.set_read_only(true);
+        let Err(Error::InvalidState(_)) = sync.apply_text_diff("changed") else { unreachable!() };
+        assert_eq!(sync.get_content(), "# Notes\n");
+    }
+
+    #[test]
+    fn test_snapshots_keep_earlier_versions() {
+        let sync = DocumentSync::with_content("First draft");
+        assert_eq!(sync.capture_snapshot(), 1);
+        sync.insert_at_char(0, "A ").expect("should edit");
+        assert_eq!(sync.capture_snapshot(), 2);
+        sync.set_content("Rewritten").expect("should edit");
+
+        let first = sync.snapshot_state(1).expect("version 1 is kept");
+        assert_eq!(DocumentSync::from_state(&first).expect("valid").get_content(), "First draft");
+        assert!(sync.snapshot_state(3).is_none());
+
+        for _ in 0..MAX_SNAPSHOTS {
+            sync.capture_snapshot();
+        }
+        assert!(sync.snapshot_state(2).is_none());
+        assert!(sync.snapshot_state(3).is_some());
+    }
+
+    #[test]
+    fn test_snapshot_at_document_version() {
+        let sync = DocumentSync::with_content("Saved as version 4");
+        sync.capture_snapshot_at(4);
+        sync.set_content("Saved as version 7").expect("should edit");
+        sync.capture_snapshot_at(7);
+        sync.set_content("Resaved as version 7").expect("should edit");
+        sync.capture_snapshot_at(7);
+
+        let content = |version| {
+            let state = sync.snapshot_state(version)?;
+            Some(DocumentSync::from_state(&state).expect("valid").get_content())
+        };
+        assert_eq!(content(4).as_deref(), Some("Saved as version 4"));
+        assert_eq!(content(7).as_deref(), Some("Resaved as version 7"));
+        assert_eq!(content(5), None);
+        assert_eq!(sync.capture_snapshot(), 8);
+    }
+
+    #[test]
+    fn test_merge_combines_diverged_edits() {
+        let peer1 = DocumentSync::with_content("Hello world");
+        let peer2 = DocumentSync::from_state(&peer1.get_state()).expect("should load state");
+
+        peer1.insert(0, "Oh, ").expect("should edit");
+        peer2.insert(11, "!").expect("should edit");
+
+        let merged = merge(&peer1, &peer2).expect("should merge");
+        assert_eq!(merged.get_content(), "Oh, Hello world!");
+        assert_eq!(
+            merge(&peer2, &peer1).expect("should merge").get_content(),
+            merged.get_content()
+        );
+        assert_eq!(peer1.get_content(), "Oh, Hello world");
+        assert_eq!(peer2.get_content(), "Hello world!");
+    }
+
+    #[test]
+    fn test_divergence_from() {
+        let peer1 = DocumentSync::new();
+        let peer2 = DocumentSync::new();
+        peer1.set_content("Hello").expect("should edit");
+        peer2.apply_update(&peer1.get_state()).expect("should apply state");
+        assert_eq!(peer1.divergence_from(&peer2.get_state_vector()), 0);
+
+        peer1.insert(5, " there").expect("should edit");
+        peer1.insert(11, "!").expect("should edit");
+        peer1.delete(0, 1).expect("should edit");
+        assert!(peer1.divergence_from(&peer2.get_state_vector()) > 0);
+        assert_eq!(peer2.divergence_from(&peer1.get_state_vector()), 0);
+
+        let update = peer1.get_update_from(&peer2.get_state_vector()).expect("should get update");
+        peer2.apply_update(&update).expect("should apply update");
+        assert_eq!(peer1.divergence_from(&peer2.get_state_vector()), 0);
+    }
+
+    #[test]
+    fn test_is_covered_by() {
+        let peer1 = DocumentSync::new();
+        let peer2 = DocumentSync::new();
+        peer1.set_content("Hello").expect("should edit");
+
+        assert!(!peer1.is_covered_by(&peer2.get_state_vector()));
+
+        let update = peer1.get_update_from(&peer2.get_state_vector()).expect("should get update");
+        peer2.apply_update(&update).expect("should apply update");
+        assert!(peer1.is_covered_by(&peer2.get_state_vector()));
+        assert!(!peer1.is_covered_by(b"not a state vector"));
+    }
 }
@@ -0,0 +1,182 @@
+//! Character diffs between two versions of a text.
+
+/// Most edits searched for before the changed middle of a text is replaced
+/// wholesale, bounding time and memory on very different texts.
+const MAX_DIFF_EDITS: usize = 1_000;
+
+/// A run of one kind of change, in characters.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DiffOp {
+    /// Characters present in both texts.
+    Equal(usize),
+    /// Characters only in the old text.
+    Delete(usize),
+    /// Characters only in the new text.
+    Insert(usize),
+}
+
+/// Diffs `old` against `new` into runs that turn one into the other.
+///
+/// Uses Myers' algorithm on what remains after trimming the common prefix
+/// and suffix, so the edits are minimal unless the texts differ by more than
+/// [`MAX_DIFF_EDITS`] characters.
+pub fn diff_chars(old: &[char], new: &[char]) -> Vec<DiffOp> {
+    let prefix = old.iter().zip(new).take_while(|(a, b)| a == b).count();
+    let suffix = old[prefix..]
+        .iter()
+        .rev()
+        .zip(new[prefix..].iter().rev())
+        .take_while(|(a, b)| a == b)
+        .count();
+    let (old_mid, new_mid) = (&old[prefix..old.len() - suffix], &new[prefix..new.len() - suffix]);
+
+    let mut ops = Vec::new();
+    push_op(&mut ops, DiffOp::Equal(prefix));
+    if let Some(middle) = myers(old_mid, new_mid) {
+        for op in middle {
+            push_op(&mut ops, op);
+        }
+    } else {
+        push_op(&mut ops, DiffOp::Delete(old_mid.len()));
+        push_op(&mut ops, DiffOp::Insert(new_mid.len()));
+    }
+    push_op(&mut ops, DiffOp::Equal(suffix));
+    ops
+}
+
+/// Appends `op`, merging it into the last run of the same kind.
+fn push_op(ops: &mut Vec<DiffOp>, op: DiffOp) {
+    match (ops.last_mut(), op) {
+        (_, DiffOp::Equal(0) | DiffOp::Delete(0) | DiffOp::Insert(0)) => {}
+        (Some(DiffOp::Equal(n)), DiffOp::Equal(m))
+        | (Some(DiffOp::Delete(n)), DiffOp::Delete(m))
+        | (Some(DiffOp::Insert(n)), DiffOp::Insert(m)) => *n += m,
+        _ => ops.push(op),
+    }
+}
+
+/// Shortest edit script from `old` to `new`, or `None` past [`MAX_DIFF_EDITS`] edits.
+fn myers(old: &[char], new: &[char]) -> Option<Vec<DiffOp>> {
+    let (old_len, new_len) = (to_isize(old.len()), to_isize(new.len()));
+    let limit = (old_len + new_len).min(to_isize(MAX_DIFF_EDITS));
+    // Furthest x reached on each diagonal k = x - y, indexed by k + limit
+    let mut furthest = vec![0isize; 2 * to_usize(limit) + 2];
+    let at = |k: isize| to_usize(k + limit);
+    // Snapshot of diagonals -d..=d after each round d, for backtracking
+    let mut trace: Vec<Vec<isize>> = Vec::new();
+
+    for d in 0..=limit {
+        for k in (-d..=d).step_by(2) {
+            let mut x = if k == -d || (k != d && furthest[at(k - 1)] < furthest[at(k + 1)]) {
+                furthest[at(k + 1)]
+            } else {
+                furthest[at(k - 1)] + 1
+            };
+            let mut y = x - k;
+            let common = old[to_usize(x).min(old.len())..]
+                .iter()
+                .zip(&new[to_usize(y).min(new.len())..])
+                .take_while(|(a, b)| a == b)
+                .count();
+            x += to_isize(common);
+            y += to_isize(common);
+            furthest[at(k)] = x;
+            if x >= old_len && y >= new_len {
+                trace.push(furthest[at(-d)..=at(d)].to_vec());
+                return Some(backtrack(&trace, old_len, new_len));
+            }
+        }
+        trace.push(furthest[at(-d)..=at(d)].to_vec());
+    }
+    None
+}
+
+/// Walks the recorded rounds back from the end into forward edit runs.
+fn backtrack(trace: &[Vec<isize>], old_len: isize, new_len: isize) -> Vec<DiffOp> {
+    let (mut x, mut y) = (old_len, new_len);
+    let mut reversed = Vec::new();
+    for d in (1..to_isize(trace.len())).rev() {
+        let prev = &trace[to_usize(d - 1)];
+        let at = |k: isize| to_usize(k + d - 1);
+        let k = x - y;
+        let prev_k =
+            if k == -d || (k != d && prev[at(k - 1)] < prev[at(k + 1)]) { k + 1 } else { k - 1 };
+        let prev_x = prev[at(prev_k)];
+        let prev_y = prev_x - prev_k;
+
+        let snake = (x - prev_x).min(y - prev_y);
+        reversed.push(DiffOp::Equal(to_usize(snake)));
+        reversed.push(if prev_k == k + 1 { DiffOp::Insert(1) } else { DiffOp::Delete(1) });
+        (x, y) = (prev_x, prev_y);
+    }
+    reversed.push(DiffOp::Equal(to_usize(x)));
+
+    let mut ops = Vec::new();
+    reversed.into_iter().rev().for_each(|op| push_op(&mut ops, op));
+    ops
+}
+
+fn to_isize(value: usize) -> isize {
+    isize::try_from(value).unwrap_or(isize::MAX)
+}
+
+fn to_usize(value: isize) -> usize {
+    usize::try_from(value).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn apply(old: &str, new: &str, ops: &[DiffOp]) -> String {
+        let (mut old, mut new) = (old.chars(), new.chars());
+        let mut out = String::new();
+        for op in ops {
+            match *op {
+                DiffOp::Equal(n) => {
+                    let kept: String = old.by_ref().take(n).collect();
+                    assert_eq!(kept, new.by_ref().take(n).collect::<String>());
+                    out.push_str(&kept);
+                }
+                DiffOp::Delete(n) => old.by_ref().take(n).for_each(drop),
+                DiffOp::Insert(n) => out.extend(new.by_ref().take(n)),
+            }
+        }
+        out
+    }
+
+    #[test]
+    fn test_diff_is_minimal_and_reproduces_new_text() {
+        let cases = [
+            ("", "", 0),
+            ("", "abc", 3),
+            ("abc", "", 3),
+            ("the cat sat", "the bat sat", 2),
+            ("ABCABBA", "CBABAC", 5),
+            ("kitten", "sitting", 5),
+            ("héllo wörld", "hello world!", 5),
+        ];
+        for (old, new, edits) in cases {
+            let (a, b): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+            let ops = diff_chars(&a, &b);
+            assert_eq!(apply(old, new, &ops), new, "{old:?} -> {new:?}");
+            let changed: usize = ops
+                .iter()
+                .map(|op| match op {
+                    DiffOp::Equal(_) => 0,
+                    DiffOp::Delete(n) | DiffOp::Insert(n) => *n,
+                })
+                .sum();
+            assert_eq!(changed, edits, "{old:?} -> {new:?}: {ops:?}");
+        }
+    }
+
+    #[test]
+    fn test_very_different_texts_are_replaced_wholesale() {
+        let (old, new) = ("a".repeat(50_000), "b".repeat(50_001));
+        let (a, b): (Vec<char>, Vec<char>) = (old.chars().collect(), new.chars().collect());
+        let ops = diff_chars(&a, &b);
+        assert_eq!(apply(&old, &new, &ops), new);
+        assert_eq!(ops, [DiffOp::Delete(50_000), DiffOp::Insert(50_001)]);
+    }
+}
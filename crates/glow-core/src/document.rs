@@ -4,17 +4,38 @@ use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
+use crate::crdt::DocumentSync;
+use crate::error::{Error, Result};
+
 /// Unique identifier for a document.
-#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+///
+/// IDs are random version 4 UUIDs unless the `time-ordered-ids` feature is
+/// enabled, in which case new IDs are version 7 UUIDs that sort in creation
+/// order. Either way they are stored and serialized as UUIDs, so both kinds
+/// can coexist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
 pub struct DocumentId(Uuid);
 
 impl DocumentId {
-    /// Creates a new random document ID.
+    /// Creates a new document ID using the configured strategy.
     #[must_use]
     pub fn new() -> Self {
+        #[cfg(feature = "time-ordered-ids")]
+        return Self::time_ordered();
+        #[cfg(not(feature = "time-ordered-ids"))]
         Self(Uuid::new_v4())
     }
 
+    /// Creates a time-ordered document ID from a version 7 UUID.
+    ///
+    /// IDs created by this process are strictly increasing, which keeps
+    /// inserts into ID-ordered indexes local.
+    #[cfg(feature = "time-ordered-ids")]
+    #[must_use]
+    pub fn time_ordered() -> Self {
+        Self(Uuid::now_v7())
+    }
+
     /// Creates a document ID from a UUID.
     #[must_use]
     pub const fn from_uuid(uuid: Uuid) -> Self {
@@ -40,6 +61,51 @@ impl std::fmt::Display for DocumentId {
     }
 }
 
+/// How a document's content is encoded.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ContentFormat {
+    /// Markdown text.
+    #[default]
+    Markdown,
+    /// Text without any markup.
+    PlainText,
+    /// A `ProseMirror` document serialized as JSON.
+    #[serde(rename = "prosemirror_json")]
+    ProseMirrorJson,
+}
+
+impl ContentFormat {
+    /// Name of the format as stored and serialized.
+    #[must_use]
+    pub const fn as_str(self) -> &'static str {
+        match self {
+            Self::Markdown => "markdown",
+            Self::PlainText => "plain_text",
+            Self::ProseMirrorJson => "prosemirror_json",
+        }
+    }
+}
+
+impl std::fmt::Display for ContentFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(self.as_str())
+    }
+}
+
+impl std::str::FromStr for ContentFormat {
+    type Err = Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        match s {
+            "markdown" => Ok(Self::Markdown),
+            "plain_text" => Ok(Self::PlainText),
+            "prosemirror_json" => Ok(Self::ProseMirrorJson),
+            _ => Err(Error::InvalidState(format!("unknown content format: {s}"))),
+        }
+    }
+}
+
 /// Metadata associated with a document.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct DocumentMetadata {
@@ -52,8 +118,24 @@ pub struct DocumentMetadata {
     /// When the document was last modified.
     pub modified_at: DateTime<Utc>,
 
+    /// When the document was last opened, if ever.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub accessed_at: Option<DateTime<Utc>>,
+
     /// Version number for optimistic concurrency.
     pub version: u64,
+
+    /// User-assigned tags.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub tags: Vec<String>,
+
+    /// Whether the document is frozen against edits.
+    #[serde(default)]
+    pub read_only: bool,
+
+    /// How the content is encoded.
+    #[serde(default)]
+    pub content_format: ContentFormat,
 }
 
 impl DocumentMetadata {
@@ -61,7 +143,16 @@ impl DocumentMetadata {
     #[must_use]
     pub fn new(title: impl Into<String>) -> Self {
         let now = Utc::now();
-        Self { title: title.into(), created_at: now, modified_at: now, version: 1 }
+        Self {
+            title: title.into(),
+            created_at: now,
+            modified_at: now,
+            accessed_at: None,
+            version: 1,
+            tags: Vec::new(),
+            read_only: false,
+            content_format: ContentFormat::default(),
+        }
     }
 
     /// Updates the modified timestamp and increments version.
@@ -86,7 +177,7 @@ pub struct Document {
     /// Document metadata.
     pub metadata: DocumentMetadata,
 
-    /// Markdown content of the document.
+    /// Content of the document, encoded as its metadata's content format.
     pub content: String,
 
     /// CRDT state for synchronization (base64 encoded).
@@ -95,6 +186,9 @@ pub struct Document {
 }
 
 impl Document {
+    /// Content size above which [`Document::size_warning`] warns (512 KiB).
+    pub const DEFAULT_SOFT_SIZE_LIMIT: usize = 512 * 1024;
+
     /// Creates a new empty document.
     #[must_use]
     pub fn new() -> Self {
@@ -117,16 +211,502 @@ impl Document {
         }
     }
 
+    /// Sets the initial content of a new document and seeds its CRDT state.
+    ///
+    /// Unlike [`Document::set_content`], this does not count as an edit.
+    #[must_use]
+    pub fn with_initial_content(mut self, content: impl Into<String>) -> Self {
+        self.content = content.into();
+        self.seed_crdt_state();
+        self
+    }
+
+    /// Replaces the CRDT state with a fresh one built from the current content.
+    pub fn seed_crdt_state(&mut self) {
+        let sync = DocumentSync::with_content(&self.content);
+        self.crdt_state = Some(sync.get_state());
+    }
+
     /// Updates the document content.
-    pub fn set_content(&mut self, content: impl Into<String>) {
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn set_content(&mut self, content: impl Into<String>) -> Result<()> {
+        self.ensure_writable()?;
         self.content = content.into();
         self.metadata.touch();
+        Ok(())
     }
 
     /// Updates the document title.
-    pub fn set_title(&mut self, title: impl Into<String>) {
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn set_title(&mut self, title: impl Into<String>) -> Result<()> {
+        self.ensure_writable()?;
         self.metadata.title = title.into();
         self.metadata.touch();
+        Ok(())
+    }
+
+    /// Freezes or unfreezes the document.
+    ///
+    /// Changing the flag counts as an edit; setting it to its current value does nothing.
+    pub fn set_read_only(&mut self, read_only: bool) {
+        if self.metadata.read_only != read_only {
+            self.metadata.read_only = read_only;
+            self.metadata.touch();
+        }
+    }
+
+    /// Returns an error if the document is read-only.
+    fn ensure_writable(&self) -> Result<()> {
+        if self.metadata.read_only {
+            return Err(Error::InvalidState(format!("document {} is read-only", self.id)));
+        }
+        Ok(())
+    }
+
+    /// Applies a title and/or content change as a single edit.
+    ///
+    /// The version is bumped once regardless of how many fields change,
+    /// and not at all if neither is given.
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::InvalidState`] if the document is read-only.
+    pub fn apply_edit(&mut self, title: Option<String>, content: Option<String>) -> Result<()> {
+        if title.is_none() && content.is_none() {
+            return Ok(());
+        }
+        self.ensure_writable()?;
+        if let Some(title) = title {
+            self.metadata.title = title;
+        }
+        if let Some(content) = content {
+            self.content = content;
+        }
+        self.metadata.touch();
+        Ok(())
+    }
+
+    /// Records that the document was opened.
+    ///
+    /// Unlike edits, this does not bump the version or modified timestamp.
+    pub fn mark_opened(&mut self) {
+        self.metadata.accessed_at = Some(Utc::now());
+    }
+
+    /// Merges metadata from another version of this document, such as one
+    /// edited offline.
+    ///
+    /// The policy is deterministic, so both sides of a sync reach the same result:
+    /// - the title of the higher version wins; on equal versions the later
+    ///   modification wins, then the greater title;
+    /// - tags are the union of both, this document's first;
+    /// - the latest modified and accessed timestamps and the earliest creation
+    ///   timestamp are kept;
+    /// - the version becomes one past the higher of the two, ahead of both.
+    ///
+    /// Other fields are left alone.
+    pub fn merge_metadata(&mut self, other: &DocumentMetadata) {
+        let ours = &mut self.metadata;
+        if (other.version, other.modified_at, &other.title)
+            > (ours.version, ours.modified_at, &ours.title)
+        {
+            ours.title.clone_from(&other.title);
+        }
+        for tag in &other.tags {
+            if !ours.tags.contains(tag) {
+                ours.tags.push(tag.clone());
+            }
+        }
+        ours.created_at = ours.created_at.min(other.created_at);
+        ours.modified_at = ours.modified_at.max(other.modified_at);
+        ours.accessed_at = ours.accessed_at.max(other.accessed_at);
+        ours.version = ours.version.max(other.version) + 1;
+    }
+
+    /// Creates an independent copy of this document.
+    ///
+    /// The copy gets a new ID, fresh timestamps, version 1, and a " (copy)"
+    /// title suffix. Its CRDT state is seeded from the content rather than
+    /// shared with the original.
+    #[must_use]
+    pub fn duplicate(&self) -> Self {
+        let mut copy = Self::with_title(format!("{} (copy)", self.metadata.title))
+            .with_initial_content(self.content.clone());
+        copy.metadata.tags.clone_from(&self.metadata.tags);
+        copy.metadata.content_format = self.metadata.content_format;
+        copy
+    }
+
+    /// Normalizes line endings to `\n`, strips trailing whitespace from each
+    /// line, and ends non-empty content with a single newline.
+    ///
+    /// Returns whether the content changed. Content that is already normal is
+    /// left alone, as are read-only documents. The version is not bumped, since
+    /// normalization happens as part of another edit or save. A CRDT state is
+    /// updated in place so peers keep syncing; one that cannot be decoded is
+    /// reseeded from the normalized content.
+    pub fn normalize_content(&mut self) -> bool {
+        if self.metadata.read_only {
+            return false;
+        }
+        let normalized = normalize_text(&self.content);
+        if normalized == self.content {
+            return false;
+        }
+        self.content = normalized;
+
+        let synced = self.crdt_state.as_deref().map(|state| {
+            let sync = DocumentSync::from_state(state)?;
+            sync.set_content(&self.content)?;
+            Ok::<_, Error>(sync.get_state())
+        });
+        match synced {
+            Some(Ok(state)) => self.crdt_state = Some(state),
+            Some(Err(_)) => self.seed_crdt_state(),
+            None => {}
+        }
+        true
+    }
+
+    /// Returns a plain-text snippet of the content for list views.
+    ///
+    /// Markup is removed according to the content format and runs of
+    /// whitespace, including line breaks, collapse to single spaces. Snippets
+    /// longer than `max_chars` characters are cut and end with `…`.
+    #[must_use]
+    pub fn preview(&self, max_chars: usize) -> String {
+        let text = self.plain_text();
+        match text.char_indices().nth(max_chars) {
+            Some((end, _)) => format!("{}…", text[..end].trim_end()),
+            None => text,
+        }
+    }
+
+    /// Warns when the content is larger than [`Document::DEFAULT_SOFT_SIZE_LIMIT`].
+    #[must_use]
+    pub fn size_warning(&self) -> Option<SizeWarning> {
+        self.size_warning_above(Self::DEFAULT_SOFT_SIZE_LIMIT)
+    }
+
+    /// Warns when the content is larger than `soft_limit_bytes`.
+    ///
+    /// The limit is soft: the document stays valid, but editing and syncing
+    /// it get slower as it grows.
+    #[must_use]
+    pub fn size_warning_above(&self, soft_limit_bytes: usize) -> Option<SizeWarning> {
+        let content_bytes = self.content.len();
+        (content_bytes > soft_limit_bytes).then(|| SizeWarning {
+            content_bytes,
+            soft_limit_bytes,
+            message: format!(
+                "document is {content_bytes} bytes, over the recommended {soft_limit_bytes}; \
+                 consider splitting it"
+            ),
+        })
+    }
+
+    /// Number of words in the content, ignoring markup.
+    #[must_use]
+    pub fn word_count(&self) -> usize {
+        self.plain_text().split_whitespace().count()
+    }
+
+    /// The Markdown headings of the content, in document order.
+    ///
+    /// Both ATX (`# Title`) and setext (`Title` underlined with `===` or
+    /// `---`) headings are recognized, and fenced code blocks are skipped.
+    /// Content in other formats has no outline.
+    #[must_use]
+    pub fn outline(&self) -> Vec<Heading> {
+        match self.metadata.content_format {
+            ContentFormat::Markdown => markdown_outline(&self.content),
+            ContentFormat::PlainText | ContentFormat::ProseMirrorJson => Vec::new(),
+        }
+    }
+
+    /// The content's text without markup, its words joined by single spaces.
+    ///
+    /// `ProseMirror` content that is not valid JSON is read as plain text.
+    fn plain_text(&self) -> String {
+        match self.metadata.content_format {
+            ContentFormat::Markdown => plain_text(&self.content),
+            ContentFormat::PlainText => collapse_whitespace(&self.content),
+            ContentFormat::ProseMirrorJson => serde_json::from_str(&self.content).map_or_else(
+                |_| collapse_whitespace(&self.content),
+                |doc| {
+                    let mut text = String::new();
+                    prosemirror_text(&doc, &mut text);
+                    collapse_whitespace(&text)
+                },
+            ),
+        }
+    }
+}
+
+/// `text` with `\n` line endings, no trailing whitespace on any line, and
+/// a single trailing newline unless it is empty.
+fn normalize_text(text: &str) -> String {
+    let mut normalized = String::with_capacity(text.len() + 1);
+    for line in text.replace("\r\n", "\n").split(['\n', '\r']) {
+        normalized.push_str(line.trim_end());
+        normalized.push('\n');
+    }
+    normalized.truncate(normalized.trim_end_matches('\n').len());
+    if !normalized.is_empty() {
+        normalized.push('\n');
+    }
+    normalized
+}
+
+/// The ATX and setext headings of `markdown`, outside fenced code blocks.
+fn markdown_outline(markdown: &str) -> Vec<Heading> {
+    let mut headings = Vec::new();
+    // Marker and length of the open code fence, if any
+    let mut fence: Option<(char, usize)> = None;
+    // Start offset and text of the paragraph a setext underline would make a heading
+    let mut paragraph: Option<(usize, String)> = None;
+    let mut offset = 0;
+    for raw in markdown.split_inclusive('\n') {
+        let line_offset = offset;
+        offset += raw.chars().count();
+        let line = raw.trim_end_matches(['\n', '\r']);
+        let indent = line.len() - line.trim_start_matches(' ').len();
+        let trimmed = line.trim();
+
+        if let Some((mark, len)) = fence {
+            let closes = fence_marker(trimmed)
+                .is_some_and(|(m, l)| m == mark && l >= len && trimmed.len() == l);
+            if indent < 4 && closes {
+                fence = None;
+            }
+            continue;
+        }
+        // Indented lines continue a paragraph or are code, never headings
+        if indent >= 4 {
+            if let Some((_, text)) = &mut paragraph {
+                text.push(' ');
+                text.push_str(trimmed);
+            }
+            continue;
+        }
+        if let Some(marker) = fence_marker(trimmed) {
+            fence = Some(marker);
+            paragraph = None;
+            continue;
+        }
+        if let Some((level, text)) = atx_heading(trimmed) {
+            paragraph = None;
+            if !text.is_empty() {
+                headings.push(Heading { level, text: text.to_owned(), char_offset: line_offset });
+            }
+            continue;
+        }
+        if let Some(level) = setext_level(trimmed)
+            && let Some((char_offset, text)) = paragraph.take()
+        {
+            headings.push(Heading { level, text, char_offset });
+            continue;
+        }
+        if trimmed.is_empty() || is_thematic_break(trimmed) || strip_block_marker(trimmed).is_some()
+        {
+            paragraph = None;
+            continue;
+        }
+        match &mut paragraph {
+            Some((_, text)) => {
+                text.push(' ');
+                text.push_str(trimmed);
+            }
+            None => paragraph = Some((line_offset, trimmed.to_owned())),
+        }
+    }
+    headings
+}
+
+/// The marker character and length of a code fence such as ```` ``` ```` or `~~~`.
+fn fence_marker(line: &str) -> Option<(char, usize)> {
+    let mark = line.chars().next().filter(|&c| c == '`' || c == '~')?;
+    let len = line.chars().take_while(|&c| c == mark).count();
+    (len >= 3).then_some((mark, len))
+}
+
+/// The level and text of an ATX heading such as `## Title ##`.
+fn atx_heading(line: &str) -> Option<(u8, &str)> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    let rest = line.get(level..)?;
+    if !(1..=6).contains(&level) || !(rest.is_empty() || rest.starts_with([' ', '\t'])) {
+        return None;
+    }
+    let text = rest.trim();
+    // Drop an optional closing sequence of `#`s
+    let unclosed = text.trim_end_matches('#');
+    let text = if unclosed.is_empty() || unclosed.ends_with([' ', '\t']) {
+        unclosed.trim_end()
+    } else {
+        text
+    };
+    Some((u8::try_from(level).ok()?, text))
+}
+
+/// The heading level a setext underline of `=`s or `-`s gives the line above it.
+fn setext_level(line: &str) -> Option<u8> {
+    if line.is_empty() {
+        None
+    } else if line.bytes().all(|b| b == b'=') {
+        Some(1)
+    } else if line.bytes().all(|b| b == b'-') {
+        Some(2)
+    } else {
+        None
+    }
+}
+
+/// Strips Markdown syntax from `markdown`, joining its words with single spaces.
+fn plain_text(markdown: &str) -> String {
+    let mut text = String::with_capacity(markdown.len());
+    for line in markdown.lines() {
+        let line = line.trim();
+        if line.starts_with("```") || line.starts_with("~~~") || is_thematic_break(line) {
+            continue;
+        }
+        text.push_str(&strip_inline_markup(strip_block_markers(line)));
+        text.push(' ');
+    }
+    collapse_whitespace(&text)
+}
+
+/// `text` with its words joined by single spaces.
+fn collapse_whitespace(text: &str) -> String {
+    text.split_whitespace().collect::<Vec<_>>().join(" ")
+}
+
+/// Appends the text of a `ProseMirror` node and its descendants to `text`.
+///
+/// Adjacent text nodes join directly, since marks can split a word; other
+/// nodes, such as paragraphs and hard breaks, end with a space.
+fn prosemirror_text(node: &serde_json::Value, text: &mut String) {
+    if let Some(node_text) = node.get("text").and_then(serde_json::Value::as_str) {
+        text.push_str(node_text);
+        return;
+    }
+    if let Some(children) = node.get("content").and_then(serde_json::Value::as_array) {
+        for child in children {
+            prosemirror_text(child, text);
+        }
+    }
+    text.push(' ');
+}
+
+/// Whether `line` is a thematic break such as `---`, `***` or `___`.
+fn is_thematic_break(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].iter().any(|&m| marks.iter().all(|&c| c == m))
+}
+
+/// Removes heading, quote, list and task markers from the start of a line.
+fn strip_block_markers(mut line: &str) -> &str {
+    while let Some(rest) = strip_block_marker(line) {
+        line = rest.trim_start();
+    }
+    line
+}
+
+/// Removes one block marker from the start of a line, if it has one.
+fn strip_block_marker(line: &str) -> Option<&str> {
+    if let Some(rest) = line.strip_prefix('>') {
+        return Some(rest);
+    }
+    if let Some(rest) = ["- ", "* ", "+ ", "[ ] ", "[x] ", "[X] "]
+        .iter()
+        .find_map(|marker| line.strip_prefix(marker))
+    {
+        return Some(rest);
+    }
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if (1..=6).contains(&level) {
+        return line[level..].strip_prefix(' ');
+    }
+    let digits = line.bytes().take_while(u8::is_ascii_digit).count();
+    if digits > 0 {
+        let rest = &line[digits..];
+        return rest.strip_prefix(". ").or_else(|| rest.strip_prefix(") "));
+    }
+    None
+}
+
+/// Removes emphasis, code and link syntax, keeping link and image text.
+fn strip_inline_markup(line: &str) -> String {
+    let mut text = String::with_capacity(line.len());
+    let mut chars = line.chars().peekable();
+    while let Some(c) = chars.next() {
+        match c {
+            '_' | '~' if chars.peek() == Some(&c) => {
+                chars.next();
+            }
+            // Drop the target of `[text](url)`
+            ']' if chars.peek() == Some(&'(') => {
+                chars.by_ref().find(|&c| c == ')');
+            }
+            '!' if chars.peek() != Some(&'[') => text.push(c),
+            '*' | '`' | '[' | ']' | '!' => {}
+            _ => text.push(c),
+        }
+    }
+    text
+}
+
+/// A heading in a document's outline.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Heading {
+    /// Heading level, from 1 for top-level headings to 6.
+    pub level: u8,
+
+    /// Text of the heading, without its markers.
+    pub text: String,
+
+    /// Offset of the heading's first line in the content, in characters.
+    pub char_offset: usize,
+}
+
+/// A non-fatal warning that a document's content is larger than recommended.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SizeWarning {
+    /// Size of the content in bytes.
+    pub content_bytes: usize,
+
+    /// Size the content exceeded, in bytes.
+    pub soft_limit_bytes: usize,
+
+    /// Human-readable description of the warning.
+    pub message: String,
+}
+
+/// A document's metadata without its content, for list views.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSummary {
+    /// Unique identifier.
+    pub id: DocumentId,
+
+    /// Document metadata.
+    pub metadata: DocumentMetadata,
+
+    /// Length of the content in characters.
+    pub content_length: usize,
+}
+
+impl From<&Document> for DocumentSummary {
+    fn from(doc: &Document) -> Self {
+        Self {
+            id: doc.id,
+            metadata: doc.metadata.clone(),
+            content_length: doc.content.chars().count(),
+        }
     }
 }
 
@@ -158,15 +738,313 @@ mod tests {
     fn test_set_content_updates_version() {
         let mut doc = Document::new();
         let initial_version = doc.metadata.version;
-        doc.set_content("Hello, world!");
+        doc.set_content("Hello, world!").expect("should edit");
         assert_eq!(doc.content, "Hello, world!");
         assert_eq!(doc.metadata.version, initial_version + 1);
     }
 
+    #[cfg(feature = "time-ordered-ids")]
+    #[test]
+    fn test_time_ordered_ids_are_increasing() {
+        let ids: Vec<DocumentId> = (0..1_000).map(|_| DocumentId::new()).collect();
+        assert!(ids.windows(2).all(|pair| pair[0] < pair[1]));
+        assert_eq!(ids[0].as_uuid().get_version_num(), 7);
+    }
+
     #[test]
     fn test_document_id_display() {
         let id = DocumentId::new();
         let display = format!("{id}");
         assert!(!display.is_empty());
     }
+
+    #[test]
+    fn test_with_initial_content_seeds_crdt() {
+        let doc = Document::with_title("Imported").with_initial_content("# Heading");
+        assert_eq!(doc.content, "# Heading");
+        assert_eq!(doc.metadata.version, 1);
+
+        let state = doc.crdt_state.as_deref().expect("should seed crdt state");
+        let sync = DocumentSync::from_state(state).expect("should decode state");
+        assert_eq!(sync.get_content(), "# Heading");
+    }
+
+    #[test]
+    fn test_apply_edit_bumps_version_once() {
+        let mut doc = Document::with_title("Draft");
+        doc.apply_edit(Some("Final".to_owned()), Some("Body".to_owned())).expect("should edit");
+        assert_eq!(doc.metadata.title, "Final");
+        assert_eq!(doc.content, "Body");
+        assert_eq!(doc.metadata.version, 2);
+
+        doc.apply_edit(None, None).expect("should edit");
+        assert_eq!(doc.metadata.version, 2);
+    }
+
+    #[test]
+    fn test_read_only_rejects_edits() {
+        let mut doc = Document::with_title("Published").with_initial_content("Final");
+        doc.set_read_only(true);
+        let version = doc.metadata.version;
+
+        assert!(matches!(doc.set_content("Changed"), Err(Error::InvalidState(_))));
+        assert!(matches!(doc.set_title("Changed"), Err(Error::InvalidState(_))));
+        assert!(doc.apply_edit(None, Some("Changed".to_owned())).is_err());
+        assert_eq!(doc.content, "Final");
+        assert_eq!(doc.metadata.title, "Published");
+        assert_eq!(doc.metadata.version, version);
+
+        doc.set_read_only(false);
+        doc.set_content("Changed").expect("should edit after unfreezing");
+        assert_eq!(doc.content, "Changed");
+    }
+
+    #[test]
+    fn test_mark_opened_keeps_version() {
+        let mut doc = Document::new();
+        assert!(doc.metadata.accessed_at.is_none());
+
+        doc.mark_opened();
+        assert!(doc.metadata.accessed_at.is_some());
+        assert_eq!(doc.metadata.version, 1);
+    }
+
+    #[test]
+    fn test_preview_of_short_and_empty_content() {
+        let doc = Document::new().with_initial_content("A short\n\n   note.  ");
+        assert_eq!(doc.preview(50), "A short note.");
+        assert_eq!(Document::new().preview(50), "");
+    }
+
+    #[test]
+    fn test_preview_truncates_long_content() {
+        let doc =
+            Document::new().with_initial_content("The quick brown fox jumps over the lazy dog");
+        assert_eq!(doc.preview(10), "The quick…");
+        assert_eq!(doc.preview(43), "The quick brown fox jumps over the lazy dog");
+    }
+
+    #[test]
+    fn test_preview_strips_markdown() {
+        let content = "# Release notes\n\n> **Bold** and *italic* with `code`.\n\n\
+                       - See [the docs](https://example.com) ![logo](logo.png)\n\
+                       1. ~~Old~~ __new__\n\n---\n```rust\nlet x = 1;\n```";
+        let doc = Document::new().with_initial_content(content);
+        assert_eq!(
+            doc.preview(200),
+            "Release notes Bold and italic with code. See the docs logo Old new let x = 1;"
+        );
+    }
+
+    #[test]
+    fn test_outline_of_nested_headings() {
+        let content = "# Guide\n\nIntro.\n\n## Install ##\n\nSteps.\n\n### From source\n\n\
+                       Usage\n-----\n\nOverview\n===\n#hashtag, not a heading\n";
+        let doc = Document::new().with_initial_content(content);
+
+        let outline: Vec<_> =
+            doc.outline().into_iter().map(|h| (h.level, h.text, h.char_offset)).collect();
+        assert_eq!(
+            outline,
+            [
+                (1, "Guide".to_owned(), 0),
+                (2, "Install".to_owned(), 17),
+                (3, "From source".to_owned(), 40),
+                (2, "Usage".to_owned(), 57),
+                (1, "Overview".to_owned(), 70),
+            ]
+        );
+        assert_eq!(&content[57..62], "Usage");
+    }
+
+    #[test]
+    fn test_outline_skips_fenced_code() {
+        let content = "# Script\n\n```sh\n# not a heading\necho hi\n```\n\n~~~~\n## Nor this\n\
+                       ~~~\n~~~~\n\n## After\n";
+        let mut doc = Document::new().with_initial_content(content);
+
+        let texts: Vec<_> = doc.outline().into_iter().map(|h| h.text).collect();
+        assert_eq!(texts, ["Script", "After"]);
+
+        doc.metadata.content_format = ContentFormat::PlainText;
+        assert_eq!(doc.outline(), []);
+    }
+
+    #[test]
+    fn test_size_warning_under_and_over_threshold() {
+        let mut doc = Document::new();
+        doc.content = "x".repeat(Document::DEFAULT_SOFT_SIZE_LIMIT);
+        assert_eq!(doc.size_warning(), None);
+
+        doc.content.push('x');
+        let warning = doc.size_warning().expect("should warn over the limit");
+        assert_eq!(warning.content_bytes, Document::DEFAULT_SOFT_SIZE_LIMIT + 1);
+        assert_eq!(warning.soft_limit_bytes, Document::DEFAULT_SOFT_SIZE_LIMIT);
+        assert!(warning.message.contains("over the recommended"));
+    }
+
+    #[test]
+    fn test_size_warning_with_custom_threshold() {
+        let doc = Document::new().with_initial_content("héllo");
+        assert_eq!(doc.size_warning_above(6), None);
+        let warning = doc.size_warning_above(5).expect("should warn over the limit");
+        assert_eq!((warning.content_bytes, warning.soft_limit_bytes), (6, 5));
+    }
+
+    #[test]
+    fn test_word_count_depends_on_format() {
+        let mut doc =
+            Document::new().with_initial_content("# Notes\n\n- **bold** [link](https://x.y)\n");
+        assert_eq!(doc.word_count(), 3);
+        assert_eq!(doc.preview(100), "Notes bold link");
+
+        doc.metadata.content_format = ContentFormat::PlainText;
+        assert_eq!(doc.word_count(), 5);
+        assert_eq!(doc.preview(100), "# Notes - **bold** [link](https://x.y)");
+
+        doc.content = r#"{"type": "doc", "content": [
+            {"type": "heading", "content": [{"type": "text", "text": "Notes"}]},
+            {"type": "paragraph", "content": [
+                {"type": "text", "text": "un"},
+                {"type": "text", "text": "broken", "marks": [{"type": "bold"}]},
+                {"type": "hard_break"},
+                {"type": "text", "text": "next line"}
+            ]}
+        ]}"#
+        .to_owned();
+        doc.metadata.content_format = ContentFormat::ProseMirrorJson;
+        assert_eq!(doc.word_count(), 4);
+        assert_eq!(doc.preview(100), "Notes unbroken next line");
+
+        doc.content = "not { json".to_owned();
+        assert_eq!(doc.word_count(), 3);
+    }
+
+    #[test]
+    fn test_content_format_defaults_to_markdown() {
+        let metadata: DocumentMetadata = serde_json::from_value(serde_json::json!({
+            "title": "Old", "created_at": Utc::now(), "modified_at": Utc::now(), "version": 1
+        }))
+        .expect("should deserialize");
+        assert_eq!(metadata.content_format, ContentFormat::Markdown);
+        for format in
+            [ContentFormat::Markdown, ContentFormat::PlainText, ContentFormat::ProseMirrorJson]
+        {
+            assert_eq!(format.as_str().parse::<ContentFormat>().expect("should parse"), format);
+            assert_eq!(serde_json::to_value(format).expect("should serialize"), format.as_str());
+        }
+    }
+
+    #[test]
+    fn test_normalize_content_converts_line_endings() {
+        let mut doc = Document::new().with_initial_content("# Title\r\nBody\rMore\r\n\r\n\r\n");
+        assert!(doc.normalize_content());
+        assert_eq!(doc.content, "# Title\nBody\nMore\n");
+        assert_eq!(doc.metadata.version, 1);
+
+        let state = doc.crdt_state.as_deref().expect("should keep crdt state");
+        let sync = DocumentSync::from_state(state).expect("should decode state");
+        assert_eq!(sync.get_content(), doc.content);
+    }
+
+    #[test]
+    fn test_normalize_content_strips_trailing_whitespace() {
+        let mut doc = Document::new().with_initial_content("First  \nSecond\t\n  Indented \nLast");
+        assert!(doc.normalize_content());
+        assert_eq!(doc.content, "First\nSecond\n  Indented\nLast\n");
+
+        let state = doc.crdt_state.clone();
+        assert!(!doc.normalize_content());
+        assert_eq!(doc.crdt_state, state);
+
+        let mut empty = Document::new();
+        assert!(!empty.normalize_content());
+        assert_eq!(empty.content, "");
+    }
+
+    #[test]
+    fn test_duplicate_document() {
+        let mut doc = Document::with_title("Original");
+        doc.set_content("Some content").expect("should edit");
+        doc.metadata.tags = vec!["draft".to_owned()];
+
+        let copy = doc.duplicate();
+        assert_ne!(copy.id, doc.id);
+        assert_eq!(copy.metadata.title, "Original (copy)");
+        assert_eq!(copy.metadata.version, 1);
+        assert_eq!(copy.content, "Some content");
+        assert_eq!(copy.metadata.tags, vec!["draft".to_owned()]);
+
+        let state = copy.crdt_state.as_deref().expect("should seed crdt state");
+        let sync = DocumentSync::from_state(state).expect("should decode state");
+        assert_eq!(sync.get_content(), "Some content");
+    }
+
+    #[test]
+    fn test_merge_metadata_title_follows_higher_version() {
+        let mut doc = Document::with_title("Draft");
+        let mut renamed = doc.metadata.clone();
+        renamed.title = "Final".to_owned();
+        renamed.version = 3;
+
+        doc.merge_metadata(&renamed);
+        assert_eq!(doc.metadata.title, "Final");
+        assert_eq!(doc.metadata.version, 4);
+
+        // The older side's title loses even when it merges last
+        let mut stale = DocumentMetadata::new("Draft");
+        stale.version = 2;
+        doc.merge_metadata(&stale);
+        assert_eq!(doc.metadata.title, "Final");
+        assert_eq!(doc.metadata.version, 5);
+    }
+
+    #[test]
+    fn test_merge_metadata_is_deterministic_on_equal_versions() {
+        let base = DocumentMetadata::new("Notes");
+        let mut left = Document { metadata: base.clone(), ..Document::new() };
+        left.metadata.title = "Apples".to_owned();
+        let mut right = Document { metadata: base, ..Document::new() };
+        right.metadata.title = "Pears".to_owned();
+
+        let (left_meta, right_meta) = (left.metadata.clone(), right.metadata.clone());
+        left.merge_metadata(&right_meta);
+        right.merge_metadata(&left_meta);
+        assert_eq!(left.metadata.title, "Pears");
+        assert_eq!(right.metadata.title, "Pears");
+        assert_eq!(left.metadata.version, right.metadata.version);
+    }
+
+    #[test]
+    fn test_merge_metadata_unions_tags() {
+        let mut doc = Document::new();
+        doc.metadata.tags = vec!["draft".to_owned(), "travel".to_owned()];
+        let mut other = doc.metadata.clone();
+        other.tags = vec!["travel".to_owned(), "2024".to_owned()];
+
+        doc.merge_metadata(&other);
+        assert_eq!(doc.metadata.tags, ["draft", "travel", "2024"]);
+    }
+
+    #[test]
+    fn test_merge_metadata_selects_timestamps() {
+        let mut doc = Document::new();
+        let mut other = doc.metadata.clone();
+        other.created_at -= chrono::Duration::days(2);
+        other.modified_at += chrono::Duration::hours(1);
+        other.accessed_at = Some(other.modified_at);
+
+        doc.merge_metadata(&other);
+        assert_eq!(doc.metadata.created_at, other.created_at);
+        assert_eq!(doc.metadata.modified_at, other.modified_at);
+        assert_eq!(doc.metadata.accessed_at, other.accessed_at);
+
+        // Merging older timestamps back in changes none of them
+        let mut older = other.clone();
+        older.modified_at -= chrono::Duration::days(1);
+        older.accessed_at = None;
+        doc.merge_metadata(&older);
+        assert_eq!(doc.metadata.modified_at, other.modified_at);
+        assert_eq!(doc.metadata.accessed_at, other.accessed_at);
+    }
 }
@@ -16,6 +16,10 @@ pub enum Error {
     #[error("serialization error: {0}")]
     Serialization(#[from] serde_json::Error),
 
+    /// Markdown frontmatter could not be parsed.
+    #[error("invalid frontmatter: {0}")]
+    Frontmatter(String),
+
     /// CRDT operation failed.
     #[error("crdt error: {0}")]
     Crdt(String),
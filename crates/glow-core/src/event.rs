@@ -0,0 +1,42 @@
+//! Document lifecycle events.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::document::DocumentId;
+
+/// Kind of change that happened to a document.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DocumentEventKind {
+    /// The document was created.
+    Created,
+    /// The document was updated.
+    Updated,
+    /// The document was deleted.
+    Deleted,
+}
+
+/// A change to a document, published to interested observers.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct DocumentEvent {
+    /// What happened.
+    pub kind: DocumentEventKind,
+
+    /// The affected document.
+    pub document_id: DocumentId,
+
+    /// Document version after the change (or at deletion).
+    pub version: u64,
+
+    /// When the change happened.
+    pub timestamp: DateTime<Utc>,
+}
+
+impl DocumentEvent {
+    /// Creates an event timestamped now.
+    #[must_use]
+    pub fn new(kind: DocumentEventKind, document_id: DocumentId, version: u64) -> Self {
+        Self { kind, document_id, version, timestamp: Utc::now() }
+    }
+}
@@ -0,0 +1,157 @@
+//! Markdown files with YAML frontmatter.
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::document::Document;
+use crate::error::{Error, Result};
+
+/// Line that opens and closes a frontmatter block.
+const FENCE: &str = "---";
+
+/// Fields read from and written to a frontmatter block.
+///
+/// Unknown keys are ignored.
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct Frontmatter {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    title: Option<String>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    tags: Vec<String>,
+    #[serde(default, alias = "date", skip_serializing_if = "Option::is_none")]
+    created: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    modified: Option<String>,
+}
+
+impl Document {
+    /// Parses a Markdown file with optional YAML frontmatter.
+    ///
+    /// A leading `---`-delimited block supplies `title`, `tags`, `created`
+    /// (or `date`) and `modified`; everything after it becomes the content.
+    /// Without frontmatter the whole file is content and the title is
+    /// "Untitled".
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Frontmatter`] if the frontmatter is not valid YAML or
+    /// holds an unparseable timestamp.
+    pub fn from_markdown(raw: &str) -> Result<Self> {
+        let (frontmatter, content) = match split_frontmatter(raw) {
+            Some((yaml, content)) if yaml.trim().is_empty() => (Frontmatter::default(), content),
+            Some((yaml, content)) => (
+                serde_yaml::from_str(yaml).map_err(|e| Error::Frontmatter(e.to_string()))?,
+                content,
+            ),
+            None => (Frontmatter::default(), raw),
+        };
+
+        let created = frontmatter.created.as_deref().map(parse_timestamp).transpose()?;
+        let modified = frontmatter.modified.as_deref().map(parse_timestamp).transpose()?;
+
+        let mut doc = frontmatter
+            .title
+            .map_or_else(Self::new, Self::with_title)
+            .with_initial_content(content);
+        doc.metadata.tags = frontmatter.tags;
+        if let Some(created) = created {
+            doc.metadata.created_at = created;
+        }
+        if let Some(modified) = modified.or(created) {
+            doc.metadata.modified_at = modified;
+        }
+        Ok(doc)
+    }
+
+    /// Serializes the document as Markdown with YAML frontmatter.
+    ///
+    /// The output parses back with [`Document::from_markdown`].
+    ///
+    /// # Errors
+    ///
+    /// Returns [`Error::Frontmatter`] if the frontmatter cannot be serialized.
+    pub fn to_markdown(&self) -> Result<String> {
+        let frontmatter = Frontmatter {
+            title: Some(self.metadata.title.clone()),
+            tags: self.metadata.tags.clone(),
+            created: Some(self.metadata.created_at.to_rfc3339()),
+            modified: Some(self.metadata.modified_at.to_rfc3339()),
+        };
+        let yaml =
+            serde_yaml::to_string(&frontmatter).map_err(|e| Error::Frontmatter(e.to_string()))?;
+        Ok(format!("{FENCE}\n{yaml}{FENCE}\n{}", self.content))
+    }
+}
+
+/// Splits `raw` into its frontmatter block and the content after it.
+///
+/// Returns `None` unless the first line is a fence and a closing fence follows.
+fn split_frontmatter(raw: &str) -> Option<(&str, &str)> {
+    let rest = raw.strip_prefix(FENCE)?;
+    let rest = rest.strip_prefix("\r\n").or_else(|| rest.strip_prefix('\n'))?;
+
+    let mut offset = 0;
+    for line in rest.split_inclusive('\n') {
+        if line.trim_end() == FENCE {
+            return Some((&rest[..offset], &rest[offset + line.len()..]));
+        }
+        offset += line.len();
+    }
+    None
+}
+
+/// Parses an RFC 3339 timestamp or a bare `YYYY-MM-DD` date (taken as midnight UTC).
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(value) {
+        return Ok(timestamp.with_timezone(&Utc));
+    }
+    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|midnight| midnight.and_utc())
+        .ok_or_else(|| Error::Frontmatter(format!("invalid timestamp: {value}")))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_markdown_with_frontmatter() {
+        let raw =
+            "---\ntitle: Trip notes\ntags: [travel, draft]\ndate: 2024-03-09\n---\n# Day one\n";
+        let doc = Document::from_markdown(raw).expect("should parse");
+
+        assert_eq!(doc.metadata.title, "Trip notes");
+        assert_eq!(doc.metadata.tags, ["travel", "draft"]);
+        assert_eq!(doc.metadata.created_at.to_rfc3339(), "2024-03-09T00:00:00+00:00");
+        assert_eq!(doc.metadata.modified_at, doc.metadata.created_at);
+        assert_eq!(doc.content, "# Day one\n");
+
+        let reparsed = Document::from_markdown(&doc.to_markdown().expect("should serialize"))
+            .expect("should parse");
+        assert_eq!(reparsed.metadata.title, doc.metadata.title);
+        assert_eq!(reparsed.metadata.tags, doc.metadata.tags);
+        assert_eq!(reparsed.metadata.created_at, doc.metadata.created_at);
+        assert_eq!(reparsed.content, doc.content);
+    }
+
+    #[test]
+    fn test_from_markdown_without_frontmatter() {
+        let raw = "# Heading\n\n---\n\nAfter a rule\n";
+        let doc = Document::from_markdown(raw).expect("should parse");
+
+        assert_eq!(doc.metadata.title, "Untitled");
+        assert_eq!(doc.metadata.tags, Vec::<String>::new());
+        assert_eq!(doc.content, raw);
+    }
+
+    #[test]
+    fn test_from_markdown_rejects_malformed_frontmatter() {
+        let unclosed_list = "---\ntitle: Notes\ntags: [a, b\n---\nBody\n";
+        assert!(matches!(Document::from_markdown(unclosed_list), Err(Error::Frontmatter(_))));
+
+        let bad_date = "---\ncreated: last tuesday\n---\nBody\n";
+        assert!(matches!(Document::from_markdown(bad_date), Err(Error::Frontmatter(_))));
+    }
+}
@@ -5,9 +5,19 @@
 //! platform-agnostic and can compile to WebAssembly.
 
 pub mod crdt;
+mod diff;
 pub mod document;
 pub mod error;
+pub mod event;
+pub mod frontmatter;
+pub mod replay;
+pub mod template;
 
 pub use crdt::DocumentSync;
-pub use document::{Document, DocumentId, DocumentMetadata};
+pub use document::{
+    ContentFormat, Document, DocumentId, DocumentMetadata, DocumentSummary, Heading, SizeWarning,
+};
 pub use error::{Error, Result};
+pub use event::{DocumentEvent, DocumentEventKind};
+pub use replay::{ApplyReport, EditConflict, ReplayableEdit};
+pub use template::Template;
@@ -0,0 +1,98 @@
+//! Replaying accepted edits against a document.
+
+use serde::{Deserialize, Serialize};
+
+use crate::document::Document;
+
+/// An edit that [`Document::replay_accepted_edits`] can apply.
+pub trait ReplayableEdit {
+    /// ID of the edit, used in the [`ApplyReport`].
+    fn edit_id(&self) -> &str;
+
+    /// Whether the edit was accepted and should be replayed.
+    fn is_accepted(&self) -> bool;
+
+    /// Byte offset in the document content where the original text starts.
+    fn offset(&self) -> usize;
+
+    /// The text the edit replaces.
+    fn original_text(&self) -> &str;
+
+    /// The text the edit puts in its place.
+    fn replacement_text(&self) -> &str;
+}
+
+/// An accepted edit that could not be replayed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct EditConflict {
+    /// ID of the edit.
+    pub edit_id: String,
+    /// Why the edit was skipped.
+    pub reason: String,
+}
+
+/// Outcome of replaying a set of edits.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ApplyReport {
+    /// IDs of the edits applied, in the order they were applied.
+    pub applied: Vec<String>,
+    /// Accepted edits that were skipped.
+    pub conflicts: Vec<EditConflict>,
+}
+
+impl ApplyReport {
+    /// Records that `edit` was skipped.
+    fn conflict(&mut self, edit: &impl ReplayableEdit, reason: impl Into<String>) {
+        self.conflicts
+            .push(EditConflict { edit_id: edit.edit_id().to_owned(), reason: reason.into() });
+    }
+}
+
+impl Document {
+    /// Applies the accepted edits, in position order, ignoring the rest.
+    ///
+    /// Each edit replaces its original text at its offset, shifted by the
+    /// length changes of the edits applied before it. An edit that overlaps an
+    /// earlier one, or whose original text is not at its offset, is reported
+    /// as a conflict and skipped. Nothing changes if the document is read-only.
+    pub fn replay_accepted_edits<E: ReplayableEdit>(&mut self, edits: &[E]) -> ApplyReport {
+        let mut accepted: Vec<&E> = edits.iter().filter(|e| e.is_accepted()).collect();
+        accepted.sort_by_key(|e| (e.offset(), e.original_text().len()));
+
+        let mut report = ApplyReport::default();
+        let mut content = self.content.clone();
+        // Bytes added and removed by the edits applied so far, and where the last one ended
+        let (mut added, mut removed, mut last_end) = (0, 0, 0);
+        for edit in accepted {
+            let (original, replacement) = (edit.original_text(), edit.replacement_text());
+            if edit.offset() < last_end {
+                report.conflict(edit, "overlaps an earlier edit");
+                continue;
+            }
+            let start = edit.offset() + added - removed;
+            let end = start + original.len();
+            if content.get(start..end) != Some(original) {
+                report.conflict(edit, "original text not found at its offset");
+                continue;
+            }
+            content.replace_range(start..end, replacement);
+            added += replacement.len();
+            removed += original.len();
+            last_end = edit.offset() + original.len();
+            report.applied.push(edit.edit_id().to_owned());
+        }
+
+        if !report.applied.is_empty()
+            && let Err(e) = self.set_content(content)
+        {
+            let reason = e.to_string();
+            let applied = std::mem::take(&mut report.applied);
+            report.conflicts.extend(
+                applied.into_iter().map(|edit_id| EditConflict { edit_id, reason: reason.clone() }),
+            );
+        }
+        report
+    }
+}
@@ -0,0 +1,70 @@
+//! Reusable starting points for new documents.
+
+use chrono::{NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+use crate::document::Document;
+
+/// A document template, such as meeting notes or a blog post.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Template {
+    /// Unique template ID.
+    pub id: String,
+    /// Display name.
+    pub name: String,
+    /// Title for new documents; `{date}` is replaced with the creation date.
+    pub title_pattern: String,
+    /// Content for new documents; `{date}` is replaced with the creation date.
+    pub content: String,
+}
+
+impl Template {
+    /// Placeholder replaced with the creation date (`YYYY-MM-DD`).
+    pub const DATE_PLACEHOLDER: &'static str = "{date}";
+
+    /// Creates a template with a new random ID.
+    #[must_use]
+    pub fn new(
+        name: impl Into<String>,
+        title_pattern: impl Into<String>,
+        content: impl Into<String>,
+    ) -> Self {
+        Self {
+            id: Uuid::new_v4().to_string(),
+            name: name.into(),
+            title_pattern: title_pattern.into(),
+            content: content.into(),
+        }
+    }
+
+    /// Creates a new document from this template, dated today.
+    #[must_use]
+    pub fn instantiate(&self) -> Document {
+        self.instantiate_on(Utc::now().date_naive())
+    }
+
+    /// Creates a new document from this template, dated `date`.
+    #[must_use]
+    pub fn instantiate_on(&self, date: NaiveDate) -> Document {
+        let date = date.format("%Y-%m-%d").to_string();
+        Document::with_title(self.title_pattern.replace(Self::DATE_PLACEHOLDER, &date))
+            .with_initial_content(self.content.replace(Self::DATE_PLACEHOLDER, &date))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_instantiate_substitutes_date() {
+        let template = Template::new("Meeting", "Meeting {date}", "# Notes for {date}\n");
+        let day = NaiveDate::from_ymd_opt(2024, 3, 9).expect("valid date");
+
+        let doc = template.instantiate_on(day);
+        assert_eq!(doc.metadata.title, "Meeting 2024-03-09");
+        assert_eq!(doc.content, "# Notes for 2024-03-09\n");
+        assert_eq!(doc.metadata.version, 1);
+    }
+}
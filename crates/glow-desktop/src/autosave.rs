@@ -0,0 +1,193 @@
+//! Debounced saving of document content as the user types.
+
+use std::collections::HashMap;
+use std::collections::hash_map::Entry;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
+
+use glow_core::DocumentId;
+
+use crate::Result;
+use crate::commands::{UpdateDocumentRequest, update_document};
+use crate::storage::SqliteStorage;
+
+/// How long a document must go without updates before it is saved.
+pub const DEFAULT_SAVE_DELAY: Duration = Duration::from_millis(500);
+
+/// Coalesces rapid content updates into one save per document.
+///
+/// Each queued update restarts the document's timer; when it fires, the
+/// latest content is saved. A save that fails stays pending until the next
+/// update or [`DebouncedSaver::flush`]. Cloning shares the pending saves,
+/// and any still pending are flushed when the last clone is dropped.
+#[derive(Clone)]
+pub struct DebouncedSaver {
+    inner: Arc<SaverInner>,
+}
+
+/// State shared between a saver and its timers.
+struct SaverInner {
+    storage: Arc<Mutex<SqliteStorage>>,
+    delay: Duration,
+    pending: Mutex<HashMap<DocumentId, PendingSave>>,
+    next_generation: AtomicU64,
+}
+
+/// The latest unsaved content of a document.
+struct PendingSave {
+    content: String,
+    generation: u64,
+}
+
+impl DebouncedSaver {
+    /// Creates a saver that persists to `storage` once a document has been
+    /// idle for `delay`.
+    #[must_use]
+    pub fn new(storage: Arc<Mutex<SqliteStorage>>, delay: Duration) -> Self {
+        Self {
+            inner: Arc::new(SaverInner {
+                storage,
+                delay,
+                pending: Mutex::new(HashMap::new()),
+                next_generation: AtomicU64::new(0),
+            }),
+        }
+    }
+
+    /// Queues `content` to be saved to document `id`, replacing any pending content.
+    ///
+    /// Must be called from within a Tokio runtime.
+    pub fn queue(&self, id: DocumentId, content: String) {
+        let generation = self.inner.next_generation.fetch_add(1, Ordering::Relaxed);
+        self.inner.lock_pending().insert(id, PendingSave { content, generation });
+
+        let inner = Arc::clone(&self.inner);
+        tokio::spawn(async move {
+            tokio::time::sleep(inner.delay).await;
+            // A failed save stays pending; the next update or flush retries it
+            let _ = inner.save_if_current(id, generation);
+        });
+    }
+
+    /// Number of documents with unsaved content.
+    #[must_use]
+    pub fn pending(&self) -> usize {
+        self.inner.lock_pending().len()
+    }
+
+    /// Saves all pending content now, such as on app shutdown.
+    ///
+    /// Returns the number of documents saved.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first error after attempting every save. Failed saves stay pending.
+    pub fn flush(&self) -> Result<usize> {
+        self.inner.flush()
+    }
+}
+
+impl SaverInner {
+    fn lock_pending(&self) -> std::sync::MutexGuard<'_, HashMap<DocumentId, PendingSave>> {
+        self.pending.lock().unwrap_or_else(PoisonError::into_inner)
+    }
+
+    /// Saves a document's pending content if no newer update has been queued.
+    fn save_if_current(&self, id: DocumentId, generation: u64) -> Result<()> {
+        let save = match self.lock_pending().entry(id) {
+            Entry::Occupied(entry) if entry.get().generation == generation => entry.remove(),
+            _ => return Ok(()),
+        };
+        self.save(id, save)
+    }
+
+    /// Saves every pending document.
+    fn flush(&self) -> Result<usize> {
+        let drained: Vec<_> = self.lock_pending().drain().collect();
+        let mut saved = 0;
+        let mut first_error = None;
+        for (id, save) in drained {
+            match self.save(id, save) {
+                Ok(()) => saved += 1,
+                Err(e) => {
+                    first_error.get_or_insert(e);
+                }
+            }
+        }
+        first_error.map_or(Ok(saved), Err)
+    }
+
+    /// Persists `save`, putting it back as pending if it fails and nothing newer was queued.
+    fn save(&self, id: DocumentId, save: PendingSave) -> Result<()> {
+        let request = UpdateDocumentRequest {
+            title: None,
+            content: Some(save.content.clone()),
+            read_only: None,
+        };
+        let storage = self.storage.lock().unwrap_or_else(PoisonError::into_inner);
+        let result = update_document(&storage, &id.to_string(), request);
+        drop(storage);
+
+        result.map(|_| ()).inspect_err(|_| {
+            self.lock_pending().entry(id).or_insert(save);
+        })
+    }
+}
+
+impl Drop for SaverInner {
+    fn drop(&mut self) {
+        // Errors cannot be reported here; callers that care flush explicitly
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::commands::{CreateDocumentRequest, create_document};
+
+    fn storage_with_document() -> (Arc<Mutex<SqliteStorage>>, DocumentId) {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let created = create_document(
+            &storage,
+            CreateDocumentRequest { title: None, content: None, tags: None },
+        )
+        .expect("should create document");
+        let uuid = uuid::Uuid::parse_str(&created.id).expect("should parse id");
+        (Arc::new(Mutex::new(storage)), DocumentId::from_uuid(uuid))
+    }
+
+    fn stored(storage: &Mutex<SqliteStorage>, id: &DocumentId) -> glow_core::Document {
+        storage.lock().expect("storage lock").get_document(id).expect("should get document")
+    }
+
+    #[tokio::test]
+    async fn test_rapid_updates_save_once_with_final_content() {
+        let (storage, id) = storage_with_document();
+        let saver = DebouncedSaver::new(Arc::clone(&storage), Duration::from_millis(50));
+
+        for content in ["H", "He", "Hel", "Hell", "Hello"] {
+            saver.queue(id, content.to_owned());
+        }
+        assert_eq!(stored(&storage, &id).metadata.version, 1);
+
+        tokio::time::sleep(Duration::from_millis(300)).await;
+        let doc = stored(&storage, &id);
+        assert_eq!(doc.content, "Hello");
+        assert_eq!(doc.metadata.version, 2);
+        assert_eq!(saver.pending(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_flush_saves_pending_content() {
+        let (storage, id) = storage_with_document();
+        let saver = DebouncedSaver::new(Arc::clone(&storage), Duration::from_secs(3600));
+
+        saver.queue(id, "Draft".to_owned());
+        saver.queue(id, "Final".to_owned());
+        assert_eq!(saver.flush().expect("should flush"), 1);
+        assert_eq!(saver.pending(), 0);
+        assert_eq!(stored(&storage, &id).content, "Final");
+    }
+}
@@ -2,11 +2,12 @@
 //!
 //! These functions are designed to be used as Tauri IPC commands.
 
-use glow_core::{Document, DocumentId};
+use glow_core::{Document, DocumentId, DocumentSummary};
 use serde::{Deserialize, Serialize};
 
 use crate::Result;
-use crate::storage::SqliteStorage;
+use crate::autosave::DebouncedSaver;
+use crate::storage::{RepairReport, SearchHit, SqliteStorage, StorageStats};
 
 /// Document response for the frontend.
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -21,8 +22,14 @@ pub struct DocumentResponse {
     pub created_at: String,
     /// Last modified timestamp (ISO 8601).
     pub modified_at: String,
+    /// Last opened timestamp (ISO 8601), if ever opened.
+    pub accessed_at: Option<String>,
     /// Document version.
     pub version: u64,
+    /// Document tags.
+    pub tags: Vec<String>,
+    /// Whether the document is frozen against edits.
+    pub read_only: bool,
 }
 
 impl From<&Document> for DocumentResponse {
@@ -33,7 +40,49 @@ impl From<&Document> for DocumentResponse {
             content: doc.content.clone(),
             created_at: doc.metadata.created_at.to_rfc3339(),
             modified_at: doc.metadata.modified_at.to_rfc3339(),
+            accessed_at: doc.metadata.accessed_at.map(|t| t.to_rfc3339()),
             version: doc.metadata.version,
+            tags: doc.metadata.tags.clone(),
+            read_only: doc.metadata.read_only,
+        }
+    }
+}
+
+/// Document metadata for the frontend's list view, without content.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct DocumentSummaryResponse {
+    /// Document ID.
+    pub id: String,
+    /// Document title.
+    pub title: String,
+    /// Creation timestamp (ISO 8601).
+    pub created_at: String,
+    /// Last modified timestamp (ISO 8601).
+    pub modified_at: String,
+    /// Last opened timestamp (ISO 8601), if ever opened.
+    pub accessed_at: Option<String>,
+    /// Document version.
+    pub version: u64,
+    /// Document tags.
+    pub tags: Vec<String>,
+    /// Whether the document is frozen against edits.
+    pub read_only: bool,
+    /// Length of the content in characters.
+    pub content_length: usize,
+}
+
+impl From<&DocumentSummary> for DocumentSummaryResponse {
+    fn from(summary: &DocumentSummary) -> Self {
+        Self {
+            id: summary.id.to_string(),
+            title: summary.metadata.title.clone(),
+            created_at: summary.metadata.created_at.to_rfc3339(),
+            modified_at: summary.metadata.modified_at.to_rfc3339(),
+            accessed_at: summary.metadata.accessed_at.map(|t| t.to_rfc3339()),
+            version: summary.metadata.version,
+            tags: summary.metadata.tags.clone(),
+            read_only: summary.metadata.read_only,
+            content_length: summary.content_length,
         }
     }
 }
@@ -43,6 +92,12 @@ impl From<&Document> for DocumentResponse {
 pub struct CreateDocumentRequest {
     /// Optional title for the new document.
     pub title: Option<String>,
+    /// Optional initial content.
+    #[serde(default)]
+    pub content: Option<String>,
+    /// Optional initial tags.
+    #[serde(default)]
+    pub tags: Option<Vec<String>>,
 }
 
 /// Request to update a document.
@@ -52,18 +107,44 @@ pub struct UpdateDocumentRequest {
     pub title: Option<String>,
     /// New content (if changing).
     pub content: Option<String>,
+    /// Freeze or unfreeze the document (if changing).
+    ///
+    /// Unfreezing applies before the edit and freezing after it.
+    #[serde(default)]
+    pub read_only: Option<bool>,
+}
+
+/// Gets all documents' metadata, most recently modified first.
+///
+/// Content is not loaded; fetch a document with [`get_document`] to open it.
+///
+/// # Errors
+///
+/// Returns an error if the database query fails.
+pub fn get_documents(storage: &SqliteStorage) -> Result<Vec<DocumentSummaryResponse>> {
+    let summaries = storage.list_metadata()?;
+    Ok(summaries.iter().map(DocumentSummaryResponse::from).collect())
 }
 
-/// Gets all documents.
+/// Gets all documents, most recently opened first.
 ///
 /// # Errors
 ///
 /// Returns an error if the database query fails.
-pub fn get_documents(storage: &SqliteStorage) -> Result<Vec<DocumentResponse>> {
-    let docs = storage.list_documents()?;
+pub fn get_recent_documents(storage: &SqliteStorage) -> Result<Vec<DocumentResponse>> {
+    let docs = storage.list_documents_by_access()?;
     Ok(docs.iter().map(DocumentResponse::from).collect())
 }
 
+/// Searches documents, returning match ranges for highlighting.
+///
+/// # Errors
+///
+/// Returns an error if the search query fails.
+pub fn search_documents(storage: &SqliteStorage, query: &str) -> Result<Vec<SearchHit>> {
+    storage.search(query)
+}
+
 /// Gets a document by ID.
 ///
 /// # Errors
@@ -85,19 +166,31 @@ pub fn create_document(
     storage: &SqliteStorage,
     request: CreateDocumentRequest,
 ) -> Result<DocumentResponse> {
-    let doc = match request.title {
+    let mut doc = match request.title {
         Some(title) => Document::with_title(title),
         None => Document::new(),
     };
+    if let Some(content) = request.content {
+        doc = doc.with_initial_content(content);
+    }
+    if let Some(tags) = request.tags {
+        doc.metadata.tags = tags;
+    }
     storage.save_document(&doc)?;
     Ok(DocumentResponse::from(&doc))
 }
 
+/// Number of times an update is attempted before a conflict is reported.
+const MAX_SAVE_ATTEMPTS: usize = 3;
+
 /// Updates an existing document.
 ///
+/// Concurrent saves are retried against the latest version.
+///
 /// # Errors
 ///
-/// Returns an error if the document is not found or cannot be saved.
+/// Returns [`crate::Error::Forbidden`] if the document is read-only, or another
+/// error if the document is not found or cannot be saved.
 pub fn update_document(
     storage: &SqliteStorage,
     id: &str,
@@ -106,17 +199,111 @@ pub fn update_document(
     let uuid = uuid::Uuid::parse_str(id).map_err(|e| crate::Error::InvalidId(e.to_string()))?;
     let doc_id = DocumentId::from_uuid(uuid);
 
-    let mut doc = storage.get_document(&doc_id)?;
-
-    if let Some(title) = request.title {
-        doc.set_title(title);
+    let UpdateDocumentRequest { title, content, read_only } = request;
+    if title.is_none() && content.is_none() && read_only.is_none() {
+        return Ok(DocumentResponse::from(&storage.get_document(&doc_id)?));
     }
-    if let Some(content) = request.content {
-        doc.set_content(content);
+
+    // Retry on conflict, re-applying the edit to a freshly loaded copy
+    let mut attempts = 0;
+    loop {
+        let mut doc = storage.get_document(&doc_id)?;
+        let loaded_version = doc.metadata.version;
+        if read_only == Some(false) {
+            doc.set_read_only(false);
+        }
+        doc.apply_edit(title.clone(), content.clone())
+            .map_err(|e| crate::Error::Forbidden(e.to_string()))?;
+        if read_only == Some(true) {
+            doc.set_read_only(true);
+        }
+        if doc.metadata.version == loaded_version {
+            return Ok(DocumentResponse::from(&doc));
+        }
+
+        match storage.save_document(&doc) {
+            Err(crate::Error::Conflict(_)) if attempts + 1 < MAX_SAVE_ATTEMPTS => attempts += 1,
+            result => return result.map(|()| DocumentResponse::from(&doc)),
+        }
     }
+}
 
-    storage.save_document(&doc)?;
-    Ok(DocumentResponse::from(&doc))
+/// Queues new content for a document, saved once edits pause.
+///
+/// Call on every keystroke; rapid updates are coalesced into one save.
+///
+/// # Errors
+///
+/// Returns an error if the ID is malformed.
+pub fn queue_save(saver: &DebouncedSaver, id: &str, content: String) -> Result<()> {
+    let uuid = uuid::Uuid::parse_str(id).map_err(|e| crate::Error::InvalidId(e.to_string()))?;
+    saver.queue(DocumentId::from_uuid(uuid), content);
+    Ok(())
+}
+
+/// Saves all queued content immediately. Call on app shutdown.
+///
+/// Returns the number of documents saved.
+///
+/// # Errors
+///
+/// Returns an error if any queued save fails.
+pub fn flush_saves(saver: &DebouncedSaver) -> Result<usize> {
+    saver.flush()
+}
+
+/// Records that a document was opened, without changing its version.
+///
+/// # Errors
+///
+/// Returns an error if the document is not found or cannot be updated.
+pub fn touch_accessed(storage: &SqliteStorage, id: &str) -> Result<()> {
+    let uuid = uuid::Uuid::parse_str(id).map_err(|e| crate::Error::InvalidId(e.to_string()))?;
+    let doc_id = DocumentId::from_uuid(uuid);
+    storage.touch_accessed(&doc_id)?;
+    Ok(())
+}
+
+/// Duplicates a document.
+///
+/// # Errors
+///
+/// Returns an error if the source document is not found or the copy cannot be saved.
+pub fn duplicate_document(storage: &SqliteStorage, id: &str) -> Result<DocumentResponse> {
+    let uuid = uuid::Uuid::parse_str(id).map_err(|e| crate::Error::InvalidId(e.to_string()))?;
+    let doc_id = DocumentId::from_uuid(uuid);
+    let copy = storage.duplicate(&doc_id)?;
+    Ok(DocumentResponse::from(&copy))
+}
+
+/// Rewrites a document's content from its CRDT state if the two have drifted.
+///
+/// # Errors
+///
+/// Returns an error if the document is not found or cannot be repaired.
+pub fn repair_document(storage: &SqliteStorage, id: &str) -> Result<RepairReport> {
+    let uuid = uuid::Uuid::parse_str(id).map_err(|e| crate::Error::InvalidId(e.to_string()))?;
+    let doc_id = DocumentId::from_uuid(uuid);
+    storage.repair_document(&doc_id)
+}
+
+/// Reports the number of documents and the space they take.
+///
+/// # Errors
+///
+/// Returns an error if the query fails.
+pub fn get_storage_stats(storage: &SqliteStorage) -> Result<StorageStats> {
+    storage.stats()
+}
+
+/// Reclaims space left by deleted documents, returning the stats afterwards.
+///
+/// # Errors
+///
+/// Returns an error if the database is busy or cannot be compacted.
+pub fn vacuum_storage(storage: &SqliteStorage) -> Result<StorageStats> {
+    storage.vacuum()?;
+    storage.stats()
 }
 
 /// Deletes a document.
@@ -129,3 +316,101 @@ pub fn delete_document(storage: &SqliteStorage, id: &str) -> Result<()> {
     let doc_id = DocumentId::from_uuid(uuid);
     storage.delete_document(&doc_id)
 }
+
+/// Deletes several documents at once, skipping IDs that do not exist.
+///
+/// Returns the number of documents deleted.
+///
+/// # Errors
+///
+/// Returns an error if any ID is malformed or the delete fails.
+pub fn delete_documents(storage: &SqliteStorage, ids: &[String]) -> Result<usize> {
+    let doc_ids = ids
+        .iter()
+        .map(|id| {
+            uuid::Uuid::parse_str(id)
+                .map(DocumentId::from_uuid)
+                .map_err(|e| crate::Error::InvalidId(e.to_string()))
+        })
+        .collect::<Result<Vec<_>>>()?;
+    storage.delete_many(&doc_ids)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_update_document_bumps_version_once() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let created = create_document(
+            &storage,
+            CreateDocumentRequest { title: None, content: None, tags: None },
+        )
+        .expect("should create document");
+
+        let request = UpdateDocumentRequest {
+            title: Some("Renamed".to_owned()),
+            content: Some("Body".to_owned()),
+            read_only: None,
+        };
+        let updated = update_document(&storage, &created.id, request).expect("should update");
+        assert_eq!(updated.version, 2);
+
+        let request = UpdateDocumentRequest { title: None, content: None, read_only: None };
+        let unchanged = update_document(&storage, &created.id, request).expect("should no-op");
+        assert_eq!(unchanged.version, 2);
+    }
+
+    #[test]
+    fn test_create_document_with_content() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let request = CreateDocumentRequest {
+            title: Some("Imported".to_owned()),
+            content: Some("Imported body".to_owned()),
+            tags: Some(vec!["import".to_owned()]),
+        };
+
+        let response = create_document(&storage, request).expect("should create document");
+        assert_eq!(response.content, "Imported body");
+        assert_eq!(response.tags, vec!["import".to_owned()]);
+        assert_eq!(response.version, 1);
+
+        let doc = get_document_model(&storage, &response.id);
+        let state = doc.crdt_state.expect("should seed crdt state");
+        let sync = glow_core::DocumentSync::from_state(&state).expect("should decode state");
+        assert_eq!(sync.get_content(), "Imported body");
+    }
+
+    fn get_document_model(storage: &SqliteStorage, id: &str) -> Document {
+        let uuid = uuid::Uuid::parse_str(id).expect("should parse id");
+        storage.get_document(&DocumentId::from_uuid(uuid)).expect("should get document")
+    }
+
+    #[test]
+    fn test_update_read_only_document() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let created = create_document(
+            &storage,
+            CreateDocumentRequest { title: None, content: Some("Final".to_owned()), tags: None },
+        )
+        .expect("should create document");
+        let update = |content: Option<&str>, read_only: Option<bool>| {
+            let request = UpdateDocumentRequest {
+                title: None,
+                content: content.map(str::to_owned),
+                read_only,
+            };
+            update_document(&storage, &created.id, request)
+        };
+
+        let frozen = update(None, Some(true)).expect("should freeze document");
+        assert!(frozen.read_only);
+        assert!(matches!(update(Some("Edited"), None), Err(crate::Error::Forbidden(_))));
+        assert_eq!(get_document_model(&storage, &created.id).content, "Final");
+
+        let edited = update(Some("Edited"), Some(false)).expect("should edit after unfreezing");
+        assert!(!edited.read_only);
+        assert_eq!(edited.content, "Edited");
+    }
+}
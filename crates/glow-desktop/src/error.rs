@@ -16,6 +16,23 @@ pub enum Error {
     #[error("invalid ID: {0}")]
     InvalidId(String),
 
+    /// The document was modified by someone else since it was loaded.
+    #[error("conflict: {0}")]
+    Conflict(String),
+
+    /// The operation is not allowed, such as editing a read-only document.
+    #[error("forbidden: {0}")]
+    Forbidden(String),
+
+    /// A configured limit, such as the maximum number of documents, was reached.
+    #[error("limit exceeded: {0}")]
+    LimitExceeded(String),
+
+    /// Stored data failed an integrity check, such as content that no longer
+    /// matches its checksum.
+    #[error("corrupted: {0}")]
+    Corrupted(String),
+
     /// Database operation failed.
     #[error("database error: {0}")]
     Database(String),
@@ -24,6 +41,14 @@ pub enum Error {
     #[error("sqlite error: {0}")]
     Sqlite(#[from] rusqlite::Error),
 
+    /// Filesystem operation failed.
+    #[error("io error: {0}")]
+    Io(#[from] std::io::Error),
+
+    /// Failed to serialize or deserialize data.
+    #[error("serialization error: {0}")]
+    Serialization(#[from] serde_json::Error),
+
     /// Core library error.
     #[error("core error: {0}")]
     Core(#[from] glow_core::Error),
@@ -2,10 +2,14 @@
 //!
 //! Desktop application library for Glow containing Tauri commands and SQLite storage.
 
+pub mod autosave;
 pub mod commands;
 pub mod error;
 pub mod storage;
 
+pub use autosave::{DEFAULT_SAVE_DELAY, DebouncedSaver};
 pub use commands::*;
 pub use error::{Error, Result};
-pub use storage::SqliteStorage;
+pub use storage::{
+    RepairReport, SearchHit, SearchSnippet, SqliteStorage, StorageConfig, StorageStats,
+};
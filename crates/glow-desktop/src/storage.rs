@@ -1,15 +1,280 @@
 //! SQLite storage for the desktop application.
 
+use std::path::Path;
+use std::time::Duration;
+
 use chrono::{DateTime, Utc};
-use glow_core::{Document, DocumentId, DocumentMetadata};
-use rusqlite::{Connection, OptionalExtension, params};
+use glow_core::{
+    Document, DocumentEvent, DocumentEventKind, DocumentId, DocumentMetadata, DocumentSummary,
+    DocumentSync, Template,
+};
+use rusqlite::{Connection, OptionalExtension, params, params_from_iter};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use tokio::sync::broadcast;
 use uuid::Uuid;
 
 use crate::{Error, Result};
 
+/// Columns selected for a full document, in [`DocumentRow::from_row`] order.
+const DOCUMENT_COLUMNS: &str = "id, title, content, crdt_state, created_at, modified_at, version, accessed_at, tags, read_only, content_format";
+
+/// Columns selected for a document summary: [`DOCUMENT_COLUMNS`] with the heavy
+/// content and CRDT state replaced by placeholders, then the content length.
+const SUMMARY_COLUMNS: &str = "id, title, '', NULL, created_at, modified_at, version, accessed_at, tags, read_only, content_format, length(content)";
+
+/// Columns added after the initial schema, with their definitions.
+///
+/// Databases created by older versions are migrated by adding any that are missing.
+const ADDED_COLUMNS: &[(&str, &str)] = &[
+    ("accessed_at", "TEXT"),
+    ("tags", "TEXT NOT NULL DEFAULT '[]'"),
+    ("read_only", "INTEGER NOT NULL DEFAULT 0"),
+    ("content_hash", "TEXT"),
+    ("content_format", "TEXT NOT NULL DEFAULT 'markdown'"),
+];
+
+/// SHA-256 checksum of document content, stored to detect corruption.
+fn content_hash(content: &str) -> String {
+    format!("{:x}", Sha256::digest(content.as_bytes()))
+}
+
+/// Raw column values of a document row, before parsing.
+struct DocumentRow {
+    id: String,
+    title: String,
+    content: String,
+    crdt_state: Option<Vec<u8>>,
+    created_at: String,
+    modified_at: String,
+    version: u64,
+    accessed_at: Option<String>,
+    tags: String,
+    read_only: bool,
+    content_format: String,
+}
+
+impl DocumentRow {
+    /// Reads the columns listed in [`DOCUMENT_COLUMNS`].
+    fn from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Self> {
+        Ok(Self {
+            id: row.get(0)?,
+            title: row.get(1)?,
+            content: row.get(2)?,
+            crdt_state: row.get(3)?,
+            created_at: row.get(4)?,
+            modified_at: row.get(5)?,
+            version: row.get(6)?,
+            accessed_at: row.get(7)?,
+            tags: row.get(8)?,
+            read_only: row.get(9)?,
+            content_format: row.get(10)?,
+        })
+    }
+
+    /// Parses the raw values into a document.
+    fn into_document(self) -> Result<Document> {
+        let uuid =
+            Uuid::parse_str(&self.id).map_err(|e| Error::Database(format!("invalid UUID: {e}")))?;
+        let accessed_at = self.accessed_at.as_deref().map(parse_timestamp).transpose()?;
+        let tags = serde_json::from_str(&self.tags)
+            .map_err(|e| Error::Database(format!("invalid tags: {e}")))?;
+        let content_format =
+            self.content_format.parse().map_err(|e| Error::Database(format!("{e}")))?;
+
+        Ok(Document {
+            id: DocumentId::from_uuid(uuid),
+            metadata: DocumentMetadata {
+                title: self.title,
+                created_at: parse_timestamp(&self.created_at)?,
+                modified_at: parse_timestamp(&self.modified_at)?,
+                accessed_at,
+                version: self.version,
+                tags,
+                read_only: self.read_only,
+                content_format,
+            },
+            content: self.content,
+            crdt_state: self.crdt_state,
+        })
+    }
+}
+
+/// Parses an RFC 3339 timestamp stored in the database.
+fn parse_timestamp(value: &str) -> Result<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|t| t.with_timezone(&Utc))
+        .map_err(|e| Error::Database(format!("invalid date: {e}")))
+}
+
+/// Reads a template from `id, name, title_pattern, content` columns.
+fn template_from_row(row: &rusqlite::Row<'_>) -> rusqlite::Result<Template> {
+    Ok(Template {
+        id: row.get(0)?,
+        name: row.get(1)?,
+        title_pattern: row.get(2)?,
+        content: row.get(3)?,
+    })
+}
+
+/// Reads every `.md` file under `dir` into a new document.
+///
+/// Files are titled by their name without the extension and tagged with the
+/// full names of the subdirectories they sit in. Symbolic links are not followed.
+fn collect_markdown(dir: &Path, tags: &mut Vec<String>, docs: &mut Vec<Document>) -> Result<()> {
+    let mut entries = std::fs::read_dir(dir)?
+        .map(|entry| entry.and_then(|e| Ok((e.path(), e.file_type()?))))
+        .collect::<std::io::Result<Vec<_>>>()?;
+    entries.sort_by(|a, b| a.0.cmp(&b.0));
+
+    for (path, file_type) in entries {
+        if file_type.is_dir() {
+            tags.push(path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+            collect_markdown(&path, tags, docs)?;
+            tags.pop();
+        } else if file_type.is_file() && path.extension().is_some_and(|ext| ext == "md") {
+            let title = path.file_stem().unwrap_or_default().to_string_lossy().into_owned();
+            let content = std::fs::read_to_string(&path)?;
+            let mut doc = Document::with_title(title).with_initial_content(content);
+            doc.metadata.tags.clone_from(tags);
+            docs.push(doc);
+        }
+    }
+    Ok(())
+}
+
+/// Connection settings applied when opening a database.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StorageConfig {
+    /// Use write-ahead logging so readers don't block the writer.
+    pub wal: bool,
+    /// How long to wait for a lock before failing with `database is locked`.
+    pub busy_timeout: Duration,
+    /// Enforce foreign key constraints.
+    pub foreign_keys: bool,
+}
+
+impl Default for StorageConfig {
+    fn default() -> Self {
+        Self { wal: true, busy_timeout: Duration::from_millis(5000), foreign_keys: true }
+    }
+}
+
+/// Outcome of [`SqliteStorage::repair_document`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum RepairReport {
+    /// The document has no CRDT state to repair from.
+    Skipped,
+    /// The stored content already matches the CRDT state.
+    Consistent,
+    /// The stored content was rewritten from the CRDT state.
+    Repaired,
+}
+
+/// Size of the stored data, from [`SqliteStorage::stats`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct StorageStats {
+    /// Number of documents.
+    pub document_count: u64,
+    /// Total size of all document content, in UTF-8 bytes.
+    pub content_bytes: u64,
+    /// Size of the database, in bytes. Space freed by deletes is only
+    /// returned by [`SqliteStorage::vacuum`].
+    pub file_size: u64,
+}
+
+/// A document matching a search, with the matches in its content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchHit {
+    /// ID of the matching document.
+    pub document_id: String,
+    /// Document title.
+    pub title: String,
+    /// Matches in the content, in order. Empty if only the title matched.
+    pub snippets: Vec<SearchSnippet>,
+}
+
+/// One match within a document's content.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct SearchSnippet {
+    /// Character offset where the match starts.
+    pub start: usize,
+    /// Character offset just past the end of the match.
+    pub end: usize,
+    /// The match with some surrounding text, for display.
+    pub preview: String,
+}
+
+/// Marks the start of a match in FTS5 `highlight()` output.
+const MATCH_START: char = '\u{2}';
+
+/// Marks the end of a match in FTS5 `highlight()` output.
+const MATCH_END: char = '\u{3}';
+
+/// Characters of context kept on each side of a match in a preview.
+const PREVIEW_CONTEXT_CHARS: usize = 30;
+
+/// Turns free text into an FTS5 query matching documents containing every word.
+///
+/// Each word is quoted so FTS5 operators in user input are matched literally.
+fn fts_query(query: &str) -> String {
+    query
+        .split_whitespace()
+        .map(|word| format!("\"{}\"", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Builds snippets from content marked up by `highlight()` with [`MATCH_START`] and [`MATCH_END`].
+fn snippets_from_highlight(highlighted: &str) -> Vec<SearchSnippet> {
+    let content: Vec<char> =
+        highlighted.chars().filter(|&c| c != MATCH_START && c != MATCH_END).collect();
+    let mut snippets = Vec::new();
+    let mut offset = 0;
+    let mut start = None;
+    for c in highlighted.chars() {
+        match c {
+            MATCH_START => start = Some(offset),
+            MATCH_END => {
+                if let Some(start) = start.take() {
+                    snippets.push(SearchSnippet {
+                        start,
+                        end: offset,
+                        preview: preview(&content, start, offset),
+                    });
+                }
+            }
+            _ => offset += 1,
+        }
+    }
+    snippets
+}
+
+/// Text around `content[start..end]`, with an ellipsis where it was cut.
+fn preview(content: &[char], start: usize, end: usize) -> String {
+    let from = start.saturating_sub(PREVIEW_CONTEXT_CHARS);
+    let to = (end + PREVIEW_CONTEXT_CHARS).min(content.len());
+    let mut preview: String = content[from..to].iter().collect();
+    preview = preview.replace('\n', " ");
+    if from > 0 {
+        preview.insert(0, '…');
+    }
+    if to < content.len() {
+        preview.push('…');
+    }
+    preview
+}
+
+/// Capacity of the document event channel before slow subscribers lag.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
 /// SQLite-based document storage.
 pub struct SqliteStorage {
     conn: Connection,
+    events: broadcast::Sender<DocumentEvent>,
+    normalize_content: bool,
+    max_documents: Option<usize>,
 }
 
 impl SqliteStorage {
@@ -19,8 +284,18 @@ impl SqliteStorage {
     ///
     /// Returns an error if the database cannot be opened or initialized.
     pub fn new(path: &str) -> Result<Self> {
+        Self::with_config(path, &StorageConfig::default())
+    }
+
+    /// Creates a new SQLite storage at the given path with custom connection settings.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database cannot be opened or initialized.
+    pub fn with_config(path: &str, config: &StorageConfig) -> Result<Self> {
         let conn = Connection::open(path)?;
-        let storage = Self { conn };
+        let storage = Self::from_connection(conn);
+        storage.configure(config)?;
         storage.init_schema()?;
         Ok(storage)
     }
@@ -32,11 +307,67 @@ impl SqliteStorage {
     /// Returns an error if the database cannot be created.
     pub fn in_memory() -> Result<Self> {
         let conn = Connection::open_in_memory()?;
-        let storage = Self { conn };
+        let storage = Self::from_connection(conn);
+        // In-memory databases have no journal file, so WAL does not apply
+        storage.configure(&StorageConfig { wal: false, ..StorageConfig::default() })?;
         storage.init_schema()?;
         Ok(storage)
     }
 
+    /// Wraps an open connection.
+    fn from_connection(conn: Connection) -> Self {
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self { conn, events, normalize_content: false, max_documents: None }
+    }
+
+    /// Normalizes line endings and trailing whitespace of content on save.
+    ///
+    /// See [`Document::normalize_content`]; documents whose content is already
+    /// normal are saved as given.
+    #[must_use]
+    pub const fn with_content_normalization(mut self, normalize: bool) -> Self {
+        self.normalize_content = normalize;
+        self
+    }
+
+    /// Limits how many documents may be stored, or `None` for no limit.
+    ///
+    /// Saving a new document past the limit fails; existing documents can
+    /// still be updated.
+    #[must_use]
+    pub const fn with_max_documents(mut self, max_documents: Option<usize>) -> Self {
+        self.max_documents = max_documents;
+        self
+    }
+
+    /// Subscribes to document lifecycle events.
+    ///
+    /// Every create, update, and delete made through this storage is published.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<DocumentEvent> {
+        self.events.subscribe()
+    }
+
+    /// Publishes an event to subscribers, if any.
+    fn publish(&self, kind: DocumentEventKind, id: DocumentId, version: u64) {
+        // No subscribers is not an error
+        let _ = self.events.send(DocumentEvent::new(kind, id, version));
+    }
+
+    /// Applies connection-level pragmas.
+    fn configure(&self, config: &StorageConfig) -> Result<()> {
+        if config.wal {
+            let mode: String =
+                self.conn.pragma_update_and_check(None, "journal_mode", "WAL", |row| row.get(0))?;
+            if !mode.eq_ignore_ascii_case("wal") {
+                return Err(Error::Database(format!("failed to enable WAL mode, got {mode}")));
+            }
+        }
+        self.conn.busy_timeout(config.busy_timeout)?;
+        self.conn.pragma_update(None, "foreign_keys", config.foreign_keys)?;
+        Ok(())
+    }
+
     /// Initializes the database schema.
     fn init_schema(&self) -> Result<()> {
         self.conn.execute_batch(
@@ -48,123 +379,314 @@ impl SqliteStorage {
                 crdt_state BLOB,
                 created_at TEXT NOT NULL,
                 modified_at TEXT NOT NULL,
-                version INTEGER NOT NULL
+                version INTEGER NOT NULL,
+                accessed_at TEXT,
+                tags TEXT NOT NULL DEFAULT '[]',
+                read_only INTEGER NOT NULL DEFAULT 0,
+                content_hash TEXT,
+                content_format TEXT NOT NULL DEFAULT 'markdown'
             );
 
             CREATE INDEX IF NOT EXISTS idx_documents_modified_at
             ON documents(modified_at DESC);
+
+            CREATE VIRTUAL TABLE IF NOT EXISTS documents_fts
+            USING fts5(id UNINDEXED, title, content);
+
+            CREATE TRIGGER IF NOT EXISTS documents_fts_insert AFTER INSERT ON documents BEGIN
+                INSERT INTO documents_fts (id, title, content)
+                VALUES (new.id, new.title, new.content);
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS documents_fts_update
+            AFTER UPDATE OF title, content ON documents BEGIN
+                UPDATE documents_fts SET title = new.title, content = new.content
+                WHERE id = old.id;
+            END;
+
+            CREATE TRIGGER IF NOT EXISTS documents_fts_delete AFTER DELETE ON documents BEGIN
+                DELETE FROM documents_fts WHERE id = old.id;
+            END;
+
+            -- Index documents saved before the search index existed
+            INSERT INTO documents_fts (id, title, content)
+            SELECT id, title, content FROM documents
+            WHERE id NOT IN (SELECT id FROM documents_fts);
+
+            CREATE TABLE IF NOT EXISTS templates (
+                id TEXT PRIMARY KEY,
+                name TEXT NOT NULL,
+                title_pattern TEXT NOT NULL,
+                content TEXT NOT NULL
+            );
             ",
         )?;
+
+        for (column, definition) in ADDED_COLUMNS {
+            if !self.has_column("documents", column)? {
+                self.conn.execute_batch(&format!(
+                    "ALTER TABLE documents ADD COLUMN {column} {definition}"
+                ))?;
+            }
+        }
+
         Ok(())
     }
 
+    /// Checks whether a table has the given column.
+    fn has_column(&self, table: &str, column: &str) -> Result<bool> {
+        let count: i64 = self.conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?) WHERE name = ?",
+            [table, column],
+            |row| row.get(0),
+        )?;
+        Ok(count > 0)
+    }
+
     /// Gets all documents, ordered by modification date.
     ///
+    /// When `modified_since` is given, only documents modified strictly after
+    /// that instant are returned.
+    ///
     /// # Errors
     ///
     /// Returns an error if the query fails.
-    pub fn list_documents(&self) -> Result<Vec<Document>> {
-        let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, crdt_state, created_at, modified_at, version
-             FROM documents
-             ORDER BY modified_at DESC",
-        )?;
+    pub fn list_documents(&self, modified_since: Option<DateTime<Utc>>) -> Result<Vec<Document>> {
+        let filter = modified_since.map(|since| ("modified_at > ?", since.to_rfc3339()));
+        self.query_documents(filter, "modified_at DESC")
+    }
+
+    /// Gets all documents, most recently opened first.
+    ///
+    /// Documents that have never been opened are listed last, by modification date.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub fn list_documents_by_access(&self) -> Result<Vec<Document>> {
+        self.query_documents(None, "accessed_at IS NULL, accessed_at DESC, modified_at DESC")
+    }
+
+    /// Gets every document's metadata without loading its content, most
+    /// recently modified first.
+    ///
+    /// Rows that fail to decode are skipped.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub fn list_metadata(&self) -> Result<Vec<DocumentSummary>> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {SUMMARY_COLUMNS} FROM documents ORDER BY modified_at DESC"
+        ))?;
+
+        let summaries = stmt
+            .query_map([], |row| Ok((DocumentRow::from_row(row)?, row.get::<_, usize>(11)?)))?
+            .filter_map(std::result::Result::ok)
+            .filter_map(|(row, content_length)| {
+                let doc = row.into_document().ok()?;
+                Some(DocumentSummary { id: doc.id, metadata: doc.metadata, content_length })
+            })
+            .collect();
+
+        Ok(summaries)
+    }
+
+    /// Runs a document query with an optional single-parameter filter.
+    ///
+    /// Rows that fail to decode are skipped.
+    fn query_documents(
+        &self,
+        filter: Option<(&str, String)>,
+        order_by: &str,
+    ) -> Result<Vec<Document>> {
+        let mut sql = format!("SELECT {DOCUMENT_COLUMNS} FROM documents");
+        let mut params = Vec::new();
+        if let Some((clause, value)) = filter {
+            sql.push_str(" WHERE ");
+            sql.push_str(clause);
+            params.push(value);
+        }
+        sql.push_str(" ORDER BY ");
+        sql.push_str(order_by);
+
+        let mut stmt = self.conn.prepare(&sql)?;
 
         let docs = stmt
-            .query_map([], |row| {
-                let id_str: String = row.get(0)?;
-                let title: String = row.get(1)?;
-                let content: String = row.get(2)?;
-                let crdt_state: Option<Vec<u8>> = row.get(3)?;
-                let created_at: String = row.get(4)?;
-                let modified_at: String = row.get(5)?;
-                let version: u64 = row.get(6)?;
-
-                Ok((id_str, title, content, crdt_state, created_at, modified_at, version))
-            })?
+            .query_map(params_from_iter(params), DocumentRow::from_row)?
             .filter_map(|r| r.ok())
-            .filter_map(|(id_str, title, content, crdt_state, created_at, modified_at, version)| {
-                let uuid = Uuid::parse_str(&id_str).ok()?;
-                let created_at =
-                    DateTime::parse_from_rfc3339(&created_at).ok()?.with_timezone(&Utc);
-                let modified_at =
-                    DateTime::parse_from_rfc3339(&modified_at).ok()?.with_timezone(&Utc);
-
-                Some(Document {
-                    id: DocumentId::from_uuid(uuid),
-                    metadata: DocumentMetadata { title, created_at, modified_at, version },
-                    content,
-                    crdt_state,
-                })
-            })
+            .filter_map(|row| row.into_document().ok())
             .collect();
 
         Ok(docs)
     }
 
-    /// Gets a document by ID.
+    /// Gets a document by ID, verifying its content against the stored checksum.
+    ///
+    /// Documents saved before checksums were recorded are not verified.
     ///
     /// # Errors
     ///
-    /// Returns an error if the document is not found.
+    /// Returns an error if the document is not found, or [`Error::Corrupted`]
+    /// if its content does not match the checksum; [`SqliteStorage::repair_document`]
+    /// can restore content from the CRDT state.
     pub fn get_document(&self, id: &DocumentId) -> Result<Document> {
+        let (doc, hash) = self.load_document(id)?;
+        if hash.is_some_and(|hash| hash != content_hash(&doc.content)) {
+            return Err(Error::Corrupted(format!(
+                "content of document {id} does not match its checksum"
+            )));
+        }
+        Ok(doc)
+    }
+
+    /// Loads a document and its stored content checksum, without verifying them.
+    fn load_document(&self, id: &DocumentId) -> Result<(Document, Option<String>)> {
+        let mut stmt = self.conn.prepare(&format!(
+            "SELECT {DOCUMENT_COLUMNS}, content_hash FROM documents WHERE id = ?"
+        ))?;
+
+        let (row, hash) = stmt
+            .query_row([id.to_string()], |row| Ok((DocumentRow::from_row(row)?, row.get(11)?)))
+            .optional()?
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
+        Ok((row.into_document()?, hash))
+    }
+
+    /// Full-text searches document titles and content, best matches first.
+    ///
+    /// Matches documents containing every word of `query`. Each hit lists
+    /// where the words occur in the content, by character offset, so the UI
+    /// can highlight them.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub fn search(&self, query: &str) -> Result<Vec<SearchHit>> {
+        let query = fts_query(query);
+        if query.is_empty() {
+            return Ok(Vec::new());
+        }
+
         let mut stmt = self.conn.prepare(
-            "SELECT id, title, content, crdt_state, created_at, modified_at, version
-             FROM documents
-             WHERE id = ?",
+            "SELECT id, title, highlight(documents_fts, 2, char(2), char(3))
+             FROM documents_fts WHERE documents_fts MATCH ? ORDER BY rank",
         )?;
-
-        let result = stmt
-            .query_row([id.to_string()], |row| {
-                let id_str: String = row.get(0)?;
-                let title: String = row.get(1)?;
-                let content: String = row.get(2)?;
-                let crdt_state: Option<Vec<u8>> = row.get(3)?;
-                let created_at: String = row.get(4)?;
-                let modified_at: String = row.get(5)?;
-                let version: u64 = row.get(6)?;
-
-                Ok((id_str, title, content, crdt_state, created_at, modified_at, version))
-            })
-            .optional()?;
-
-        match result {
-            Some((id_str, title, content, crdt_state, created_at, modified_at, version)) => {
-                let uuid = Uuid::parse_str(&id_str)
-                    .map_err(|e| Error::Database(format!("invalid UUID: {e}")))?;
-                let created_at = DateTime::parse_from_rfc3339(&created_at)
-                    .map_err(|e| Error::Database(format!("invalid date: {e}")))?
-                    .with_timezone(&Utc);
-                let modified_at = DateTime::parse_from_rfc3339(&modified_at)
-                    .map_err(|e| Error::Database(format!("invalid date: {e}")))?
-                    .with_timezone(&Utc);
-
-                Ok(Document {
-                    id: DocumentId::from_uuid(uuid),
-                    metadata: DocumentMetadata { title, created_at, modified_at, version },
-                    content,
-                    crdt_state,
+        let hits = stmt
+            .query_map([query], |row| {
+                let highlighted: String = row.get(2)?;
+                Ok(SearchHit {
+                    document_id: row.get(0)?,
+                    title: row.get(1)?,
+                    snippets: snippets_from_highlight(&highlighted),
                 })
-            }
-            None => Err(Error::NotFound(id.to_string())),
+            })?
+            .collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(hits)
+    }
+
+    /// Records that a document was opened, without bumping its version.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is not found or the update fails.
+    pub fn touch_accessed(&self, id: &DocumentId) -> Result<DateTime<Utc>> {
+        let now = Utc::now();
+        let rows = self.conn.execute(
+            "UPDATE documents SET accessed_at = ? WHERE id = ?",
+            params![now.to_rfc3339(), id.to_string()],
+        )?;
+
+        if rows == 0 {
+            return Err(Error::NotFound(id.to_string()));
         }
+
+        Ok(now)
+    }
+
+    /// Rewrites a document's content from its CRDT state if the two have drifted.
+    ///
+    /// The CRDT state is the source of truth. The version is not bumped, since
+    /// the document's logical state does not change.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is not found, its CRDT state cannot be
+    /// decoded, or the update fails.
+    pub fn repair_document(&self, id: &DocumentId) -> Result<RepairReport> {
+        let (doc, _) = self.load_document(id)?;
+        let Some(state) = doc.crdt_state.as_deref() else {
+            return Ok(RepairReport::Skipped);
+        };
+
+        let derived = DocumentSync::from_state(state)?.get_content();
+        if derived == doc.content {
+            return Ok(RepairReport::Consistent);
+        }
+
+        self.conn.execute(
+            "UPDATE documents SET content = ?, content_hash = ? WHERE id = ?",
+            params![derived, content_hash(&derived), id.to_string()],
+        )?;
+        Ok(RepairReport::Repaired)
     }
 
     /// Saves a document (insert or update).
     ///
+    /// Updates only apply if the document's version is newer than the stored one,
+    /// so a save based on a stale copy cannot overwrite a concurrent edit. With
+    /// [`SqliteStorage::with_content_normalization`], the saved content is
+    /// normalized; `doc` itself is not changed.
+    ///
     /// # Errors
     ///
-    /// Returns an error if the save fails.
+    /// Returns [`Error::Conflict`] if the stored document is at the same or a newer
+    /// version, [`Error::LimitExceeded`] if a new document would exceed
+    /// [`SqliteStorage::with_max_documents`], or another error if the save fails.
     pub fn save_document(&self, doc: &Document) -> Result<()> {
-        self.conn.execute(
-            "INSERT INTO documents (id, title, content, crdt_state, created_at, modified_at, version)
-             VALUES (?, ?, ?, ?, ?, ?, ?)
+        let (kind, version) = self.write_document(doc)?;
+        self.publish(kind, doc.id, version);
+        Ok(())
+    }
+
+    /// Writes a document as [`SqliteStorage::save_document`] does, returning
+    /// the event to publish and the saved version instead of publishing it.
+    fn write_document(&self, doc: &Document) -> Result<(DocumentEventKind, u64)> {
+        let mut normalized = None;
+        if self.normalize_content {
+            let mut copy = doc.clone();
+            if copy.normalize_content() {
+                normalized = Some(copy);
+            }
+        }
+        let doc = normalized.as_ref().unwrap_or(doc);
+
+        let exists: bool = self.conn.query_row(
+            "SELECT EXISTS(SELECT 1 FROM documents WHERE id = ?)",
+            [doc.id.to_string()],
+            |row| row.get(0),
+        )?;
+        if !exists {
+            self.ensure_room_for_document()?;
+        }
+
+        let changed = self.conn.execute(
+            "INSERT INTO documents
+                (id, title, content, crdt_state, created_at, modified_at, version, accessed_at, tags,
+                 read_only, content_hash, content_format)
+             VALUES (?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?, ?)
              ON CONFLICT(id) DO UPDATE SET
                 title = excluded.title,
                 content = excluded.content,
                 crdt_state = excluded.crdt_state,
                 modified_at = excluded.modified_at,
-                version = excluded.version",
+                version = excluded.version,
+                accessed_at = excluded.accessed_at,
+                tags = excluded.tags,
+                read_only = excluded.read_only,
+                content_hash = excluded.content_hash,
+                content_format = excluded.content_format
+             WHERE documents.version < excluded.version",
             params![
                 doc.id.to_string(),
                 doc.metadata.title,
@@ -173,38 +695,246 @@ impl SqliteStorage {
                 doc.metadata.created_at.to_rfc3339(),
                 doc.metadata.modified_at.to_rfc3339(),
                 doc.metadata.version,
+                doc.metadata.accessed_at.map(|t| t.to_rfc3339()),
+                serde_json::to_string(&doc.metadata.tags)?,
+                doc.metadata.read_only,
+                content_hash(&doc.content),
+                doc.metadata.content_format.as_str(),
             ],
         )?;
+
+        if changed == 0 {
+            return Err(Error::Conflict(format!(
+                "document {} was saved concurrently; version {} is stale",
+                doc.id, doc.metadata.version
+            )));
+        }
+
+        let kind = if exists { DocumentEventKind::Updated } else { DocumentEventKind::Created };
+        Ok((kind, doc.metadata.version))
+    }
+
+    /// Returns an error if the document limit leaves no room for another document.
+    fn ensure_room_for_document(&self) -> Result<()> {
+        let Some(max) = self.max_documents else {
+            return Ok(());
+        };
+        let count: usize =
+            self.conn.query_row("SELECT COUNT(*) FROM documents", [], |row| row.get(0))?;
+        if count >= max {
+            return Err(Error::LimitExceeded(format!(
+                "the workspace already holds the maximum of {max} documents"
+            )));
+        }
         Ok(())
     }
 
+    /// Duplicates a document, saving the copy under a new ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the source document is not found or the copy cannot be saved.
+    pub fn duplicate(&self, id: &DocumentId) -> Result<Document> {
+        let copy = self.get_document(id)?.duplicate();
+        self.save_document(&copy)?;
+        Ok(copy)
+    }
+
     /// Deletes a document.
     ///
     /// # Errors
     ///
     /// Returns an error if the delete fails.
     pub fn delete_document(&self, id: &DocumentId) -> Result<()> {
-        let rows = self.conn.execute("DELETE FROM documents WHERE id = ?", [id.to_string()])?;
+        let version: u64 = self
+            .conn
+            .query_row(
+                "DELETE FROM documents WHERE id = ? RETURNING version",
+                [id.to_string()],
+                |row| row.get(0),
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(id.to_string()))?;
 
-        if rows == 0 {
-            return Err(Error::NotFound(id.to_string()));
+        self.publish(DocumentEventKind::Deleted, *id, version);
+        Ok(())
+    }
+
+    /// Deletes several documents in a single transaction.
+    ///
+    /// IDs that do not exist are skipped. Returns the number of documents deleted.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the transaction fails; no documents are deleted in that case.
+    pub fn delete_many(&self, ids: &[DocumentId]) -> Result<usize> {
+        let tx = self.conn.unchecked_transaction()?;
+        let mut deleted = Vec::new();
+        let mut stmt = tx.prepare("DELETE FROM documents WHERE id = ? RETURNING version")?;
+        for id in ids {
+            let version: Option<u64> =
+                stmt.query_row([id.to_string()], |row| row.get(0)).optional()?;
+            if let Some(version) = version {
+                deleted.push((*id, version));
+            }
+        }
+        drop(stmt);
+        tx.commit()?;
+
+        for (id, version) in &deleted {
+            self.publish(DocumentEventKind::Deleted, *id, *version);
         }
+        Ok(deleted.len())
+    }
 
+    /// Reports how many documents are stored and how much space they take.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub fn stats(&self) -> Result<StorageStats> {
+        let (document_count, content_bytes) = self.conn.query_row(
+            "SELECT COUNT(*), COALESCE(SUM(LENGTH(CAST(content AS BLOB))), 0) FROM documents",
+            [],
+            |row| Ok((row.get(0)?, row.get(1)?)),
+        )?;
+        let file_size = self.conn.query_row(
+            "SELECT page_count * page_size FROM pragma_page_count(), pragma_page_size()",
+            [],
+            |row| row.get(0),
+        )?;
+        Ok(StorageStats { document_count, content_bytes, file_size })
+    }
+
+    /// Rebuilds the database to reclaim space left by deleted documents.
+    ///
+    /// Also checkpoints the write-ahead log so the file on disk shrinks. Must
+    /// not be called while a transaction is open on this connection; other
+    /// connections are waited on up to the busy timeout.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the database is busy or the rebuild fails.
+    pub fn vacuum(&self) -> Result<()> {
+        self.conn.execute_batch("VACUUM")?;
+        self.conn.query_row("PRAGMA wal_checkpoint(TRUNCATE)", [], |_| Ok(()))?;
         Ok(())
     }
+
+    /// Saves a template, replacing any existing template with the same ID.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the save fails.
+    pub fn save_template(&self, template: &Template) -> Result<()> {
+        self.conn.execute(
+            "INSERT INTO templates (id, name, title_pattern, content) VALUES (?, ?, ?, ?)
+             ON CONFLICT(id) DO UPDATE SET
+                name = excluded.name,
+                title_pattern = excluded.title_pattern,
+                content = excluded.content",
+            params![template.id, template.name, template.title_pattern, template.content],
+        )?;
+        Ok(())
+    }
+
+    /// Gets all templates, ordered by name.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the query fails.
+    pub fn list_templates(&self) -> Result<Vec<Template>> {
+        let mut stmt = self
+            .conn
+            .prepare("SELECT id, name, title_pattern, content FROM templates ORDER BY name")?;
+        let templates =
+            stmt.query_map([], template_from_row)?.collect::<rusqlite::Result<Vec<_>>>()?;
+        Ok(templates)
+    }
+
+    /// Creates and saves a new document from a template.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the template is not found or the document cannot be saved.
+    pub fn create_from_template(&self, template_id: &str) -> Result<Document> {
+        let template = self
+            .conn
+            .query_row(
+                "SELECT id, name, title_pattern, content FROM templates WHERE id = ?",
+                [template_id],
+                template_from_row,
+            )
+            .optional()?
+            .ok_or_else(|| Error::NotFound(format!("template {template_id}")))?;
+
+        let doc = template.instantiate();
+        self.save_document(&doc)?;
+        Ok(doc)
+    }
+
+    /// Imports every `.md` file under `dir` as a new document.
+    ///
+    /// Each file's name without the extension becomes the title and its
+    /// contents the initial content. Files in subdirectories are tagged with
+    /// the subdirectory names. All files are read before any are saved, and
+    /// they are saved in a single transaction.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the directory or a file cannot be read, or a
+    /// document cannot be saved; no documents are imported in that case.
+    pub fn import_directory(&self, dir: &Path) -> Result<Vec<DocumentId>> {
+        let mut docs = Vec::new();
+        collect_markdown(dir, &mut Vec::new(), &mut docs)?;
+
+        let tx = self.conn.unchecked_transaction()?;
+        let mut saved = Vec::with_capacity(docs.len());
+        for doc in &docs {
+            saved.push((doc.id, self.write_document(doc)?));
+        }
+        tx.commit()?;
+
+        for &(id, (kind, version)) in &saved {
+            self.publish(kind, id, version);
+        }
+        Ok(saved.into_iter().map(|(id, _)| id).collect())
+    }
 }
 
 #[cfg(test)]
 mod tests {
+    use glow_core::ContentFormat;
+
     use super::*;
 
     #[test]
     fn test_create_storage() {
         let storage = SqliteStorage::in_memory().expect("should create storage");
-        let docs = storage.list_documents().expect("should list documents");
+        let docs = storage.list_documents(None).expect("should list documents");
         assert!(docs.is_empty());
     }
 
+    #[test]
+    fn test_wal_mode_enabled() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("glow.db");
+        let storage =
+            SqliteStorage::new(path.to_str().expect("should be utf-8")).expect("should open");
+
+        let mode: String = storage
+            .conn
+            .query_row("PRAGMA journal_mode", [], |row| row.get(0))
+            .expect("should query journal mode");
+        assert_eq!(mode, "wal");
+
+        let foreign_keys: bool = storage
+            .conn
+            .query_row("PRAGMA foreign_keys", [], |row| row.get(0))
+            .expect("should query foreign keys");
+        assert!(foreign_keys);
+    }
+
     #[test]
     fn test_save_and_get_document() {
         let storage = SqliteStorage::in_memory().expect("should create storage");
@@ -216,6 +946,56 @@ mod tests {
         assert_eq!(retrieved.metadata.title, "Test Document");
     }
 
+    #[test]
+    fn test_tags_roundtrip() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let mut doc = Document::with_title("Tagged");
+        doc.metadata.tags = vec!["draft".to_owned(), "notes".to_owned()];
+        storage.save_document(&doc).expect("should save document");
+
+        let retrieved = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(retrieved.metadata.tags, doc.metadata.tags);
+    }
+
+    #[test]
+    fn test_content_format_roundtrip() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let mut doc = Document::with_title("Plain").with_initial_content("# not a heading\n");
+        doc.metadata.content_format = ContentFormat::PlainText;
+        storage.save_document(&doc).expect("should save document");
+
+        let retrieved = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(retrieved.metadata.content_format, ContentFormat::PlainText);
+        assert_eq!(retrieved.word_count(), 4);
+        let summaries = storage.list_metadata().expect("should list");
+        assert_eq!(summaries[0].metadata.content_format, ContentFormat::PlainText);
+    }
+
+    #[test]
+    fn test_max_documents_rejects_new_documents() {
+        let storage =
+            SqliteStorage::in_memory().expect("should create storage").with_max_documents(Some(2));
+        let mut first = Document::with_title("First");
+        storage.save_document(&first).expect("should save first");
+        storage.save_document(&Document::with_title("Second")).expect("should save second");
+
+        let Err(Error::LimitExceeded(message)) =
+            storage.save_document(&Document::with_title("Third"))
+        else {
+            unreachable!()
+        };
+        assert!(message.contains('2'));
+        assert!(matches!(storage.duplicate(&first.id), Err(Error::LimitExceeded(_))));
+
+        // Existing documents can still be edited, and deleting makes room
+        first.set_content("Edited").expect("should edit");
+        storage.save_document(&first).expect("should update first");
+        storage.delete_many(&[first.id]).expect("should delete");
+        storage.save_document(&Document::with_title("Third")).expect("should save third");
+    }
+
     #[test]
     fn test_list_documents() {
         let storage = SqliteStorage::in_memory().expect("should create storage");
@@ -226,10 +1006,32 @@ mod tests {
         storage.save_document(&doc1).expect("should save doc1");
         storage.save_document(&doc2).expect("should save doc2");
 
-        let docs = storage.list_documents().expect("should list documents");
+        let docs = storage.list_documents(None).expect("should list documents");
         assert_eq!(docs.len(), 2);
     }
 
+    #[test]
+    fn test_list_metadata_omits_content() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let mut older = Document::with_title("Older").with_initial_content("Short");
+        older.metadata.modified_at -= chrono::Duration::hours(1);
+        let newer = Document::with_title("Newer").with_initial_content("Naïve café");
+        storage.save_document(&older).expect("should save older");
+        storage.save_document(&newer).expect("should save newer");
+
+        let summaries = storage.list_metadata().expect("should list metadata");
+        let titles: Vec<&str> = summaries.iter().map(|s| s.metadata.title.as_str()).collect();
+        assert_eq!(titles, ["Newer", "Older"]);
+        assert_eq!(summaries[0].id, newer.id);
+        assert_eq!(summaries[0].content_length, 10);
+        assert_eq!(summaries[1].content_length, 5);
+
+        let json = serde_json::to_value(&summaries[0]).expect("should serialize");
+        assert_eq!(json.get("content"), None);
+        assert_eq!(json.get("crdt_state"), None);
+    }
+
     #[test]
     fn test_delete_document() {
         let storage = SqliteStorage::in_memory().expect("should create storage");
@@ -242,4 +1044,353 @@ mod tests {
         let result = storage.get_document(&doc.id);
         assert!(result.is_err());
     }
+
+    #[test]
+    fn test_list_documents_modified_since() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let cutoff = Utc::now() - chrono::Duration::minutes(30);
+
+        let mut old = Document::with_title("Old");
+        old.metadata.modified_at = cutoff - chrono::Duration::minutes(30);
+        let new = Document::with_title("New");
+
+        storage.save_document(&old).expect("should save old document");
+        storage.save_document(&new).expect("should save new document");
+
+        let docs = storage.list_documents(Some(cutoff)).expect("should list documents");
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].id, new.id);
+
+        let all = storage.list_documents(None).expect("should list documents");
+        assert_eq!(all.len(), 2);
+        assert_eq!(all[0].id, new.id);
+    }
+
+    #[test]
+    fn test_duplicate_document() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let mut doc = Document::with_title("Template");
+        doc.set_content("Body text").expect("should edit");
+        doc.crdt_state = Some(vec![1, 2, 3]);
+        storage.save_document(&doc).expect("should save document");
+
+        let copy = storage.duplicate(&doc.id).expect("should duplicate document");
+        assert_ne!(copy.id, doc.id);
+        assert_eq!(copy.metadata.title, "Template (copy)");
+        assert_eq!(copy.metadata.version, 1);
+        assert_ne!(copy.crdt_state, doc.crdt_state);
+
+        let stored = storage.get_document(&copy.id).expect("should get copy");
+        assert_eq!(stored.content, "Body text");
+
+        let original = storage.get_document(&doc.id).expect("should get original");
+        assert_eq!(original.metadata.title, "Template");
+    }
+
+    #[test]
+    fn test_delete_many() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let first = Document::with_title("First");
+        let second = Document::with_title("Second");
+        let keep = Document::with_title("Keep");
+        storage.save_document(&first).expect("should save first");
+        storage.save_document(&second).expect("should save second");
+        storage.save_document(&keep).expect("should save keep");
+
+        let deleted = storage
+            .delete_many(&[first.id, DocumentId::new(), second.id])
+            .expect("should delete documents");
+        assert_eq!(deleted, 2);
+
+        let docs = storage.list_documents(None).expect("should list documents");
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].id, keep.id);
+    }
+
+    #[test]
+    fn test_vacuum_shrinks_database_after_deletes() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("glow.db");
+        let storage =
+            SqliteStorage::new(path.to_str().expect("should be utf-8")).expect("should open");
+
+        let docs: Vec<_> = (0..50)
+            .map(|i| {
+                Document::with_title(format!("Doc {i}")).with_initial_content("x".repeat(8192))
+            })
+            .collect();
+        for doc in &docs {
+            storage.save_document(doc).expect("should save document");
+        }
+        let full = storage.stats().expect("should get stats");
+        assert_eq!(full.document_count, 50);
+        assert_eq!(full.content_bytes, 50 * 8192);
+
+        let ids: Vec<_> = docs.iter().skip(1).map(|doc| doc.id).collect();
+        storage.delete_many(&ids).expect("should delete documents");
+        let deleted = storage.stats().expect("should get stats");
+        assert_eq!((deleted.document_count, deleted.content_bytes), (1, 8192));
+        assert_eq!(deleted.file_size, full.file_size);
+
+        storage.vacuum().expect("should vacuum");
+        let vacuumed = storage.stats().expect("should get stats");
+        assert!(vacuumed.file_size < full.file_size / 4);
+        assert_eq!(vacuumed.document_count, 1);
+        let on_disk = std::fs::metadata(&path).expect("should stat database").len();
+        assert_eq!(on_disk, vacuumed.file_size);
+    }
+
+    #[test]
+    fn test_touch_accessed_keeps_version() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let doc = Document::with_title("Opened");
+        storage.save_document(&doc).expect("should save document");
+
+        let opened_at = storage.touch_accessed(&doc.id).expect("should touch document");
+
+        let retrieved = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(retrieved.metadata.accessed_at, Some(opened_at));
+        assert_eq!(retrieved.metadata.version, doc.metadata.version);
+        assert_eq!(retrieved.metadata.modified_at, doc.metadata.modified_at);
+    }
+
+    #[test]
+    fn test_list_documents_by_access() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+
+        let never = Document::with_title("Never opened");
+        let earlier = Document::with_title("Opened earlier");
+        let latest = Document::with_title("Opened latest");
+        for doc in [&never, &earlier, &latest] {
+            storage.save_document(doc).expect("should save document");
+        }
+
+        storage.touch_accessed(&earlier.id).expect("should touch document");
+        storage.touch_accessed(&latest.id).expect("should touch document");
+
+        let ids: Vec<DocumentId> = storage
+            .list_documents_by_access()
+            .expect("should list documents")
+            .into_iter()
+            .map(|doc| doc.id)
+            .collect();
+        assert_eq!(ids, vec![latest.id, earlier.id, never.id]);
+    }
+
+    #[test]
+    fn test_content_normalization_on_save() {
+        let messy = "Line one  \r\nLine two\r\n\r\n";
+        let doc = Document::with_title("Notes").with_initial_content(messy);
+
+        let plain = SqliteStorage::in_memory().expect("should create storage");
+        plain.save_document(&doc).expect("should save document");
+        assert_eq!(plain.get_document(&doc.id).expect("should get document").content, messy);
+
+        let storage = SqliteStorage::in_memory()
+            .expect("should create storage")
+            .with_content_normalization(true);
+        storage.save_document(&doc).expect("should save document");
+        let stored = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(stored.content, "Line one\nLine two\n");
+        assert_eq!(stored.metadata.version, doc.metadata.version);
+        assert_eq!(doc.content, messy);
+    }
+
+    #[test]
+    fn test_stale_save_is_rejected() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let doc = Document::with_title("Shared");
+        storage.save_document(&doc).expect("should save document");
+
+        let mut first = storage.get_document(&doc.id).expect("should load first copy");
+        let mut second = storage.get_document(&doc.id).expect("should load second copy");
+
+        first.set_content("First edit").expect("should edit");
+        storage.save_document(&first).expect("first save should succeed");
+
+        second.set_content("Second edit").expect("should edit");
+        let result = storage.save_document(&second);
+        assert!(matches!(result, Err(Error::Conflict(_))));
+
+        let stored = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(stored.content, "First edit");
+    }
+
+    #[test]
+    fn test_repair_document_from_crdt() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let doc = Document::with_title("Drifted").with_initial_content("From the CRDT");
+        storage.save_document(&doc).expect("should save document");
+        storage
+            .conn
+            .execute(
+                "UPDATE documents SET content = 'Partial wri' WHERE id = ?",
+                [doc.id.to_string()],
+            )
+            .expect("should corrupt content");
+
+        let report = storage.repair_document(&doc.id).expect("should repair");
+        assert_eq!(report, RepairReport::Repaired);
+        let repaired = storage.get_document(&doc.id).expect("should get document");
+        assert_eq!(repaired.content, "From the CRDT");
+        assert_eq!(repaired.metadata.version, doc.metadata.version);
+
+        let report = storage.repair_document(&doc.id).expect("should check again");
+        assert_eq!(report, RepairReport::Consistent);
+
+        let plain = Document::with_title("No CRDT");
+        storage.save_document(&plain).expect("should save document");
+        let report = storage.repair_document(&plain.id).expect("should skip");
+        assert_eq!(report, RepairReport::Skipped);
+    }
+
+    #[test]
+    fn test_tampered_content_is_detected_on_load() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let doc = Document::with_title("Checked").with_initial_content("Original text");
+        storage.save_document(&doc).expect("should save document");
+        assert_eq!(storage.get_document(&doc.id).expect("should load").content, "Original text");
+
+        storage
+            .conn
+            .execute(
+                "UPDATE documents SET content = 'Flipped bits' WHERE id = ?",
+                [doc.id.to_string()],
+            )
+            .expect("should tamper with content");
+        let loaded = storage.get_document(&doc.id);
+        assert!(matches!(loaded, Err(Error::Corrupted(_))), "expected corruption, got {loaded:?}");
+
+        // Content saved before checksums existed is trusted
+        storage
+            .conn
+            .execute("UPDATE documents SET content_hash = NULL WHERE id = ?", [doc.id.to_string()])
+            .expect("should clear checksum");
+        assert_eq!(storage.get_document(&doc.id).expect("should load").content, "Flipped bits");
+    }
+
+    #[test]
+    fn test_search_returns_match_offsets() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let content = "Café notes: the fox ran.\nLater the Fox slept.";
+        let doc = Document::with_title("Animals").with_initial_content(content);
+        storage.save_document(&doc).expect("should save");
+        let other = Document::with_title("Plants").with_initial_content("Ferns and moss");
+        storage.save_document(&other).expect("should save");
+
+        let hits = storage.search("fox").expect("should search");
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].document_id, doc.id.to_string());
+        assert_eq!(hits[0].title, "Animals");
+
+        let chars: Vec<char> = content.chars().collect();
+        let matched: Vec<String> =
+            hits[0].snippets.iter().map(|s| chars[s.start..s.end].iter().collect()).collect();
+        assert_eq!(matched, ["fox", "Fox"]);
+        assert_eq!(hits[0].snippets[0].start, 16);
+        assert!(hits[0].snippets[0].preview.contains("the fox ran"));
+
+        // The index follows edits
+        let mut edited = storage.get_document(&other.id).expect("should get document");
+        edited.set_content("A fox among the ferns").expect("should edit");
+        storage.save_document(&edited).expect("should save");
+        assert_eq!(storage.search("fox").expect("should search").len(), 2);
+        assert_eq!(storage.search("\"unbalanced").expect("should search"), Vec::new());
+    }
+
+    #[test]
+    fn test_import_directory() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        std::fs::write(dir.path().join("Ideas.md"), "# Ideas\n").expect("should write file");
+        std::fs::write(dir.path().join("todo.txt"), "not markdown").expect("should write file");
+        let nested = dir.path().join("work").join("meetings.2024");
+        std::fs::create_dir_all(&nested).expect("should create nested dir");
+        std::fs::write(nested.join("Standup.md"), "- shipped import").expect("should write file");
+
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let ids = storage.import_directory(dir.path()).expect("should import directory");
+        assert_eq!(ids.len(), 2);
+
+        let ideas = storage.get_document(&ids[0]).expect("should get document");
+        assert_eq!(ideas.metadata.title, "Ideas");
+        assert_eq!(ideas.content, "# Ideas\n");
+        assert_eq!(ideas.metadata.tags, Vec::<String>::new());
+        assert!(ideas.crdt_state.is_some());
+
+        let standup = storage.get_document(&ids[1]).expect("should get document");
+        assert_eq!(standup.metadata.title, "Standup");
+        assert_eq!(standup.content, "- shipped import");
+        assert_eq!(standup.metadata.tags, ["work", "meetings.2024"]);
+    }
+
+    #[test]
+    fn test_failed_import_saves_nothing() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        for name in ["a.md", "b.md", "c.md"] {
+            std::fs::write(dir.path().join(name), name).expect("should write file");
+        }
+
+        let storage =
+            SqliteStorage::in_memory().expect("should create storage").with_max_documents(Some(2));
+        let mut events = storage.subscribe();
+        let result = storage.import_directory(dir.path());
+        assert!(matches!(result, Err(Error::LimitExceeded(_))));
+        assert!(storage.list_documents(None).expect("should list documents").is_empty());
+        assert!(events.try_recv().is_err());
+    }
+
+    #[test]
+    fn test_create_from_template() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let mut template = Template::new("Meeting notes", "Meeting {date}", "## Attendees\n");
+        storage.save_template(&template).expect("should save template");
+        template.content = "## Attendees\n\n## Actions\n".to_owned();
+        storage.save_template(&template).expect("should update template");
+        storage.save_template(&Template::new("Blog post", "Draft", "")).expect("should save");
+
+        let names: Vec<_> = storage
+            .list_templates()
+            .expect("should list templates")
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert_eq!(names, ["Blog post", "Meeting notes"]);
+
+        let doc = storage.create_from_template(&template.id).expect("should create document");
+        let today = Utc::now().date_naive().format("%Y-%m-%d").to_string();
+        assert_eq!(doc.metadata.title, format!("Meeting {today}"));
+        assert_eq!(doc.content, template.content);
+
+        let stored = storage.get_document(&doc.id).expect("document should be saved");
+        assert_eq!(stored.content, template.content);
+        assert!(matches!(storage.create_from_template("missing"), Err(Error::NotFound(_))));
+    }
+
+    #[test]
+    fn test_document_events() {
+        let storage = SqliteStorage::in_memory().expect("should create storage");
+        let mut events = storage.subscribe();
+
+        let mut doc = Document::with_title("Observed");
+        storage.save_document(&doc).expect("should save document");
+        doc.set_content("Edited").expect("should edit");
+        storage.save_document(&doc).expect("should update document");
+        storage.delete_document(&doc.id).expect("should delete document");
+
+        let expected = [
+            (DocumentEventKind::Created, 1),
+            (DocumentEventKind::Updated, 2),
+            (DocumentEventKind::Deleted, 2),
+        ];
+        for (kind, version) in expected {
+            let event = events.try_recv().expect("should receive event");
+            assert_eq!(event.kind, kind);
+            assert_eq!(event.document_id, doc.id);
+            assert_eq!(event.version, version);
+        }
+        assert!(events.try_recv().is_err());
+    }
 }
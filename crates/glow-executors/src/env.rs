@@ -1,7 +1,7 @@
 //! Execution environment configuration.
 
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::path::{Path, PathBuf};
 use tokio::process::Command;
 
@@ -16,9 +16,15 @@ pub struct DocumentContext {
     pub document_content: String,
     /// Working directory for the executor.
     pub working_dir: PathBuf,
+    /// Whether the content was cut to fit a size limit.
+    #[serde(default)]
+    pub was_truncated: bool,
 }
 
 impl DocumentContext {
+    /// Marker appended to content cut by [`DocumentContext::with_max_content_chars`].
+    pub const TRUNCATION_MARKER: &'static str = "\n[truncated]";
+
     /// Create a new document context.
     #[must_use]
     pub fn new(document_id: impl Into<String>, content: impl Into<String>) -> Self {
@@ -36,6 +42,20 @@ impl DocumentContext {
         self
     }
 
+    /// Limit the content to `max_chars` characters.
+    ///
+    /// Longer content is cut, suffixed with [`Self::TRUNCATION_MARKER`] and
+    /// flagged via `was_truncated`.
+    #[must_use]
+    pub fn with_max_content_chars(mut self, max_chars: usize) -> Self {
+        if let Some((cut, _)) = self.document_content.char_indices().nth(max_chars) {
+            self.document_content.truncate(cut);
+            self.document_content.push_str(Self::TRUNCATION_MARKER);
+            self.was_truncated = true;
+        }
+        self
+    }
+
     /// Set the working directory.
     #[must_use]
     pub fn with_working_dir(mut self, dir: impl Into<PathBuf>) -> Self {
@@ -44,6 +64,19 @@ impl DocumentContext {
     }
 }
 
+/// Placeholder that replaces secret values in [`ExecutionEnv::redacted_vars`].
+pub const REDACTED: &str = "[redacted]";
+
+/// Name fragments that mark an environment variable as secret.
+const SECRET_MARKERS: &[&str] = &["KEY", "TOKEN", "SECRET", "PASSWORD", "CREDENTIAL", "AUTH"];
+
+/// Whether an environment variable name looks like it holds a secret.
+#[must_use]
+pub fn is_secret_var(name: &str) -> bool {
+    let name = name.to_ascii_uppercase();
+    SECRET_MARKERS.iter().any(|marker| name.contains(marker))
+}
+
 /// Environment variables and context for executor processes.
 #[derive(Debug, Clone, Default)]
 pub struct ExecutionEnv {
@@ -89,6 +122,18 @@ impl ExecutionEnv {
         self.vars.get(key)
     }
 
+    /// Variables safe to log, sorted by name, with secret values replaced by [`REDACTED`].
+    #[must_use]
+    pub fn redacted_vars(&self) -> BTreeMap<String, String> {
+        self.vars
+            .iter()
+            .map(|(key, value)| {
+                let value = if is_secret_var(key) { REDACTED.to_owned() } else { value.clone() };
+                (key.clone(), value)
+            })
+            .collect()
+    }
+
     /// Apply environment variables to a command.
     pub fn apply_to_command(&self, cmd: &mut Command) {
         for (key, value) in &self.vars {
@@ -126,6 +171,35 @@ mod tests {
         assert_eq!(ctx.working_dir, PathBuf::from("/tmp/glow"));
     }
 
+    #[test]
+    fn test_redacted_vars_hide_secrets() {
+        let mut env = ExecutionEnv::new();
+        env.insert("ANTHROPIC_API_KEY", "sk-ant-123");
+        env.insert("GITHUB_TOKEN", "ghp_456");
+        env.insert("CI", "true");
+
+        let redacted = env.redacted_vars();
+        assert_eq!(redacted["ANTHROPIC_API_KEY"], REDACTED);
+        assert_eq!(redacted["GITHUB_TOKEN"], REDACTED);
+        assert_eq!(redacted["CI"], "true");
+    }
+
+    #[test]
+    fn test_content_under_limit_is_untouched() {
+        let ctx = DocumentContext::new("doc-123", "Héllo").with_max_content_chars(5);
+
+        assert_eq!(ctx.document_content, "Héllo");
+        assert!(!ctx.was_truncated);
+    }
+
+    #[test]
+    fn test_content_over_limit_is_truncated() {
+        let ctx = DocumentContext::new("doc-123", "Héllo world").with_max_content_chars(5);
+
+        assert_eq!(ctx.document_content, "Héllo\n[truncated]");
+        assert!(ctx.was_truncated);
+    }
+
     #[test]
     fn test_execution_env_merge() {
         let mut env = ExecutionEnv::new();
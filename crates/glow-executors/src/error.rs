@@ -62,6 +62,24 @@ pub enum ExecutorError {
     Other(String),
 }
 
+impl ExecutorError {
+    /// Classify a failure to start `program`.
+    ///
+    /// A missing binary becomes [`ExecutorError::NotAvailable`], pointing at the
+    /// executor's setup action, so callers can offer to install it; anything
+    /// else is [`ExecutorError::SpawnFailed`].
+    #[must_use]
+    pub fn from_spawn_error(program: &str, error: &std::io::Error) -> Self {
+        if error.kind() == std::io::ErrorKind::NotFound {
+            Self::NotAvailable(format!(
+                "`{program}` was not found on PATH; run the executor's setup action to install it"
+            ))
+        } else {
+            Self::SpawnFailed(error.to_string())
+        }
+    }
+}
+
 impl From<String> for ExecutorError {
     fn from(s: String) -> Self {
         Self::Other(s)
@@ -4,11 +4,14 @@
 
 use serde::{Deserialize, Serialize};
 use std::sync::Arc;
-use tracing::debug;
+use tracing::{debug, warn};
 
+use crate::language::detect_language;
 use crate::logs::{MsgStore, NormalizedEntry, NormalizedEntryType};
 use crate::types::SuggestedEdit;
 
+use super::markdown::simplify_markdown;
+
 /// Message types from Claude Code's JSON stream output.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -48,6 +51,8 @@ pub enum ClaudeMessage {
         #[serde(default)]
         total_cost_usd: Option<f64>,
         #[serde(default)]
+        usage: Option<serde_json::Value>,
+        #[serde(default)]
         is_error: bool,
     },
     /// Error message.
@@ -59,6 +64,20 @@ pub enum ClaudeMessage {
     },
 }
 
+impl ClaudeMessage {
+    /// Session ID carried by the message, if any.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        match self {
+            Self::System { session_id, .. }
+            | Self::Assistant { session_id, .. }
+            | Self::StreamEvent { session_id, .. }
+            | Self::Result { session_id, .. } => session_id.as_deref(),
+            Self::User { .. } | Self::Error { .. } => None,
+        }
+    }
+}
+
 /// Stream event data from Claude Code.
 #[derive(Debug, Clone, Serialize, Deserialize)]
 #[serde(tag = "type", rename_all = "snake_case")]
@@ -159,15 +178,116 @@ pub enum ContentBlock {
     Thinking { thinking: String },
 }
 
+/// Tokens used between progress entries reporting the running total.
+pub const TOKEN_PROGRESS_INTERVAL: u64 = 1_000;
+
+/// Input and output tokens used by a session.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct TokenUsage {
+    /// Tokens sent to the model.
+    pub input_tokens: u64,
+    /// Tokens generated by the model.
+    pub output_tokens: u64,
+}
+
+impl TokenUsage {
+    /// Input plus output tokens.
+    #[must_use]
+    pub const fn total(&self) -> u64 {
+        self.input_tokens + self.output_tokens
+    }
+
+    /// This usage with any counts present in a `usage` object replacing its own.
+    fn updated_from(self, usage: &serde_json::Value) -> Self {
+        let tokens = |key: &str| usage.get(key).and_then(serde_json::Value::as_u64);
+        Self {
+            input_tokens: tokens("input_tokens").unwrap_or(self.input_tokens),
+            output_tokens: tokens("output_tokens").unwrap_or(self.output_tokens),
+        }
+    }
+}
+
+impl std::ops::Add for TokenUsage {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self {
+            input_tokens: self.input_tokens + other.input_tokens,
+            output_tokens: self.output_tokens + other.output_tokens,
+        }
+    }
+}
+
+/// How much detail the log processor records.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Verbosity {
+    /// Only messages, suggested edits and errors; no thinking, tool calls or progress.
+    Minimal,
+    /// Everything except diagnostics.
+    #[default]
+    Normal,
+    /// Everything, including diagnostics such as session start and result details.
+    Verbose,
+}
+
+impl Verbosity {
+    /// Whether entries of `entry_type` are recorded at this verbosity.
+    #[must_use]
+    pub const fn includes(self, entry_type: &NormalizedEntryType) -> bool {
+        match entry_type {
+            NormalizedEntryType::SystemMessage => matches!(self, Self::Verbose),
+            NormalizedEntryType::ThinkingMessage
+            | NormalizedEntryType::ToolCall
+            | NormalizedEntryType::ToolResult
+            | NormalizedEntryType::Progress => !matches!(self, Self::Minimal),
+            NormalizedEntryType::UserMessage
+            | NormalizedEntryType::AssistantMessage
+            | NormalizedEntryType::ErrorMessage
+            | NormalizedEntryType::SuggestedEdit
+            | NormalizedEntryType::Unknown => true,
+        }
+    }
+}
+
+/// Largest JSON object, in bytes, held back while waiting for its remaining lines.
+pub const MAX_PARTIAL_JSON_BYTES: usize = 1024 * 1024;
+
 /// Processor for Claude Code log output.
 pub struct ClaudeLogProcessor {
     msg_store: Arc<MsgStore>,
     buffer: String,
+    /// Lines of a JSON object that spans several lines, awaiting the rest
+    partial_json: String,
     current_content: String,
     current_thinking: String,
     suggested_edits: Vec<SuggestedEdit>,
     /// Track if we've already sent content via streaming deltas
     has_streamed_content: bool,
+    /// Simplify markdown in assistant messages
+    strip_markdown: bool,
+    /// Input plus output tokens reported by result messages
+    total_tokens: u64,
+    /// Streamed token usage of the messages before the current one
+    finished_usage: TokenUsage,
+    /// Streamed token usage of the current message so far
+    message_usage: TokenUsage,
+    /// Running token total at which the next progress entry is emitted
+    next_progress_tokens: u64,
+    /// Cost in USD reported by result messages
+    total_cost_usd: f64,
+    /// Cost in USD past which the run should be stopped
+    max_cost_usd: Option<f64>,
+    /// Model named by the latest streamed message, used to price its tokens
+    model: Option<String>,
+    /// Claude session ID, used to resume the session
+    session_id: Option<String>,
+    /// Detect the language of the final assistant message
+    detect_language: bool,
+    /// Text of the most recent assistant message
+    last_assistant_text: Option<String>,
+    /// Which entry types are recorded
+    verbosity: Verbosity,
 }
 
 impl ClaudeLogProcessor {
@@ -177,10 +297,78 @@ impl ClaudeLogProcessor {
         Self {
             msg_store,
             buffer: String::new(),
+            partial_json: String::new(),
             current_content: String::new(),
             current_thinking: String::new(),
             suggested_edits: Vec::new(),
             has_streamed_content: false,
+            strip_markdown: false,
+            total_tokens: 0,
+            finished_usage: TokenUsage::default(),
+            message_usage: TokenUsage::default(),
+            next_progress_tokens: TOKEN_PROGRESS_INTERVAL,
+            total_cost_usd: 0.0,
+            max_cost_usd: None,
+            model: None,
+            session_id: None,
+            detect_language: false,
+            last_assistant_text: None,
+            verbosity: Verbosity::Normal,
+        }
+    }
+
+    /// Simplify markdown (headers, fences, emphasis) in assistant messages.
+    ///
+    /// Suggested edits are never modified.
+    #[must_use]
+    pub const fn with_strip_markdown(mut self, strip_markdown: bool) -> Self {
+        self.strip_markdown = strip_markdown;
+        self
+    }
+
+    /// Detect the language of the final assistant message, reported by [`Self::language`].
+    #[must_use]
+    pub const fn with_language_detection(mut self, detect_language: bool) -> Self {
+        self.detect_language = detect_language;
+        self
+    }
+
+    /// Set the cost in USD past which [`Self::cost_limit_exceeded`] reports
+    /// the run should be stopped, or `None` for no ceiling.
+    ///
+    /// The ceiling is checked against [`Self::estimated_cost_usd`] while the
+    /// run streams, since the actual cost is only reported once it ends.
+    #[must_use]
+    pub const fn with_max_cost_usd(mut self, max_cost_usd: Option<f64>) -> Self {
+        self.max_cost_usd = max_cost_usd;
+        self
+    }
+
+    /// Set which entry types are recorded.
+    ///
+    /// Suggested edits are collected at every verbosity.
+    #[must_use]
+    pub const fn with_verbosity(mut self, verbosity: Verbosity) -> Self {
+        self.verbosity = verbosity;
+        self
+    }
+
+    /// Record an entry, unless the verbosity leaves its type out.
+    async fn push_entry(&self, entry: NormalizedEntry) {
+        if self.verbosity.includes(&entry.entry_type) {
+            self.msg_store.push_entry(entry).await;
+        }
+    }
+
+    /// Build an assistant message entry, simplifying markdown if configured.
+    fn assistant_entry(&mut self, text: String) -> NormalizedEntry {
+        if self.detect_language {
+            self.last_assistant_text = Some(text.clone());
+        }
+        if self.strip_markdown {
+            NormalizedEntry::assistant_message(simplify_markdown(&text))
+        } else {
+            NormalizedEntry::assistant_message(text)
         }
     }
 
@@ -200,29 +388,72 @@ impl ClaudeLogProcessor {
     }
 
     /// Process a single line of JSON output.
+    ///
+    /// A line that starts a JSON object but ends before it closes is held and
+    /// joined with the following lines until the object parses.
     async fn process_line(&mut self, line: &str) {
+        if !self.partial_json.is_empty() {
+            let held = std::mem::take(&mut self.partial_json);
+            let joined = format!("{held}\n{line}");
+            match serde_json::from_str::<ClaudeMessage>(&joined) {
+                Ok(msg) => return self.handle_message(msg).await,
+                Err(e) if e.is_eof() => return self.hold_partial_json(joined).await,
+                // The held lines never became an object; parse this line on its own
+                Err(_) => self.push_raw(held).await,
+            }
+        }
+
         match serde_json::from_str::<ClaudeMessage>(line) {
             Ok(msg) => self.handle_message(msg).await,
+            Err(e) if e.is_eof() && line.starts_with('{') => {
+                self.hold_partial_json(line.to_owned()).await;
+            }
             Err(e) => {
                 debug!(line = %line, error = %e, "Failed to parse Claude message");
-                // Store raw line as fallback
-                self.msg_store.push(crate::logs::LogMsg::Raw(line.to_owned())).await;
+                self.push_raw(line.to_owned()).await;
             }
         }
     }
 
+    /// Hold an incomplete JSON object, giving up on it past [`MAX_PARTIAL_JSON_BYTES`].
+    async fn hold_partial_json(&mut self, text: String) {
+        if text.len() > MAX_PARTIAL_JSON_BYTES {
+            warn!(bytes = text.len(), "Incomplete JSON object exceeded the size cap");
+            self.push_raw(text).await;
+        } else {
+            self.partial_json = text;
+        }
+    }
+
+    /// Store output that is not a Claude message as raw text, unless minimal.
+    async fn push_raw(&self, text: String) {
+        if self.verbosity != Verbosity::Minimal {
+            self.msg_store.push(crate::logs::LogMsg::Raw(text)).await;
+        }
+    }
+
     /// Handle a parsed Claude message.
     async fn handle_message(&mut self, msg: ClaudeMessage) {
+        if let Some(session_id) = msg.session_id() {
+            self.session_id = Some(session_id.to_owned());
+        }
+
         match msg {
             ClaudeMessage::System { subtype, session_id } => {
                 debug!(subtype = ?subtype, session_id = ?session_id, "System message");
-                // System init messages are informational, we can skip them
+                // System init messages are informational, recorded only as diagnostics
+                let entry = NormalizedEntry::system_message(format!(
+                    "System message: {}",
+                    subtype.as_deref().unwrap_or("unknown")
+                ))
+                .with_metadata(serde_json::json!({ "subtype": subtype, "sessionId": session_id }));
+                self.push_entry(entry).await;
             }
 
             ClaudeMessage::User { message } => {
                 if let Some(m) = message {
                     if let Some(content) = m.content {
-                        self.msg_store.push_entry(NormalizedEntry::user_message(content)).await;
+                        self.push_entry(NormalizedEntry::user_message(content)).await;
                     }
                 }
             }
@@ -231,109 +462,31 @@ impl ClaudeLogProcessor {
                 // Process content blocks
                 // Skip text/thinking if we've already streamed it via deltas
                 for block in message.content {
-                    match block {
-                        ContentBlock::Text { text } => {
-                            if !self.has_streamed_content {
-                                self.msg_store
-                                    .push_entry(NormalizedEntry::assistant_message(text))
-                                    .await;
-                            }
-                            // else: content was already sent via streaming deltas
-                        }
-                        ContentBlock::Thinking { thinking } => {
-                            if !self.has_streamed_content {
-                                self.msg_store
-                                    .push_entry(NormalizedEntry::thinking(thinking))
-                                    .await;
-                            }
-                        }
-                        ContentBlock::ToolUse { id, name, input } => {
-                            // Check if this is a suggest_edit tool
-                            if name == "suggest_edit" {
-                                if let Ok(edit) = self.parse_suggested_edit(&id, &input) {
-                                    self.suggested_edits.push(edit.clone());
-                                    self.msg_store
-                                        .push_entry(NormalizedEntry {
-                                            timestamp: Some(chrono::Utc::now().timestamp_millis()),
-                                            entry_type: NormalizedEntryType::SuggestedEdit,
-                                            content: serde_json::to_string(&edit)
-                                                .unwrap_or_default(),
-                                            metadata: Some(input),
-                                        })
-                                        .await;
-                                }
-                            } else {
-                                self.msg_store
-                                    .push_entry(NormalizedEntry::tool_call(name, input))
-                                    .await;
-                            }
-                        }
-                    }
+                    self.handle_content_block(block).await;
                 }
             }
 
-            ClaudeMessage::StreamEvent { event, .. } => {
-                // Handle streaming events - accumulate deltas
-                match event {
-                    StreamEventData::ContentBlockDelta { delta, .. } => {
-                        // Mark that we're receiving streaming content
-                        self.has_streamed_content = true;
-                        match delta {
-                            DeltaContent::TextDelta { text } => {
-                                // Accumulate text for streaming
-                                self.current_content.push_str(&text);
-                            }
-                            DeltaContent::ThinkingDelta { thinking } => {
-                                // Accumulate thinking
-                                self.current_thinking.push_str(&thinking);
-                            }
-                            DeltaContent::InputJsonDelta { .. } => {
-                                // Tool input JSON - ignore for now
-                            }
-                        }
-                    }
-                    StreamEventData::ContentBlockStop { .. } => {
-                        // Block finished - flush accumulated content
-                        if !self.current_thinking.is_empty() {
-                            self.msg_store
-                                .push_entry(NormalizedEntry::thinking(std::mem::take(
-                                    &mut self.current_thinking,
-                                )))
-                                .await;
-                            self.has_streamed_content = true;
-                        }
-                        if !self.current_content.is_empty() {
-                            self.msg_store
-                                .push_entry(NormalizedEntry::assistant_message(std::mem::take(
-                                    &mut self.current_content,
-                                )))
-                                .await;
-                            self.has_streamed_content = true;
-                        }
-                    }
-                    _ => {
-                        // Other stream events (message_start, message_stop, etc.)
-                        // are informational
-                    }
-                }
-            }
+            ClaudeMessage::StreamEvent { event, .. } => self.handle_stream_event(event).await,
 
-            ClaudeMessage::Result { result, session_id, total_cost_usd, is_error, .. } => {
+            ClaudeMessage::Result {
+                subtype,
+                result,
+                session_id,
+                total_cost_usd,
+                usage,
+                is_error,
+            } => {
                 // Flush any remaining content before ending
                 if !self.current_thinking.is_empty() {
-                    self.msg_store
-                        .push_entry(NormalizedEntry::thinking(std::mem::take(
-                            &mut self.current_thinking,
-                        )))
-                        .await;
+                    let entry =
+                        NormalizedEntry::thinking(std::mem::take(&mut self.current_thinking));
+                    self.push_entry(entry).await;
                     self.has_streamed_content = true;
                 }
                 if !self.current_content.is_empty() {
-                    self.msg_store
-                        .push_entry(NormalizedEntry::assistant_message(std::mem::take(
-                            &mut self.current_content,
-                        )))
-                        .await;
+                    let text = std::mem::take(&mut self.current_content);
+                    let entry = self.assistant_entry(text);
+                    self.push_entry(entry).await;
                 }
 
                 // If there's a result string and we haven't already sent content, send it
@@ -343,6 +496,25 @@ impl ClaudeLogProcessor {
                     }
                 }
 
+                if let Some(usage) = &usage {
+                    let tokens = |key: &str| usage.get(key).and_then(serde_json::Value::as_u64);
+                    self.total_tokens +=
+                        tokens("input_tokens").unwrap_or(0) + tokens("output_tokens").unwrap_or(0);
+                }
+                self.total_cost_usd += total_cost_usd.unwrap_or(0.0);
+
+                let entry = NormalizedEntry::system_message(format!(
+                    "Session completed: {}",
+                    subtype.as_deref().unwrap_or("unknown")
+                ))
+                .with_metadata(serde_json::json!({
+                    "subtype": subtype,
+                    "totalCostUsd": total_cost_usd,
+                    "isError": is_error,
+                    "usage": usage,
+                }));
+                self.push_entry(entry).await;
+
                 // Mark session as ended
                 self.msg_store.push(crate::logs::LogMsg::Ended).await;
 
@@ -356,12 +528,128 @@ impl ClaudeLogProcessor {
 
             ClaudeMessage::Error { message, error } => {
                 let err_msg = message.or(error).unwrap_or_else(|| "Unknown error".to_owned());
-                self.msg_store.push_entry(NormalizedEntry::error(err_msg)).await;
+                self.push_entry(NormalizedEntry::error(err_msg)).await;
+            }
+        }
+    }
+
+    /// Handle a streaming event, accumulating content deltas and token usage.
+    async fn handle_stream_event(&mut self, event: StreamEventData) {
+        match event {
+            StreamEventData::ContentBlockDelta { delta, .. } => {
+                // Mark that we're receiving streaming content
+                self.has_streamed_content = true;
+                match delta {
+                    DeltaContent::TextDelta { text } => {
+                        // Accumulate text for streaming
+                        self.current_content.push_str(&text);
+                    }
+                    DeltaContent::ThinkingDelta { thinking } => {
+                        // Accumulate thinking
+                        self.current_thinking.push_str(&thinking);
+                    }
+                    DeltaContent::InputJsonDelta { .. } => {
+                        // Tool input JSON - ignore for now
+                    }
+                }
+            }
+            StreamEventData::ContentBlockStop { .. } => {
+                // Block finished - flush accumulated content
+                if !self.current_thinking.is_empty() {
+                    let entry =
+                        NormalizedEntry::thinking(std::mem::take(&mut self.current_thinking));
+                    self.push_entry(entry).await;
+                    self.has_streamed_content = true;
+                }
+                if !self.current_content.is_empty() {
+                    let text = std::mem::take(&mut self.current_content);
+                    let entry = self.assistant_entry(text);
+                    self.push_entry(entry).await;
+                    self.has_streamed_content = true;
+                }
+            }
+            StreamEventData::MessageStart { message } => {
+                self.finished_usage = self.token_usage();
+                self.message_usage = TokenUsage::default();
+                if let Some(model) = message.as_ref().and_then(|m| m["model"].as_str()) {
+                    self.model = Some(model.to_owned());
+                }
+                if let Some(usage) = message.as_ref().and_then(|m| m.get("usage")) {
+                    self.record_usage(usage).await;
+                }
+            }
+            StreamEventData::MessageDelta { usage: Some(usage), .. } => {
+                self.record_usage(&usage).await;
+            }
+            _ => {
+                // Other stream events (message_stop, etc.) are informational
+            }
+        }
+    }
+
+    /// Update the current message's token usage from a stream event.
+    ///
+    /// Usage in message events is cumulative for the message, so it replaces
+    /// the message's counts rather than adding to them. Each time the running
+    /// total passes another [`TOKEN_PROGRESS_INTERVAL`] tokens, a progress
+    /// entry reports it.
+    async fn record_usage(&mut self, usage: &serde_json::Value) {
+        self.message_usage = self.message_usage.updated_from(usage);
+        let usage = self.token_usage();
+        if usage.total() < self.next_progress_tokens {
+            return;
+        }
+        self.next_progress_tokens =
+            (usage.total() / TOKEN_PROGRESS_INTERVAL + 1) * TOKEN_PROGRESS_INTERVAL;
+        let entry = NormalizedEntry::progress(
+            format!("{} tokens used so far", usage.total()),
+            serde_json::to_value(usage).unwrap_or_default(),
+        );
+        self.push_entry(entry).await;
+    }
+
+    /// Handle a single content block from a complete assistant message.
+    async fn handle_content_block(&mut self, block: ContentBlock) {
+        match block {
+            ContentBlock::Text { text } => {
+                if !self.has_streamed_content {
+                    let entry = self.assistant_entry(text);
+                    self.push_entry(entry).await;
+                }
+                // else: content was already sent via streaming deltas
+            }
+            ContentBlock::Thinking { thinking } => {
+                if !self.has_streamed_content {
+                    self.push_entry(NormalizedEntry::thinking(thinking)).await;
+                }
+            }
+            ContentBlock::ToolUse { id, name, input } => {
+                // Check if this is a suggest_edit tool
+                if name == "suggest_edit" {
+                    match self.parse_suggested_edit(&id, &input) {
+                        Ok(edit) => {
+                            self.suggested_edits.push(edit.clone());
+                            self.push_entry(NormalizedEntry {
+                                timestamp: Some(chrono::Utc::now().timestamp_millis()),
+                                entry_type: NormalizedEntryType::SuggestedEdit,
+                                content: serde_json::to_string(&edit).unwrap_or_default(),
+                                metadata: Some(input),
+                            })
+                            .await;
+                        }
+                        Err(reason) => debug!(id = %id, reason, "Dropping suggested edit"),
+                    }
+                } else {
+                    self.push_entry(NormalizedEntry::tool_call(name, input)).await;
+                }
             }
         }
     }
 
     /// Parse a suggested edit from tool input.
+    ///
+    /// Edits with an empty `original_text`, or whose suggestion is identical
+    /// to the original, are rejected since there is nothing to apply.
     fn parse_suggested_edit(
         &self,
         id: &str,
@@ -379,6 +667,13 @@ impl ClaudeLogProcessor {
             .ok_or("missing suggested_text")?
             .to_owned();
 
+        if original_text.trim().is_empty() {
+            return Err("empty original_text".to_owned());
+        }
+        if original_text == suggested_text {
+            return Err("suggested_text is identical to original_text".to_owned());
+        }
+
         let explanation =
             input.get("explanation").and_then(|v| v.as_str()).unwrap_or("").to_owned();
 
@@ -399,6 +694,57 @@ impl ClaudeLogProcessor {
         &self.suggested_edits
     }
 
+    /// Claude session ID reported by the output, if any.
+    #[must_use]
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Language of the final assistant message as an ISO 639-3 code, if
+    /// detection is enabled and the message has enough prose to tell.
+    #[must_use]
+    pub fn language(&self) -> Option<String> {
+        self.last_assistant_text.as_deref().and_then(detect_language)
+    }
+
+    /// Total input and output tokens reported by the session so far.
+    #[must_use]
+    pub const fn total_tokens(&self) -> u64 {
+        self.total_tokens
+    }
+
+    /// Input and output tokens reported by streamed message events so far.
+    ///
+    /// Unlike [`Self::total_tokens`], this updates while the session runs
+    /// rather than when it finishes.
+    #[must_use]
+    pub fn token_usage(&self) -> TokenUsage {
+        self.finished_usage + self.message_usage
+    }
+
+    /// Total cost in USD reported by the session so far.
+    #[must_use]
+    pub const fn total_cost_usd(&self) -> f64 {
+        self.total_cost_usd
+    }
+
+    /// Cost in USD of the streamed token usage so far, at the price of the
+    /// model streaming it (see [`crate::pricing::price_for`]).
+    ///
+    /// Unlike [`Self::total_cost_usd`], this updates while the session runs.
+    /// It ignores cache pricing, so it is only an estimate.
+    #[must_use]
+    pub fn estimated_cost_usd(&self) -> f64 {
+        crate::pricing::price_for(self.model.as_deref().unwrap_or_default())
+            .cost_usd(self.token_usage())
+    }
+
+    /// Whether the estimated cost has passed the configured ceiling.
+    #[must_use]
+    pub fn cost_limit_exceeded(&self) -> bool {
+        self.max_cost_usd.is_some_and(|max| self.estimated_cost_usd() > max)
+    }
+
     /// Flush any remaining buffered content.
     pub async fn flush(&mut self) {
         if !self.buffer.is_empty() {
@@ -406,18 +752,20 @@ impl ClaudeLogProcessor {
             self.process_line(&remaining).await;
         }
 
+        if !self.partial_json.is_empty() {
+            let held = std::mem::take(&mut self.partial_json);
+            self.push_raw(held).await;
+        }
+
         if !self.current_thinking.is_empty() {
-            self.msg_store
-                .push_entry(NormalizedEntry::thinking(std::mem::take(&mut self.current_thinking)))
-                .await;
+            let entry = NormalizedEntry::thinking(std::mem::take(&mut self.current_thinking));
+            self.push_entry(entry).await;
         }
 
         if !self.current_content.is_empty() {
-            self.msg_store
-                .push_entry(NormalizedEntry::assistant_message(std::mem::take(
-                    &mut self.current_content,
-                )))
-                .await;
+            let text = std::mem::take(&mut self.current_content);
+            let entry = self.assistant_entry(text);
+            self.push_entry(entry).await;
         }
     }
 }
@@ -426,6 +774,31 @@ impl ClaudeLogProcessor {
 mod tests {
     use super::*;
 
+    /// Language detected for a session whose final assistant message is `text`.
+    async fn detected_language(text: &str, enabled: bool) -> Option<String> {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store).with_language_detection(enabled);
+        let message = serde_json::json!({
+            "type": "assistant",
+            "message": { "content": [{ "type": "text", "text": text }] },
+        });
+        processor.process_chunk(&format!("{message}\n")).await;
+        processor.language()
+    }
+
+    #[tokio::test]
+    async fn test_language_detection() {
+        let english = "The opening paragraph is clear, but the conclusion repeats the \
+                       introduction almost word for word. Try summarising the argument instead.";
+        let french = "Le premier paragraphe est clair, mais la conclusion répète presque \
+                      mot pour mot l'introduction. Essayez plutôt de résumer l'argument.";
+
+        assert_eq!(detected_language(english, true).await.as_deref(), Some("eng"));
+        assert_eq!(detected_language(french, true).await.as_deref(), Some("fra"));
+        assert_eq!(detected_language("Done.", true).await, None);
+        assert_eq!(detected_language(english, false).await, None);
+    }
+
     #[tokio::test]
     async fn test_process_assistant_message() {
         let store = Arc::new(MsgStore::new());
@@ -438,6 +811,141 @@ mod tests {
 
         let history = store.get_history().await;
         assert_eq!(history.len(), 1);
+        assert_eq!(processor.session_id(), Some("test-123"));
+    }
+
+    #[tokio::test]
+    async fn test_json_object_split_across_chunks() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone());
+
+        for chunk in [
+            "{\"type\":\"assistant\",\n",
+            "\"message\":{\"content\":[{\"type\":\"text\",\"text\":\"Split\"}]},\n",
+            "\"session_id\":\"split-1\"}\n",
+        ] {
+            processor.process_chunk(chunk).await;
+        }
+        processor.process_chunk("{\"type\":\n").await;
+        processor.process_chunk("not json\n").await;
+        processor.flush().await;
+
+        let history = store.get_history().await;
+        assert!(matches!(&history[0], crate::logs::LogMsg::Entry(e) if e.content == "Split"));
+        assert_eq!(processor.session_id(), Some("split-1"));
+        assert!(matches!(&history[1], crate::logs::LogMsg::Raw(raw) if raw == "{\"type\":"));
+        assert!(matches!(&history[2], crate::logs::LogMsg::Raw(raw) if raw == "not json"));
+        assert_eq!(history.len(), 3);
+    }
+
+    #[tokio::test]
+    async fn test_strip_markdown_in_assistant_messages() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone()).with_strip_markdown(true);
+
+        let json = serde_json::json!({
+            "type": "assistant",
+            "message": {"content": [
+                {"type": "text", "text": "## Review\n**Tighten** the `intro`."},
+                {"type": "tool_use", "id": "edit-1", "name": "suggest_edit", "input": {
+                    "original_text": "# Title",
+                    "suggested_text": "## **Better** title",
+                }},
+            ]},
+        });
+        processor.process_chunk(&format!("{json}\n")).await;
+
+        let history = store.get_history().await;
+        let contents: Vec<(NormalizedEntryType, String)> = history
+            .iter()
+            .filter_map(|m| match m {
+                crate::logs::LogMsg::Entry(e) => Some((e.entry_type.clone(), e.content.clone())),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            contents[0],
+            (NormalizedEntryType::AssistantMessage, "Review\nTighten the `intro`.".to_owned())
+        );
+        assert_eq!(contents[1].0, NormalizedEntryType::SuggestedEdit);
+        assert!(contents[1].1.contains("## **Better** title"));
+    }
+
+    #[tokio::test]
+    async fn test_message_delta_usage_accumulates() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone());
+        let event = |event: serde_json::Value| {
+            format!("{}\n", serde_json::json!({ "type": "stream_event", "event": event }))
+        };
+
+        let start = |input: u64| {
+            event(serde_json::json!({
+                "type": "message_start",
+                "message": { "usage": { "input_tokens": input, "output_tokens": 1 } },
+            }))
+        };
+        let delta = |output: u64| {
+            event(serde_json::json!({
+                "type": "message_delta",
+                "delta": { "stop_reason": null },
+                "usage": { "output_tokens": output },
+            }))
+        };
+
+        processor.process_chunk(&start(600)).await;
+        processor.process_chunk(&delta(200)).await;
+        processor.process_chunk(&delta(350)).await;
+        assert_eq!(processor.token_usage(), TokenUsage { input_tokens: 600, output_tokens: 350 });
+
+        processor.process_chunk(&start(900)).await;
+        processor.process_chunk(&delta(100)).await;
+        processor.process_chunk(&delta(150)).await;
+        let usage = processor.token_usage();
+        assert_eq!(usage, TokenUsage { input_tokens: 1500, output_tokens: 500 });
+        assert_eq!(usage.total(), 2000);
+
+        let progress: Vec<String> = store
+            .get_history()
+            .await
+            .iter()
+            .filter_map(|m| match m {
+                crate::logs::LogMsg::Entry(e) if e.entry_type == NormalizedEntryType::Progress => {
+                    Some(e.content.clone())
+                }
+                _ => None,
+            })
+            .collect();
+        assert_eq!(progress, ["1851 tokens used so far", "2000 tokens used so far"]);
+    }
+
+    #[tokio::test]
+    async fn test_long_thinking_has_preview() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone());
+
+        let thinking = format!("The intro buries the lede. {}", "Consider the reader. ".repeat(20));
+        let delta = serde_json::json!({
+            "type": "stream_event",
+            "event": {"type": "content_block_delta", "index": 0,
+                      "delta": {"type": "thinking_delta", "thinking": thinking}},
+        });
+        let stop = serde_json::json!({
+            "type": "stream_event",
+            "event": {"type": "content_block_stop", "index": 0},
+        });
+        processor.process_chunk(&format!("{delta}\n{stop}\n")).await;
+
+        let history = store.get_history().await;
+        let Some(crate::logs::LogMsg::Entry(entry)) = history.first() else {
+            unreachable!("thinking should be flushed at block stop");
+        };
+        assert_eq!(entry.entry_type, NormalizedEntryType::ThinkingMessage);
+        assert_eq!(entry.content, thinking);
+        let preview = entry.preview().expect("thinking should have a preview");
+        assert!(preview.starts_with("The intro buries the lede."));
+        assert!(preview.ends_with('…'));
+        assert!(preview.chars().count() < thinking.chars().count());
     }
 
     #[tokio::test]
@@ -453,6 +961,44 @@ mod tests {
         assert!(history.iter().any(|m| matches!(m, crate::logs::LogMsg::Ended)));
     }
 
+    #[tokio::test]
+    async fn test_result_usage_counts_tokens() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store);
+
+        let json = r#"{"type":"result","subtype":"success","usage":{"input_tokens":120,"cache_read_input_tokens":900,"output_tokens":30},"is_error":false}"#;
+        processor.process_chunk(&format!("{json}\n")).await;
+
+        assert_eq!(processor.total_tokens(), 150);
+    }
+
+    #[tokio::test]
+    async fn test_streamed_cost_is_checked_against_ceiling() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store).with_max_cost_usd(Some(0.05));
+
+        let event = |event: serde_json::Value| {
+            format!(
+                "{}\n",
+                serde_json::json!({
+                    "type": "stream_event", "event": event,
+                })
+            )
+        };
+        let start = serde_json::json!({"type": "message_start", "message": {
+            "model": "claude-sonnet-4-20250514",
+            "usage": {"input_tokens": 10_000, "output_tokens": 1},
+        }});
+        processor.process_chunk(&event(start)).await;
+        assert!(!processor.cost_limit_exceeded());
+
+        // 10k input and 2k output tokens at $3/$15 per million cost $0.06
+        let delta = serde_json::json!({"type": "message_delta", "usage": {"output_tokens": 2_000}});
+        processor.process_chunk(&event(delta)).await;
+        assert!((processor.estimated_cost_usd() - 0.06).abs() < 1e-9);
+        assert!(processor.cost_limit_exceeded());
+    }
+
     #[tokio::test]
     async fn test_parse_suggested_edit() {
         let store = Arc::new(MsgStore::new());
@@ -468,4 +1014,106 @@ mod tests {
         assert_eq!(edit.original_text, "Hello world");
         assert_eq!(edit.suggested_text, "Hello, world!");
     }
+
+    #[tokio::test]
+    async fn test_noop_suggested_edits_are_dropped() {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone());
+
+        for (id, original, suggested) in
+            [("noop", "Hello world", "Hello world"), ("empty", "  ", "Hello"), ("ok", "teh", "the")]
+        {
+            let message = serde_json::json!({
+                "type": "assistant",
+                "message": { "content": [{
+                    "type": "tool_use",
+                    "id": id,
+                    "name": "suggest_edit",
+                    "input": { "original_text": original, "suggested_text": suggested },
+                }] },
+            });
+            processor.process_chunk(&format!("{message}\n")).await;
+        }
+
+        let ids: Vec<_> = processor.suggested_edits().iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["ok"]);
+        let history = store.get_history().await;
+        let pushed = history
+            .iter()
+            .filter(|m| {
+                matches!(m, crate::logs::LogMsg::Entry(e)
+                    if e.entry_type == NormalizedEntryType::SuggestedEdit)
+            })
+            .count();
+        assert_eq!(pushed, 1);
+    }
+
+    /// Entry types recorded for a session with thinking, a tool call, an edit and a reply.
+    async fn entry_types(verbosity: Verbosity) -> Vec<NormalizedEntryType> {
+        let store = Arc::new(MsgStore::new());
+        let mut processor = ClaudeLogProcessor::new(store.clone()).with_verbosity(verbosity);
+
+        let stream = [
+            serde_json::json!({"type": "system", "subtype": "init", "session_id": "s-1"}),
+            serde_json::json!({"type": "assistant", "message": {"content": [
+                {"type": "thinking", "thinking": "Check the opening first."},
+                {"type": "tool_use", "id": "t-1", "name": "Read", "input": {"path": "doc.md"}},
+                {"type": "tool_use", "id": "e-1", "name": "suggest_edit",
+                 "input": {"original_text": "teh", "suggested_text": "the"}},
+                {"type": "text", "text": "Fixed a typo."},
+            ]}}),
+            serde_json::json!({"type": "result", "subtype": "success", "is_error": false}),
+        ];
+        for message in stream {
+            processor.process_chunk(&format!("{message}\n")).await;
+        }
+        assert_eq!(processor.suggested_edits().len(), 1);
+
+        let history = store.get_history().await;
+        history
+            .into_iter()
+            .filter_map(|m| match m {
+                crate::logs::LogMsg::Entry(entry) => Some(entry.entry_type),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_verbosity_controls_recorded_entries() {
+        use NormalizedEntryType::{
+            AssistantMessage, SuggestedEdit, SystemMessage, ThinkingMessage, ToolCall,
+        };
+
+        let minimal = entry_types(Verbosity::Minimal).await;
+        let normal = entry_types(Verbosity::Normal).await;
+        let verbose = entry_types(Verbosity::Verbose).await;
+
+        assert_eq!(minimal, [SuggestedEdit, AssistantMessage]);
+        assert_eq!(normal, [ThinkingMessage, ToolCall, SuggestedEdit, AssistantMessage]);
+        assert_eq!(
+            verbose,
+            [
+                SystemMessage,
+                ThinkingMessage,
+                ToolCall,
+                SuggestedEdit,
+                AssistantMessage,
+                SystemMessage
+            ]
+        );
+        assert!(minimal.len() < verbose.len());
+    }
+
+    #[tokio::test]
+    async fn test_raw_output_is_dropped_only_when_minimal() {
+        for (verbosity, recorded) in
+            [(Verbosity::Minimal, 0), (Verbosity::Normal, 1), (Verbosity::Verbose, 1)]
+        {
+            let store = Arc::new(MsgStore::new());
+            let mut processor = ClaudeLogProcessor::new(store.clone()).with_verbosity(verbosity);
+            processor.process_chunk("npm warn deprecated package\n").await;
+            assert_eq!(store.get_history().await.len(), recorded, "{verbosity:?}");
+        }
+    }
 }
@@ -0,0 +1,85 @@
+//! Markdown simplification for assistant output shown in comments.
+
+/// Strip block-level markdown that clutters the comment UI.
+///
+/// Removes heading markers, code fence lines (keeping the code itself),
+/// blockquote markers, horizontal rules and `**`/`__` emphasis. Inline code
+/// and links are left intact.
+#[must_use]
+pub fn simplify_markdown(text: &str) -> String {
+    let mut lines = Vec::new();
+    let mut in_fence = false;
+
+    for line in text.lines() {
+        let trimmed = line.trim_start();
+        if trimmed.starts_with("```") || trimmed.starts_with("~~~") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            lines.push(line.to_owned());
+            continue;
+        }
+        if is_horizontal_rule(trimmed) {
+            continue;
+        }
+
+        let line = strip_heading(trimmed).unwrap_or(line);
+        let line = line.strip_prefix("> ").or_else(|| line.strip_prefix('>')).unwrap_or(line);
+        lines.push(strip_emphasis(line));
+    }
+
+    lines.join("\n")
+}
+
+/// Returns the heading text if `line` is an ATX heading (`# ...` to `###### ...`).
+fn strip_heading(line: &str) -> Option<&str> {
+    let level = line.bytes().take_while(|&b| b == b'#').count();
+    if !(1..=6).contains(&level) {
+        return None;
+    }
+    line[level..].strip_prefix(' ').map(str::trim_end)
+}
+
+/// Whether `line` is a thematic break such as `---`, `***` or `___`.
+fn is_horizontal_rule(line: &str) -> bool {
+    let marks: Vec<char> = line.chars().filter(|c| !c.is_whitespace()).collect();
+    marks.len() >= 3 && ['-', '*', '_'].iter().any(|&m| marks.iter().all(|&c| c == m))
+}
+
+/// Remove `**` and `__` outside inline code spans.
+fn strip_emphasis(line: &str) -> String {
+    line.split('`')
+        .enumerate()
+        .map(|(i, part)| {
+            // Odd segments are inside backticks
+            if i % 2 == 1 { part.to_owned() } else { part.replace("**", "").replace("__", "") }
+        })
+        .collect::<Vec<_>>()
+        .join("`")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_strips_headers_and_fences() {
+        let input = "## Summary\n\nLooks **good**.\n\n```rust\n# not a heading\nlet x = 1;\n```\n---\n> Quoted";
+        assert_eq!(
+            simplify_markdown(input),
+            "Summary\n\nLooks good.\n\n# not a heading\nlet x = 1;\nQuoted"
+        );
+    }
+
+    #[test]
+    fn test_preserves_inline_code_and_links() {
+        let input = "Use `__init__` or `**kwargs`, see [the docs](https://example.com/a_b).";
+        assert_eq!(simplify_markdown(input), input);
+    }
+
+    #[test]
+    fn test_leaves_plain_text_unchanged() {
+        assert_eq!(simplify_markdown("#hashtag and 3 - 2"), "#hashtag and 3 - 2");
+    }
+}
@@ -4,30 +4,42 @@
 //! for AI-powered document feedback and editing suggestions.
 
 mod log_processor;
+mod markdown;
 mod protocol;
+mod toolchain;
 
 use async_trait::async_trait;
-use command_group::AsyncCommandGroup;
+use command_group::{AsyncCommandGroup, AsyncGroupChild};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::process::Stdio;
 use std::sync::Arc;
 use tokio::process::Command;
-use tracing::{debug, info};
+use tracing::{debug, info, warn};
 
 use crate::approvals::ExecutorApprovalService;
 use crate::env::ExecutionEnv;
 use crate::error::ExecutorError;
 use crate::logs::MsgStore;
-use crate::types::{AppendPrompt, AvailabilityInfo, SetupAction};
+use crate::types::{AppendPrompt, AvailabilityInfo, Capabilities, SetupAction, SetupActionKind};
 
 use super::{InterruptSender, SpawnedChild, StandardDocumentExecutor};
 
-pub use log_processor::ClaudeLogProcessor;
+pub use log_processor::{ClaudeLogProcessor, TOKEN_PROGRESS_INTERVAL, TokenUsage, Verbosity};
+pub use toolchain::{MIN_NODE_MAJOR, ToolchainReport};
+
+/// Program that downloads and runs Claude Code.
+const LAUNCHER: &str = "npx";
+
+/// npm package of Claude Code.
+const PACKAGE: &str = "@anthropic-ai/claude-code";
 
 /// Version of Claude Code to use.
 const CLAUDE_CODE_VERSION: &str = "2.1.7";
 
+/// Claude Code documentation, including installation and login.
+const DOCS_URL: &str = "https://docs.anthropic.com/en/docs/claude-code";
+
 /// Claude Code executor configuration.
 #[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq)]
 pub struct ClaudeCode {
@@ -62,6 +74,23 @@ pub struct ClaudeCode {
     /// Custom system prompt for document feedback.
     #[serde(default)]
     pub system_prompt: Option<String>,
+
+    /// Simplify markdown in assistant responses before display.
+    #[serde(default)]
+    pub strip_markdown: Option<bool>,
+
+    /// Detect the language of the final response and report it with the feedback.
+    #[serde(default)]
+    pub detect_language: Option<bool>,
+
+    /// How much of the session's output is recorded; `normal` when unset.
+    #[serde(default)]
+    pub verbosity: Option<Verbosity>,
+
+    /// Send the prompt through stdin instead of as an argument to `-p`,
+    /// keeping it out of process listings and argument length limits.
+    #[serde(default)]
+    pub prompt_via_stdin: Option<bool>,
 }
 
 impl ClaudeCode {
@@ -92,21 +121,35 @@ impl ClaudeCode {
         self
     }
 
+    /// Send the prompt through stdin rather than as an argument.
+    #[must_use]
+    pub const fn with_prompt_via_stdin(mut self) -> Self {
+        self.prompt_via_stdin = Some(true);
+        self
+    }
+
     /// Build the command to spawn Claude Code.
     fn build_command(&self, prompt: &str, session_id: Option<&str>) -> Command {
-        let mut cmd = Command::new("npx");
+        let mut cmd = Command::new(LAUNCHER);
+
+        // CI=true disables interactive TTY requirements
+        cmd.env("CI", "true");
 
         // Base arguments
         cmd.arg("-y");
-        cmd.arg(format!("@anthropic-ai/claude-code@{}", CLAUDE_CODE_VERSION));
+        cmd.arg(format!("{PACKAGE}@{CLAUDE_CODE_VERSION}"));
 
         // Output format for structured communication
         cmd.arg("--output-format=stream-json");
         cmd.arg("--verbose"); // Required when using -p with stream-json
         cmd.arg("--include-partial-messages"); // Stream chunks as they arrive
 
-        // Permission mode - bypass for non-interactive use
-        cmd.arg("--permission-mode=bypassPermissions");
+        // Permission mode - plan mode is read-only, otherwise bypass for non-interactive use
+        if self.plan == Some(true) {
+            cmd.arg("--permission-mode=plan");
+        } else {
+            cmd.arg("--permission-mode=bypassPermissions");
+        }
 
         // Model selection
         if let Some(model) = &self.model {
@@ -123,9 +166,12 @@ impl ClaudeCode {
             cmd.arg(format!("--system-prompt={system}"));
         }
 
-        // Pass prompt as command line argument
+        // Pass prompt as command line argument, unless it is sent through stdin,
+        // where -p reads it when no argument is given
         cmd.arg("-p");
-        cmd.arg(prompt);
+        if self.prompt_via_stdin != Some(true) {
+            cmd.arg(prompt);
+        }
 
         debug!(
             prompt_len = prompt.len(),
@@ -136,6 +182,24 @@ impl ClaudeCode {
         cmd
     }
 
+    /// Write the prompt to the child's stdin in stdin mode, then close it.
+    ///
+    /// The write runs in the background so a prompt larger than the pipe
+    /// buffer does not hold up the caller while the child starts reading.
+    fn deliver_prompt(&self, child: &mut AsyncGroupChild, prompt: String) {
+        if self.prompt_via_stdin != Some(true) {
+            return;
+        }
+        let Some(stdin) = child.inner().stdin.take() else {
+            return;
+        };
+        tokio::spawn(async move {
+            if let Err(e) = send_prompt(stdin, prompt).await {
+                warn!(error = %e, "Failed to write prompt to Claude Code stdin");
+            }
+        });
+    }
+
     /// Get the default system prompt for document feedback.
     #[must_use]
     pub fn document_feedback_system_prompt() -> String {
@@ -155,6 +219,43 @@ When suggesting edits, use the suggest_edit tool with:
 If the instruction is unclear, ask for clarification rather than guessing."#
             .to_owned()
     }
+
+    /// Get the system prompt for answering questions about a document, with no edits.
+    ///
+    /// Unlike [`ClaudeCode::document_feedback_system_prompt`], it never mentions
+    /// the edit tool, so questions get answers rather than rewrites.
+    #[must_use]
+    pub fn document_review_system_prompt() -> String {
+        r"You are a document review assistant integrated into Glow, a document editor.
+Your role is to answer the user's questions about their writing.
+
+When answering:
+1. Be concise and direct
+2. Focus on the specific question asked
+3. Quote the relevant passages of the document to support your answer
+
+Answer in prose only; the user has not asked for changes to the document.
+
+If the question is unclear, ask for clarification rather than guessing."
+            .to_owned()
+    }
+
+    /// Get the system prompt for plan-mode feedback, which analyzes without editing.
+    #[must_use]
+    pub fn plan_feedback_system_prompt() -> String {
+        r"You are a document review assistant integrated into Glow, a document editor.
+Your role is to analyze the user's writing based on their instructions, without changing it.
+
+When providing feedback:
+1. Be concise and actionable
+2. Focus on the specific instruction given
+3. Describe what could be improved and outline a plan for revising it
+
+Do not call the suggest_edit tool or propose replacement text; the user will make any edits.
+
+If the instruction is unclear, ask for clarification rather than guessing."
+            .to_owned()
+    }
 }
 
 #[async_trait]
@@ -186,7 +287,9 @@ impl StandardDocumentExecutor for ClaudeCode {
 
         env.apply_to_command(&mut cmd);
 
-        let child = cmd.group_spawn().map_err(|e| ExecutorError::SpawnFailed(e.to_string()))?;
+        let mut child =
+            cmd.group_spawn().map_err(|e| ExecutorError::from_spawn_error(LAUNCHER, &e))?;
+        self.deliver_prompt(&mut child, final_prompt);
 
         // Create interrupt channel
         let (interrupt_tx, _interrupt_rx): (InterruptSender, _) = tokio::sync::mpsc::channel(1);
@@ -218,7 +321,9 @@ impl StandardDocumentExecutor for ClaudeCode {
 
         env.apply_to_command(&mut cmd);
 
-        let child = cmd.group_spawn().map_err(|e| ExecutorError::SpawnFailed(e.to_string()))?;
+        let mut child =
+            cmd.group_spawn().map_err(|e| ExecutorError::from_spawn_error(LAUNCHER, &e))?;
+        self.deliver_prompt(&mut child, final_prompt);
 
         let (interrupt_tx, _interrupt_rx): (InterruptSender, _) = tokio::sync::mpsc::channel(1);
 
@@ -235,11 +340,47 @@ impl StandardDocumentExecutor for ClaudeCode {
         dirs::home_dir().map(|p| p.join(".claude.json"))
     }
 
-    async fn get_setup_helper_action(&self) -> Result<SetupAction, ExecutorError> {
-        Ok(SetupAction {
-            description: "Install and authenticate Claude Code CLI".to_owned(),
-            command: Some("npx -y @anthropic-ai/claude-code --help".to_owned()),
-            url: Some("https://docs.anthropic.com/en/docs/claude-code".to_owned()),
+    fn capabilities(&self) -> Capabilities {
+        Capabilities {
+            follow_ups: true,
+            // Runs bypass permissions, so tool use never waits on an approval
+            approvals: false,
+            plan_mode: true,
+            suggested_edits: true,
+        }
+    }
+
+    async fn get_setup_helper_action(
+        &self,
+        availability: &AvailabilityInfo,
+    ) -> Result<SetupAction, ExecutorError> {
+        let package = format!("{PACKAGE}@{CLAUDE_CODE_VERSION}");
+        let docs = Some(DOCS_URL.to_owned());
+        Ok(match availability {
+            AvailabilityInfo::NotFound => SetupAction {
+                kind: SetupActionKind::Install,
+                description: "Install Node.js and the Claude Code CLI".to_owned(),
+                command: Some(format!("npm install -g {package}")),
+                url: docs,
+            },
+            AvailabilityInfo::InstallationFound => SetupAction {
+                kind: SetupActionKind::Login,
+                description: "Log in to Claude Code".to_owned(),
+                command: Some(format!("{LAUNCHER} -y {package} /login")),
+                url: docs,
+            },
+            AvailabilityInfo::Unavailable { reason } => SetupAction {
+                kind: SetupActionKind::Troubleshoot,
+                description: format!("Claude Code cannot run: {reason}"),
+                command: None,
+                url: docs,
+            },
+            AvailabilityInfo::Available => SetupAction {
+                kind: SetupActionKind::Ready,
+                description: "Claude Code is ready".to_owned(),
+                command: None,
+                url: None,
+            },
         })
     }
 
@@ -254,13 +395,24 @@ impl StandardDocumentExecutor for ClaudeCode {
         }
 
         // Check if npx is available
-        match std::process::Command::new("npx").arg("--version").output() {
+        match std::process::Command::new(LAUNCHER).arg("--version").output() {
             Ok(output) if output.status.success() => AvailabilityInfo::InstallationFound,
             _ => AvailabilityInfo::NotFound,
         }
     }
 }
 
+/// Write `prompt` as plain text and close the writer.
+async fn send_prompt<W>(mut writer: W, prompt: String) -> std::io::Result<()>
+where
+    W: tokio::io::AsyncWrite + Unpin,
+{
+    use tokio::io::AsyncWriteExt;
+
+    writer.write_all(prompt.as_bytes()).await?;
+    writer.shutdown().await
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -273,6 +425,26 @@ mod tests {
         assert_eq!(executor.plan, Some(true));
     }
 
+    #[tokio::test]
+    async fn test_missing_launcher_is_not_available() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let mut env = ExecutionEnv::new();
+        // An empty PATH so the launcher cannot be found
+        env.insert("PATH", dir.path().to_string_lossy());
+        let executor = ClaudeCode::new();
+
+        let spawned = executor.spawn(dir.path(), "Review", &env).await;
+        assert!(matches!(spawned, Err(ExecutorError::NotAvailable(ref m)) if m.contains(LAUNCHER)));
+        let follow_up = executor.spawn_follow_up(dir.path(), "Again", "session-1", &env).await;
+        assert!(matches!(follow_up, Err(ExecutorError::NotAvailable(_))));
+
+        let denied = std::io::Error::from(std::io::ErrorKind::PermissionDenied);
+        assert!(matches!(
+            ExecutorError::from_spawn_error(LAUNCHER, &denied),
+            ExecutorError::SpawnFailed(_)
+        ));
+    }
+
     #[test]
     fn test_default_system_prompt() {
         let prompt = ClaudeCode::document_feedback_system_prompt();
@@ -280,6 +452,115 @@ mod tests {
         assert!(prompt.contains("suggest_edit"));
     }
 
+    #[test]
+    fn test_review_system_prompt_omits_edit_tool() {
+        let prompt = ClaudeCode::document_review_system_prompt();
+        assert!(prompt.contains("answer the user's questions"));
+        assert!(!prompt.contains("suggest_edit"));
+        assert!(!prompt.contains("suggested_text"));
+    }
+
+    #[test]
+    fn test_plan_mode_command() {
+        let args = |executor: &ClaudeCode| -> Vec<String> {
+            let cmd = executor.build_command("Review", None);
+            cmd.as_std().get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+        };
+
+        let plan = ClaudeCode::new().with_plan_mode();
+        assert!(args(&plan).contains(&"--permission-mode=plan".to_owned()));
+        assert!(
+            args(&ClaudeCode::new()).contains(&"--permission-mode=bypassPermissions".to_owned())
+        );
+
+        let prompt = ClaudeCode::plan_feedback_system_prompt();
+        assert!(prompt.contains("Do not call the suggest_edit tool"));
+    }
+
+    #[test]
+    fn test_stdin_mode_command() {
+        let args = |executor: &ClaudeCode| -> Vec<String> {
+            let cmd = executor.build_command("Review", None);
+            cmd.as_std().get_args().map(|a| a.to_string_lossy().into_owned()).collect()
+        };
+
+        let stdin_mode = args(&ClaudeCode::new().with_prompt_via_stdin());
+        assert_eq!(stdin_mode.last().map(String::as_str), Some("-p"));
+        assert!(!stdin_mode.contains(&"Review".to_owned()));
+        assert!(!stdin_mode.iter().any(|a| a.starts_with("--input-format")));
+
+        let default = args(&ClaudeCode::new());
+        assert!(default.ends_with(&["-p".to_owned(), "Review".to_owned()]));
+        assert_eq!(default.len(), stdin_mode.len() + 1);
+    }
+
+    #[tokio::test]
+    async fn test_stdin_mode_writes_prompt_to_stdin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        // A stand-in launcher that saves what it reads from stdin
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let launcher = dir.path().join(LAUNCHER);
+        std::fs::write(&launcher, "#!/bin/sh\ncat > stdin.txt\n").expect("should write launcher");
+        std::fs::set_permissions(&launcher, std::fs::Permissions::from_mode(0o755))
+            .expect("should make launcher executable");
+        let mut env = ExecutionEnv::new();
+        env.insert("PATH", format!("{}:/usr/bin:/bin", dir.path().display()));
+
+        let stdin_mode = ClaudeCode::new().with_prompt_via_stdin();
+        let mut spawned =
+            stdin_mode.spawn(dir.path(), "Review this", &env).await.expect("should spawn");
+        let status = spawned.child.wait().await.expect("should exit");
+        assert!(status.success());
+
+        let written = std::fs::read_to_string(dir.path().join("stdin.txt")).expect("should read");
+        assert_eq!(written, stdin_mode.append_prompt.apply("Review this"));
+    }
+
+    #[test]
+    fn test_command_runs_non_interactively() {
+        for session_id in [None, Some("claude-1")] {
+            let cmd = ClaudeCode::new().build_command("Review", session_id);
+            let ci = cmd.as_std().get_envs().find(|(key, _)| *key == "CI").and_then(|(_, v)| v);
+            assert_eq!(ci, Some(std::ffi::OsStr::new("true")));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_setup_action_matches_availability() {
+        let executor = ClaudeCode::new();
+
+        let install = executor
+            .get_setup_helper_action(&AvailabilityInfo::NotFound)
+            .await
+            .expect("should have an action");
+        assert_eq!(install.kind, SetupActionKind::Install);
+        assert!(install.command.is_some_and(|c| c.starts_with("npm install")));
+
+        let login = executor
+            .get_setup_helper_action(&AvailabilityInfo::InstallationFound)
+            .await
+            .expect("should have an action");
+        assert_eq!(login.kind, SetupActionKind::Login);
+        assert!(login.command.is_some_and(|c| c.ends_with("/login")));
+
+        let ready = executor
+            .get_setup_helper_action(&AvailabilityInfo::Available)
+            .await
+            .expect("should have an action");
+        assert_eq!(ready.kind, SetupActionKind::Ready);
+        assert_eq!(ready.command, None);
+    }
+
+    #[test]
+    fn test_capabilities() {
+        let capabilities = ClaudeCode::new().capabilities();
+        assert!(capabilities.follow_ups);
+        assert!(capabilities.suggested_edits);
+        assert!(capabilities.plan_mode);
+        assert!(!capabilities.approvals);
+    }
+
     #[test]
     fn test_default_mcp_config_path() {
         let executor = ClaudeCode::new();
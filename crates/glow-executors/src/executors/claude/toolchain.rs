@@ -0,0 +1,162 @@
+//! Diagnostics for the Node toolchain Claude Code runs on.
+
+use serde::{Deserialize, Serialize};
+use ts_rs::TS;
+
+use super::{CLAUDE_CODE_VERSION, LAUNCHER, PACKAGE};
+
+/// Oldest Node.js major version Claude Code runs on.
+pub const MIN_NODE_MAJOR: u32 = 18;
+
+/// What a probe of the Node toolchain found, with a fix for each problem.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+pub struct ToolchainReport {
+    /// Output of `node --version`, or `None` if Node.js did not run.
+    pub node_version: Option<String>,
+    /// Output of `npm --version`, or `None` if npm did not run.
+    pub npm_version: Option<String>,
+    /// Output of `npx --version`, or `None` if npx did not run.
+    pub npx_version: Option<String>,
+    /// Whether the pinned Claude Code package was found in the npm registry.
+    pub package_resolvable: bool,
+    /// Problems found, each saying what to do about it. Empty when healthy.
+    pub problems: Vec<String>,
+}
+
+impl ToolchainReport {
+    /// Probes the toolchain by running `node`, `npm` and `npx`.
+    ///
+    /// Resolving the package queries the npm registry, so this can take a
+    /// few seconds; callers should cache the result.
+    #[must_use]
+    pub fn probe() -> Self {
+        Self::probe_with(|program, args| {
+            let output = std::process::Command::new(program).args(args).output().ok()?;
+            output
+                .status
+                .success()
+                .then(|| String::from_utf8_lossy(&output.stdout).trim().to_owned())
+        })
+    }
+
+    /// Probes the toolchain with `run`, which returns a command's trimmed
+    /// output if it ran successfully.
+    #[must_use]
+    pub fn probe_with(run: impl Fn(&str, &[&str]) -> Option<String>) -> Self {
+        let mut report = Self {
+            node_version: run("node", &["--version"]),
+            npm_version: run("npm", &["--version"]),
+            npx_version: run(LAUNCHER, &["--version"]),
+            package_resolvable: false,
+            problems: Vec::new(),
+        };
+        let spec = format!("{PACKAGE}@{CLAUDE_CODE_VERSION}");
+        report.package_resolvable = report.npm_version.is_some()
+            && run("npm", &["view", &spec, "version"]).as_deref() == Some(CLAUDE_CODE_VERSION);
+
+        let problems = &mut report.problems;
+        match report.node_version.as_deref().map(node_major) {
+            None => problems.push(format!(
+                "Node.js was not found on PATH; install Node.js {MIN_NODE_MAJOR} or later"
+            )),
+            Some(Some(major)) if major < MIN_NODE_MAJOR => problems.push(format!(
+                "Node.js {major} is too old; upgrade to Node.js {MIN_NODE_MAJOR} or later"
+            )),
+            Some(_) => {}
+        }
+        if report.npm_version.is_none() {
+            problems.push("npm was not found on PATH; reinstall Node.js with npm".to_owned());
+        } else if !report.package_resolvable {
+            problems.push(format!(
+                "{spec} could not be resolved; check network access and the npm registry \
+                 configuration"
+            ));
+        }
+        if report.npx_version.is_none() {
+            problems.push(format!("{LAUNCHER} was not found on PATH; install npm 7 or later"));
+        }
+        report
+    }
+
+    /// Whether the probe found no problems.
+    #[must_use]
+    pub const fn is_healthy(&self) -> bool {
+        self.problems.is_empty()
+    }
+}
+
+/// Major version of a `node --version` string such as `v20.11.1`.
+fn node_major(version: &str) -> Option<u32> {
+    version.trim_start_matches('v').split('.').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// A runner answering with `node` and `npm` as given and a working `npx`
+    /// and registry.
+    fn runner(
+        node: Option<&'static str>,
+        npm: Option<&'static str>,
+        registry: bool,
+    ) -> impl Fn(&str, &[&str]) -> Option<String> {
+        move |program, args| {
+            let output = match (program, args.first().copied()) {
+                ("node", _) => node,
+                ("npm", Some("view")) => registry.then_some(CLAUDE_CODE_VERSION),
+                ("npm", _) => npm,
+                (LAUNCHER, _) => Some("10.2.4"),
+                _ => None,
+            };
+            output.map(str::to_owned)
+        }
+    }
+
+    #[test]
+    fn test_healthy_toolchain() {
+        let report = ToolchainReport::probe_with(runner(Some("v20.11.1"), Some("10.2.4"), true));
+
+        assert!(report.is_healthy(), "unexpected problems: {:?}", report.problems);
+        assert_eq!(report.node_version.as_deref(), Some("v20.11.1"));
+        assert_eq!(report.npm_version.as_deref(), Some("10.2.4"));
+        assert!(report.package_resolvable);
+    }
+
+    #[test]
+    fn test_missing_node() {
+        let report = ToolchainReport::probe_with(runner(None, Some("10.2.4"), true));
+
+        assert_eq!(report.node_version, None);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("Node.js was not found"));
+    }
+
+    #[test]
+    fn test_outdated_node() {
+        let report = ToolchainReport::probe_with(runner(Some("v16.20.2"), Some("8.19.4"), true));
+
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("Node.js 16 is too old"));
+    }
+
+    #[test]
+    fn test_missing_npm_skips_package_resolution() {
+        let report = ToolchainReport::probe_with(runner(Some("v20.11.1"), None, true));
+
+        assert!(!report.package_resolvable);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].starts_with("npm was not found"));
+    }
+
+    #[test]
+    fn test_unresolvable_package() {
+        let report = ToolchainReport::probe_with(runner(Some("v20.11.1"), Some("10.2.4"), false));
+
+        assert!(!report.package_resolvable);
+        assert_eq!(report.problems.len(), 1);
+        assert!(report.problems[0].contains("could not be resolved"));
+    }
+}
@@ -0,0 +1,152 @@
+//! Executor backed by an arbitrary external command.
+//!
+//! The prompt is written to the command's stdin and every line it prints is
+//! streamed as an assistant message, so any script can stand in for an AI
+//! backend. Registered through the [`ExecutorRegistry`](super::ExecutorRegistry).
+
+use async_trait::async_trait;
+use command_group::AsyncCommandGroup;
+use std::path::{Path, PathBuf};
+use std::process::Stdio;
+use std::sync::{Arc, Mutex, PoisonError};
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::process::{ChildStdout, Command};
+use tokio::sync::mpsc;
+use tracing::warn;
+
+use crate::env::ExecutionEnv;
+use crate::error::ExecutorError;
+use crate::logs::{MsgStore, NormalizedEntry};
+
+use super::{SpawnedChild, StandardDocumentExecutor};
+
+/// Output of a spawned command not yet being read, with its exit signal.
+type PendingOutput = (ChildStdout, mpsc::Sender<Result<(), ExecutorError>>);
+
+/// Runs `program` with the prompt on stdin, streaming its stdout lines.
+///
+/// Each instance runs one process: [`StandardDocumentExecutor::normalize_logs`]
+/// reads the output of the last [`StandardDocumentExecutor::spawn`], and the
+/// spawned child's exit signal fires once all of it is in the message store.
+#[derive(Debug)]
+pub struct CommandExecutor {
+    program: PathBuf,
+    output: Mutex<Option<PendingOutput>>,
+}
+
+impl CommandExecutor {
+    /// Create an executor that runs `program`.
+    #[must_use]
+    pub fn new(program: impl Into<PathBuf>) -> Self {
+        Self { program: program.into(), output: Mutex::new(None) }
+    }
+
+    /// The command this executor runs.
+    #[must_use]
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+}
+
+#[async_trait]
+impl StandardDocumentExecutor for CommandExecutor {
+    async fn spawn(
+        &self,
+        current_dir: &Path,
+        prompt: &str,
+        env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        let mut cmd = Command::new(&self.program);
+        cmd.current_dir(current_dir)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::null())
+            .kill_on_drop(true);
+        env.apply_to_command(&mut cmd);
+
+        let program = self.program.display().to_string();
+        let mut child =
+            cmd.group_spawn().map_err(|e| ExecutorError::from_spawn_error(&program, &e))?;
+        if let Some(mut stdin) = child.inner().stdin.take() {
+            let prompt = prompt.to_owned();
+            tokio::spawn(async move {
+                if let Err(e) = stdin.write_all(prompt.as_bytes()).await {
+                    warn!(error = %e, "Failed to write prompt to executor command");
+                }
+            });
+        }
+
+        let (exit_tx, exit_rx) = mpsc::channel(1);
+        if let Some(stdout) = child.inner().stdout.take() {
+            *self.output.lock().unwrap_or_else(PoisonError::into_inner) = Some((stdout, exit_tx));
+        }
+        Ok(SpawnedChild { child, exit_signal: Some(exit_rx), interrupt_sender: None })
+    }
+
+    async fn spawn_follow_up(
+        &self,
+        _current_dir: &Path,
+        _prompt: &str,
+        session_id: &str,
+        _env: &ExecutionEnv,
+    ) -> Result<SpawnedChild, ExecutorError> {
+        Err(ExecutorError::SessionNotFound(session_id.to_owned()))
+    }
+
+    fn normalize_logs(&self, msg_store: Arc<MsgStore>, _worktree_path: &Path) {
+        let Some((stdout, exit_tx)) =
+            self.output.lock().unwrap_or_else(PoisonError::into_inner).take()
+        else {
+            return;
+        };
+        tokio::spawn(async move {
+            let _ = exit_tx.send(stream_lines(stdout, &msg_store).await).await;
+        });
+    }
+
+    fn default_mcp_config_path(&self) -> Option<PathBuf> {
+        None
+    }
+}
+
+/// Push each line of `stdout` to `msg_store` as an assistant message until it ends.
+async fn stream_lines(stdout: ChildStdout, msg_store: &MsgStore) -> Result<(), ExecutorError> {
+    let mut lines = BufReader::new(stdout).lines();
+    while let Some(line) = lines.next_line().await? {
+        msg_store.push_entry(NormalizedEntry::assistant_message(line)).await;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::env::DocumentContext;
+    use crate::logs::LogMsg;
+
+    #[tokio::test]
+    async fn test_command_output_streams_as_assistant_messages() {
+        let executor = CommandExecutor::new("cat");
+        let env = ExecutionEnv::from_document(DocumentContext::new("doc", String::new()));
+        let mut spawned = executor
+            .spawn(&std::env::temp_dir(), "Tighten\nthe intro", &env)
+            .await
+            .expect("cat should spawn");
+        let msg_store = Arc::new(MsgStore::new());
+        executor.normalize_logs(msg_store.clone(), &std::env::temp_dir());
+
+        let mut exit_signal = spawned.exit_signal.take().expect("should signal exit");
+        assert!(matches!(exit_signal.recv().await, Some(Ok(()))));
+        spawned.child.wait().await.expect("cat should exit");
+        let lines: Vec<String> = msg_store
+            .get_history()
+            .await
+            .into_iter()
+            .filter_map(|msg| match msg {
+                LogMsg::Entry(entry) => Some(entry.content),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(lines, ["Tighten", "the intro"]);
+    }
+}
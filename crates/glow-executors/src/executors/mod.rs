@@ -4,6 +4,8 @@
 //! the [`DocumentAgent`] enum for runtime polymorphism.
 
 pub mod claude;
+pub mod command;
+pub mod registry;
 
 use async_trait::async_trait;
 use command_group::AsyncGroupChild;
@@ -19,10 +21,12 @@ use crate::approvals::ExecutorApprovalService;
 use crate::env::ExecutionEnv;
 use crate::error::ExecutorError;
 use crate::logs::MsgStore;
-use crate::types::{AvailabilityInfo, SetupAction};
+use crate::types::{AvailabilityInfo, Capabilities, SetupAction};
 
 // Re-export executor implementations
 pub use claude::ClaudeCode;
+pub use command::CommandExecutor;
+pub use registry::{DynExecutor, ExecutorFactory, ExecutorRegistry};
 
 /// Signal to indicate executor has completed.
 pub type ExecutorExitSignal = mpsc::Receiver<Result<(), ExecutorError>>;
@@ -110,11 +114,22 @@ pub trait StandardDocumentExecutor {
 
     /// Get a setup helper action if the executor needs configuration.
     ///
-    /// Returns instructions to help the user set up the executor.
-    async fn get_setup_helper_action(&self) -> Result<SetupAction, ExecutorError> {
+    /// Returns the next step for the executor's current `availability`, such
+    /// as installing it when not found or logging in when installed.
+    async fn get_setup_helper_action(
+        &self,
+        _availability: &AvailabilityInfo,
+    ) -> Result<SetupAction, ExecutorError> {
         Err(ExecutorError::SetupHelperNotSupported)
     }
 
+    /// Optional features this executor supports.
+    ///
+    /// Defaults to none, so executors opt in to each one.
+    fn capabilities(&self) -> Capabilities {
+        Capabilities::default()
+    }
+
     /// Check if this executor is available on the system.
     ///
     /// Checks for installation, login status, API keys, etc.
@@ -154,6 +169,20 @@ pub enum DocumentAgent {
 }
 
 impl DocumentAgent {
+    /// Create an agent of the given type with its default configuration.
+    #[must_use]
+    pub fn from_base(base: BaseDocumentAgent) -> Self {
+        match base {
+            BaseDocumentAgent::ClaudeCode => Self::ClaudeCode(ClaudeCode::default()),
+        }
+    }
+
+    /// List every supported base agent type.
+    #[must_use]
+    pub fn all_base_agents() -> Vec<BaseDocumentAgent> {
+        Self::VARIANTS.iter().filter_map(|name| name.parse().ok()).collect()
+    }
+
     /// Get the base agent type.
     #[must_use]
     pub fn base_agent(&self) -> BaseDocumentAgent {
@@ -188,6 +217,15 @@ mod tests {
         assert_eq!(agent.base_agent(), BaseDocumentAgent::ClaudeCode);
     }
 
+    #[test]
+    fn test_all_base_agents() {
+        let agents = DocumentAgent::all_base_agents();
+        assert_eq!(agents.len(), DocumentAgent::VARIANTS.len());
+        for agent in agents {
+            assert_eq!(DocumentAgent::from_base(agent).base_agent(), agent);
+        }
+    }
+
     #[test]
     fn test_base_agent_from_string() {
         let agent: BaseDocumentAgent = "CLAUDE_CODE".parse().unwrap();
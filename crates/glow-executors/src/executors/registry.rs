@@ -0,0 +1,122 @@
+//! Runtime registry for executors defined outside this crate.
+//!
+//! [`DocumentAgent`](super::DocumentAgent) covers the built-in executors with
+//! static dispatch; the registry lets downstream crates plug in their own
+//! [`StandardDocumentExecutor`] implementations by name.
+
+use std::collections::HashMap;
+
+use super::StandardDocumentExecutor;
+
+/// A boxed executor usable across threads.
+pub type DynExecutor = Box<dyn StandardDocumentExecutor + Send + Sync>;
+
+/// Factory that builds a fresh executor instance.
+pub type ExecutorFactory = Box<dyn Fn() -> DynExecutor + Send + Sync>;
+
+/// Maps executor names to factories for custom executors.
+///
+/// Names are matched case-insensitively.
+#[derive(Default)]
+pub struct ExecutorRegistry {
+    factories: HashMap<String, ExecutorFactory>,
+}
+
+impl ExecutorRegistry {
+    /// Create an empty registry.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Register a factory under `name`, replacing any previous registration.
+    pub fn register<F>(&mut self, name: impl Into<String>, factory: F)
+    where
+        F: Fn() -> DynExecutor + Send + Sync + 'static,
+    {
+        self.factories.insert(name.into().to_lowercase(), Box::new(factory));
+    }
+
+    /// Build a new instance of the executor registered under `name`.
+    #[must_use]
+    pub fn resolve(&self, name: &str) -> Option<DynExecutor> {
+        self.factories.get(&name.to_lowercase()).map(|factory| factory())
+    }
+
+    /// Check whether an executor is registered under `name`.
+    #[must_use]
+    pub fn contains(&self, name: &str) -> bool {
+        self.factories.contains_key(&name.to_lowercase())
+    }
+
+    /// Names of all registered executors.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.factories.keys().map(String::as_str)
+    }
+}
+
+impl std::fmt::Debug for ExecutorRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ExecutorRegistry").field("names", &self.factories.keys()).finish()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::{Path, PathBuf};
+    use std::sync::Arc;
+
+    use async_trait::async_trait;
+
+    use super::*;
+    use crate::env::ExecutionEnv;
+    use crate::error::ExecutorError;
+    use crate::executors::SpawnedChild;
+    use crate::logs::MsgStore;
+
+    struct EchoExecutor;
+
+    #[async_trait]
+    impl StandardDocumentExecutor for EchoExecutor {
+        async fn spawn(
+            &self,
+            _current_dir: &Path,
+            _prompt: &str,
+            _env: &ExecutionEnv,
+        ) -> Result<SpawnedChild, ExecutorError> {
+            Err(ExecutorError::NotAvailable("echo".to_owned()))
+        }
+
+        async fn spawn_follow_up(
+            &self,
+            _current_dir: &Path,
+            _prompt: &str,
+            _session_id: &str,
+            _env: &ExecutionEnv,
+        ) -> Result<SpawnedChild, ExecutorError> {
+            Err(ExecutorError::NotAvailable("echo".to_owned()))
+        }
+
+        fn normalize_logs(&self, _msg_store: Arc<MsgStore>, _worktree_path: &Path) {}
+
+        fn default_mcp_config_path(&self) -> Option<PathBuf> {
+            Some(PathBuf::from("/nonexistent/echo.json"))
+        }
+    }
+
+    #[test]
+    fn test_register_and_resolve_custom_executor() {
+        let mut registry = ExecutorRegistry::new();
+        registry.register("Echo", || Box::new(EchoExecutor));
+
+        assert!(registry.contains("echo"));
+        assert_eq!(registry.names().collect::<Vec<_>>(), vec!["echo"]);
+
+        let executor = registry.resolve("ECHO").expect("should resolve by name");
+        assert_eq!(
+            executor.default_mcp_config_path(),
+            Some(PathBuf::from("/nonexistent/echo.json"))
+        );
+        assert!(registry.resolve("unknown").is_none());
+    }
+}
@@ -0,0 +1,62 @@
+//! Language detection for executor responses.
+
+/// Fewest letters of prose needed before a language is reported.
+pub const MIN_LANGUAGE_LETTERS: usize = 20;
+
+/// Detects the natural language of `text`, as an ISO 639-3 code such as `"eng"`.
+///
+/// Code blocks and inline code are ignored. Returns `None` when too little
+/// prose remains or the detection is not reliable.
+#[must_use]
+pub fn detect_language(text: &str) -> Option<String> {
+    let prose = strip_code(text);
+    if prose.chars().filter(|c| c.is_alphabetic()).count() < MIN_LANGUAGE_LETTERS {
+        return None;
+    }
+    let info = whatlang::detect(&prose)?;
+    info.is_reliable().then(|| info.lang().code().to_owned())
+}
+
+/// Removes fenced code blocks and inline code spans.
+fn strip_code(text: &str) -> String {
+    let mut prose = String::with_capacity(text.len());
+    let mut in_fence = false;
+    for line in text.lines() {
+        if line.trim_start().starts_with("```") {
+            in_fence = !in_fence;
+            continue;
+        }
+        if in_fence {
+            continue;
+        }
+        // Odd-numbered segments between backticks are inline code
+        for (i, segment) in line.split('`').enumerate() {
+            if i % 2 == 0 {
+                prose.push_str(segment);
+            }
+        }
+        prose.push('\n');
+    }
+    prose
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let english = "This paragraph reads well, but the second sentence repeats the \
+                       point made in the first one. Consider merging them.";
+        assert_eq!(detect_language(english).as_deref(), Some("eng"));
+
+        let spanish = "Este párrafo se lee bien, pero la segunda oración repite la idea \
+                       de la primera. Considera unirlas en una sola.";
+        assert_eq!(detect_language(spanish).as_deref(), Some("spa"));
+
+        assert_eq!(detect_language("Looks good!"), None);
+        let code =
+            "```rust\nfn main() {\n    println!(\"hello world from the code block\");\n}\n```";
+        assert_eq!(detect_language(code), None);
+    }
+}
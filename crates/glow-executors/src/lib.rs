@@ -8,7 +8,8 @@
 //! The system uses a trait-based abstraction pattern:
 //!
 //! 1. **Core Executor Trait** - [`StandardDocumentExecutor`] defines the interface all AI integrations must implement
-//! 2. **Enum Dispatch** - Runtime polymorphism via [`DocumentAgent`] enum
+//! 2. **Enum Dispatch** - Runtime polymorphism via [`DocumentAgent`] enum, plus an
+//!    [`ExecutorRegistry`] for executors registered at runtime
 //! 3. **Profile System** - Configuration variants per executor
 //! 4. **Log Normalization** - Unified log format via [`NormalizedEntry`]
 //! 5. **Approval System** - Permission gating for tool use
@@ -30,15 +31,27 @@ pub mod approvals;
 pub mod env;
 pub mod error;
 pub mod executors;
+pub mod language;
+pub mod limits;
 pub mod logs;
+pub mod pricing;
 pub mod profile;
+pub mod prompt;
+mod replay;
 pub mod types;
 
 // Re-exports
 pub use approvals::{ApprovalStatus, ExecutorApprovalService, NoopApprovalService};
-pub use env::{DocumentContext, ExecutionEnv};
+pub use env::{DocumentContext, ExecutionEnv, REDACTED, is_secret_var};
 pub use error::ExecutorError;
-pub use executors::{BaseDocumentAgent, DocumentAgent, StandardDocumentExecutor};
-pub use logs::{LogMsg, MsgStore, NormalizedEntry, NormalizedEntryType};
-pub use profile::{ExecutorConfig, ExecutorConfigs, ExecutorProfileId};
+pub use executors::{BaseDocumentAgent, DocumentAgent, ExecutorRegistry, StandardDocumentExecutor};
+pub use language::detect_language;
+pub use limits::{CHARS_PER_TOKEN, FALLBACK_MODEL_LIMIT, ModelLimit, ModelLimits, estimate_tokens};
+pub use logs::{LogMsg, MsgStore, NormalizedEntry, NormalizedEntryType, thinking_preview};
+pub use pricing::{FALLBACK_MODEL_PRICE, ModelPrice, price_for};
+pub use profile::{
+    ExecutorConfig, ExecutorConfigs, ExecutorProfileId, FallbackChoice, FeedbackRoute,
+    FeedbackRouting,
+};
+pub use prompt::{ContextWindow, PromptTemplate, SELECTION_END, SELECTION_START};
 pub use types::*;
@@ -0,0 +1,166 @@
+//! Context window sizes of the models executors can run.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Token limits of a model.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelLimit {
+    /// Most tokens the model accepts as input, including the system prompt.
+    pub max_input_tokens: u32,
+    /// Most tokens the model can generate in one response.
+    pub max_output_tokens: u32,
+}
+
+impl ModelLimit {
+    /// Create a limit.
+    #[must_use]
+    pub const fn new(max_input_tokens: u32, max_output_tokens: u32) -> Self {
+        Self { max_input_tokens, max_output_tokens }
+    }
+}
+
+/// Characters per token assumed by [`estimate_tokens`]; typical for English prose.
+pub const CHARS_PER_TOKEN: usize = 4;
+
+/// Roughly estimates how many tokens `text` takes, rounding up.
+///
+/// Uses a fixed characters-per-token ratio, so the estimate is only meant
+/// for checking whether input comfortably fits a model's limits.
+#[must_use]
+pub fn estimate_tokens(text: &str) -> u32 {
+    let tokens = text.chars().count().div_ceil(CHARS_PER_TOKEN);
+    u32::try_from(tokens).unwrap_or(u32::MAX)
+}
+
+/// Limit assumed for models missing from the table; smaller than any known model.
+pub const FALLBACK_MODEL_LIMIT: ModelLimit = ModelLimit::new(100_000, 4_096);
+
+/// Built-in limits, keyed by model name or name prefix.
+const DEFAULT_MODEL_LIMITS: &[(&str, ModelLimit)] = &[
+    ("opus", ModelLimit::new(200_000, 32_000)),
+    ("sonnet", ModelLimit::new(200_000, 64_000)),
+    ("haiku", ModelLimit::new(200_000, 64_000)),
+    ("claude-opus-4", ModelLimit::new(200_000, 32_000)),
+    ("claude-sonnet-4", ModelLimit::new(200_000, 64_000)),
+    ("claude-haiku-4", ModelLimit::new(200_000, 64_000)),
+    ("claude-3-7-sonnet", ModelLimit::new(200_000, 64_000)),
+    ("claude-3-5-sonnet", ModelLimit::new(200_000, 8_192)),
+    ("claude-3-5-haiku", ModelLimit::new(200_000, 8_192)),
+];
+
+/// Table of model token limits.
+///
+/// Models are matched by exact name first, then by the longest key that is a
+/// prefix of the name, so `claude-sonnet-4-20250514` uses the
+/// `claude-sonnet-4` entry.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ModelLimits {
+    /// Limits by model name or name prefix.
+    #[serde(default)]
+    pub models: HashMap<String, ModelLimit>,
+    /// Limit for models not in the table.
+    #[serde(default = "fallback_model_limit")]
+    pub fallback: ModelLimit,
+}
+
+const fn fallback_model_limit() -> ModelLimit {
+    FALLBACK_MODEL_LIMIT
+}
+
+impl ModelLimits {
+    /// Create the built-in table of known models.
+    #[must_use]
+    pub fn with_defaults() -> Self {
+        let models = DEFAULT_MODEL_LIMITS
+            .iter()
+            .map(|(model, limit)| ((*model).to_owned(), *limit))
+            .collect();
+        Self { models, fallback: FALLBACK_MODEL_LIMIT }
+    }
+
+    /// Load limits from a JSON file on top of the built-in defaults.
+    ///
+    /// Entries in the file replace built-in entries of the same name, and a
+    /// `fallback` in the file replaces the default fallback.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read or parsed.
+    pub fn load_from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)?;
+        let overrides: Self = serde_json::from_str(&content)?;
+        let mut limits = Self::with_defaults();
+        limits.models.extend(overrides.models);
+        limits.fallback = overrides.fallback;
+        Ok(limits)
+    }
+
+    /// The known limit of `model`, if it is in the table.
+    #[must_use]
+    pub fn get(&self, model: &str) -> Option<ModelLimit> {
+        if let Some(limit) = self.models.get(model) {
+            return Some(*limit);
+        }
+        self.models
+            .iter()
+            .filter(|(key, _)| model.starts_with(key.as_str()))
+            .max_by_key(|(key, _)| key.len())
+            .map(|(_, limit)| *limit)
+    }
+
+    /// The limit of `model`, or the fallback with a warning if it is unknown.
+    #[must_use]
+    pub fn limit_for(&self, model: &str) -> ModelLimit {
+        self.get(model).unwrap_or_else(|| {
+            tracing::warn!(model, fallback = ?self.fallback, "Unknown model, using fallback limits");
+            self.fallback
+        })
+    }
+}
+
+impl Default for ModelLimits {
+    fn default() -> Self {
+        Self::with_defaults()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_limit_for_known_and_unknown_models() {
+        let limits = ModelLimits::with_defaults();
+        assert_eq!(limits.limit_for("sonnet"), ModelLimit::new(200_000, 64_000));
+        assert_eq!(limits.limit_for("claude-opus-4-1-20250805"), ModelLimit::new(200_000, 32_000));
+        assert_eq!(limits.limit_for("gpt-unknown"), FALLBACK_MODEL_LIMIT);
+    }
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("abcde"), 2);
+        assert_eq!(estimate_tokens("éééé"), 1);
+    }
+
+    #[test]
+    fn test_load_from_file_overrides_defaults() {
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("model-limits.json");
+        let config = serde_json::json!({
+            "models": {
+                "claude-sonnet-4-5": { "max_input_tokens": 1_000_000, "max_output_tokens": 64_000 },
+                "local-llm": { "max_input_tokens": 8_192, "max_output_tokens": 2_048 },
+            },
+        });
+        std::fs::write(&path, config.to_string()).expect("should write config");
+
+        let limits = ModelLimits::load_from_file(&path).expect("should load limits");
+        assert_eq!(limits.limit_for("claude-sonnet-4-5-20250929").max_input_tokens, 1_000_000);
+        assert_eq!(limits.limit_for("claude-sonnet-4-20250514").max_input_tokens, 200_000);
+        assert_eq!(limits.limit_for("local-llm"), ModelLimit::new(8_192, 2_048));
+        assert_eq!(limits.fallback, FALLBACK_MODEL_LIMIT);
+    }
+}
@@ -1,9 +1,28 @@
 //! Log management and normalization.
 
 use serde::{Deserialize, Serialize};
-use std::sync::Arc;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::{Arc, PoisonError};
+use std::time::Duration;
 use tokio::sync::Mutex;
-use tokio::sync::broadcast;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::warn;
+
+/// Maximum characters in a thinking preview, before the ellipsis.
+pub const THINKING_PREVIEW_CHARS: usize = 120;
+
+/// Summarize thinking text on one line for display while collapsed.
+///
+/// Whitespace is collapsed and the text is cut to [`THINKING_PREVIEW_CHARS`]
+/// characters, ending in an ellipsis if anything was cut.
+#[must_use]
+pub fn thinking_preview(text: &str) -> String {
+    let line = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    match line.char_indices().nth(THINKING_PREVIEW_CHARS) {
+        Some((cut, _)) => format!("{}…", line[..cut].trim_end()),
+        None => line,
+    }
+}
 
 /// Type of a normalized log entry.
 #[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
@@ -72,16 +91,25 @@ impl NormalizedEntry {
     }
 
     /// Create a new thinking entry.
+    ///
+    /// The metadata carries a one-line `preview` (see [`thinking_preview`]).
     #[must_use]
     pub fn thinking(content: impl Into<String>) -> Self {
+        let content = content.into();
         Self {
             timestamp: Some(chrono::Utc::now().timestamp_millis()),
             entry_type: NormalizedEntryType::ThinkingMessage,
-            content: content.into(),
-            metadata: None,
+            metadata: Some(serde_json::json!({ "preview": thinking_preview(&content) })),
+            content,
         }
     }
 
+    /// The one-line preview of a thinking entry, if it has one.
+    #[must_use]
+    pub fn preview(&self) -> Option<&str> {
+        self.metadata.as_ref()?.get("preview")?.as_str()
+    }
+
     /// Create a new tool call entry.
     #[must_use]
     pub fn tool_call(tool_name: impl Into<String>, input: serde_json::Value) -> Self {
@@ -93,6 +121,17 @@ impl NormalizedEntry {
         }
     }
 
+    /// Create a new progress entry.
+    #[must_use]
+    pub fn progress(content: impl Into<String>, metadata: serde_json::Value) -> Self {
+        Self {
+            timestamp: Some(chrono::Utc::now().timestamp_millis()),
+            entry_type: NormalizedEntryType::Progress,
+            content: content.into(),
+            metadata: Some(metadata),
+        }
+    }
+
     /// Create a new error entry.
     #[must_use]
     pub fn error(message: impl Into<String>) -> Self {
@@ -104,6 +143,17 @@ impl NormalizedEntry {
         }
     }
 
+    /// Create a new system message entry.
+    #[must_use]
+    pub fn system_message(content: impl Into<String>) -> Self {
+        Self {
+            timestamp: Some(chrono::Utc::now().timestamp_millis()),
+            entry_type: NormalizedEntryType::SystemMessage,
+            content: content.into(),
+            metadata: None,
+        }
+    }
+
     /// Add metadata to this entry.
     #[must_use]
     pub fn with_metadata(mut self, metadata: serde_json::Value) -> Self {
@@ -127,13 +177,20 @@ pub enum LogMsg {
     Error(String),
 }
 
+/// Sending half of a [`MsgStore`]'s broadcast channel.
+type LogSender = broadcast::Sender<Arc<Result<LogMsg, String>>>;
+
 /// Message store for real-time log streaming.
 ///
 /// Provides both history access and pub/sub for streaming updates
 /// to the frontend via WebSocket.
 pub struct MsgStore {
-    history: Arc<Mutex<Vec<LogMsg>>>,
-    sender: broadcast::Sender<Arc<Result<LogMsg, String>>>,
+    history: Arc<Mutex<History>>,
+    /// Dropped on finalize, closing every subscriber's channel.
+    sender: std::sync::Mutex<Option<LogSender>>,
+    subscriber_lag: AtomicU64,
+    include_thinking: bool,
+    finalized: AtomicBool,
 }
 
 impl MsgStore {
@@ -141,17 +198,57 @@ impl MsgStore {
     #[must_use]
     pub fn new() -> Self {
         let (sender, _) = broadcast::channel(256);
-        Self { history: Arc::new(Mutex::new(Vec::new())), sender }
+        Self {
+            history: Arc::new(Mutex::new(History::default())),
+            sender: std::sync::Mutex::new(Some(sender)),
+            subscriber_lag: AtomicU64::new(0),
+            include_thinking: true,
+            finalized: AtomicBool::new(false),
+        }
+    }
+
+    /// Set whether thinking entries are kept.
+    ///
+    /// When disabled, `ThinkingMessage` entries are dropped from both history and broadcast.
+    #[must_use]
+    pub const fn with_include_thinking(mut self, include_thinking: bool) -> Self {
+        self.include_thinking = include_thinking;
+        self
+    }
+
+    /// Create an empty store with the same settings, for a later run of the same session.
+    #[must_use]
+    pub fn next_segment(&self) -> Self {
+        Self::new().with_include_thinking(self.include_thinking)
     }
 
     /// Push a message to the store and broadcast to subscribers.
     pub async fn push(&self, msg: LogMsg) {
+        if !self.include_thinking
+            && matches!(&msg, LogMsg::Entry(e) if e.entry_type == NormalizedEntryType::ThinkingMessage)
+        {
+            return;
+        }
+
         let mut history = self.history.lock().await;
+        if self.is_finalized() {
+            drop(history);
+            warn!(?msg, "Dropping message pushed after the store was finalized");
+            return;
+        }
         history.push(msg.clone());
+
+        // Broadcast while holding the lock so subscribers see each message exactly once
+        self.broadcast(msg);
         drop(history);
+    }
 
-        // Broadcast to subscribers (ignore errors if no subscribers)
-        let _ = self.sender.send(Arc::new(Ok(msg)));
+    /// Send `msg` to subscribers, if the channel is still open.
+    fn broadcast(&self, msg: LogMsg) {
+        if let Some(sender) = &*self.sender.lock().unwrap_or_else(PoisonError::into_inner) {
+            // No subscribers is not an error
+            let _ = sender.send(Arc::new(Ok(msg)));
+        }
     }
 
     /// Push a normalized entry.
@@ -164,23 +261,144 @@ impl MsgStore {
         self.push(LogMsg::Error(error.into())).await;
     }
 
+    /// Close the store so that `Ended` is the last message it ever broadcasts.
+    ///
+    /// Pushes `Ended` unless it is already the last message, then closes the
+    /// channel: subscribers receive what is left and then see it closed. Later
+    /// pushes are dropped with a warning, and later subscribers get a closed receiver.
+    pub async fn finalize(&self) {
+        let mut history = self.history.lock().await;
+        if !self.finalized.swap(true, Ordering::AcqRel)
+            && !matches!(history.messages.last(), Some(LogMsg::Ended))
+        {
+            history.push(LogMsg::Ended);
+            self.broadcast(LogMsg::Ended);
+        }
+        self.sender.lock().unwrap_or_else(PoisonError::into_inner).take();
+        drop(history);
+    }
+
+    /// Whether [`MsgStore::finalize`] has been called.
+    #[must_use]
+    pub fn is_finalized(&self) -> bool {
+        self.finalized.load(Ordering::Acquire)
+    }
+
     /// Subscribe to receive new messages.
+    ///
+    /// After [`MsgStore::finalize`] the receiver is already closed.
     #[must_use]
     pub fn subscribe(&self) -> broadcast::Receiver<Arc<Result<LogMsg, String>>> {
-        self.sender.subscribe()
+        self.sender
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .as_ref()
+            .map_or_else(closed_receiver, broadcast::Sender::subscribe)
+    }
+
+    /// Atomically snapshot history from `offset` onwards and subscribe to later messages.
+    ///
+    /// Every message is either in the returned history or delivered to the receiver,
+    /// never both, so subscribers can recover from lag without gaps or duplicates.
+    pub async fn subscribe_from(
+        &self,
+        offset: usize,
+    ) -> (Vec<LogMsg>, broadcast::Receiver<Arc<Result<LogMsg, String>>>) {
+        let history = self.history.lock().await;
+        let receiver = self.subscribe();
+        let missed = history.messages.get(offset..).map(<[LogMsg]>::to_vec).unwrap_or_default();
+        drop(history);
+        (missed, receiver)
+    }
+
+    /// Atomically snapshot the whole history and subscribe to later messages.
+    ///
+    /// Like [`MsgStore::subscribe_from`] from the start, but the snapshot is
+    /// shared: subscribers that join without anything pushed in between get the
+    /// same allocation, so many subscribers cost one copy of the history.
+    pub async fn subscribe_shared(
+        &self,
+    ) -> (Arc<Vec<LogMsg>>, broadcast::Receiver<Arc<Result<LogMsg, String>>>) {
+        let mut history = self.history.lock().await;
+        let receiver = self.subscribe();
+        let snapshot = history.snapshot();
+        drop(history);
+        (snapshot, receiver)
+    }
+
+    /// Record that a subscriber fell behind and skipped `skipped` messages.
+    pub fn record_subscriber_lag(&self, skipped: u64) {
+        self.subscriber_lag.fetch_add(skipped, Ordering::Relaxed);
+    }
+
+    /// Take the number of messages skipped by lagging subscribers since the last call.
+    pub fn drain_subscriber_lag(&self) -> u64 {
+        self.subscriber_lag.swap(0, Ordering::Relaxed)
+    }
+
+    /// Wait until no message has been pushed for `window`.
+    ///
+    /// The window restarts whenever a message arrives, so this only completes
+    /// once the producer has gone quiet.
+    pub async fn idle_for(&self, window: Duration) {
+        let mut receiver = self.subscribe();
+        loop {
+            match tokio::time::timeout(window, receiver.recv()).await {
+                Ok(Ok(_) | Err(RecvError::Lagged(_))) => {}
+                // Nothing arrives once the store is finalized
+                Ok(Err(RecvError::Closed)) => return tokio::time::sleep(window).await,
+                Err(_) => return,
+            }
+        }
     }
 
     /// Get the message history.
     pub async fn get_history(&self) -> Vec<LogMsg> {
-        self.history.lock().await.clone()
+        self.history.lock().await.messages.clone()
+    }
+
+    /// Get the message history as a shared snapshot.
+    ///
+    /// The snapshot is copied from the history at most once per change, so
+    /// repeated calls without new messages return the same allocation.
+    pub async fn history_arc(&self) -> Arc<Vec<LogMsg>> {
+        self.history.lock().await.snapshot()
     }
 
     /// Clear the history.
     pub async fn clear(&self) {
-        self.history.lock().await.clear();
+        let mut history = self.history.lock().await;
+        history.messages.clear();
+        history.snapshot = None;
+        drop(history);
     }
 }
 
+/// Messages pushed to a [`MsgStore`], with a cached shared copy.
+#[derive(Default)]
+struct History {
+    messages: Vec<LogMsg>,
+    /// Copy of `messages`, dropped whenever they change.
+    snapshot: Option<Arc<Vec<LogMsg>>>,
+}
+
+impl History {
+    fn push(&mut self, msg: LogMsg) {
+        self.messages.push(msg);
+        self.snapshot = None;
+    }
+
+    /// The shared copy of the messages, made now if they changed since the last one.
+    fn snapshot(&mut self) -> Arc<Vec<LogMsg>> {
+        Arc::clone(self.snapshot.get_or_insert_with(|| Arc::new(self.messages.clone())))
+    }
+}
+
+/// A receiver whose channel has no senders left.
+fn closed_receiver<T: Clone>() -> broadcast::Receiver<T> {
+    broadcast::channel(1).1
+}
+
 impl Default for MsgStore {
     fn default() -> Self {
         Self::new()
@@ -203,6 +421,16 @@ mod tests {
         assert!(assistant_msg.metadata.is_some());
     }
 
+    #[test]
+    fn test_thinking_preview() {
+        assert_eq!(thinking_preview("  Short\n thought  "), "Short thought");
+
+        let long = "word ".repeat(60);
+        let preview = thinking_preview(&long);
+        assert!(preview.ends_with('…'));
+        assert!(preview.chars().count() <= THINKING_PREVIEW_CHARS + 1);
+    }
+
     #[tokio::test]
     async fn test_msg_store_push_and_history() {
         let store = MsgStore::new();
@@ -225,4 +453,116 @@ mod tests {
         let msg = rx.recv().await.unwrap();
         assert!(matches!(*msg, Ok(LogMsg::Started)));
     }
+
+    #[tokio::test]
+    async fn test_msg_store_subscribe_from() {
+        let store = MsgStore::new();
+        store.push(LogMsg::Started).await;
+        store.push(LogMsg::Raw("one".to_owned())).await;
+
+        let (missed, mut rx) = store.subscribe_from(1).await;
+        assert_eq!(missed.len(), 1);
+        assert!(matches!(&missed[0], LogMsg::Raw(s) if s == "one"));
+
+        store.push(LogMsg::Ended).await;
+        let msg = rx.recv().await.expect("should receive message");
+        assert!(matches!(*msg, Ok(LogMsg::Ended)));
+        assert!(store.subscribe_from(10).await.0.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_subscribers_share_history_snapshot() {
+        let store = MsgStore::new();
+        for i in 0..1_000 {
+            store.push(LogMsg::Raw(format!("line {i}"))).await;
+        }
+
+        let (first, _rx) = store.subscribe_shared().await;
+        let mut subscribers = Vec::new();
+        for _ in 0..100 {
+            let (snapshot, rx) = store.subscribe_shared().await;
+            assert!(Arc::ptr_eq(&snapshot, &first));
+            subscribers.push((snapshot, rx));
+        }
+        assert!(Arc::ptr_eq(&store.history_arc().await, &first));
+        // One allocation held by the store's cache, the first subscriber and the other 100
+        assert_eq!(Arc::strong_count(&first), 102);
+
+        store.push(LogMsg::Ended).await;
+        let (after_push, _rx) = store.subscribe_shared().await;
+        assert!(!Arc::ptr_eq(&after_push, &first));
+        assert_eq!((first.len(), after_push.len()), (1_000, 1_001));
+        for (_, rx) in &mut subscribers {
+            assert!(matches!(*rx.recv().await.expect("should receive end"), Ok(LogMsg::Ended)));
+        }
+    }
+
+    #[tokio::test]
+    async fn test_msg_store_without_thinking() {
+        let store = MsgStore::new().with_include_thinking(false);
+        let mut rx = store.subscribe();
+
+        store.push_entry(NormalizedEntry::thinking("Let me consider...")).await;
+        store.push_entry(NormalizedEntry::assistant_message("Answer")).await;
+
+        let msg = rx.recv().await.expect("should receive message");
+        assert!(matches!(&*msg, Ok(LogMsg::Entry(e)) if e.content == "Answer"));
+        assert!(rx.try_recv().is_err());
+
+        let history = store.get_history().await;
+        assert_eq!(history.len(), 1);
+    }
+
+    #[test]
+    fn test_drain_subscriber_lag() {
+        let store = MsgStore::new();
+        store.record_subscriber_lag(3);
+        store.record_subscriber_lag(4);
+        assert_eq!(store.drain_subscriber_lag(), 7);
+        assert_eq!(store.drain_subscriber_lag(), 0);
+    }
+
+    #[tokio::test]
+    async fn test_push_after_finalize_is_dropped() {
+        let store = MsgStore::new();
+        let mut rx = store.subscribe();
+        store.push_entry(NormalizedEntry::assistant_message("Answer")).await;
+
+        store.finalize().await;
+        store.push_entry(NormalizedEntry::assistant_message("Late")).await;
+        store.finalize().await;
+
+        let history = store.get_history().await;
+        assert_eq!(history.len(), 2);
+        assert!(matches!(history.last(), Some(LogMsg::Ended)));
+
+        assert!(matches!(&*rx.recv().await.expect("should receive answer"), Ok(LogMsg::Entry(_))));
+        assert!(matches!(&*rx.recv().await.expect("should receive end"), Ok(LogMsg::Ended)));
+        assert!(matches!(rx.recv().await, Err(RecvError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn test_subscribe_after_finalize_gets_history_then_close() {
+        let store = MsgStore::new();
+        store.push(LogMsg::Ended).await;
+        store.finalize().await;
+
+        let (history, mut rx) = store.subscribe_from(0).await;
+        assert_eq!(history.len(), 1);
+        assert!(matches!(rx.recv().await, Err(RecvError::Closed)));
+    }
+
+    #[tokio::test]
+    async fn test_idle_for_restarts_on_push() {
+        let store = MsgStore::new();
+        let start = std::time::Instant::now();
+
+        let push_later = async {
+            tokio::time::sleep(Duration::from_millis(80)).await;
+            store.push_entry(NormalizedEntry::assistant_message("still here")).await;
+        };
+
+        tokio::join!(store.idle_for(Duration::from_millis(100)), push_later);
+        assert!(start.elapsed() >= Duration::from_millis(180));
+    }
 }
@@ -0,0 +1,79 @@
+//! Approximate prices of the models executors can run, for estimating cost
+//! while a session is still running.
+
+use crate::executors::claude::TokenUsage;
+
+/// Price of a model's tokens, in USD per million tokens.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ModelPrice {
+    /// Price of a million input tokens.
+    pub input_usd_per_mtok: f64,
+    /// Price of a million output tokens.
+    pub output_usd_per_mtok: f64,
+}
+
+impl ModelPrice {
+    /// Create a price.
+    #[must_use]
+    pub const fn new(input_usd_per_mtok: f64, output_usd_per_mtok: f64) -> Self {
+        Self { input_usd_per_mtok, output_usd_per_mtok }
+    }
+
+    /// Cost in USD of `usage` at this price.
+    #[must_use]
+    #[allow(clippy::cast_precision_loss, reason = "token counts are far below 2^52")]
+    pub fn cost_usd(&self, usage: TokenUsage) -> f64 {
+        (usage.input_tokens as f64)
+            .mul_add(self.input_usd_per_mtok, usage.output_tokens as f64 * self.output_usd_per_mtok)
+            / 1_000_000.0
+    }
+}
+
+/// Price assumed for models missing from the table: the most expensive known
+/// model, so cost ceilings trip early rather than late.
+pub const FALLBACK_MODEL_PRICE: ModelPrice = ModelPrice::new(15.0, 75.0);
+
+/// Built-in prices, keyed by model name or name prefix.
+const MODEL_PRICES: &[(&str, ModelPrice)] = &[
+    ("opus", ModelPrice::new(15.0, 75.0)),
+    ("sonnet", ModelPrice::new(3.0, 15.0)),
+    ("haiku", ModelPrice::new(1.0, 5.0)),
+    ("claude-opus-4", ModelPrice::new(15.0, 75.0)),
+    ("claude-opus-4-5", ModelPrice::new(5.0, 25.0)),
+    ("claude-sonnet-4", ModelPrice::new(3.0, 15.0)),
+    ("claude-haiku-4", ModelPrice::new(1.0, 5.0)),
+    ("claude-3-7-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-5-sonnet", ModelPrice::new(3.0, 15.0)),
+    ("claude-3-5-haiku", ModelPrice::new(0.8, 4.0)),
+];
+
+/// The price of `model`, matched by exact name, then by the longest known
+/// prefix, falling back to [`FALLBACK_MODEL_PRICE`].
+#[must_use]
+pub fn price_for(model: &str) -> ModelPrice {
+    MODEL_PRICES
+        .iter()
+        .filter(|(key, _)| model.starts_with(key))
+        .max_by_key(|(key, _)| key.len())
+        .map_or(FALLBACK_MODEL_PRICE, |(_, price)| *price)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_price_for_matches_longest_prefix() {
+        assert_eq!(price_for("claude-sonnet-4-20250514"), ModelPrice::new(3.0, 15.0));
+        assert_eq!(price_for("claude-opus-4-5-20251101"), ModelPrice::new(5.0, 25.0));
+        assert_eq!(price_for("claude-opus-4-1-20250805"), ModelPrice::new(15.0, 75.0));
+        assert_eq!(price_for("gpt-unknown"), FALLBACK_MODEL_PRICE);
+    }
+
+    #[test]
+    fn test_cost_of_usage() {
+        let usage = TokenUsage { input_tokens: 10_000, output_tokens: 2_000 };
+        let cost = price_for("sonnet").cost_usd(usage);
+        assert!((cost - 0.06).abs() < 1e-9, "unexpected cost {cost}");
+    }
+}
@@ -2,9 +2,11 @@
 
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
 
-use crate::executors::{BaseDocumentAgent, DocumentAgent};
+use crate::error::ExecutorError;
+use crate::executors::{BaseDocumentAgent, ClaudeCode, DocumentAgent};
+use crate::types::AvailabilityInfo;
 
 /// Identifier for an executor profile.
 #[derive(Debug, Clone, PartialEq, Eq, Hash, Serialize, Deserialize)]
@@ -36,7 +38,7 @@ impl ExecutorProfileId {
 }
 
 /// Configuration for a specific executor.
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct ExecutorConfig {
     /// Map of variant names to agent configurations.
     pub configurations: HashMap<String, DocumentAgent>,
@@ -67,13 +69,94 @@ impl ExecutorConfig {
     pub fn get_default(&self) -> Option<&DocumentAgent> {
         self.get("DEFAULT")
     }
+
+    /// Names of the configured variants, sorted with `DEFAULT` first.
+    #[must_use]
+    pub fn variant_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self.configurations.keys().map(String::as_str).collect();
+        names.sort_unstable_by_key(|name| (*name != "DEFAULT", *name));
+        names
+    }
 }
 
 /// Collection of all executor configurations.
-#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct ExecutorConfigs {
     /// Map of executor types to their configurations.
     pub executors: HashMap<BaseDocumentAgent, ExecutorConfig>,
+    /// Executors to try in order, the first being the primary; empty runs
+    /// the requested executor without checking its availability.
+    #[serde(default)]
+    pub fallback_chain: Vec<BaseDocumentAgent>,
+    /// Routes picking a profile for each feedback request.
+    #[serde(default)]
+    pub routing: FeedbackRouting,
+}
+
+/// Picks executor profiles for feedback requests by instruction keyword or comment tag.
+///
+/// Routes are tried in order and the first match wins; unmatched requests
+/// use the default route, if any.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedbackRouting {
+    /// Routes in priority order.
+    #[serde(default)]
+    pub routes: Vec<FeedbackRoute>,
+    /// Profile for requests no route matches.
+    #[serde(default)]
+    pub default: Option<ExecutorProfileId>,
+}
+
+/// Sends matching feedback requests to an executor profile.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FeedbackRoute {
+    /// Matches instructions containing any of these, ignoring case.
+    #[serde(default)]
+    pub keywords: Vec<String>,
+    /// Matches comments carrying any of these tags, ignoring case.
+    #[serde(default)]
+    pub tags: Vec<String>,
+    /// Profile matching requests run with.
+    pub profile: ExecutorProfileId,
+}
+
+impl FeedbackRoute {
+    /// Whether a request with `instruction` on a comment tagged `tags` matches.
+    #[must_use]
+    pub fn matches(&self, instruction: &str, tags: &[String]) -> bool {
+        let instruction = instruction.to_lowercase();
+        self.keywords.iter().any(|keyword| instruction.contains(&keyword.to_lowercase()))
+            || self.tags.iter().any(|tag| tags.iter().any(|t| t.eq_ignore_ascii_case(tag)))
+    }
+}
+
+impl FeedbackRouting {
+    /// The profile for a request with `instruction` on a comment tagged `tags`.
+    #[must_use]
+    pub fn route(&self, instruction: &str, tags: &[String]) -> Option<&ExecutorProfileId> {
+        self.routes
+            .iter()
+            .find(|route| route.matches(instruction, tags))
+            .map(|route| &route.profile)
+            .or(self.default.as_ref())
+    }
+}
+
+/// An executor picked from the fallback chain.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FallbackChoice {
+    /// The executor to run.
+    pub executor: BaseDocumentAgent,
+    /// Position in the chain; anything past the first stands in for the primary.
+    pub position: usize,
+}
+
+impl FallbackChoice {
+    /// Whether the primary executor was unavailable and another is used instead.
+    #[must_use]
+    pub const fn is_substitute(&self) -> bool {
+        self.position > 0
+    }
 }
 
 impl ExecutorConfigs {
@@ -83,6 +166,30 @@ impl ExecutorConfigs {
         Self::default()
     }
 
+    /// Create the built-in profile set: Claude Code `DEFAULT` and `PLAN` (plan mode).
+    #[must_use]
+    pub fn with_defaults() -> Self {
+        let mut claude = ExecutorConfig::new(DocumentAgent::ClaudeCode(ClaudeCode::new()));
+        claude.add_variant("PLAN", DocumentAgent::ClaudeCode(ClaudeCode::new().with_plan_mode()));
+
+        let mut configs = Self::new();
+        configs.add(BaseDocumentAgent::ClaudeCode, claude);
+        configs
+    }
+
+    /// Load the user profiles file, falling back to [`ExecutorConfigs::with_defaults`]
+    /// if it does not exist or cannot be read.
+    #[must_use]
+    pub fn load_user_or_defaults() -> Self {
+        let Some(path) = Self::user_profiles_path().filter(|path| path.exists()) else {
+            return Self::with_defaults();
+        };
+        Self::load_from_file(&path).unwrap_or_else(|e| {
+            tracing::warn!(path = %path.display(), error = %e, "Failed to load user profiles");
+            Self::with_defaults()
+        })
+    }
+
     /// Load configurations from a file.
     ///
     /// # Errors
@@ -93,6 +200,30 @@ impl ExecutorConfigs {
         Ok(configs)
     }
 
+    /// Save configurations to a file as pretty-printed JSON.
+    ///
+    /// Parent directories are created if they do not exist.
+    ///
+    /// # Errors
+    /// Returns an error if the directories or file cannot be written.
+    pub fn save_to_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let content = serde_json::to_string_pretty(self)?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Save configurations to the user profiles file.
+    ///
+    /// # Errors
+    /// Returns an error if there is no config directory or the file cannot be written.
+    pub fn save_default(&self) -> Result<(), Box<dyn std::error::Error>> {
+        let path = Self::user_profiles_path().ok_or("no user config directory")?;
+        self.save_to_file(&path)
+    }
+
     /// Get the default configuration directory.
     #[must_use]
     pub fn default_config_dir() -> Option<PathBuf> {
@@ -116,6 +247,50 @@ impl ExecutorConfigs {
         self.executors.get(executor)
     }
 
+    /// Names of the variants configured for an executor, sorted with `DEFAULT` first.
+    ///
+    /// Returns an empty list if the executor has no configuration.
+    #[must_use]
+    pub fn variants_for(&self, executor: &BaseDocumentAgent) -> Vec<String> {
+        self.get(executor)
+            .map(|config| config.variant_names().into_iter().map(str::to_owned).collect())
+            .unwrap_or_default()
+    }
+
+    /// Set the executors to try in order, the first being the primary.
+    #[must_use]
+    pub fn with_fallback_chain(mut self, chain: Vec<BaseDocumentAgent>) -> Self {
+        self.fallback_chain = chain;
+        self
+    }
+
+    /// Walk the fallback chain and pick the first executor `availability` reports usable.
+    ///
+    /// Returns `None` if no chain is configured.
+    ///
+    /// # Errors
+    /// Returns [`ExecutorError::NotAvailable`] listing every executor tried if none is usable.
+    pub fn select_available(
+        &self,
+        mut availability: impl FnMut(BaseDocumentAgent) -> AvailabilityInfo,
+    ) -> Result<Option<FallbackChoice>, ExecutorError> {
+        if self.fallback_chain.is_empty() {
+            return Ok(None);
+        }
+        let mut tried = Vec::new();
+        for (position, &executor) in self.fallback_chain.iter().enumerate() {
+            let info = availability(executor);
+            if info.is_available() {
+                return Ok(Some(FallbackChoice { executor, position }));
+            }
+            tried.push(format!("{executor} ({info})"));
+        }
+        Err(ExecutorError::NotAvailable(format!(
+            "no executor in the fallback chain is available, tried {}",
+            tried.join(", ")
+        )))
+    }
+
     /// Get a specific agent by profile ID.
     #[must_use]
     pub fn get_agent(&self, profile: &ExecutorProfileId) -> Option<&DocumentAgent> {
@@ -135,4 +310,123 @@ mod tests {
         let id = ExecutorProfileId::with_variant(BaseDocumentAgent::ClaudeCode, "PLAN");
         assert_eq!(id.variant_name(), "PLAN");
     }
+
+    #[test]
+    fn test_default_profiles() {
+        let configs = ExecutorConfigs::with_defaults();
+        assert_eq!(configs.variants_for(&BaseDocumentAgent::ClaudeCode), vec!["DEFAULT", "PLAN"]);
+
+        let plan = ExecutorProfileId::with_variant(BaseDocumentAgent::ClaudeCode, "PLAN");
+        let Some(DocumentAgent::ClaudeCode(claude)) = configs.get_agent(&plan) else {
+            unreachable!("PLAN should be a Claude Code profile");
+        };
+        assert_eq!(claude.plan, Some(true));
+
+        let default = ExecutorProfileId::new(BaseDocumentAgent::ClaudeCode);
+        assert!(configs.get_agent(&default).is_some());
+    }
+
+    #[test]
+    fn test_save_and_load_roundtrip() {
+        let mut config = ExecutorConfig::new(DocumentAgent::ClaudeCode(ClaudeCode::default()));
+        config.add_variant("OPUS", DocumentAgent::ClaudeCode(ClaudeCode::new().with_model("opus")));
+        let mut configs = ExecutorConfigs::new();
+        configs.add(BaseDocumentAgent::ClaudeCode, config);
+
+        let dir = tempfile::tempdir().expect("should create temp dir");
+        let path = dir.path().join("nested").join("profiles.json");
+        configs.save_to_file(&path).expect("should save configs");
+
+        let loaded = ExecutorConfigs::load_from_file(&path).expect("should load configs");
+        assert_eq!(loaded, configs);
+    }
+
+    #[test]
+    fn test_fallback_chain_selection() {
+        let claude = BaseDocumentAgent::ClaudeCode;
+        assert_eq!(ExecutorConfigs::new().select_available(|_| unreachable!()).ok(), Some(None));
+
+        // Primary available
+        let configs = ExecutorConfigs::new().with_fallback_chain(vec![claude, claude]);
+        let choice = configs.select_available(|_| AvailabilityInfo::Available);
+        let choice = choice.expect("should pick an executor").expect("chain is configured");
+        assert_eq!(choice, FallbackChoice { executor: claude, position: 0 });
+        assert!(!choice.is_substitute());
+
+        // Primary missing, fallback used
+        let mut checks = 0;
+        let choice = configs.select_available(|_| {
+            checks += 1;
+            if checks == 1 {
+                AvailabilityInfo::NotFound
+            } else {
+                AvailabilityInfo::InstallationFound
+            }
+        });
+        let choice = choice.expect("should pick an executor").expect("chain is configured");
+        assert_eq!(choice.position, 1);
+        assert!(choice.is_substitute());
+
+        // None available
+        let Err(err) = configs
+            .select_available(|_| AvailabilityInfo::Unavailable { reason: "offline".to_owned() })
+        else {
+            unreachable!("nothing in the chain is available");
+        };
+        assert_eq!(
+            err.to_string(),
+            "executor not available: no executor in the fallback chain is available, \
+             tried CLAUDE_CODE (unavailable (offline)), CLAUDE_CODE (unavailable (offline))"
+        );
+    }
+
+    #[test]
+    fn test_routing_by_keyword_and_tag() {
+        let claude = BaseDocumentAgent::ClaudeCode;
+        let routing = FeedbackRouting {
+            routes: vec![
+                FeedbackRoute {
+                    keywords: vec!["grammar".to_owned(), "typo".to_owned()],
+                    tags: vec![],
+                    profile: ExecutorProfileId::with_variant(claude, "HAIKU"),
+                },
+                FeedbackRoute {
+                    keywords: vec!["deep review".to_owned()],
+                    tags: vec!["legal".to_owned()],
+                    profile: ExecutorProfileId::with_variant(claude, "OPUS"),
+                },
+            ],
+            default: Some(ExecutorProfileId::new(claude)),
+        };
+        let variant = |instruction: &str, tags: &[&str]| {
+            let tags: Vec<String> = tags.iter().map(|&t| t.to_owned()).collect();
+            routing.route(instruction, &tags).map(|p| p.variant_name().to_owned())
+        };
+
+        assert_eq!(variant("Fix the Grammar here", &[]).as_deref(), Some("HAIKU"));
+        assert_eq!(variant("Please do a deep review", &[]).as_deref(), Some("OPUS"));
+        assert_eq!(variant("Check this clause", &["Legal"]).as_deref(), Some("OPUS"));
+        assert_eq!(variant("Is this clear?", &["draft"]).as_deref(), Some("DEFAULT"));
+        assert_eq!(FeedbackRouting::default().route("Fix the grammar", &[]), None);
+    }
+
+    #[test]
+    fn test_variant_names() {
+        let claude = || DocumentAgent::ClaudeCode(ClaudeCode::default());
+        let mut config = ExecutorConfig::new(claude());
+        config.add_variant("ROUTER", claude());
+        config.add_variant("PLAN", claude());
+        config.add_variant("ASK", claude());
+
+        assert_eq!(config.variant_names(), vec!["DEFAULT", "ASK", "PLAN", "ROUTER"]);
+
+        let mut configs = ExecutorConfigs::new();
+        assert!(configs.variants_for(&BaseDocumentAgent::ClaudeCode).is_empty());
+
+        configs.add(BaseDocumentAgent::ClaudeCode, config);
+        assert_eq!(
+            configs.variants_for(&BaseDocumentAgent::ClaudeCode),
+            vec!["DEFAULT", "ASK", "PLAN", "ROUTER"]
+        );
+    }
 }
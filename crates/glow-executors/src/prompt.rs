@@ -0,0 +1,202 @@
+//! Prompt templates for document feedback.
+
+use std::collections::HashMap;
+use std::path::Path;
+
+use serde::{Deserialize, Serialize};
+
+/// Default feedback prompt.
+const DEFAULT_TEMPLATE: &str = r"DOCUMENT CONTEXT:
+Title: {title}
+Document ID: {document_id}
+{context}
+SELECTED TEXT:
+{selected_text}
+
+USER INSTRUCTION:
+{instruction}
+
+Please analyze the selected text according to the user's instruction and provide helpful feedback. If you have specific text improvements to suggest, use the suggest_edit tool.";
+
+/// A prompt with `{name}` placeholders.
+///
+/// Supported placeholders are listed in [`PromptTemplate::VARIABLES`]. Supported
+/// placeholders without a value render as empty; anything else in braces is
+/// left as-is.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct PromptTemplate {
+    template: String,
+}
+
+impl PromptTemplate {
+    /// Placeholder names substituted by [`PromptTemplate::render`].
+    pub const VARIABLES: [&'static str; 5] =
+        ["title", "selected_text", "instruction", "document_id", "context"];
+
+    /// Create a template from a string.
+    #[must_use]
+    pub fn new(template: impl Into<String>) -> Self {
+        Self { template: template.into() }
+    }
+
+    /// Load a template from a text file.
+    ///
+    /// # Errors
+    /// Returns an error if the file cannot be read.
+    pub fn load_from_file(path: &Path) -> std::io::Result<Self> {
+        std::fs::read_to_string(path).map(Self::new)
+    }
+
+    /// The raw template text.
+    #[must_use]
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Substitute `vars` into the template.
+    #[must_use]
+    pub fn render(&self, vars: &HashMap<&str, &str>) -> String {
+        let mut out = String::with_capacity(self.template.len());
+        let mut rest = self.template.as_str();
+
+        while let Some(start) = rest.find('{') {
+            out.push_str(&rest[..start]);
+            let after = &rest[start + 1..];
+            match after.find('}').map(|end| (&after[..end], end)) {
+                Some((name, end)) if Self::VARIABLES.contains(&name) => {
+                    out.push_str(vars.get(name).copied().unwrap_or_default());
+                    rest = &after[end + 1..];
+                }
+                _ => {
+                    out.push('{');
+                    rest = after;
+                }
+            }
+        }
+
+        out.push_str(rest);
+        out
+    }
+}
+
+impl Default for PromptTemplate {
+    fn default() -> Self {
+        Self::new(DEFAULT_TEMPLATE)
+    }
+}
+
+/// Opening tag around the selection in a [`ContextWindow`].
+pub const SELECTION_START: &str = "<selection>";
+
+/// Closing tag around the selection in a [`ContextWindow`].
+pub const SELECTION_END: &str = "</selection>";
+
+/// An excerpt of a document around the selected text, for the prompt.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ContextWindow {
+    /// The excerpt, with `…` where it was cut.
+    pub text: String,
+    /// Whether the selection is in the excerpt, wrapped in [`SELECTION_START`]
+    /// and [`SELECTION_END`].
+    pub contains_selection: bool,
+}
+
+impl ContextWindow {
+    /// Up to `radius_chars` characters either side of the first occurrence of
+    /// `selection` in `content`, with the selection marked.
+    ///
+    /// When the selection is empty or not in the content, such as when it was
+    /// truncated away, the excerpt is the start of the content instead.
+    #[must_use]
+    pub fn around(content: &str, selection: &str, radius_chars: usize) -> Self {
+        let found = (!selection.is_empty()).then(|| content.find(selection)).flatten();
+        let Some(start) = found else {
+            let end = char_offset(content, radius_chars.saturating_mul(2));
+            let cut = if end < content.len() { "…" } else { "" };
+            return Self { text: format!("{}{cut}", &content[..end]), contains_selection: false };
+        };
+
+        let end = start + selection.len();
+        let before = &content[..start];
+        let before = &before[char_offset_from_end(before, radius_chars)..];
+        let after = &content[end..];
+        let after = &after[..char_offset(after, radius_chars)];
+        let lead = if before.len() < start { "…" } else { "" };
+        let trail = if end + after.len() < content.len() { "…" } else { "" };
+        Self {
+            text: format!(
+                "{lead}{before}{SELECTION_START}{selection}{SELECTION_END}{after}{trail}"
+            ),
+            contains_selection: true,
+        }
+    }
+}
+
+/// Byte offset of the character `n` characters into `text`, or its length.
+fn char_offset(text: &str, n: usize) -> usize {
+    text.char_indices().nth(n).map_or(text.len(), |(i, _)| i)
+}
+
+/// Byte offset of the character `n` characters before the end of `text`, or 0.
+fn char_offset_from_end(text: &str, n: usize) -> usize {
+    n.checked_sub(1)
+        .map_or(text.len(), |skip| text.char_indices().rev().nth(skip).map_or(0, |(i, _)| i))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_all_variables() {
+        let template =
+            PromptTemplate::new("{title} ({document_id}): {instruction} -> {selected_text}");
+        let vars = HashMap::from([
+            ("title", "Essay"),
+            ("document_id", "doc-1"),
+            ("instruction", "Fix grammar"),
+            ("selected_text", "Their going"),
+        ]);
+
+        assert_eq!(template.render(&vars), "Essay (doc-1): Fix grammar -> Their going");
+    }
+
+    #[test]
+    fn test_render_missing_and_unknown_variables() {
+        let template = PromptTemplate::new("[{title}] {instruction} {tone} {unclosed");
+        let vars = HashMap::from([("instruction", "Make it friendlier")]);
+
+        assert_eq!(template.render(&vars), "[] Make it friendlier {tone} {unclosed");
+    }
+
+    #[test]
+    fn test_context_window_marks_selection() {
+        let content = "Once upon a time there was a very long sentence.";
+        let window = ContextWindow::around(content, "there was", 7);
+
+        assert!(window.contains_selection);
+        assert_eq!(window.text, "…a time <selection>there was</selection> a very…");
+        assert_eq!(window.text.matches("there was").count(), 1);
+
+        let whole = ContextWindow::around("Short note", "note", 100);
+        assert_eq!(whole.text, "Short <selection>note</selection>");
+    }
+
+    #[test]
+    fn test_context_window_without_selection_is_document_start() {
+        let window = ContextWindow::around("Héllo wörld and more", "missing", 3);
+
+        assert!(!window.contains_selection);
+        assert_eq!(window.text, "Héllo …");
+        assert_eq!(ContextWindow::around("Héllo", "", 3).text, "Héllo");
+    }
+
+    #[test]
+    fn test_default_template_uses_all_variables() {
+        let template = PromptTemplate::default();
+        for name in PromptTemplate::VARIABLES {
+            assert!(template.as_str().contains(&format!("{{{name}}}")));
+        }
+    }
+}
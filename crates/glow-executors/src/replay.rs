@@ -0,0 +1,126 @@
+//! Replaying a session's accepted edits against a document.
+
+use glow_core::ReplayableEdit;
+
+use crate::types::SuggestedEdit;
+
+/// Suggested edits replay at their range start, read as a byte offset into
+/// the document content.
+impl ReplayableEdit for SuggestedEdit {
+    fn edit_id(&self) -> &str {
+        &self.id
+    }
+
+    fn is_accepted(&self) -> bool {
+        self.applied
+    }
+
+    fn offset(&self) -> usize {
+        self.range.from
+    }
+
+    fn original_text(&self) -> &str {
+        &self.original_text
+    }
+
+    fn replacement_text(&self) -> &str {
+        &self.suggested_text
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use glow_core::Document;
+
+    use super::*;
+    use crate::types::TextRange;
+
+    fn edit(
+        id: &str,
+        from: usize,
+        original: &str,
+        suggested: &str,
+        applied: bool,
+    ) -> SuggestedEdit {
+        SuggestedEdit {
+            id: id.to_owned(),
+            original_text: original.to_owned(),
+            suggested_text: suggested.to_owned(),
+            explanation: String::new(),
+            range: TextRange { from, to: from + original.len(), quoted_text: original.to_owned() },
+            applied,
+            rejected: !applied,
+        }
+    }
+
+    #[test]
+    fn test_replays_only_accepted_edits_in_position_order() {
+        let mut doc = Document::new().with_initial_content("The cat sat on the mat.");
+        let edits = [
+            // Out of position order, to check they are sorted
+            edit("mat", 19, "mat", "rug", true),
+            edit("sat", 8, "sat", "slept", false),
+            edit("cat", 4, "cat", "dog", true),
+            edit("the", 15, "the", "a", false),
+        ];
+
+        let report = doc.replay_accepted_edits(&edits);
+        assert_eq!(doc.content, "The dog sat on the rug.");
+        assert_eq!(report.applied, ["cat", "mat"]);
+        assert!(report.conflicts.is_empty());
+    }
+
+    #[test]
+    fn test_replay_reports_conflicts() {
+        let mut doc = Document::new().with_initial_content("Hello there, world");
+        let edits = [
+            edit("greeting", 0, "Hello there", "Hi", true),
+            // Overlaps the greeting
+            edit("there", 6, "there", "here", true),
+            edit("missing", 12, "planet", "globe", true),
+            edit("world", 13, "world", "everyone", true),
+        ];
+
+        let report = doc.replay_accepted_edits(&edits);
+        assert_eq!(doc.content, "Hi, everyone");
+        assert_eq!(report.applied, ["greeting", "world"]);
+        let conflicts: Vec<_> =
+            report.conflicts.iter().map(|c| (c.edit_id.as_str(), c.reason.as_str())).collect();
+        assert_eq!(
+            conflicts,
+            [
+                ("there", "overlaps an earlier edit"),
+                ("missing", "original text not found at its offset")
+            ]
+        );
+    }
+
+    #[test]
+    fn test_replay_anchors_edits_at_their_offsets() {
+        let mut doc = Document::new().with_initial_content("the cat saw the cat");
+        let edits = [
+            edit("first", 0, "the", "a", true),
+            // The second "cat", after the first edit shortened the text
+            edit("second", 16, "cat", "dog", true),
+            // "saw" is in the document, but not at this offset
+            edit("moved", 4, "saw", "met", true),
+        ];
+
+        let report = doc.replay_accepted_edits(&edits);
+        assert_eq!(doc.content, "a cat saw the dog");
+        assert_eq!(report.applied, ["first", "second"]);
+        assert_eq!(report.conflicts.len(), 1);
+        assert_eq!(report.conflicts[0].edit_id, "moved");
+    }
+
+    #[test]
+    fn test_replay_on_read_only_document_changes_nothing() {
+        let mut doc = Document::new().with_initial_content("Frozen text");
+        doc.set_read_only(true);
+
+        let report = doc.replay_accepted_edits(&[edit("frozen", 0, "Frozen", "Thawed", true)]);
+        assert_eq!(doc.content, "Frozen text");
+        assert!(report.applied.is_empty());
+        assert_eq!(report.conflicts.len(), 1);
+    }
+}
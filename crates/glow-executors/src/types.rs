@@ -3,6 +3,8 @@
 use serde::{Deserialize, Serialize};
 use ts_rs::TS;
 
+use crate::executors::BaseDocumentAgent;
+
 /// Text range in a document (ProseMirror positions).
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
@@ -37,6 +39,61 @@ pub struct SuggestedEdit {
     pub rejected: bool,
 }
 
+impl SuggestedEdit {
+    /// Combines runs of edits whose ranges touch into single edits.
+    ///
+    /// Ranges are byte offsets into the document content. The edits are
+    /// ordered by position. A merged edit spans the combined range, with the
+    /// original and suggested texts concatenated, the IDs joined by `+` and
+    /// the distinct explanations joined by spaces. Only edits with the same
+    /// applied and rejected state merge, and edits separated by a gap stay
+    /// distinct: their ranges say nothing about the text between them, so it
+    /// cannot be carried into a merged edit.
+    #[must_use]
+    pub fn merge_adjacent(edits: &[Self]) -> Vec<Self> {
+        let mut sorted: Vec<&Self> = edits.iter().collect();
+        sorted.sort_by_key(|e| (e.range.from, e.range.to));
+
+        let mut runs: Vec<Vec<&Self>> = Vec::new();
+        for edit in sorted {
+            match runs.last_mut() {
+                Some(run) if run.last().is_some_and(|last| last.touches(edit)) => run.push(edit),
+                _ => runs.push(vec![edit]),
+            }
+        }
+        runs.iter()
+            .filter_map(|run| run.split_first())
+            .map(|(first, rest)| first.merged_with(rest))
+            .collect()
+    }
+
+    /// Whether `next` starts where this edit ends, in the same state.
+    const fn touches(&self, next: &Self) -> bool {
+        let same_state = self.applied == next.applied && self.rejected == next.rejected;
+        same_state && self.range.to == next.range.from
+    }
+
+    /// This edit extended with the ones following it, in order.
+    fn merged_with(&self, rest: &[&Self]) -> Self {
+        let mut merged = self.clone();
+        let mut explanations: Vec<&str> = Vec::new();
+        for next in std::iter::once(self).chain(rest.iter().copied()) {
+            if !next.explanation.is_empty() && !explanations.contains(&next.explanation.as_str()) {
+                explanations.push(&next.explanation);
+            }
+        }
+        for next in rest {
+            merged.id = format!("{}+{}", merged.id, next.id);
+            merged.original_text.push_str(&next.original_text);
+            merged.suggested_text.push_str(&next.suggested_text);
+            merged.range.to = next.range.to;
+            merged.range.quoted_text.push_str(&next.range.quoted_text);
+        }
+        merged.explanation = explanations.join(" ");
+        merged
+    }
+}
+
 /// Request for AI feedback on document content.
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
@@ -79,6 +136,12 @@ pub struct FeedbackResponse {
     pub session_id: String,
     /// Error message if failed.
     pub error: Option<String>,
+    /// Language of the response (ISO 639-3), when detection is enabled and conclusive.
+    pub language: Option<String>,
+    /// Secret required to open the session's stream; only returned when the session is created.
+    pub stream_token: Option<String>,
+    /// Executor run in place of the requested one because it was unavailable.
+    pub fallback_executor: Option<BaseDocumentAgent>,
 }
 
 /// Status of a feedback request.
@@ -106,7 +169,11 @@ pub enum StreamMessage {
     /// Suggested edit.
     Edit { edit: SuggestedEdit },
     /// AI thinking/reasoning (usually collapsed).
-    Thinking { content: String },
+    Thinking {
+        content: String,
+        /// One-line summary to show while collapsed.
+        preview: String,
+    },
     /// Stream completed successfully.
     Complete,
     /// An error occurred.
@@ -124,6 +191,11 @@ pub enum StreamControl {
     Approve { tool_use_id: String },
     /// Deny a tool use request.
     Deny { tool_use_id: String, message: String },
+    /// Continue the session with a follow-up instruction.
+    FollowUp {
+        /// What the user asks next, sent to the executor as a new prompt.
+        instruction: String,
+    },
 }
 
 /// Availability information for an executor.
@@ -141,10 +213,46 @@ pub enum AvailabilityInfo {
     Unavailable { reason: String },
 }
 
+impl AvailabilityInfo {
+    /// Whether the executor can be run, possibly after logging in.
+    #[must_use]
+    pub const fn is_available(&self) -> bool {
+        matches!(self, Self::Available | Self::InstallationFound)
+    }
+}
+
+impl std::fmt::Display for AvailabilityInfo {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Available => f.write_str("available"),
+            Self::InstallationFound => f.write_str("installation found"),
+            Self::NotFound => f.write_str("not found"),
+            Self::Unavailable { reason } => write!(f, "unavailable ({reason})"),
+        }
+    }
+}
+
+/// The kind of step a [`SetupAction`] asks the user to take.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "snake_case")]
+pub enum SetupActionKind {
+    /// Install the executor.
+    Install,
+    /// Log in to an installed executor.
+    Login,
+    /// Resolve a problem preventing the executor from running.
+    Troubleshoot,
+    /// Nothing to do; the executor is ready.
+    Ready,
+}
+
 /// Action to help user set up an executor.
 #[derive(Debug, Clone, Serialize, Deserialize, TS)]
 #[ts(export)]
 pub struct SetupAction {
+    /// Which step this is, for choosing how to present it.
+    pub kind: SetupActionKind,
     /// Human-readable description.
     pub description: String,
     /// Command to run (if applicable).
@@ -153,6 +261,22 @@ pub struct SetupAction {
     pub url: Option<String>,
 }
 
+/// Optional features an executor supports, for clients deciding what to offer.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize, TS)]
+#[ts(export)]
+#[serde(rename_all = "camelCase")]
+#[allow(clippy::struct_excessive_bools, reason = "independent feature flags, not a state machine")]
+pub struct Capabilities {
+    /// Whether a finished session can be resumed with a follow-up prompt.
+    pub follow_ups: bool,
+    /// Whether tool use can be routed through an approval service.
+    pub approvals: bool,
+    /// Whether the executor can run read-only in plan mode.
+    pub plan_mode: bool,
+    /// Whether the executor reports suggested edits to the document.
+    pub suggested_edits: bool,
+}
+
 /// Prompt configuration for document feedback.
 #[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
 pub struct AppendPrompt {
@@ -179,3 +303,68 @@ impl AppendPrompt {
         result
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(id: &str, from: usize, original: &str, suggested: &str) -> SuggestedEdit {
+        SuggestedEdit {
+            id: id.to_owned(),
+            original_text: original.to_owned(),
+            suggested_text: suggested.to_owned(),
+            explanation: format!("Reword {}", original.trim()),
+            range: TextRange { from, to: from + original.len(), quoted_text: original.to_owned() },
+            applied: false,
+            rejected: false,
+        }
+    }
+
+    #[test]
+    fn test_merge_adjacent_combines_touching_edits() {
+        // "The quick brown fox": "quick " and "brown" touch
+        let edits = [edit("b", 10, "brown", "red"), edit("a", 4, "quick ", "slow ")];
+
+        let merged = SuggestedEdit::merge_adjacent(&edits);
+        assert_eq!(merged.len(), 1);
+        let edit = &merged[0];
+        assert_eq!(edit.id, "a+b");
+        assert_eq!(edit.original_text, "quick brown");
+        assert_eq!(edit.suggested_text, "slow red");
+        assert_eq!(edit.explanation, "Reword quick Reword brown");
+        assert_eq!((edit.range.from, edit.range.to), (4, 15));
+        assert_eq!(edit.range.quoted_text, "quick brown");
+    }
+
+    #[test]
+    fn test_merge_adjacent_keeps_separated_edits() {
+        let mut accepted = edit("fox", 15, "fox", "cat");
+        accepted.applied = true;
+        let edits = [
+            edit("quick", 4, "quick", "slow"),
+            // One position after "quick"
+            edit("brown", 10, "brown", "red"),
+            // Touches "brown" but was accepted
+            accepted,
+        ];
+
+        let merged = SuggestedEdit::merge_adjacent(&edits);
+        let ids: Vec<&str> = merged.iter().map(|e| e.id.as_str()).collect();
+        assert_eq!(ids, ["quick", "brown", "fox"]);
+        assert_eq!(merged[1].suggested_text, "red");
+    }
+
+    #[test]
+    fn test_merge_adjacent_keeps_distinct_explanations() {
+        let mut typo = edit("typo", 0, "Teh ", "The ");
+        typo.explanation = "Fix typo".to_owned();
+        let mut fix = edit("fix", 4, "quick", "fast");
+        fix.explanation = "Fix".to_owned();
+        let mut again = edit("again", 9, " brown", " red");
+        again.explanation = "Fix typo".to_owned();
+
+        let merged = SuggestedEdit::merge_adjacent(&[typo, fix, again]);
+        assert_eq!(merged.len(), 1);
+        assert_eq!(merged[0].explanation, "Fix typo Fix");
+    }
+}
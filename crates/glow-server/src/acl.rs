@@ -0,0 +1,119 @@
+//! Per-document access control.
+
+use std::collections::HashMap;
+use std::convert::Infallible;
+
+use axum::{extract::FromRequestParts, http::request::Parts};
+use serde::{Deserialize, Serialize};
+
+/// Header carrying the user ID.
+///
+/// This is a development stub, not authentication: the server trusts the
+/// header as sent, so any client can claim to be any user. Only expose the
+/// server behind a proxy that authenticates requests, sets this header, and
+/// strips it from incoming requests.
+pub const USER_ID_HEADER: &str = "x-user-id";
+
+/// What a user may do with a document. `Write` implies `Read`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AccessLevel {
+    /// View the document.
+    Read,
+    /// View and change the document.
+    Write,
+}
+
+impl std::fmt::Display for AccessLevel {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Self::Read => f.write_str("read"),
+            Self::Write => f.write_str("write"),
+        }
+    }
+}
+
+/// Owner and shares of a document.
+///
+/// Documents without an ACL, such as those created anonymously, are open to everyone.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DocumentAcl {
+    /// User with full access, who alone may change the shares.
+    pub owner_id: String,
+    /// Other users and their access level.
+    pub shared: HashMap<String, AccessLevel>,
+}
+
+impl DocumentAcl {
+    /// Creates an ACL owned by `owner_id` with no shares.
+    #[must_use]
+    pub fn new(owner_id: impl Into<String>) -> Self {
+        Self { owner_id: owner_id.into(), shared: HashMap::new() }
+    }
+
+    /// Access granted to `user_id`, or `None` for no access.
+    #[must_use]
+    pub fn level_for(&self, user_id: Option<&str>) -> Option<AccessLevel> {
+        let user_id = user_id?;
+        if user_id == self.owner_id {
+            return Some(AccessLevel::Write);
+        }
+        self.shared.get(user_id).copied()
+    }
+
+    /// Returns whether `user_id` has at least `level` access.
+    #[must_use]
+    pub fn allows(&self, user_id: Option<&str>, level: AccessLevel) -> bool {
+        self.level_for(user_id).is_some_and(|granted| granted >= level)
+    }
+}
+
+/// The user making a request, from the [`USER_ID_HEADER`] header.
+///
+/// `None` for anonymous requests. The ID is whatever the client sent; see
+/// [`USER_ID_HEADER`] for why it must not be trusted without a proxy.
+#[derive(Debug, Clone, Default)]
+pub struct CurrentUser(pub Option<String>);
+
+impl CurrentUser {
+    /// The user ID, if the request is authenticated.
+    #[must_use]
+    pub fn id(&self) -> Option<&str> {
+        self.0.as_deref()
+    }
+}
+
+impl<S: Send + Sync> FromRequestParts<S> for CurrentUser {
+    type Rejection = Infallible;
+
+    fn from_request_parts(
+        parts: &mut Parts,
+        _state: &S,
+    ) -> impl Future<Output = Result<Self, Self::Rejection>> + Send {
+        let user_id = parts
+            .headers
+            .get(USER_ID_HEADER)
+            .and_then(|v| v.to_str().ok())
+            .map(str::trim)
+            .filter(|v| !v.is_empty())
+            .map(str::to_owned);
+        std::future::ready(Ok(Self(user_id)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_acl_levels() {
+        let mut acl = DocumentAcl::new("alice");
+        acl.shared.insert("bob".to_owned(), AccessLevel::Read);
+
+        assert!(acl.allows(Some("alice"), AccessLevel::Write));
+        assert!(acl.allows(Some("bob"), AccessLevel::Read));
+        assert!(!acl.allows(Some("bob"), AccessLevel::Write));
+        assert!(!acl.allows(Some("carol"), AccessLevel::Read));
+        assert!(!acl.allows(None, AccessLevel::Read));
+    }
+}
@@ -0,0 +1,72 @@
+//! API error responses.
+
+use axum::{
+    Json,
+    http::StatusCode,
+    response::{IntoResponse, Response},
+};
+use serde::Serialize;
+
+/// An error returned from an API route, rendered as a JSON body.
+#[derive(Debug, Serialize)]
+pub struct ApiError {
+    /// HTTP status of the response.
+    #[serde(skip)]
+    pub status: StatusCode,
+    /// Machine-readable error code.
+    pub code: &'static str,
+    /// Human-readable description.
+    pub message: String,
+}
+
+impl ApiError {
+    /// Creates an error with the given status, code and message.
+    pub fn new(status: StatusCode, code: &'static str, message: impl Into<String>) -> Self {
+        Self { status, code, message: message.into() }
+    }
+
+    /// The request was malformed.
+    pub fn bad_request(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::BAD_REQUEST, "bad_request", message)
+    }
+
+    /// The request is not allowed, such as editing a read-only document.
+    pub fn forbidden(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::FORBIDDEN, "forbidden", message)
+    }
+
+    /// A configured limit, such as the maximum number of documents, was reached.
+    pub fn limit_exceeded(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::TOO_MANY_REQUESTS, "limit_exceeded", message)
+    }
+
+    /// The requested resource does not exist.
+    pub fn not_found(message: impl Into<String>) -> Self {
+        Self::new(StatusCode::NOT_FOUND, "not_found", message)
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        (self.status, Json(self)).into_response()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_api_error_response_body() {
+        let response = ApiError::not_found("document abc not found").into_response();
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+
+        let bytes =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        let body: serde_json::Value = serde_json::from_slice(&bytes).expect("should be JSON");
+        assert_eq!(
+            body,
+            serde_json::json!({ "code": "not_found", "message": "document abc not found" })
+        );
+    }
+}
@@ -9,10 +9,12 @@ use tower_http::cors::{Any, CorsLayer};
 use tower_http::trace::TraceLayer;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
+mod acl;
+mod error;
 mod routes;
 mod state;
 
-use state::AppState;
+use state::{AppState, SyncLimits};
 
 #[tokio::main]
 async fn main() -> anyhow::Result<()> {
@@ -26,11 +28,55 @@ async fn main() -> anyhow::Result<()> {
         .init();
 
     // Create application state
-    let state = AppState::new();
+    let mut state = AppState::new();
+
+    // Connect to the database if one is configured
+    if let Ok(url) = std::env::var("DATABASE_URL") {
+        let pool = sqlx::SqlitePool::connect(&url).await?;
+        tracing::info!("Connected to database");
+        state = state.with_database(pool);
+    }
+
+    // Request body limit, overridable for large-document deployments
+    let max_body_bytes = std::env::var("GLOW_MAX_BODY_BYTES")
+        .ok()
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(routes::DEFAULT_MAX_BODY_BYTES);
+
+    // Sync payload cap, protecting the broadcast path from oversized messages
+    if let Some(max_payload_bytes) =
+        std::env::var("GLOW_MAX_SYNC_PAYLOAD_BYTES").ok().and_then(|v| v.parse().ok())
+    {
+        state = state.with_sync_limits(SyncLimits { max_payload_bytes, ..SyncLimits::default() });
+    }
+
+    // Document cap, guarding against runaway document creation
+    if let Some(max_documents) =
+        std::env::var("GLOW_MAX_DOCUMENTS").ok().and_then(|v| v.parse().ok())
+    {
+        state = state.with_max_documents(Some(max_documents));
+    }
+
+    // Soft document size limit, above which saves include a warning
+    if let Some(soft_size_limit) =
+        std::env::var("GLOW_SOFT_SIZE_LIMIT_BYTES").ok().and_then(|v| v.parse().ok())
+    {
+        state = state.with_soft_size_limit(soft_size_limit);
+    }
+
+    // Debounce for saving live sync edits to the document
+    if let Some(flush_delay_ms) =
+        std::env::var("GLOW_SYNC_FLUSH_DELAY_MS").ok().and_then(|v| v.parse().ok())
+    {
+        state = state.with_sync_flush_delay(std::time::Duration::from_millis(flush_delay_ms));
+    }
+
+    // Log document changes as they happen
+    let _activity_log = state.spawn_activity_log();
 
     // Build router
     let app = Router::new()
-        .nest("/api", routes::api_routes())
+        .nest("/api", routes::api_routes(max_body_bytes))
         .nest("/ws", routes::ws_routes())
         .layer(CorsLayer::new().allow_origin(Any).allow_methods(Any).allow_headers(Any))
         .layer(TraceLayer::new_for_http())
@@ -2,20 +2,29 @@
 
 use axum::{
     Json, Router,
-    extract::{Path, State},
-    http::StatusCode,
-    routing::get,
+    extract::{DefaultBodyLimit, Path, Query, State},
+    http::{HeaderMap, StatusCode, header},
+    response::{IntoResponse, Response},
+    routing::{get, post, put},
 };
-use glow_core::{Document, DocumentId};
+use chrono::{DateTime, Utc};
+use glow_core::{Document, DocumentEventKind, DocumentId, DocumentSummary, SizeWarning};
 use serde::{Deserialize, Serialize};
-use uuid::Uuid;
+use tower_http::limit::RequestBodyLimitLayer;
 
+use super::{authorize, parse_document_id};
+use crate::acl::{AccessLevel, CurrentUser, DocumentAcl};
+use crate::error::ApiError;
 use crate::state::AppState;
 
 /// Request to create a new document.
 #[derive(Deserialize)]
 pub struct CreateDocumentRequest {
     title: Option<String>,
+    #[serde(default)]
+    content: Option<String>,
+    #[serde(default)]
+    tags: Option<Vec<String>>,
 }
 
 /// Response containing a document.
@@ -26,7 +35,30 @@ pub struct DocumentResponse {
     content: String,
     created_at: String,
     modified_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed_at: Option<String>,
     version: u64,
+    tags: Vec<String>,
+    read_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+    /// Non-fatal warning that a saved document is larger than recommended.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    warning: Option<SizeWarning>,
+}
+
+impl DocumentResponse {
+    /// Sets the owner reported for the document.
+    fn with_owner(mut self, owner_id: Option<String>) -> Self {
+        self.owner_id = owner_id;
+        self
+    }
+
+    /// Reports a warning if the saved document is over the configured soft size limit.
+    fn with_size_warning(mut self, doc: &Document, state: &AppState) -> Self {
+        self.warning = doc.size_warning_above(state.soft_size_limit);
+        self
+    }
 }
 
 impl From<&Document> for DocumentResponse {
@@ -37,46 +69,258 @@ impl From<&Document> for DocumentResponse {
             content: doc.content.clone(),
             created_at: doc.metadata.created_at.to_rfc3339(),
             modified_at: doc.metadata.modified_at.to_rfc3339(),
+            accessed_at: doc.metadata.accessed_at.map(|t| t.to_rfc3339()),
             version: doc.metadata.version,
+            tags: doc.metadata.tags.clone(),
+            read_only: doc.metadata.read_only,
+            owner_id: None,
+            warning: None,
         }
     }
 }
 
-/// List all documents.
-async fn list_documents(State(state): State<AppState>) -> Json<Vec<DocumentResponse>> {
+/// Response listing a document without its content.
+#[derive(Serialize)]
+pub struct DocumentSummaryResponse {
+    id: String,
+    title: String,
+    created_at: String,
+    modified_at: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    accessed_at: Option<String>,
+    version: u64,
+    tags: Vec<String>,
+    read_only: bool,
+    content_length: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    owner_id: Option<String>,
+}
+
+impl From<&DocumentSummary> for DocumentSummaryResponse {
+    fn from(summary: &DocumentSummary) -> Self {
+        Self {
+            id: summary.id.to_string(),
+            title: summary.metadata.title.clone(),
+            created_at: summary.metadata.created_at.to_rfc3339(),
+            modified_at: summary.metadata.modified_at.to_rfc3339(),
+            accessed_at: summary.metadata.accessed_at.map(|t| t.to_rfc3339()),
+            version: summary.metadata.version,
+            tags: summary.metadata.tags.clone(),
+            read_only: summary.metadata.read_only,
+            content_length: summary.content_length,
+            owner_id: None,
+        }
+    }
+}
+
+/// Query parameters for listing documents.
+#[derive(Deserialize)]
+pub struct ListDocumentsQuery {
+    modified_since: Option<DateTime<Utc>>,
+}
+
+/// List the documents the user may read, most recently modified first.
+///
+/// Content is omitted; fetch a document by ID to read it. With
+/// `modified_since`, only documents modified after that instant are returned.
+async fn list_documents(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Query(query): Query<ListDocumentsQuery>,
+) -> Json<Vec<DocumentSummaryResponse>> {
+    let acls = state.acls.read().await;
     let documents = state.documents.read().await;
-    let response: Vec<DocumentResponse> = documents.values().map(DocumentResponse::from).collect();
+    let mut matching: Vec<&Document> = documents
+        .values()
+        .filter(|doc| query.modified_since.is_none_or(|since| doc.metadata.modified_at > since))
+        .filter(|doc| acls.get(&doc.id).is_none_or(|acl| acl.allows(user.id(), AccessLevel::Read)))
+        .collect();
+    matching.sort_by_key(|doc| std::cmp::Reverse(doc.metadata.modified_at));
+    let response = matching
+        .into_iter()
+        .map(|doc| {
+            let mut response = DocumentSummaryResponse::from(&DocumentSummary::from(doc));
+            response.owner_id = acls.get(&doc.id).map(|acl| acl.owner_id.clone());
+            response
+        })
+        .collect();
+    drop(documents);
+    drop(acls);
+
     Json(response)
 }
 
+/// Query parameters for fetching a single document.
+#[derive(Deserialize)]
+pub struct GetDocumentQuery {
+    format: Option<String>,
+}
+
+/// Representation requested for a document.
+#[derive(Debug, PartialEq, Eq)]
+enum DocumentFormat {
+    Json,
+    Markdown,
+}
+
+impl DocumentFormat {
+    /// Picks the format from `?format=`, falling back to the `Accept` header.
+    ///
+    /// Accept media ranges are taken in listed order; quality values are ignored.
+    fn negotiate(format: Option<&str>, headers: &HeaderMap) -> Result<Self, ApiError> {
+        if let Some(format) = format {
+            return match format {
+                "md" | "markdown" => Ok(Self::Markdown),
+                "json" => Ok(Self::Json),
+                _ => Err(ApiError::bad_request(format!("unsupported format: {format}"))),
+            };
+        }
+
+        let accept = headers.get(header::ACCEPT).and_then(|v| v.to_str().ok()).unwrap_or_default();
+        let preferred = accept
+            .split(',')
+            .map(|range| range.split(';').next().unwrap_or_default().trim())
+            .find_map(|media| match media {
+                "text/markdown" => Some(Self::Markdown),
+                "application/json" => Some(Self::Json),
+                _ => None,
+            });
+        Ok(preferred.unwrap_or(Self::Json))
+    }
+}
+
+/// Responds with `content` as markdown, with a `Content-Length` header.
+fn markdown_response(content: String) -> Response {
+    (
+        [
+            (header::CONTENT_TYPE, "text/markdown; charset=utf-8".to_owned()),
+            (header::CONTENT_LENGTH, content.len().to_string()),
+        ],
+        content,
+    )
+        .into_response()
+}
+
+/// Error for a document that does not exist.
+fn not_found(id: DocumentId) -> ApiError {
+    ApiError::not_found(format!("document {id} not found"))
+}
+
 /// Get a document by ID.
+///
+/// Returns the raw markdown content for `Accept: text/markdown` or `?format=md`,
+/// with a `Content-Length`, and a JSON `DocumentResponse` otherwise.
 async fn get_document(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<Json<DocumentResponse>, StatusCode> {
-    let uuid = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let doc_id = DocumentId::from_uuid(uuid);
+    Query(query): Query<GetDocumentQuery>,
+    user: CurrentUser,
+    headers: HeaderMap,
+) -> Result<Response, ApiError> {
+    let doc_id = parse_document_id(&id)?;
+    let format = DocumentFormat::negotiate(query.format.as_deref(), &headers)?;
+    let owner_id = authorize(&state, doc_id, &user, AccessLevel::Read).await?;
 
     let documents = state.documents.read().await;
-    documents.get(&doc_id).map(|doc| Json(DocumentResponse::from(doc))).ok_or(StatusCode::NOT_FOUND)
+    let doc = documents.get(&doc_id).ok_or_else(|| not_found(doc_id))?;
+    let response = match format {
+        DocumentFormat::Json => {
+            Json(DocumentResponse::from(doc).with_owner(owner_id)).into_response()
+        }
+        DocumentFormat::Markdown => markdown_response(doc.content.clone()),
+    };
+    drop(documents);
+
+    Ok(response)
 }
 
-/// Create a new document.
+/// Create a new document, owned by the user if the request is authenticated.
 async fn create_document(
     State(state): State<AppState>,
+    user: CurrentUser,
     Json(request): Json<CreateDocumentRequest>,
-) -> (StatusCode, Json<DocumentResponse>) {
-    let doc = match request.title {
+) -> Result<(StatusCode, Json<DocumentResponse>), ApiError> {
+    let mut doc = match request.title {
         Some(title) => Document::with_title(title),
         None => Document::new(),
     };
+    if let Some(content) = request.content {
+        doc = doc.with_initial_content(content);
+    }
+    if let Some(tags) = request.tags {
+        doc.metadata.tags = tags;
+    }
 
-    let response = DocumentResponse::from(&doc);
+    let owner_id = user.0;
+    let response =
+        DocumentResponse::from(&doc).with_owner(owner_id.clone()).with_size_warning(&doc, &state);
+    let (id, version) = (doc.id, doc.metadata.version);
 
     let mut documents = state.documents.write().await;
+    ensure_room_for_document(&state, documents.len())?;
     documents.insert(doc.id, doc);
+    drop(documents);
+    if let Some(owner_id) = owner_id {
+        state.acls.write().await.insert(id, DocumentAcl::new(owner_id));
+    }
+
+    state.publish(DocumentEventKind::Created, id, version);
+    Ok((StatusCode::CREATED, Json(response)))
+}
+
+/// Rejects adding a document to a workspace already holding `count` when that
+/// would exceed the configured maximum.
+fn ensure_room_for_document(state: &AppState, count: usize) -> Result<(), ApiError> {
+    match state.max_documents {
+        Some(max) if count >= max => Err(ApiError::limit_exceeded(format!(
+            "the workspace already holds the maximum of {max} documents"
+        ))),
+        _ => Ok(()),
+    }
+}
+
+/// Record that a document was opened, without bumping its version.
+async fn touch_accessed(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: CurrentUser,
+) -> Result<Json<DocumentResponse>, ApiError> {
+    let doc_id = parse_document_id(&id)?;
+    let owner_id = authorize(&state, doc_id, &user, AccessLevel::Read).await?;
+
+    let mut documents = state.documents.write().await;
+    let doc = documents.get_mut(&doc_id).ok_or_else(|| not_found(doc_id))?;
+    doc.mark_opened();
+    let response = DocumentResponse::from(&*doc).with_owner(owner_id);
+    drop(documents);
 
-    (StatusCode::CREATED, Json(response))
+    Ok(Json(response))
+}
+
+/// Duplicate a document, owned by the user if the request is authenticated.
+async fn duplicate_document(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+    user: CurrentUser,
+) -> Result<(StatusCode, Json<DocumentResponse>), ApiError> {
+    let doc_id = parse_document_id(&id)?;
+    authorize(&state, doc_id, &user, AccessLevel::Read).await?;
+
+    let mut documents = state.documents.write().await;
+    let copy = documents.get(&doc_id).ok_or_else(|| not_found(doc_id))?.duplicate();
+    ensure_room_for_document(&state, documents.len())?;
+    let response = DocumentResponse::from(&copy).with_owner(user.0.clone());
+    let (id, version) = (copy.id, copy.metadata.version);
+    documents.insert(copy.id, copy);
+    drop(documents);
+
+    if let Some(owner_id) = user.0 {
+        state.acls.write().await.insert(id, DocumentAcl::new(owner_id));
+    }
+
+    state.publish(DocumentEventKind::Created, id, version);
+
+    Ok((StatusCode::CREATED, Json(response)))
 }
 
 /// Request to update a document.
@@ -84,45 +328,577 @@ async fn create_document(
 pub struct UpdateDocumentRequest {
     title: Option<String>,
     content: Option<String>,
+    /// Freeze or unfreeze the document.
+    ///
+    /// Unfreezing applies before the edit and freezing after it, so one request can do both.
+    #[serde(default)]
+    read_only: Option<bool>,
 }
 
 /// Update a document.
+///
+/// Edits to a read-only document, or by a user without write access, are rejected with 403.
 async fn update_document(
     State(state): State<AppState>,
     Path(id): Path<String>,
+    user: CurrentUser,
     Json(request): Json<UpdateDocumentRequest>,
-) -> Result<Json<DocumentResponse>, StatusCode> {
-    let uuid = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let doc_id = DocumentId::from_uuid(uuid);
+) -> Result<Json<DocumentResponse>, ApiError> {
+    let doc_id = parse_document_id(&id)?;
+    let owner_id = authorize(&state, doc_id, &user, AccessLevel::Write).await?;
 
     let mut documents = state.documents.write().await;
-    let doc = documents.get_mut(&doc_id).ok_or(StatusCode::NOT_FOUND)?;
+    let doc = documents.get_mut(&doc_id).ok_or_else(|| not_found(doc_id))?;
 
+    if request.read_only == Some(false) {
+        doc.set_read_only(false);
+    }
     if let Some(title) = request.title {
-        doc.set_title(title);
+        doc.set_title(title).map_err(|e| ApiError::forbidden(e.to_string()))?;
     }
     if let Some(content) = request.content {
-        doc.set_content(content);
+        doc.set_content(content).map_err(|e| ApiError::forbidden(e.to_string()))?;
+    }
+    if request.read_only == Some(true) {
+        doc.set_read_only(true);
     }
+    let response =
+        DocumentResponse::from(&*doc).with_owner(owner_id).with_size_warning(doc, &state);
+    let version = doc.metadata.version;
+    drop(documents);
 
-    Ok(Json(DocumentResponse::from(&*doc)))
+    state.publish(DocumentEventKind::Updated, doc_id, version);
+    Ok(Json(response))
 }
 
 /// Delete a document.
 async fn delete_document(
     State(state): State<AppState>,
     Path(id): Path<String>,
-) -> Result<StatusCode, StatusCode> {
-    let uuid = Uuid::parse_str(&id).map_err(|_| StatusCode::BAD_REQUEST)?;
-    let doc_id = DocumentId::from_uuid(uuid);
+    user: CurrentUser,
+) -> Result<StatusCode, ApiError> {
+    let doc_id = parse_document_id(&id)?;
+    authorize(&state, doc_id, &user, AccessLevel::Write).await?;
+
+    let removed = state.documents.write().await.remove(&doc_id).ok_or_else(|| not_found(doc_id))?;
+    state.acls.write().await.remove(&doc_id);
+
+    state.publish(DocumentEventKind::Deleted, doc_id, removed.metadata.version);
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Request to delete several documents.
+#[derive(Deserialize)]
+pub struct BatchDeleteRequest {
+    ids: Vec<String>,
+}
+
+/// Response for a batch delete.
+#[derive(Serialize)]
+pub struct BatchDeleteResponse {
+    deleted: usize,
+}
+
+/// Delete several documents at once, skipping IDs that do not exist.
+///
+/// Nothing is deleted if the user lacks write access to any of them.
+async fn batch_delete_documents(
+    State(state): State<AppState>,
+    user: CurrentUser,
+    Json(request): Json<BatchDeleteRequest>,
+) -> Result<Json<BatchDeleteResponse>, ApiError> {
+    let doc_ids =
+        request.ids.iter().map(|id| parse_document_id(id)).collect::<Result<Vec<_>, _>>()?;
+    for &doc_id in &doc_ids {
+        authorize(&state, doc_id, &user, AccessLevel::Write).await?;
+    }
 
     let mut documents = state.documents.write().await;
-    documents.remove(&doc_id).map(|_| StatusCode::NO_CONTENT).ok_or(StatusCode::NOT_FOUND)
+    let removed: Vec<Document> = doc_ids.iter().filter_map(|id| documents.remove(id)).collect();
+    drop(documents);
+
+    let mut acls = state.acls.write().await;
+    for doc in &removed {
+        acls.remove(&doc.id);
+    }
+    drop(acls);
+
+    for doc in &removed {
+        state.publish(DocumentEventKind::Deleted, doc.id, doc.metadata.version);
+    }
+    Ok(Json(BatchDeleteResponse { deleted: removed.len() }))
+}
+
+/// Request to share a document with a user.
+#[derive(Deserialize)]
+pub struct ShareRequest {
+    access: AccessLevel,
+}
+
+/// Apply `change` to a document's shares on behalf of its owner.
+async fn change_shares(
+    state: &AppState,
+    id: &str,
+    user: &CurrentUser,
+    change: impl FnOnce(&mut DocumentAcl),
+) -> Result<Json<DocumentAcl>, ApiError> {
+    let doc_id = parse_document_id(id)?;
+    if !state.documents.read().await.contains_key(&doc_id) {
+        return Err(not_found(doc_id));
+    }
+
+    let mut acls = state.acls.write().await;
+    let acl =
+        acls.get_mut(&doc_id).filter(|acl| user.id() == Some(acl.owner_id.as_str())).ok_or_else(
+            || ApiError::forbidden(format!("only the owner can share document {doc_id}")),
+        )?;
+    change(acl);
+    let response = acl.clone();
+    drop(acls);
+
+    Ok(Json(response))
+}
+
+/// Share a document with a user, or change their access level. Owner only.
+async fn share_document(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(String, String)>,
+    user: CurrentUser,
+    Json(request): Json<ShareRequest>,
+) -> Result<Json<DocumentAcl>, ApiError> {
+    change_shares(&state, &id, &user, |acl| {
+        acl.shared.insert(user_id, request.access);
+    })
+    .await
+}
+
+/// Stop sharing a document with a user. Owner only.
+async fn unshare_document(
+    State(state): State<AppState>,
+    Path((id, user_id)): Path<(String, String)>,
+    user: CurrentUser,
+) -> Result<Json<DocumentAcl>, ApiError> {
+    change_shares(&state, &id, &user, |acl| {
+        acl.shared.remove(&user_id);
+    })
+    .await
 }
 
 /// Creates document routes.
-pub fn routes() -> Router<AppState> {
+///
+/// Requests with bodies larger than `max_body_bytes` are rejected with 413.
+pub fn routes(max_body_bytes: usize) -> Router<AppState> {
     Router::new()
         .route("/documents", get(list_documents).post(create_document))
         .route("/documents/{id}", get(get_document).put(update_document).delete(delete_document))
+        .route("/documents/batch-delete", post(batch_delete_documents))
+        .route("/documents/{id}/accessed", post(touch_accessed))
+        .route("/documents/{id}/duplicate", post(duplicate_document))
+        .route("/documents/{id}/shares/{user_id}", put(share_document).delete(unshare_document))
+        // Replace axum's built-in 2 MiB extractor limit with the configured one
+        .layer(DefaultBodyLimit::disable())
+        .layer(RequestBodyLimitLayer::new(max_body_bytes))
+}
+
+#[cfg(test)]
+mod tests {
+    use axum::{body::Body, http::Request};
+    use tower::ServiceExt;
+    use uuid::Uuid;
+
+    use super::*;
+
+    fn create_request(body: String) -> Request<Body> {
+        Request::post("/documents")
+            .header("content-type", "application/json")
+            .body(Body::from(body))
+            .expect("should build request")
+    }
+
+    async fn get_with(app: Router, uri: &str, accept: Option<&str>) -> Response {
+        let mut request = Request::get(uri);
+        if let Some(accept) = accept {
+            request = request.header(header::ACCEPT, accept);
+        }
+        app.oneshot(request.body(Body::empty()).expect("should build request"))
+            .await
+            .expect("should respond")
+    }
+
+    async fn app_with_document() -> (Router, String) {
+        let state = AppState::new();
+        let doc = Document::with_title("Notes").with_initial_content("# Heading\n\nBody");
+        let id = doc.id.to_string();
+        state.documents.write().await.insert(doc.id, doc);
+        (routes(1024).with_state(state), id)
+    }
+
+    async fn body_text(response: Response) -> String {
+        let bytes =
+            axum::body::to_bytes(response.into_body(), usize::MAX).await.expect("should read body");
+        String::from_utf8(bytes.to_vec()).expect("body should be UTF-8")
+    }
+
+    #[tokio::test]
+    async fn test_get_document_as_json() {
+        let (app, id) = app_with_document().await;
+
+        let response = get_with(app, &format!("/documents/{id}"), Some("application/json")).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let body: serde_json::Value =
+            serde_json::from_str(&body_text(response).await).expect("should be JSON");
+        assert_eq!(body["title"], "Notes");
+    }
+
+    #[tokio::test]
+    async fn test_get_document_as_markdown() {
+        let (app, id) = app_with_document().await;
+
+        let response =
+            get_with(app, &format!("/documents/{id}"), Some("text/markdown, application/json"))
+                .await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/markdown; charset=utf-8");
+        assert_eq!(body_text(response).await, "# Heading\n\nBody");
+    }
+
+    #[tokio::test]
+    async fn test_large_markdown_download_is_sent_whole() {
+        let state = AppState::new();
+        let content = "Ünïcödé paragraph text.\n".repeat(10_000);
+        let doc = Document::with_title("Big").with_initial_content(content.clone());
+        let id = doc.id;
+        state.documents.write().await.insert(id, doc);
+        let app = routes(1024).with_state(state);
+
+        let response = get_with(app, &format!("/documents/{id}?format=md"), None).await;
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[header::CONTENT_LENGTH], content.len().to_string().as_str());
+        assert_eq!(body_text(response).await, content);
+    }
+
+    #[tokio::test]
+    async fn test_get_document_format_query_overrides_accept() {
+        let (app, id) = app_with_document().await;
+
+        let uri = format!("/documents/{id}?format=md");
+        let response = get_with(app.clone(), &uri, Some("application/json")).await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "text/markdown; charset=utf-8");
+
+        let uri = format!("/documents/{id}?format=json");
+        let response = get_with(app.clone(), &uri, Some("text/markdown")).await;
+        assert_eq!(response.headers()[header::CONTENT_TYPE], "application/json");
+
+        let uri = format!("/documents/{id}?format=pdf");
+        assert_eq!(get_with(app, &uri, None).await.status(), StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_error_responses_carry_json_body() {
+        let (app, _) = app_with_document().await;
+
+        let response = get_with(app.clone(), "/documents/not-a-uuid", None).await;
+        assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+        let body: serde_json::Value =
+            serde_json::from_str(&body_text(response).await).expect("should be JSON");
+        assert_eq!(body["code"], "bad_request");
+        assert_eq!(body["message"], "invalid document id: not-a-uuid");
+
+        let missing = Uuid::new_v4();
+        let response = get_with(app, &format!("/documents/{missing}"), None).await;
+        assert_eq!(response.status(), StatusCode::NOT_FOUND);
+        let body: serde_json::Value =
+            serde_json::from_str(&body_text(response).await).expect("should be JSON");
+        assert_eq!(body["code"], "not_found");
+        assert_eq!(body["message"], format!("document {missing} not found"));
+    }
+
+    #[tokio::test]
+    async fn test_body_within_limit_is_accepted() {
+        let app = routes(1024).with_state(AppState::new());
+
+        let response = app
+            .oneshot(create_request(r#"{"title":"Small"}"#.to_owned()))
+            .await
+            .expect("should respond");
+        assert_eq!(response.status(), StatusCode::CREATED);
+    }
+
+    #[tokio::test]
+    async fn test_document_limit_rejects_creation() {
+        let app = routes(1024).with_state(AppState::new().with_max_documents(Some(2)));
+
+        for title in ["First", "Second"] {
+            let request = create_request(format!(r#"{{"title":"{title}"}}"#));
+            let response = app.clone().oneshot(request).await.expect("should respond");
+            assert_eq!(response.status(), StatusCode::CREATED);
+        }
+
+        let request = create_request(r#"{"title":"Third"}"#.to_owned());
+        let response = app.oneshot(request).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::TOO_MANY_REQUESTS);
+        let body: serde_json::Value =
+            serde_json::from_str(&body_text(response).await).expect("should be JSON");
+        assert_eq!(body["code"], "limit_exceeded");
+    }
+
+    #[tokio::test]
+    async fn test_saves_over_soft_size_limit_warn() {
+        let app = routes(1024).with_state(AppState::new().with_soft_size_limit(10));
+
+        let (status, body) =
+            send_as(&app, "POST", "/documents", None, r#"{"content":"Short"}"#).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let json: serde_json::Value = serde_json::from_str(&body).expect("should be JSON");
+        assert!(json.get("warning").is_none());
+
+        let uri = format!("/documents/{}", json["id"].as_str().expect("should have id"));
+        let (status, body) =
+            send_as(&app, "PUT", &uri, None, r#"{"content":"Much longer content"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        let json: serde_json::Value = serde_json::from_str(&body).expect("should be JSON");
+        assert_eq!(json["content"], "Much longer content");
+        assert_eq!(json["warning"]["content_bytes"], 19);
+        assert_eq!(json["warning"]["soft_limit_bytes"], 10);
+    }
+
+    #[tokio::test]
+    async fn test_oversized_body_is_rejected() {
+        let app = routes(1024).with_state(AppState::new());
+        let title = "x".repeat(4096);
+
+        let response = app
+            .oneshot(create_request(format!(r#"{{"title":"{title}"}}"#)))
+            .await
+            .expect("should respond");
+        assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    }
+
+    #[tokio::test]
+    async fn test_list_documents_modified_since() {
+        let state = AppState::new();
+        let cutoff = Utc::now() - chrono::Duration::minutes(30);
+
+        let mut old = Document::with_title("Old");
+        old.metadata.modified_at = cutoff - chrono::Duration::minutes(30);
+        let new = Document::with_title("New");
+        let new_id = new.id.to_string();
+        {
+            let mut documents = state.documents.write().await;
+            documents.insert(old.id, old);
+            documents.insert(new.id, new);
+        }
+
+        let query = ListDocumentsQuery { modified_since: Some(cutoff) };
+        let Json(docs) =
+            list_documents(State(state.clone()), CurrentUser::default(), Query(query)).await;
+        assert_eq!(docs.len(), 1);
+        assert_eq!(docs[0].id, new_id);
+
+        let query = ListDocumentsQuery { modified_since: None };
+        let Json(docs) = list_documents(State(state), CurrentUser::default(), Query(query)).await;
+        assert_eq!(docs.len(), 2);
+        assert_eq!(docs[0].id, new_id);
+    }
+
+    #[tokio::test]
+    async fn test_batch_delete_skips_missing_ids() {
+        let state = AppState::new();
+        let doc1 = Document::with_title("First");
+        let doc2 = Document::with_title("Second");
+        let ids = vec![doc1.id.to_string(), DocumentId::new().to_string(), doc2.id.to_string()];
+        {
+            let mut documents = state.documents.write().await;
+            documents.insert(doc1.id, doc1);
+            documents.insert(doc2.id, doc2);
+        }
+
+        let Json(response) = batch_delete_documents(
+            State(state.clone()),
+            CurrentUser::default(),
+            Json(BatchDeleteRequest { ids }),
+        )
+        .await
+        .expect("batch delete should succeed");
+        assert_eq!(response.deleted, 2);
+        assert!(state.documents.read().await.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_create_document_with_content() {
+        let state = AppState::new();
+        let request = CreateDocumentRequest {
+            title: Some("Imported".to_owned()),
+            content: Some("Imported body".to_owned()),
+            tags: Some(vec!["import".to_owned()]),
+        };
+
+        let (code, Json(response)) =
+            create_document(State(state.clone()), CurrentUser::default(), Json(request))
+                .await
+                .expect("should create document");
+        assert_eq!(code, StatusCode::CREATED);
+        assert_eq!(response.content, "Imported body");
+        assert_eq!(response.tags, vec!["import".to_owned()]);
+
+        let stored = state.documents.read().await.values().next().cloned();
+        let doc = stored.expect("should store document");
+        assert!(doc.crdt_state.is_some());
+    }
+
+    #[tokio::test]
+    async fn test_crud_emits_document_events() {
+        let state = AppState::new();
+        let mut events = state.subscribe();
+
+        let request = CreateDocumentRequest { title: None, content: None, tags: None };
+        let (_, Json(created)) =
+            create_document(State(state.clone()), CurrentUser::default(), Json(request))
+                .await
+                .expect("should create document");
+
+        let request = UpdateDocumentRequest {
+            title: Some("Renamed".to_owned()),
+            content: None,
+            read_only: None,
+        };
+        let _updated = update_document(
+            State(state.clone()),
+            Path(created.id.clone()),
+            CurrentUser::default(),
+            Json(request),
+        )
+        .await
+        .expect("should update document");
+        delete_document(State(state.clone()), Path(created.id.clone()), CurrentUser::default())
+            .await
+            .expect("should delete document");
+
+        let expected = [
+            (DocumentEventKind::Created, 1),
+            (DocumentEventKind::Updated, 2),
+            (DocumentEventKind::Deleted, 2),
+        ];
+        for (kind, version) in expected {
+            let event = events.try_recv().expect("should receive event");
+            assert_eq!(event.kind, kind);
+            assert_eq!(event.document_id.to_string(), created.id);
+            assert_eq!(event.version, version);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_read_only_document_rejects_updates() {
+        let (app, id) = app_with_document().await;
+        let uri = format!("/documents/{id}");
+        let put = |body: &'static str| {
+            Request::put(uri.as_str())
+                .header("content-type", "application/json")
+                .body(Body::from(body))
+                .expect("should build request")
+        };
+
+        let response =
+            app.clone().oneshot(put(r#"{"read_only":true}"#)).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+
+        let response =
+            app.clone().oneshot(put(r#"{"content":"Edited"}"#)).await.expect("should respond");
+        assert_eq!(response.status(), StatusCode::FORBIDDEN);
+        assert!(body_text(response).await.contains("\"forbidden\""));
+
+        let response = app
+            .oneshot(put(r#"{"read_only":false,"content":"Edited"}"#))
+            .await
+            .expect("should respond");
+        assert_eq!(response.status(), StatusCode::OK);
+        let json: serde_json::Value =
+            serde_json::from_str(&body_text(response).await).expect("should be JSON");
+        assert_eq!(json["content"], "Edited");
+        assert_eq!(json["read_only"], false);
+    }
+
+    /// Sends a JSON request to `app` as `user`, returning the status and body.
+    async fn send_as(
+        app: &Router,
+        method: &str,
+        uri: &str,
+        user: Option<&str>,
+        body: &str,
+    ) -> (StatusCode, String) {
+        let mut request =
+            Request::builder().method(method).uri(uri).header("content-type", "application/json");
+        if let Some(user) = user {
+            request = request.header(crate::acl::USER_ID_HEADER, user);
+        }
+        let request = request.body(Body::from(body.to_owned())).expect("should build request");
+        let response = app.clone().oneshot(request).await.expect("should respond");
+        (response.status(), body_text(response).await)
+    }
+
+    /// Creates a document owned by `owner`, returning its ID.
+    async fn create_owned(app: &Router, owner: &str) -> String {
+        let (status, body) =
+            send_as(app, "POST", "/documents", Some(owner), r#"{"title":"Private"}"#).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let json: serde_json::Value = serde_json::from_str(&body).expect("should be JSON");
+        assert_eq!(json["owner_id"], owner);
+        json["id"].as_str().expect("should have id").to_owned()
+    }
+
+    #[tokio::test]
+    async fn test_owner_has_full_access() {
+        let app = routes(1024).with_state(AppState::new());
+        let id = create_owned(&app, "alice").await;
+        let uri = format!("/documents/{id}");
+
+        let (status, _) = send_as(&app, "GET", &uri, Some("alice"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send_as(&app, "PUT", &uri, Some("alice"), r#"{"content":"Mine"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        let (status, _) = send_as(&app, "DELETE", &uri, Some("alice"), "").await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+    }
+
+    #[tokio::test]
+    async fn test_shared_read_access() {
+        let app = routes(1024).with_state(AppState::new());
+        let id = create_owned(&app, "alice").await;
+        let uri = format!("/documents/{id}");
+        let share_uri = format!("/documents/{id}/shares/bob");
+
+        let (status, _) =
+            send_as(&app, "PUT", &share_uri, Some("bob"), r#"{"access":"read"}"#).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        let (status, body) =
+            send_as(&app, "PUT", &share_uri, Some("alice"), r#"{"access":"read"}"#).await;
+        assert_eq!(status, StatusCode::OK);
+        assert!(body.contains(r#""bob":"read""#));
+
+        let (status, _) = send_as(&app, "GET", &uri, Some("bob"), "").await;
+        assert_eq!(status, StatusCode::OK);
+        let (_, body) = send_as(&app, "GET", "/documents", Some("bob"), "").await;
+        assert!(body.contains(&id));
+        let (status, _) = send_as(&app, "PUT", &uri, Some("bob"), r#"{"content":"Theirs"}"#).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+    }
+
+    #[tokio::test]
+    async fn test_unshared_user_is_denied() {
+        let app = routes(1024).with_state(AppState::new());
+        let id = create_owned(&app, "alice").await;
+        let uri = format!("/documents/{id}");
+
+        for user in [Some("mallory"), None] {
+            let (status, body) = send_as(&app, "GET", &uri, user, "").await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+            assert!(body.contains(r#""code":"forbidden""#));
+            let (status, _) = send_as(&app, "DELETE", &uri, user, "").await;
+            assert_eq!(status, StatusCode::FORBIDDEN);
+        }
+        let (_, body) = send_as(&app, "GET", "/documents", Some("mallory"), "").await;
+        assert_eq!(body, "[]");
+    }
 }
@@ -1,7 +1,8 @@
 //! Health check endpoints.
 
-use axum::{Json, Router, routing::get};
+use axum::{Json, Router, extract::State, http::StatusCode, routing::get};
 use serde::Serialize;
+use sqlx::SqlitePool;
 
 use crate::state::AppState;
 
@@ -10,14 +11,85 @@ use crate::state::AppState;
 struct HealthResponse {
     status: &'static str,
     version: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    storage: Option<StorageHealth>,
+}
+
+/// Storage connectivity details.
+#[derive(Serialize)]
+struct StorageHealth {
+    status: &'static str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Runs a cheap query against the database to verify connectivity.
+async fn check_storage(pool: &SqlitePool) -> StorageHealth {
+    match sqlx::query("SELECT 1").execute(pool).await {
+        Ok(_) => StorageHealth { status: "ok", error: None },
+        Err(e) => StorageHealth { status: "unavailable", error: Some(e.to_string()) },
+    }
 }
 
 /// Health check handler.
-async fn health() -> Json<HealthResponse> {
-    Json(HealthResponse { status: "healthy", version: env!("CARGO_PKG_VERSION") })
+///
+/// Returns 503 with a `degraded` status when storage is configured but unreachable.
+async fn health(State(state): State<AppState>) -> (StatusCode, Json<HealthResponse>) {
+    let storage = match &state.db {
+        Some(pool) => Some(check_storage(pool).await),
+        None => None,
+    };
+
+    let healthy = storage.as_ref().is_none_or(|s| s.error.is_none());
+    let (code, status) = if healthy {
+        (StatusCode::OK, "healthy")
+    } else {
+        (StatusCode::SERVICE_UNAVAILABLE, "degraded")
+    };
+
+    (code, Json(HealthResponse { status, version: env!("CARGO_PKG_VERSION"), storage }))
 }
 
 /// Creates health check routes.
 pub fn routes() -> Router<AppState> {
     Router::new().route("/health", get(health))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_health_without_storage() {
+        let (code, Json(body)) = health(State(AppState::new())).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body.status, "healthy");
+        assert!(body.storage.is_none());
+    }
+
+    #[tokio::test]
+    async fn test_health_with_working_storage() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.expect("should open database");
+        let state = AppState::new().with_database(pool);
+
+        let (code, Json(body)) = health(State(state)).await;
+        assert_eq!(code, StatusCode::OK);
+        assert_eq!(body.status, "healthy");
+        assert_eq!(body.storage.map(|s| s.status), Some("ok"));
+    }
+
+    #[tokio::test]
+    async fn test_health_with_closed_storage() {
+        let pool = SqlitePool::connect("sqlite::memory:").await.expect("should open database");
+        pool.close().await;
+        let state = AppState::new().with_database(pool);
+
+        let (code, Json(body)) = health(State(state)).await;
+        assert_eq!(code, StatusCode::SERVICE_UNAVAILABLE);
+        assert_eq!(body.status, "degraded");
+
+        let storage = body.storage.expect("should report storage");
+        assert_eq!(storage.status, "unavailable");
+        assert!(storage.error.is_some());
+    }
+}
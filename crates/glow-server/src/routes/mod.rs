@@ -5,15 +5,53 @@ mod health;
 mod sync;
 
 use axum::Router;
+use glow_core::DocumentId;
+use uuid::Uuid;
 
+use crate::acl::{AccessLevel, CurrentUser};
+use crate::error::ApiError;
 use crate::state::AppState;
 
+/// Default maximum request body size in bytes (4 MiB).
+pub const DEFAULT_MAX_BODY_BYTES: usize = 4 * 1024 * 1024;
+
 /// Creates the API routes.
-pub fn api_routes() -> Router<AppState> {
-    Router::new().merge(health::routes()).merge(documents::routes())
+///
+/// Document endpoints reject request bodies larger than `max_body_bytes`.
+pub fn api_routes(max_body_bytes: usize) -> Router<AppState> {
+    Router::new().merge(health::routes()).merge(documents::routes(max_body_bytes))
 }
 
 /// Creates the WebSocket routes.
 pub fn ws_routes() -> Router<AppState> {
     sync::routes()
 }
+
+/// Parses a document ID from a path or body parameter.
+fn parse_document_id(id: &str) -> Result<DocumentId, ApiError> {
+    Uuid::parse_str(id)
+        .map(DocumentId::from_uuid)
+        .map_err(|_| ApiError::bad_request(format!("invalid document id: {id}")))
+}
+
+/// Checks that `user` has `level` access to a document, returning its owner if it has one.
+///
+/// Documents without an ACL are open to everyone.
+async fn authorize(
+    state: &AppState,
+    doc_id: DocumentId,
+    user: &CurrentUser,
+    level: AccessLevel,
+) -> Result<Option<String>, ApiError> {
+    let acl = state.acls.read().await.get(&doc_id).cloned();
+    match (acl, user.id()) {
+        (None, _) => Ok(None),
+        (Some(acl), _) if acl.allows(user.id(), level) => Ok(Some(acl.owner_id)),
+        (Some(_), Some(user_id)) => Err(ApiError::forbidden(format!(
+            "user {user_id} has no {level} access to document {doc_id}"
+        ))),
+        (Some(_), None) => {
+            Err(ApiError::forbidden(format!("document {doc_id} requires authentication")))
+        }
+    }
+}
@@ -9,19 +9,51 @@ use axum::{
     response::Response,
     routing::get,
 };
-use glow_core::DocumentSync;
+use std::sync::Arc;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+use glow_core::{DocumentId, DocumentSync};
 use serde::{Deserialize, Serialize};
+use tokio::sync::broadcast::error::RecvError;
+
+use super::{authorize, parse_document_id};
+use crate::acl::{AccessLevel, CurrentUser};
+use crate::error::ApiError;
+use crate::state::{AppState, SyncLimits, SyncRoom};
+
+/// Sync protocol version spoken by this server.
+const PROTOCOL_VERSION: u32 = 1;
 
-use crate::state::AppState;
+/// Oldest client protocol version still accepted.
+const MIN_PROTOCOL_VERSION: u32 = 1;
 
 /// Sync message types.
 #[derive(Debug, Serialize, Deserialize)]
 #[serde(tag = "type", content = "payload")]
 enum SyncMessage {
+    /// Opening handshake: the client's protocol version, echoed with the server's on success.
+    #[serde(rename = "hello")]
+    Hello { protocol_version: u32 },
+
+    /// Opening handshake of a reconnecting client: its protocol version and the
+    /// state vector it last synced to. Answered with a `sync_response` holding
+    /// only the changes it missed.
+    #[serde(rename = "resume")]
+    Resume { protocol_version: u32, state_vector: Vec<u8> },
+
+    /// The client's protocol version is not supported; the server closes the connection.
+    #[serde(rename = "incompatible")]
+    Incompatible { min: u32, max: u32 },
+
     /// Request sync state vector.
     #[serde(rename = "sync_request")]
     SyncRequest { state_vector: Vec<u8> },
 
+    /// Request the complete document state (for clients with no local state).
+    #[serde(rename = "full_state_request")]
+    FullStateRequest,
+
     /// Sync update response.
     #[serde(rename = "sync_response")]
     SyncResponse { update: Vec<u8> },
@@ -33,59 +65,293 @@ enum SyncMessage {
     /// Awareness update (cursor position, etc.).
     #[serde(rename = "awareness")]
     Awareness { client_id: u64, state: Vec<u8> },
+
+    /// Request the document as it was at an earlier version.
+    ///
+    /// Versions are the document's versions: the one it had when its room
+    /// opened, and each one saved since.
+    #[serde(rename = "version_request")]
+    VersionRequest { version: u64 },
+
+    /// The document at a requested version: its content and full encoded state.
+    #[serde(rename = "version_response")]
+    VersionResponse { version: u64, content: String, state: Vec<u8> },
+
+    /// Client acknowledges it has applied updates up to `state_vector`.
+    #[serde(rename = "ack")]
+    Ack { state_vector: Vec<u8> },
+
+    /// Protocol error; the offending message was dropped.
+    #[serde(rename = "error")]
+    Error { message: String },
+}
+
+/// Source of connection IDs, used to avoid echoing a client's own updates.
+static NEXT_PEER_ID: AtomicU64 = AtomicU64::new(1);
+
+/// Per-connection sync progress.
+#[derive(Debug)]
+struct PeerState {
+    /// Identifies this connection's updates in its sync room.
+    id: u64,
+    /// Protocol version agreed in the handshake, if it has completed.
+    protocol_version: Option<u32>,
+    /// Latest state vector acknowledged by the client.
+    acked_state_vector: Option<Vec<u8>>,
+    /// Limits enforced on this client's messages.
+    limits: SyncLimits,
+    /// Start of the current awareness rate-limit window.
+    awareness_window: Instant,
+    /// Awareness updates accepted in the current window.
+    awareness_count: u32,
+    /// Updates from this client applied to the room.
+    updates_applied: u64,
+    /// Whether the client may change the document.
+    writable: bool,
+}
+
+impl PeerState {
+    fn new(limits: SyncLimits) -> Self {
+        Self {
+            id: NEXT_PEER_ID.fetch_add(1, Ordering::Relaxed),
+            protocol_version: None,
+            acked_state_vector: None,
+            limits,
+            awareness_window: Instant::now(),
+            awareness_count: 0,
+            updates_applied: 0,
+            writable: true,
+        }
+    }
+
+    /// Checks an incoming message against the payload and rate limits.
+    fn check_limits(&mut self, msg: &SyncMessage) -> Result<(), String> {
+        let payload_len = match msg {
+            SyncMessage::Update { update } | SyncMessage::SyncResponse { update } => update.len(),
+            SyncMessage::Awareness { state, .. } => state.len(),
+            _ => return Ok(()),
+        };
+        if payload_len > self.limits.max_payload_bytes {
+            return Err(format!(
+                "payload of {payload_len} bytes exceeds limit of {} bytes",
+                self.limits.max_payload_bytes
+            ));
+        }
+
+        if matches!(msg, SyncMessage::Awareness { .. }) {
+            if self.awareness_window.elapsed() >= Duration::from_secs(1) {
+                self.awareness_window = Instant::now();
+                self.awareness_count = 0;
+            }
+            if self.awareness_count >= self.limits.max_awareness_per_sec {
+                return Err("awareness update rate limit exceeded".to_owned());
+            }
+            self.awareness_count += 1;
+        }
+
+        Ok(())
+    }
+
+    /// Whether the client has acknowledged every change in `sync`.
+    fn is_caught_up(&self, sync: &DocumentSync) -> bool {
+        self.acked_state_vector.as_deref().is_some_and(|sv| sync.is_covered_by(sv))
+    }
 }
 
 /// Handle WebSocket upgrade for document sync.
+///
+/// The user needs read access to the document to connect, and write access
+/// for their updates to be applied. Updates to a read-only document are
+/// refused for everyone.
 async fn ws_handler(
     ws: WebSocketUpgrade,
-    State(_state): State<AppState>,
-    Path(_doc_id): Path<String>,
-) -> Response {
-    ws.on_upgrade(handle_socket)
+    State(state): State<AppState>,
+    Path(doc_id): Path<String>,
+    user: CurrentUser,
+) -> Result<Response, ApiError> {
+    let doc_id = parse_document_id(&doc_id)?;
+    authorize(&state, doc_id, &user, AccessLevel::Read).await?;
+    let read_only =
+        state.documents.read().await.get(&doc_id).is_some_and(|doc| doc.metadata.read_only);
+    let writable = !read_only && authorize(&state, doc_id, &user, AccessLevel::Write).await.is_ok();
+    Ok(ws.on_upgrade(move |socket| handle_socket(socket, state, doc_id, writable)))
 }
 
 /// Handle individual WebSocket connection.
-async fn handle_socket(mut socket: WebSocket) {
-    let sync = DocumentSync::new();
+///
+/// Answers the client's messages and relays updates applied by the room's
+/// other connections once the handshake has completed. Updates are saved to
+/// the document after a debounce, and when the last connection closes.
+async fn handle_socket(socket: WebSocket, state: AppState, doc_id: DocumentId, writable: bool) {
+    let room = state.join_sync_room(doc_id).await;
+    let peer = PeerState { writable, ..PeerState::new(state.sync_limits) };
+    sync_socket(socket, &state, doc_id, &room, peer).await;
+    state.leave_sync_room(doc_id, &room).await;
+}
 
-    while let Some(msg) = socket.recv().await {
-        let Ok(msg) = msg else {
-            break;
-        };
+/// Exchanges messages with one client until either side disconnects.
+async fn sync_socket(
+    mut socket: WebSocket,
+    state: &AppState,
+    doc_id: DocumentId,
+    room: &Arc<SyncRoom>,
+    mut peer: PeerState,
+) {
+    let mut updates = room.subscribe();
 
-        if let Message::Text(text) = msg {
-            if let Ok(sync_msg) = serde_json::from_str::<SyncMessage>(&text) {
-                let response = handle_sync_message(&sync, sync_msg);
-                if let Some(response) = response {
-                    let json = serde_json::to_string(&response).unwrap_or_default();
-                    if socket.send(Message::Text(json.into())).await.is_err() {
-                        break;
-                    }
+    loop {
+        let outgoing = tokio::select! {
+            msg = socket.recv() => {
+                let Some(Ok(msg)) = msg else {
+                    break;
+                };
+                let applied = peer.updates_applied;
+                let response = respond(room, &mut peer, msg);
+                if peer.updates_applied != applied {
+                    state.schedule_sync_flush(doc_id, room);
                 }
+                response
             }
+            update = updates.recv() => match update {
+                Err(RecvError::Closed) => break,
+                update => relay(&peer, update),
+            },
+        };
+        let Some(outgoing) = outgoing else {
+            continue;
+        };
+
+        let json = serde_json::to_string(&outgoing).unwrap_or_default();
+        if socket.send(Message::Text(json.into())).await.is_err() {
+            break;
+        }
+        if matches!(outgoing, SyncMessage::Incompatible { .. }) {
+            let _ = socket.send(Message::Close(None)).await;
+            break;
         }
     }
 }
 
+/// The response to a WebSocket message from the client, if any.
+fn respond(room: &SyncRoom, peer: &mut PeerState, msg: Message) -> Option<SyncMessage> {
+    let Message::Text(text) = msg else {
+        return None;
+    };
+    let sync_msg = serde_json::from_str::<SyncMessage>(&text).ok()?;
+    handle_sync_message(room, peer, sync_msg)
+}
+
+/// The message relaying another connection's update to this client, if any.
+///
+/// A client that fell too far behind is told to catch up with a sync request.
+fn relay(peer: &PeerState, update: Result<(u64, Vec<u8>), RecvError>) -> Option<SyncMessage> {
+    peer.protocol_version?;
+    match update {
+        Ok((origin, update)) => (origin != peer.id).then_some(SyncMessage::Update { update }),
+        Err(RecvError::Lagged(missed)) => Some(SyncMessage::Error {
+            message: format!("missed {missed} updates; send a sync_request to catch up"),
+        }),
+        Err(RecvError::Closed) => None,
+    }
+}
+
 /// Process a sync message and return optional response.
-fn handle_sync_message(sync: &DocumentSync, msg: SyncMessage) -> Option<SyncMessage> {
+///
+/// The first message must be a compatible [`SyncMessage::Hello`] or
+/// [`SyncMessage::Resume`]; anything else gets [`SyncMessage::Incompatible`]
+/// and the connection is closed. Updates the client sends are relayed to the
+/// room's other connections.
+fn handle_sync_message(
+    room: &SyncRoom,
+    peer: &mut PeerState,
+    msg: SyncMessage,
+) -> Option<SyncMessage> {
+    let sync = &room.doc;
+    let incompatible =
+        SyncMessage::Incompatible { min: MIN_PROTOCOL_VERSION, max: PROTOCOL_VERSION };
+    match msg {
+        SyncMessage::Hello { protocol_version } | SyncMessage::Resume { protocol_version, .. }
+            if !(MIN_PROTOCOL_VERSION..=PROTOCOL_VERSION).contains(&protocol_version) =>
+        {
+            tracing::warn!(protocol_version, "Rejected incompatible sync client");
+            return Some(incompatible);
+        }
+        SyncMessage::Hello { protocol_version } => {
+            peer.protocol_version = Some(protocol_version);
+            return Some(SyncMessage::Hello { protocol_version: PROTOCOL_VERSION });
+        }
+        SyncMessage::Resume { protocol_version, state_vector } => {
+            peer.protocol_version = Some(protocol_version);
+            let update = sync.get_update_from(&state_vector);
+            peer.acked_state_vector = Some(state_vector);
+            // An unreadable state vector gets the whole document
+            return Some(SyncMessage::SyncResponse {
+                update: update.unwrap_or_else(|| sync.get_state()),
+            });
+        }
+        _ => {}
+    }
+    if peer.protocol_version.is_none() {
+        tracing::warn!("Sync client did not start with a hello");
+        return Some(incompatible);
+    }
+
+    if let Err(message) = peer.check_limits(&msg) {
+        tracing::warn!(%message, "Rejected sync message");
+        return Some(SyncMessage::Error { message });
+    }
+
     match msg {
         SyncMessage::SyncRequest { state_vector } => {
             let update = sync.get_update_from(&state_vector)?;
             Some(SyncMessage::SyncResponse { update })
         }
-        SyncMessage::Update { update } => {
-            sync.apply_update(&update).ok()?;
-            None
+        SyncMessage::FullStateRequest => {
+            Some(SyncMessage::SyncResponse { update: sync.get_state() })
+        }
+        SyncMessage::Update { .. } | SyncMessage::SyncResponse { .. } if !peer.writable => {
+            Some(SyncMessage::Error { message: "no write access to this document".to_owned() })
         }
-        SyncMessage::SyncResponse { update } => {
+        SyncMessage::Update { update } | SyncMessage::SyncResponse { update } => {
             sync.apply_update(&update).ok()?;
+            peer.updates_applied += 1;
+            room.publish(peer.id, update);
             None
         }
+        SyncMessage::VersionRequest { version } => Some(version_response(sync, version)),
         SyncMessage::Awareness { .. } => {
             // TODO: Broadcast awareness to other connected clients
             None
         }
+        SyncMessage::Ack { state_vector } => {
+            peer.acked_state_vector = Some(state_vector);
+            if !peer.is_caught_up(sync) {
+                tracing::debug!("Client acknowledged a state behind the server");
+            }
+            None
+        }
+        SyncMessage::Error { message } => {
+            tracing::warn!(%message, "Client reported sync error");
+            None
+        }
+        // Handshakes are handled above; the rest are only sent by the server
+        SyncMessage::Hello { .. }
+        | SyncMessage::Resume { .. }
+        | SyncMessage::Incompatible { .. }
+        | SyncMessage::VersionResponse { .. } => None,
+    }
+}
+
+/// Answers a request for the document at `version` from its snapshot.
+fn version_response(sync: &DocumentSync, version: u64) -> SyncMessage {
+    let Some(state) = sync.snapshot_state(version) else {
+        return SyncMessage::Error { message: format!("version {version} is not available") };
+    };
+    match DocumentSync::from_state(&state) {
+        Ok(snapshot) => {
+            SyncMessage::VersionResponse { version, content: snapshot.get_content(), state }
+        }
+        Err(e) => SyncMessage::Error { message: format!("version {version} is unreadable: {e}") },
     }
 }
 
@@ -93,3 +359,427 @@ fn handle_sync_message(sync: &DocumentSync, msg: SyncMessage) -> Option<SyncMess
 pub fn routes() -> Router<AppState> {
     Router::new().route("/sync/{doc_id}", get(ws_handler))
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn roundtrip(msg: &SyncMessage) -> SyncMessage {
+        let json = serde_json::to_string(msg).expect("should serialize");
+        serde_json::from_str(&json).expect("should deserialize")
+    }
+
+    /// A peer that has completed the handshake.
+    fn greeted_peer(limits: SyncLimits) -> PeerState {
+        PeerState { protocol_version: Some(PROTOCOL_VERSION), ..PeerState::new(limits) }
+    }
+
+    #[test]
+    fn test_compatible_hello_proceeds() {
+        let room = SyncRoom::new();
+        let mut peer = PeerState::new(SyncLimits::default());
+
+        let hello = SyncMessage::Hello { protocol_version: PROTOCOL_VERSION };
+        let response =
+            handle_sync_message(&room, &mut peer, roundtrip(&hello)).map(|r| roundtrip(&r));
+        assert!(matches!(
+            response,
+            Some(SyncMessage::Hello { protocol_version: PROTOCOL_VERSION })
+        ));
+
+        let response = handle_sync_message(&room, &mut peer, SyncMessage::FullStateRequest);
+        assert!(matches!(response, Some(SyncMessage::SyncResponse { .. })));
+    }
+
+    #[test]
+    fn test_incompatible_hello_is_rejected() {
+        let room = SyncRoom::new();
+        let mut peer = PeerState::new(SyncLimits::default());
+
+        let hello = SyncMessage::Hello { protocol_version: PROTOCOL_VERSION + 1 };
+        let response = handle_sync_message(&room, &mut peer, hello);
+        assert!(matches!(
+            response,
+            Some(SyncMessage::Incompatible { min: MIN_PROTOCOL_VERSION, max: PROTOCOL_VERSION })
+        ));
+
+        // Without a successful handshake nothing else is served
+        let response = handle_sync_message(&room, &mut peer, SyncMessage::FullStateRequest);
+        assert!(matches!(response, Some(SyncMessage::Incompatible { .. })));
+    }
+
+    #[tokio::test]
+    async fn test_incompatible_client_is_disconnected() {
+        use futures::{SinkExt, StreamExt};
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let app = routes().with_state(AppState::new());
+        tokio::spawn(axum::serve(listener, app).into_future());
+
+        let url = format!("ws://{addr}/sync/{}", DocumentId::new());
+        let (mut socket, _) = tokio_tungstenite::connect_async(url).await.expect("should connect");
+        let hello = r#"{"type":"hello","payload":{"protocol_version":0}}"#;
+        socket.send(WsMessage::text(hello)).await.expect("should send hello");
+
+        let reply = socket.next().await.expect("should reply").expect("should read reply");
+        let reply: serde_json::Value =
+            serde_json::from_str(reply.to_text().expect("should be text")).expect("should be JSON");
+        assert_eq!(reply["type"], "incompatible");
+        assert_eq!(reply["payload"]["max"], PROTOCOL_VERSION);
+
+        let close = socket.next().await.expect("should close").expect("should read close");
+        assert!(close.is_close());
+    }
+
+    type Client = tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >;
+
+    async fn send(client: &mut Client, msg: &SyncMessage) {
+        use futures::SinkExt;
+        use tokio_tungstenite::tungstenite::Message as WsMessage;
+
+        let json = serde_json::to_string(msg).expect("should serialize");
+        client.send(WsMessage::text(json)).await.expect("should send");
+    }
+
+    async fn recv(client: &mut Client) -> SyncMessage {
+        use futures::StreamExt;
+
+        let reply = client.next().await.expect("should reply").expect("should read reply");
+        serde_json::from_str(reply.to_text().expect("should be text")).expect("should parse")
+    }
+
+    /// Sends `msg` and waits for the full state, so the server has applied it.
+    async fn send_applied(client: &mut Client, msg: &SyncMessage) {
+        send(client, msg).await;
+        send(client, &SyncMessage::FullStateRequest).await;
+        assert!(matches!(recv(client).await, SyncMessage::SyncResponse { .. }));
+    }
+
+    async fn greeted_client(url: &str, handshake: &SyncMessage) -> (Client, SyncMessage) {
+        let (mut client, _) = tokio_tungstenite::connect_async(url).await.expect("should connect");
+        send(&mut client, handshake).await;
+        let reply = recv(&mut client).await;
+        (client, reply)
+    }
+
+    #[tokio::test]
+    async fn test_reconnecting_client_resumes_from_state_vector() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let state = AppState::new();
+        tokio::spawn(axum::serve(listener, routes().with_state(state.clone())).into_future());
+        let doc_id = DocumentId::new();
+        let url = format!("ws://{addr}/sync/{doc_id}");
+        let hello = SyncMessage::Hello { protocol_version: PROTOCOL_VERSION };
+
+        let writer = DocumentSync::new();
+        let (mut writer_client, _) = greeted_client(&url, &hello).await;
+        writer.set_content("Hello").expect("should edit");
+        let edit = SyncMessage::Update { update: writer.get_state() };
+        send_applied(&mut writer_client, &edit).await;
+
+        let reader = DocumentSync::new();
+        let (mut reader_client, _) = greeted_client(&url, &hello).await;
+        send(&mut reader_client, &SyncMessage::FullStateRequest).await;
+        let SyncMessage::SyncResponse { update } = recv(&mut reader_client).await else {
+            unreachable!("full state request should get a sync response");
+        };
+        reader.apply_update(&update).expect("should apply full state");
+        drop(reader_client);
+
+        let before = writer.get_state_vector();
+        writer.insert(5, " world").expect("should edit");
+        let missed = writer.get_update_from(&before).expect("should have an update");
+        send_applied(&mut writer_client, &SyncMessage::Update { update: missed }).await;
+
+        let resume = SyncMessage::Resume {
+            protocol_version: PROTOCOL_VERSION,
+            state_vector: reader.get_state_vector(),
+        };
+        let (mut reader_client, reply) = greeted_client(&url, &resume).await;
+        let SyncMessage::SyncResponse { update } = reply else {
+            unreachable!("resume should get a sync response");
+        };
+        let room = state.sync_room(doc_id).await;
+        assert_eq!(room.doc.get_update_from(&reader.get_state_vector()), Some(update.clone()));
+        assert!(update.len() < room.doc.get_state().len());
+        reader.apply_update(&update).expect("should apply missed update");
+        assert_eq!(reader.get_content(), "Hello world");
+
+        // After resuming, the writer's edits arrive live
+        let before = writer.get_state_vector();
+        writer.insert(0, "Oh, ").expect("should edit");
+        let live = writer.get_update_from(&before).expect("should have an update");
+        send(&mut writer_client, &SyncMessage::Update { update: live }).await;
+        let SyncMessage::Update { update } = recv(&mut reader_client).await else {
+            unreachable!("resumed client should receive live updates");
+        };
+        reader.apply_update(&update).expect("should apply live update");
+        assert_eq!(reader.get_content(), "Oh, Hello world");
+    }
+
+    /// Completes once the document's content is `content`.
+    async fn saved(state: &AppState, doc_id: DocumentId, content: &str) {
+        while state.documents.read().await[&doc_id].content != content {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_close_persists_final_state() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let state = AppState::new().with_sync_flush_delay(Duration::from_millis(50));
+        tokio::spawn(axum::serve(listener, routes().with_state(state.clone())).into_future());
+        let doc = glow_core::Document::new().with_initial_content("Start");
+        let doc_id = doc.id;
+        state.documents.write().await.insert(doc_id, doc);
+        let url = format!("ws://{addr}/sync/{doc_id}");
+        let hello = SyncMessage::Hello { protocol_version: PROTOCOL_VERSION };
+
+        // Rapid edits, then close before the debounced flush runs
+        let (mut client, _) = greeted_client(&url, &hello).await;
+        send(&mut client, &SyncMessage::FullStateRequest).await;
+        let SyncMessage::SyncResponse { update } = recv(&mut client).await else {
+            unreachable!("full state request should get a sync response");
+        };
+        let writer = DocumentSync::from_state(&update).expect("should load state");
+        for draft in ["Draft one", "Draft two", "Final text"] {
+            let before = writer.get_state_vector();
+            writer.set_content(draft).expect("should edit");
+            let update = writer.get_update_from(&before).expect("should have an update");
+            send(&mut client, &SyncMessage::Update { update }).await;
+        }
+        client.close(None).await.expect("should close");
+
+        // Intermediate drafts may be flushed on the way; the final text must be saved
+        tokio::time::timeout(Duration::from_secs(5), saved(&state, doc_id, "Final text"))
+            .await
+            .expect("close should save the final state");
+        let crdt_state =
+            state.documents.read().await[&doc_id].crdt_state.clone().expect("should save state");
+        let saved_sync = DocumentSync::from_state(&crdt_state).expect("should load saved state");
+        assert_eq!(saved_sync.get_content(), "Final text");
+
+        // The cancelled flush does not overwrite a newer change
+        state.documents.write().await.get_mut(&doc_id).expect("should exist").content =
+            "Edited elsewhere".to_owned();
+        tokio::time::sleep(Duration::from_millis(150)).await;
+        assert_eq!(state.documents.read().await[&doc_id].content, "Edited elsewhere");
+
+        // Reopening starts from the saved document
+        let (mut client, _) = greeted_client(&url, &hello).await;
+        send(&mut client, &SyncMessage::FullStateRequest).await;
+        let SyncMessage::SyncResponse { update } = recv(&mut client).await else {
+            unreachable!("full state request should get a sync response");
+        };
+        let reader = DocumentSync::from_state(&update).expect("should load state");
+        assert_eq!(reader.get_content(), "Edited elsewhere");
+    }
+
+    #[test]
+    fn test_new_client_requests_full_state() {
+        let room = SyncRoom::new();
+        let server = &room.doc;
+        server.set_content("Shared document").expect("should edit");
+        let mut peer = greeted_peer(SyncLimits::default());
+
+        let request = roundtrip(&SyncMessage::FullStateRequest);
+        let response = handle_sync_message(&room, &mut peer, request).map(|r| roundtrip(&r));
+        let Some(SyncMessage::SyncResponse { update }) = response else {
+            unreachable!("full state request should get a sync response");
+        };
+
+        let client = DocumentSync::new();
+        client.apply_update(&update).expect("should apply full state");
+        assert_eq!(client.get_content(), "Shared document");
+        assert!(!peer.is_caught_up(server));
+
+        let ack = SyncMessage::Ack { state_vector: client.get_state_vector() };
+        assert!(handle_sync_message(&room, &mut peer, roundtrip(&ack)).is_none());
+        assert!(peer.is_caught_up(server));
+
+        // Further server edits put the client behind again
+        server.insert(0, "Our ").expect("should edit");
+        assert!(!peer.is_caught_up(server));
+    }
+
+    #[tokio::test]
+    async fn test_version_request_returns_earlier_content() {
+        let state = AppState::new();
+        let doc = glow_core::Document::new().with_initial_content("First draft");
+        let doc_id = doc.id;
+        state.documents.write().await.insert(doc_id, doc);
+        let room = state.join_sync_room(doc_id).await;
+        let mut peer = greeted_peer(SyncLimits::default());
+
+        let client = DocumentSync::from_state(&room.doc.get_state()).expect("should load state");
+        let before = client.get_state_vector();
+        client.set_content("Second draft").expect("should edit");
+        let update = client.get_update_from(&before).expect("should have an update");
+        assert!(handle_sync_message(&room, &mut peer, SyncMessage::Update { update }).is_none());
+        // Applying an update alone does not make a new version
+        let request = SyncMessage::VersionRequest { version: 2 };
+        let response = handle_sync_message(&room, &mut peer, request);
+        assert!(matches!(response, Some(SyncMessage::Error { .. })));
+
+        assert!(state.flush_sync_room(doc_id, &room).await);
+        assert_eq!(state.documents.read().await[&doc_id].metadata.version, 2);
+        for (version, expected) in [(1, "First draft"), (2, "Second draft")] {
+            let request = roundtrip(&SyncMessage::VersionRequest { version });
+            let response = handle_sync_message(&room, &mut peer, request).map(|r| roundtrip(&r));
+            let Some(SyncMessage::VersionResponse { content, state, .. }) = response else {
+                unreachable!("version request should get a version response");
+            };
+            assert_eq!(content, expected);
+            assert_eq!(DocumentSync::from_state(&state).expect("valid").get_content(), expected);
+        }
+
+        let request = SyncMessage::VersionRequest { version: 3 };
+        let response = handle_sync_message(&room, &mut peer, request);
+        assert!(matches!(response, Some(SyncMessage::Error { .. })));
+    }
+
+    fn small_limits() -> SyncLimits {
+        SyncLimits { max_payload_bytes: 16, max_awareness_per_sec: 2 }
+    }
+
+    #[test]
+    fn test_oversized_awareness_is_rejected() {
+        let room = SyncRoom::new();
+        let mut peer = greeted_peer(small_limits());
+
+        let msg = SyncMessage::Awareness { client_id: 1, state: vec![0; 17] };
+        let response = handle_sync_message(&room, &mut peer, msg);
+        assert!(matches!(response, Some(SyncMessage::Error { .. })));
+        // Rejected before reaching the awareness path
+        assert_eq!(peer.awareness_count, 0);
+    }
+
+    /// Completes once the document's sync room has been released.
+    ///
+    /// A released room is replaced by a new, empty one when asked for.
+    async fn released(state: &AppState, doc_id: DocumentId) {
+        while !state.sync_room(doc_id).await.doc.get_content().is_empty() {
+            tokio::time::sleep(Duration::from_millis(5)).await;
+        }
+    }
+
+    #[tokio::test]
+    async fn test_room_without_document_is_released() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let state = AppState::new();
+        tokio::spawn(axum::serve(listener, routes().with_state(state.clone())).into_future());
+        let doc_id = DocumentId::new();
+        let url = format!("ws://{addr}/sync/{doc_id}");
+
+        let (mut client, _) =
+            greeted_client(&url, &SyncMessage::Hello { protocol_version: PROTOCOL_VERSION }).await;
+        let edit = DocumentSync::new();
+        edit.set_content("Nowhere to save").expect("should edit");
+        send_applied(&mut client, &SyncMessage::Update { update: edit.get_state() }).await;
+        client.close(None).await.expect("should close");
+
+        tokio::time::timeout(Duration::from_secs(5), released(&state, doc_id))
+            .await
+            .expect("the room should be released");
+    }
+
+    #[tokio::test]
+    async fn test_sync_enforces_document_acl() {
+        use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let state = AppState::new();
+        tokio::spawn(axum::serve(listener, routes().with_state(state.clone())).into_future());
+        let doc = glow_core::Document::new().with_initial_content("Private");
+        let doc_id = doc.id;
+        state.documents.write().await.insert(doc_id, doc);
+        let mut acl = crate::acl::DocumentAcl::new("alice");
+        acl.shared.insert("bob".to_owned(), AccessLevel::Read);
+        state.acls.write().await.insert(doc_id, acl);
+        let url = format!("ws://{addr}/sync/{doc_id}");
+
+        // Anonymous clients cannot connect at all
+        assert!(tokio_tungstenite::connect_async(url.as_str()).await.is_err());
+
+        // A reader can connect, but their updates are refused
+        let mut request = url.as_str().into_client_request().expect("should build request");
+        request
+            .headers_mut()
+            .insert(crate::acl::USER_ID_HEADER, "bob".parse().expect("valid header"));
+        let (mut reader, _) =
+            tokio_tungstenite::connect_async(request).await.expect("reader should connect");
+        send(&mut reader, &SyncMessage::Hello { protocol_version: PROTOCOL_VERSION }).await;
+        assert!(matches!(recv(&mut reader).await, SyncMessage::Hello { .. }));
+
+        let edit = DocumentSync::new();
+        edit.set_content("Defaced").expect("should edit");
+        send(&mut reader, &SyncMessage::Update { update: edit.get_state() }).await;
+        assert!(matches!(recv(&mut reader).await, SyncMessage::Error { .. }));
+        assert_eq!(state.sync_room(doc_id).await.doc.get_content(), "Private");
+    }
+
+    #[tokio::test]
+    async fn test_read_only_document_refuses_sync_updates() {
+        let listener =
+            tokio::net::TcpListener::bind("127.0.0.1:0").await.expect("should bind listener");
+        let addr = listener.local_addr().expect("should have address");
+        let state = AppState::new();
+        tokio::spawn(axum::serve(listener, routes().with_state(state.clone())).into_future());
+        let mut doc = glow_core::Document::new().with_initial_content("Final");
+        doc.set_read_only(true);
+        let doc_id = doc.id;
+        state.documents.write().await.insert(doc_id, doc);
+
+        let (mut client, _) =
+            tokio_tungstenite::connect_async(format!("ws://{addr}/sync/{doc_id}"))
+                .await
+                .expect("should connect");
+        send(&mut client, &SyncMessage::Hello { protocol_version: PROTOCOL_VERSION }).await;
+        assert!(matches!(recv(&mut client).await, SyncMessage::Hello { .. }));
+
+        let edit = DocumentSync::new();
+        edit.set_content("Edited").expect("should edit");
+        send(&mut client, &SyncMessage::Update { update: edit.get_state() }).await;
+        assert!(matches!(recv(&mut client).await, SyncMessage::Error { .. }));
+        assert_eq!(state.sync_room(doc_id).await.doc.get_content(), "Final");
+    }
+
+    #[test]
+    fn test_oversized_update_is_not_applied() {
+        let room = SyncRoom::new();
+        let mut peer = greeted_peer(small_limits());
+
+        let source = DocumentSync::new();
+        source.set_content("This update is well over sixteen bytes").expect("should edit");
+        let msg = SyncMessage::Update { update: source.get_state() };
+
+        let response = handle_sync_message(&room, &mut peer, msg);
+        assert!(matches!(response, Some(SyncMessage::Error { .. })));
+        assert!(room.doc.get_content().is_empty());
+    }
+
+    #[test]
+    fn test_awareness_rate_limit() {
+        let room = SyncRoom::new();
+        let mut peer = greeted_peer(small_limits());
+        let awareness = || SyncMessage::Awareness { client_id: 1, state: vec![1, 2, 3] };
+
+        assert!(handle_sync_message(&room, &mut peer, awareness()).is_none());
+        assert!(handle_sync_message(&room, &mut peer, awareness()).is_none());
+        let response = handle_sync_message(&room, &mut peer, awareness());
+        assert!(matches!(response, Some(SyncMessage::Error { .. })));
+    }
+}
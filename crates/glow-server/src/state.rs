@@ -1,23 +1,351 @@
 //! Application state management.
 
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::{Arc, Mutex, PoisonError};
+use std::time::Duration;
 
-use glow_core::{Document, DocumentId};
-use tokio::sync::RwLock;
+use glow_core::{Document, DocumentEvent, DocumentEventKind, DocumentId, DocumentSync};
+use sqlx::SqlitePool;
+use tokio::sync::{RwLock, RwLockWriteGuard, broadcast};
+use tokio_util::sync::CancellationToken;
+
+use crate::acl::DocumentAcl;
+
+/// Capacity of the document event channel before slow subscribers lag.
+const EVENT_CHANNEL_CAPACITY: usize = 256;
+
+/// Limits applied to messages received over the sync WebSocket.
+#[derive(Debug, Clone, Copy)]
+pub struct SyncLimits {
+    /// Largest `update` or awareness `state` payload accepted, in bytes.
+    pub max_payload_bytes: usize,
+    /// Awareness updates accepted per client per second.
+    pub max_awareness_per_sec: u32,
+}
+
+impl SyncLimits {
+    /// Default payload cap (1 MiB).
+    pub const DEFAULT_MAX_PAYLOAD_BYTES: usize = 1024 * 1024;
+    /// Default awareness update rate.
+    pub const DEFAULT_MAX_AWARENESS_PER_SEC: u32 = 20;
+}
+
+impl Default for SyncLimits {
+    fn default() -> Self {
+        Self {
+            max_payload_bytes: Self::DEFAULT_MAX_PAYLOAD_BYTES,
+            max_awareness_per_sec: Self::DEFAULT_MAX_AWARENESS_PER_SEC,
+        }
+    }
+}
+
+/// Capacity of a sync room's update channel before slow connections lag.
+const SYNC_UPDATE_CAPACITY: usize = 256;
+
+/// How long a sync room waits after an update before saving its state to the document.
+pub const DEFAULT_SYNC_FLUSH_DELAY: Duration = Duration::from_secs(1);
+
+/// The CRDT state of one document shared by its sync connections.
+#[derive(Debug)]
+pub struct SyncRoom {
+    /// The server's replica of the document.
+    pub doc: DocumentSync,
+    /// Updates applied by connections, tagged with the ID of the connection they came from.
+    updates: broadcast::Sender<(u64, Vec<u8>)>,
+    /// Open connections; the room is closed when the last one leaves.
+    connections: AtomicUsize,
+    /// Cancels the pending debounced flush, if any.
+    flush: Mutex<CancellationToken>,
+}
+
+impl SyncRoom {
+    /// Creates a room for a document with no content yet.
+    #[must_use]
+    pub fn new() -> Self {
+        Self::with_doc(DocumentSync::new())
+    }
+
+    /// Creates a room sharing an existing replica.
+    #[must_use]
+    pub fn with_doc(doc: DocumentSync) -> Self {
+        let (updates, _) = broadcast::channel(SYNC_UPDATE_CAPACITY);
+        Self { doc, updates, connections: AtomicUsize::new(0), flush: Mutex::default() }
+    }
+
+    /// Subscribes to updates applied by any connection.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<(u64, Vec<u8>)> {
+        self.updates.subscribe()
+    }
+
+    /// Relays an update applied by connection `origin` to the other connections.
+    pub fn publish(&self, origin: u64, update: Vec<u8>) {
+        // No other connections is not an error
+        let _ = self.updates.send((origin, update));
+    }
+
+    /// Cancels the pending flush, returning a token for its replacement.
+    fn reschedule_flush(&self) -> CancellationToken {
+        let mut flush = self.flush.lock().unwrap_or_else(PoisonError::into_inner);
+        std::mem::take(&mut *flush).cancel();
+        flush.clone()
+    }
+
+    /// Cancels the pending flush, if any.
+    fn cancel_flush(&self) {
+        self.flush.lock().unwrap_or_else(PoisonError::into_inner).cancel();
+    }
+}
+
+impl Default for SyncRoom {
+    fn default() -> Self {
+        Self::new()
+    }
+}
 
 /// Shared application state.
 #[derive(Clone)]
 pub struct AppState {
     /// In-memory document storage (will be replaced with database).
     pub documents: Arc<RwLock<HashMap<DocumentId, Document>>>,
+
+    /// Access control lists, for documents that have an owner.
+    pub acls: Arc<RwLock<HashMap<DocumentId, DocumentAcl>>>,
+
+    /// Database connection pool, if persistent storage is configured.
+    pub db: Option<SqlitePool>,
+
+    /// Limits for sync WebSocket messages.
+    pub sync_limits: SyncLimits,
+
+    /// Most documents the workspace may hold, or `None` for no limit.
+    pub max_documents: Option<usize>,
+
+    /// Content size above which saves warn that a document is too large, in bytes.
+    pub soft_size_limit: usize,
+
+    /// How long after a sync update the document is saved, so bursts of edits save once.
+    pub sync_flush_delay: Duration,
+
+    /// Sync state of documents with live connections, kept across reconnects.
+    sync_rooms: Arc<RwLock<HashMap<DocumentId, Arc<SyncRoom>>>>,
+
+    /// Document lifecycle events.
+    events: broadcast::Sender<DocumentEvent>,
 }
 
 impl AppState {
     /// Creates a new application state.
     #[must_use]
     pub fn new() -> Self {
-        Self { documents: Arc::new(RwLock::new(HashMap::new())) }
+        let (events, _) = broadcast::channel(EVENT_CHANNEL_CAPACITY);
+        Self {
+            documents: Arc::new(RwLock::new(HashMap::new())),
+            acls: Arc::new(RwLock::new(HashMap::new())),
+            db: None,
+            sync_limits: SyncLimits::default(),
+            max_documents: None,
+            soft_size_limit: Document::DEFAULT_SOFT_SIZE_LIMIT,
+            sync_flush_delay: DEFAULT_SYNC_FLUSH_DELAY,
+            sync_rooms: Arc::default(),
+            events,
+        }
+    }
+
+    /// Attaches a database connection pool.
+    #[must_use]
+    pub fn with_database(mut self, pool: SqlitePool) -> Self {
+        self.db = Some(pool);
+        self
+    }
+
+    /// Overrides the sync WebSocket message limits.
+    #[must_use]
+    pub const fn with_sync_limits(mut self, limits: SyncLimits) -> Self {
+        self.sync_limits = limits;
+        self
+    }
+
+    /// Limits how many documents the workspace may hold, or `None` for no limit.
+    #[must_use]
+    pub const fn with_max_documents(mut self, max_documents: Option<usize>) -> Self {
+        self.max_documents = max_documents;
+        self
+    }
+
+    /// Overrides the content size above which saves warn, in bytes.
+    #[must_use]
+    pub const fn with_soft_size_limit(mut self, soft_size_limit: usize) -> Self {
+        self.soft_size_limit = soft_size_limit;
+        self
+    }
+
+    /// Overrides how long after a sync update the document is saved.
+    #[must_use]
+    pub const fn with_sync_flush_delay(mut self, delay: Duration) -> Self {
+        self.sync_flush_delay = delay;
+        self
+    }
+
+    /// The sync room of a document, created on first use.
+    #[cfg(test)]
+    pub async fn sync_room(&self, id: DocumentId) -> Arc<SyncRoom> {
+        if let Some(room) = self.sync_rooms.read().await.get(&id) {
+            return Arc::clone(room);
+        }
+        let mut rooms = self.sync_rooms.write().await;
+        self.room_entry(&mut rooms, id).await
+    }
+
+    /// Joins a connection to a document's sync room, created on first use.
+    ///
+    /// Each join must be matched by a [`AppState::leave_sync_room`].
+    pub async fn join_sync_room(&self, id: DocumentId) -> Arc<SyncRoom> {
+        let mut rooms = self.sync_rooms.write().await;
+        let room = self.room_entry(&mut rooms, id).await;
+        room.connections.fetch_add(1, Ordering::Relaxed);
+        drop(rooms);
+        room
+    }
+
+    /// Removes a connection from a document's sync room.
+    ///
+    /// When the last connection leaves, the document is closed: the pending
+    /// flush is cancelled, so it cannot later overwrite newer changes, and the
+    /// latest state is saved before the room is released. The room is released
+    /// even if the document cannot be saved to, because it does not exist or is
+    /// read-only; its state is then discarded.
+    pub async fn leave_sync_room(&self, id: DocumentId, room: &SyncRoom) {
+        // Holding the rooms lock keeps a new connection from starting a room
+        // from the document before the final state is saved
+        let mut rooms = self.sync_rooms.write().await;
+        if room.connections.fetch_sub(1, Ordering::Relaxed) > 1 {
+            return;
+        }
+        room.cancel_flush();
+        if !self.flush_sync_room(id, room).await {
+            tracing::warn!(%id, "Discarding sync state of a document that cannot be saved");
+        }
+        rooms.remove(&id);
+    }
+
+    /// Saves a sync room's state to its document once [`AppState::sync_flush_delay`]
+    /// passes without another update, replacing any pending flush.
+    pub fn schedule_sync_flush(&self, id: DocumentId, room: &Arc<SyncRoom>) {
+        let token = room.reschedule_flush();
+        let (state, room) = (self.clone(), Arc::clone(room));
+        tokio::spawn(async move {
+            tokio::select! {
+                () = token.cancelled() => {}
+                () = tokio::time::sleep(state.sync_flush_delay) => {
+                    let documents = state.documents.write().await;
+                    // A later update may have rescheduled the flush while this one waited
+                    if !token.is_cancelled() {
+                        state.save_sync_room(documents, id, &room);
+                    }
+                }
+            }
+        });
+    }
+
+    /// Saves a sync room's CRDT state and content to its document.
+    ///
+    /// A content change bumps the document version and is kept as a snapshot
+    /// of that version, for sync clients asking for earlier versions. Returns
+    /// `false` if the document does not exist or is read-only.
+    pub async fn flush_sync_room(&self, id: DocumentId, room: &SyncRoom) -> bool {
+        let documents = self.documents.write().await;
+        self.save_sync_room(documents, id, room)
+    }
+
+    /// [`AppState::flush_sync_room`] with the documents already locked.
+    fn save_sync_room(
+        &self,
+        mut documents: RwLockWriteGuard<'_, HashMap<DocumentId, Document>>,
+        id: DocumentId,
+        room: &SyncRoom,
+    ) -> bool {
+        let Some(doc) = documents.get_mut(&id).filter(|doc| !doc.metadata.read_only) else {
+            return false;
+        };
+        doc.crdt_state = Some(room.doc.get_state());
+        let content = room.doc.get_content();
+        if content != doc.content {
+            doc.content = content;
+            doc.metadata.touch();
+            let version = doc.metadata.version;
+            drop(documents);
+            room.doc.capture_snapshot_at(version);
+            self.publish(DocumentEventKind::Updated, id, version);
+        }
+        true
+    }
+
+    /// The room for `id` in `rooms`.
+    ///
+    /// A new room starts from the document's saved CRDT state, brought up to
+    /// date with its content and kept as a snapshot of its current version.
+    async fn room_entry(
+        &self,
+        rooms: &mut HashMap<DocumentId, Arc<SyncRoom>>,
+        id: DocumentId,
+    ) -> Arc<SyncRoom> {
+        if let Some(room) = rooms.get(&id) {
+            return Arc::clone(room);
+        }
+        let sync = self.documents.read().await.get(&id).map_or_else(DocumentSync::new, |doc| {
+            let sync = doc
+                .crdt_state
+                .as_deref()
+                .and_then(|state| DocumentSync::from_state(state).ok())
+                .unwrap_or_else(DocumentSync::new);
+            // Content saved outside sync since the state was last flushed wins
+            if sync.get_content() != doc.content
+                && let Err(e) = sync.set_content(&doc.content)
+            {
+                tracing::warn!(%id, error = %e, "Failed to bring sync state up to date");
+            }
+            sync.capture_snapshot_at(doc.metadata.version);
+            sync
+        });
+        Arc::clone(rooms.entry(id).or_insert_with(|| Arc::new(SyncRoom::with_doc(sync))))
+    }
+
+    /// Subscribes to document created/updated/deleted events.
+    #[must_use]
+    pub fn subscribe(&self) -> broadcast::Receiver<DocumentEvent> {
+        self.events.subscribe()
+    }
+
+    /// Logs every document event in the background, as an activity log.
+    #[must_use]
+    pub fn spawn_activity_log(&self) -> tokio::task::JoinHandle<()> {
+        tokio::spawn(log_document_events(self.subscribe()))
+    }
+
+    /// Publishes a document event to subscribers, if any.
+    pub fn publish(&self, kind: DocumentEventKind, id: DocumentId, version: u64) {
+        // No subscribers is not an error
+        let _ = self.events.send(DocumentEvent::new(kind, id, version));
+    }
+}
+
+/// Log each event from `events` until the channel closes.
+async fn log_document_events(mut events: broadcast::Receiver<DocumentEvent>) {
+    loop {
+        match events.recv().await {
+            Ok(event) => tracing::info!(
+                kind = ?event.kind,
+                document_id = %event.document_id,
+                version = event.version,
+                "Document changed"
+            ),
+            Err(broadcast::error::RecvError::Lagged(skipped)) => {
+                tracing::warn!(skipped, "Activity log fell behind document events");
+            }
+            Err(broadcast::error::RecvError::Closed) => break,
+        }
     }
 }
 
@@ -3,8 +3,21 @@
 //! WebAssembly bindings for Glow, exposing CRDT operations to the browser.
 
 use glow_core::DocumentSync;
+use serde::Serialize;
 use wasm_bindgen::prelude::*;
 
+/// Result of one sync step: the update a peer is missing and this document's state vector.
+#[wasm_bindgen(getter_with_clone)]
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct SyncStepResult {
+    /// Update bringing the peer up to date, or `None` if its state vector was invalid.
+    pub update: Option<Vec<u8>>,
+    /// This document's state vector, for the peer to compute its reply.
+    #[wasm_bindgen(js_name = stateVector)]
+    pub state_vector: Vec<u8>,
+}
+
 /// WASM-compatible document sync wrapper.
 #[wasm_bindgen]
 pub struct WasmDocumentSync {
@@ -33,19 +46,62 @@ impl WasmDocumentSync {
     }
 
     /// Sets the text content, replacing all existing content.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is read-only.
     #[wasm_bindgen(js_name = setContent)]
-    pub fn set_content(&self, content: &str) {
-        self.inner.set_content(content);
+    pub fn set_content(&self, content: &str) -> Result<(), JsError> {
+        self.inner.set_content(content).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Replaces the content with the fewest edits, keeping unchanged ranges.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is read-only.
+    #[wasm_bindgen(js_name = applyTextDiff)]
+    pub fn apply_text_diff(&self, content: &str) -> Result<(), JsError> {
+        self.inner.apply_text_diff(content).map_err(|e| JsError::new(&e.to_string()))
     }
 
     /// Inserts text at the given position.
-    pub fn insert(&self, index: u32, content: &str) {
-        self.inner.insert(index, content);
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is read-only.
+    pub fn insert(&self, index: u32, content: &str) -> Result<(), JsError> {
+        self.inner.insert(index, content).map_err(|e| JsError::new(&e.to_string()))
     }
 
     /// Deletes text at the given range.
-    pub fn delete(&self, index: u32, length: u32) {
-        self.inner.delete(index, length);
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is read-only.
+    pub fn delete(&self, index: u32, length: u32) -> Result<(), JsError> {
+        self.inner.delete(index, length).map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Removes all content, keeping the document syncing with its peers.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error if the document is read-only.
+    pub fn clear(&self) -> Result<(), JsError> {
+        self.inner.clear().map_err(|e| JsError::new(&e.to_string()))
+    }
+
+    /// Returns whether local edits are rejected.
+    #[wasm_bindgen(js_name = isReadOnly)]
+    pub fn is_read_only(&self) -> bool {
+        self.inner.is_read_only()
+    }
+
+    /// Freezes or unfreezes the document for local edits.
+    #[wasm_bindgen(js_name = setReadOnly)]
+    pub fn set_read_only(&self, read_only: bool) {
+        self.inner.set_read_only(read_only);
     }
 
     /// Gets the state vector for synchronization.
@@ -66,11 +122,32 @@ impl WasmDocumentSync {
         self.inner.get_update_from(state_vector)
     }
 
+    /// Computes the update for a peer and this document's state vector in one call.
+    #[wasm_bindgen(js_name = syncStep)]
+    pub fn sync_step(&self, peer_state_vector: &[u8]) -> SyncStepResult {
+        SyncStepResult {
+            update: self.inner.get_update_from(peer_state_vector),
+            state_vector: self.inner.get_state_vector(),
+        }
+    }
+
     /// Applies an update from a remote peer.
     #[wasm_bindgen(js_name = applyUpdate)]
     pub fn apply_update(&self, update: &[u8]) -> Result<(), JsError> {
         self.inner.apply_update(update).map_err(|e| JsError::new(&e.to_string()))
     }
+
+    /// Returns how many remote updates are waiting for updates they build on.
+    #[wasm_bindgen(js_name = pendingUpdateCount)]
+    pub fn pending_update_count(&self) -> usize {
+        self.inner.pending_update_count()
+    }
+
+    /// Returns the number of words, kept up to date as the text changes.
+    #[wasm_bindgen(js_name = wordCount)]
+    pub fn word_count(&self) -> usize {
+        self.inner.word_count()
+    }
 }
 
 impl Default for WasmDocumentSync {
@@ -78,3 +155,22 @@ impl Default for WasmDocumentSync {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wasm_bindgen_test::wasm_bindgen_test;
+
+    #[wasm_bindgen_test]
+    fn test_sync_step_returns_update_and_state_vector() {
+        let local = WasmDocumentSync::new();
+        local.set_content("Hello").expect("should edit");
+        let peer = WasmDocumentSync::new();
+
+        let step = local.sync_step(&peer.get_state_vector());
+        assert_eq!(step.state_vector, local.get_state_vector());
+        let update = step.update.expect("should produce an update");
+        peer.apply_update(&update).expect("should apply update");
+        assert_eq!(peer.get_content(), "Hello");
+    }
+}